@@ -0,0 +1,226 @@
+//! User annotations from the `verifopt` companion crate (/verifopt).
+//!
+//! `#[verifopt::closed]` and `#[verifopt::no_devirt]` expand to
+//! `#[cfg_attr(verifopt, verifopt_tool::closed)]` (resp. `no_devirt`), so a
+//! plain build doesn't see them and a `cargo verifopt` one, which compiles
+//! the analyzed crate with `RUSTC_FLAGS`, keeps them as inert tool
+//! attributes in HIR for `has_attr`:
+//!
+//! - `closed` on a trait makes it `TraitClass::Asserted` (see
+//!   `trait_collect`), whatever its visibility,
+//! - `no_devirt` on a fn (or a closure's enclosing fn) leaves its dyn calls
+//!   alone, and on a trait every dyn call to one of its methods (see
+//!   `no_devirt`); `FsaCallbacks::after_analysis` reports them as `Leave`.
+//!
+//! `verifopt::assert_targets!(expr, [Cat, Dog])` expands to
+//! `verifopt::__assert_targets::<(Cat, Dog), _, _>(|| expr)` instead, which
+//! needs neither: `find_assertions` finds those calls in MIR, and
+//! `check_assertions` compares the Self types of the analysis' targets at
+//! the closure's dyn call sites against the tuple's, failing the build on a
+//! difference. That's how a test suite pins down what FSA should find, the
+//! way tests/golden does for the fixtures.
+
+extern crate rustc_hir;
+extern crate rustc_middle;
+extern crate rustc_span;
+
+use rustc_hir::def::DefKind;
+use rustc_middle::mir::{BasicBlock, TerminatorKind};
+use rustc_middle::ty::{Ty, TyCtxt, TyKind};
+use rustc_public::rustc_internal;
+use rustc_span::def_id::DefId;
+use rustc_span::{Span, Symbol};
+
+use std::collections::HashMap;
+
+/// The tool the annotations' attributes live under
+pub const TOOL: &str = "verifopt_tool";
+pub const CLOSED: &str = "closed";
+pub const NO_DEVIRT: &str = "no_devirt";
+
+/// Extra rustc flags for the crate being analyzed, under which the
+/// `verifopt` crate's attributes expand to `TOOL` ones.
+pub const RUSTC_FLAGS: [&str; 6] = [
+    "--cfg",
+    "verifopt",
+    "--check-cfg",
+    "cfg(verifopt)",
+    "-Zcrate-attr=feature(register_tool)",
+    "-Zcrate-attr=register_tool(verifopt_tool)",
+];
+
+/// Analysis results as `start_verifopt` hands them out
+type Targets = HashMap<
+    (rustc_public::DefId, usize),
+    (
+        rustc_public::ty::Span,
+        Vec<(rustc_public::DefId, Option<rustc_public::ty::GenericArgs>)>,
+    ),
+>;
+
+/// Whether `did` carries `#[verifopt_tool::<name>]`.
+pub fn has_attr(tcx: TyCtxt<'_>, did: DefId, name: &str) -> bool {
+    tcx.get_attrs_by_path(did, &[Symbol::intern(TOOL), Symbol::intern(name)])
+        .next()
+        .is_some()
+}
+
+/// Whether `#[verifopt::no_devirt]` keeps the dyn call ending `bb` of
+/// `body` as it is: it's on `body`, on the fn `body` is a closure in, or on
+/// the trait whose method is called.
+pub fn no_devirt(tcx: TyCtxt<'_>, body: DefId, bb: usize) -> bool {
+    if has_attr(tcx, body, NO_DEVIRT) || has_attr(tcx, tcx.typeck_root_def_id(body), NO_DEVIRT) {
+        return true;
+    }
+
+    let mir = tcx.optimized_mir(body);
+    let Some(data) = mir.basic_blocks.get(BasicBlock::from_usize(bb)) else {
+        return false;
+    };
+    let TerminatorKind::Call { func, .. } = &data.terminator().kind else {
+        return false;
+    };
+    func.const_fn_def()
+        .and_then(|(callee, _)| tcx.trait_of_assoc(callee))
+        .is_some_and(|trait_did| has_attr(tcx, trait_did, NO_DEVIRT))
+}
+
+/// One `assert_targets!` use.
+pub struct TargetAssertion<'tcx> {
+    pub span: Span,
+    /// The closure holding the asserted expression, whose dyn call sites
+    /// are checked
+    pub closure: DefId,
+    /// The Self types the targets must have: `[Cat, Dog]`
+    pub expected: Vec<Ty<'tcx>>,
+}
+
+/// Every `verifopt::__assert_targets` call in this crate's MIR.
+pub fn find_assertions<'tcx>(tcx: TyCtxt<'tcx>) -> Vec<TargetAssertion<'tcx>> {
+    let mut found = Vec::new();
+    for owner in tcx.hir_body_owners() {
+        if !matches!(
+            tcx.def_kind(owner),
+            DefKind::Fn | DefKind::AssocFn | DefKind::Closure
+        ) {
+            continue;
+        }
+
+        for data in tcx.optimized_mir(owner).basic_blocks.iter() {
+            let TerminatorKind::Call { func, fn_span, .. } = &data.terminator().kind else {
+                continue;
+            };
+            let Some((callee, args)) = func.const_fn_def() else {
+                continue;
+            };
+            if !is_assert_fn(tcx, callee) {
+                continue;
+            }
+
+            let expected = match args.type_at(0).kind() {
+                TyKind::Tuple(tys) => tys.iter().collect(),
+                _ => continue,
+            };
+            let TyKind::Closure(closure, _) = args.type_at(2).kind() else {
+                continue;
+            };
+            found.push(TargetAssertion {
+                span: *fn_span,
+                closure: *closure,
+                expected,
+            });
+        }
+    }
+    found
+}
+
+fn is_assert_fn(tcx: TyCtxt<'_>, did: DefId) -> bool {
+    tcx.crate_name(did.krate).as_str() == "verifopt"
+        && tcx.item_name(did).as_str() == "__assert_targets"
+}
+
+/// Emits an error for every assertion the analysis' `targets` disagree
+/// with: a Self type found but not listed or listed but not found, or no
+/// dyn call in the closure at all. rustc then fails the build once
+/// `after_analysis` returns.
+pub fn check_assertions<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    assertions: &[TargetAssertion<'tcx>],
+    targets: &Targets,
+) {
+    for assertion in assertions {
+        let mut sites = 0;
+        let mut found: Vec<Ty<'tcx>> = Vec::new();
+        for ((body, _), (_, ts)) in targets {
+            if rustc_internal::internal(tcx, *body) != assertion.closure {
+                continue;
+            }
+            sites += 1;
+            for (did, genargs) in ts {
+                let Some(ty) = self_ty(tcx, *did, genargs.as_ref()) else {
+                    continue;
+                };
+                if !found.iter().any(|f| same_type(*f, ty)) {
+                    found.push(ty);
+                }
+            }
+        }
+
+        if sites == 0 {
+            tcx.dcx().span_err(
+                assertion.span,
+                "verifopt: assert_targets! found no dyn call in this expression",
+            );
+            continue;
+        }
+
+        let missing = assertion
+            .expected
+            .iter()
+            .any(|e| !found.iter().any(|f| same_type(*f, *e)));
+        let extra = found
+            .iter()
+            .any(|f| !assertion.expected.iter().any(|e| same_type(*f, *e)));
+        if missing || extra {
+            tcx.dcx().span_err(
+                assertion.span,
+                format!(
+                    "verifopt: assert_targets! expected [{}], but the analysis found [{}]",
+                    list(&assertion.expected),
+                    list(&found)
+                ),
+            );
+        }
+    }
+}
+
+/// The Self type a target impl fn is for: its impl's, or for a trait's
+/// default method, the first of the instance's generic args.
+fn self_ty<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    did: rustc_public::DefId,
+    genargs: Option<&rustc_public::ty::GenericArgs>,
+) -> Option<Ty<'tcx>> {
+    let did = rustc_internal::internal(tcx, did);
+    match tcx.impl_of_assoc(did) {
+        Some(impl_did) => Some(tcx.type_of(impl_did).instantiate_identity()),
+        None => rustc_internal::internal(tcx, genargs?).types().next(),
+    }
+}
+
+/// ADTs compare by definition, so a listed `Wrapper<u8>` matches the
+/// `impl<T> Trait for Wrapper<T>` target; anything else has to be the same
+/// type.
+fn same_type<'tcx>(a: Ty<'tcx>, b: Ty<'tcx>) -> bool {
+    match (a.ty_adt_def(), b.ty_adt_def()) {
+        (Some(a), Some(b)) => a.did() == b.did(),
+        _ => a == b,
+    }
+}
+
+fn list(tys: &[Ty<'_>]) -> String {
+    tys.iter()
+        .map(|ty| ty.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
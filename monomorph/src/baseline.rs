@@ -0,0 +1,224 @@
+//! CHA and RTA, the baselines FSA is measured against, as analyses of
+//! their own (`--verifopt-type cha`/`rta`).
+//!
+//! Neither interprets anything. Every virtual call in a body the program
+//! can reach (`sig_collect::for_each_reachable_body`) gets CHA's candidates,
+//! the same set `interp_virtual_call` records as a site's CHA column; RTA
+//! keeps only the impls for types the reachable program constructs: an ADT
+//! aggregate or a closure, in a reachable body or in a `static`'s or
+//! `const`'s initializer, or any type a constant's value can hold (see
+//! `note_instantiated_const`). The results land in the same
+//! `dispatch_targets`/`dispatch_cha` FSA's do, so `stats` and the rewrite
+//! work as they do for FSA - minus tagged rewrites, since tag plans come
+//! from FSA's provenance.
+
+use rustc_data_structures::fx::FxHashSet as HashSet;
+use rustc_public::mir::alloc::{AllocId, GlobalAlloc};
+use rustc_public::mir::mono::{Instance, InstanceKind};
+use rustc_public::mir::{AggregateKind, Body, Operand, Rvalue, StatementKind, TerminatorKind};
+use rustc_public::ty::{
+    Allocation, ConstantKind, FnDef, GenericArgs, Prov, RigidTy, Span, Ty, TyKind,
+};
+use rustc_public::{DefId, ItemKind};
+
+use log::debug;
+
+use crate::InterpPass;
+use crate::common::VerifOptType;
+use crate::sig_collect::for_each_reachable_body;
+
+impl<'a> InterpPass<'a> {
+    /// Fills in `dispatch_targets` and `dispatch_cha` for `style`, CHA or
+    /// RTA, in place of `run`
+    pub fn run_baseline(&self, style: VerifOptType) {
+        let mut sites: Vec<((DefId, usize), Span, FnDef, GenericArgs)> = Vec::new();
+        let mut instantiated: HashSet<DefId> = HashSet::default();
+
+        // What a `static` or `const` holds is built when its initializer
+        // is evaluated, so it's never in a reachable body
+        for item in rustc_public::all_local_items() {
+            if matches!(item.kind(), ItemKind::Static | ItemKind::Const)
+                && let Some(body) = item.body()
+            {
+                note_instantiated_body(&mut instantiated, &body);
+            }
+        }
+
+        for_each_reachable_body(|instance, body| {
+            note_instantiated_body(&mut instantiated, body);
+            let caller = instance.def.def_id();
+            for (bb, block) in body.blocks.iter().enumerate() {
+                let TerminatorKind::Call { func, .. } = &block.terminator.kind else {
+                    continue;
+                };
+                if let Ok(func_ty) = func.ty(body.locals())
+                    && let TyKind::RigidTy(RigidTy::FnDef(fndef, genargs)) = func_ty.kind()
+                    && let Ok(callee) = Instance::resolve(fndef, &genargs)
+                    && matches!(callee.kind, InstanceKind::Virtual { .. })
+                {
+                    sites.push(((caller, bb), block.terminator.span, fndef, genargs));
+                }
+            }
+        });
+        debug!(
+            "baseline {:?}: {} virtual call sites, {} instantiated types",
+            style,
+            sites.len(),
+            instantiated.len()
+        );
+
+        for (key, span, fndef, genargs) in sites {
+            let trait_defid = self.get_trait_defid(&fndef.0);
+            let cha = self.get_impls_cha(&fndef.0, &trait_defid, &genargs);
+            let targets = match style {
+                VerifOptType::RapidType => {
+                    self.get_impls_cha_among(&fndef.0, &trait_defid, &genargs, |defid| {
+                        instantiated.contains(defid)
+                    })
+                }
+                _ => cha.clone(),
+            };
+
+            // Instances of one generic fn share a key; like FSA, report the
+            // union of their targets
+            let mut dc = self.dispatch_cha.borrow_mut();
+            let entry = dc.entry(key).or_insert((span, Vec::new()));
+            for f in cha {
+                if !entry.1.contains(&f) {
+                    entry.1.push(f);
+                }
+            }
+            let mut dt = self.dispatch_targets.borrow_mut();
+            let entry = dt.entry(key).or_insert((span, Vec::new()));
+            for f in targets {
+                if !entry.1.contains(&f) {
+                    entry.1.push(f);
+                }
+            }
+            // Doesn't depend on any fn's interpretation being complete
+            self.dependencies.borrow_mut().entry(span).or_default();
+        }
+    }
+}
+
+fn note_instantiated_body(instantiated: &mut HashSet<DefId>, body: &Body) {
+    for block in &body.blocks {
+        for stmt in &block.statements {
+            if let StatementKind::Assign(_, rvalue) = &stmt.kind {
+                note_instantiated(instantiated, rvalue);
+            }
+        }
+        if let TerminatorKind::Call { args, .. } = &block.terminator.kind {
+            for arg in args {
+                note_instantiated_const(instantiated, arg);
+            }
+        }
+    }
+}
+
+fn note_instantiated(instantiated: &mut HashSet<DefId>, rvalue: &Rvalue) {
+    match rvalue {
+        Rvalue::Aggregate(kind, ops) => {
+            match kind {
+                AggregateKind::Adt(adtdef, ..) => {
+                    instantiated.insert(adtdef.0);
+                }
+                AggregateKind::Closure(def, _) => {
+                    instantiated.insert(def.0);
+                }
+                _ => {}
+            }
+            for op in ops {
+                note_instantiated_const(instantiated, op);
+            }
+        }
+        Rvalue::Use(op) | Rvalue::Cast(_, op, _) | Rvalue::Repeat(op, _) => {
+            note_instantiated_const(instantiated, op)
+        }
+        _ => {}
+    }
+}
+
+/// A constant's value is built at compile time, not by an aggregate: a
+/// fieldless struct (`Box::new(Cat)`), a promoted `&Cat`, a `const` array
+/// of `&dyn Animal`s, a reference to a `static`. So take every type it
+/// can hold, see `ConstTypes`.
+fn note_instantiated_const(instantiated: &mut HashSet<DefId>, op: &Operand) {
+    let Operand::Constant(constant) = op else {
+        return;
+    };
+    let mut types = ConstTypes {
+        instantiated,
+        seen_tys: HashSet::default(),
+        seen_allocs: HashSet::default(),
+    };
+    types.ty(constant.const_.ty());
+    if let ConstantKind::Allocated(alloc) = constant.const_.kind() {
+        types.provenance(alloc);
+    }
+}
+
+/// The types a constant can hold, without reading its bytes: every ADT
+/// (and closure) its type reaches through refs, raw pointers, arrays,
+/// slices, tuples and any variant's fields, plus, through its
+/// provenance, the types behind its trait objects' vtables and the
+/// statics it points to, and what those reach.
+struct ConstTypes<'i> {
+    instantiated: &'i mut HashSet<DefId>,
+    seen_tys: HashSet<Ty>,
+    seen_allocs: HashSet<AllocId>,
+}
+
+impl ConstTypes<'_> {
+    fn ty(&mut self, ty: Ty) {
+        if !self.seen_tys.insert(ty) {
+            return;
+        }
+        let TyKind::RigidTy(rigid) = ty.kind() else {
+            return;
+        };
+        match rigid {
+            RigidTy::Adt(adtdef, genargs) => {
+                self.instantiated.insert(adtdef.0);
+                for variant in adtdef.variants() {
+                    for field in variant.fields() {
+                        self.ty(field.ty_with_args(&genargs));
+                    }
+                }
+            }
+            RigidTy::Closure(def, _) => {
+                self.instantiated.insert(def.0);
+            }
+            RigidTy::Ref(_, ty, _)
+            | RigidTy::RawPtr(ty, _)
+            | RigidTy::Array(ty, _)
+            | RigidTy::Slice(ty) => self.ty(ty),
+            RigidTy::Tuple(tys) => {
+                for ty in tys {
+                    self.ty(ty);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn provenance(&mut self, alloc: &Allocation) {
+        for (_offset, Prov(id)) in alloc.provenance.ptrs.iter() {
+            if !self.seen_allocs.insert(*id) {
+                continue;
+            }
+            match GlobalAlloc::from(*id) {
+                GlobalAlloc::Memory(alloc) => self.provenance(&alloc),
+                GlobalAlloc::Static(def) => {
+                    self.ty(def.ty());
+                    if let Ok(alloc) = def.eval_initializer() {
+                        self.provenance(&alloc);
+                    }
+                }
+                // A `&dyn Trait` to a `ty`
+                GlobalAlloc::VTable(ty, _) => self.ty(ty),
+                _ => {}
+            }
+        }
+    }
+}
@@ -10,30 +10,52 @@
 //! The subcommand is the same as "cargo build" but with three differences:
 //! 1) It implicitly adds the options "-Z always_encode_mir" to the rustc invocation.
 //! 2) It calls `verifopt` rather than `rustc` for all the targets of the current package.
-//! 3) It runs `cargo test --no-run` for test targets.
+//! 3) It runs `cargo test --no-run` for test targets, and `cargo bench --no-run` for benches.
+//!
+//! Cargo's target selection flags (`-p`, `--bin`, `--example`, `--all-targets`, ...) are
+//! handled here: each selected target gets its own cargo command, whose one `verifopt` call
+//! `call_rustc_or_verifopt` picks out. Every other cargo flag (features, `--profile`,
+//! `--target-dir`, ...) is passed on to those commands as-is.
+//!
+//! Each package's `verifopt.toml` (see monomorph/src/config.rs) is passed on to
+//! `verifopt` in `VERIFOPT_CONFIG`.
 
 #![feature(rustc_private)]
 
 extern crate rustc_driver;
 
-use cargo_metadata::{Package, TargetKind};
+use cargo_metadata::{Metadata, Package, Target, TargetKind};
 use log::info;
 use serde_json;
+use std::collections::HashSet;
 use std::env;
 use std::ffi::OsString;
-use std::ops::Index;
 use std::path::Path;
 use std::process::{Command, Stdio};
 
+use monomorph::config::{CONFIG_ENV, Config};
 use monomorph::util;
 
 /// The help message for `cargo-verifopt`
 const CARGO_VERIFOPT_HELP: &str = r#"Flow-sensitive analysis tool for Rust programs
 Usage:
-    cargo verifopt
+    cargo verifopt [CARGO OPTIONS] [-- VERIFOPT OPTIONS]
+
+Package and target selection (default: the lib, bins and tests of the current package,
+or of every workspace member):
+    -p, --package <name>    Analyze only the given package (repeatable)
+    --workspace             Analyze every workspace member
+    --lib                   Analyze only the library
+    --bin <name>, --bins    Analyze the given binary, or all of them
+    --example <name>, --examples
+    --test <name>, --tests
+    --bench <name>, --benches
+    --all-targets           All of the above, and build scripts
+
+Any other cargo option (--release, --features, --profile, --target-dir, ...) is passed on to cargo.
 "#;
 
-/// Set the environment variable `VERIFOPT_BUILD_STD` to enable the building of std library when running verifopt.
+/// Set the environment variable `VERIFOPT_BUILD_STD` (or `build_std = true` in verifopt.toml) to enable the building of std library when running verifopt.
 const VERIFOPT_BUILD_STD: &str = "VERIFOPT_BUILD_STD";
 
 pub fn main() {
@@ -70,6 +92,199 @@ pub fn main() {
     }
 }
 
+/// `--bins` (all of them) or `--bin a --bin b`, and likewise for examples, tests and benches.
+#[derive(Default)]
+struct Names {
+    all: bool,
+    names: Vec<String>,
+}
+
+impl Names {
+    fn selects(&self, name: &str) -> bool {
+        self.all || self.names.iter().any(|n| n == name)
+    }
+
+    fn is_empty(&self) -> bool {
+        !self.all && self.names.is_empty()
+    }
+}
+
+/// Cargo's target selection flags.
+#[derive(Default)]
+struct TargetSelection {
+    lib: bool,
+    bins: Names,
+    examples: Names,
+    tests: Names,
+    benches: Names,
+    all_targets: bool,
+}
+
+impl TargetSelection {
+    fn selects(&self, target: &Target) -> bool {
+        let kind = target
+            .kind
+            .first()
+            .expect("bad cargo metadata: target::kind");
+        let is_lib = matches!(
+            kind,
+            TargetKind::Lib
+                | TargetKind::RLib
+                | TargetKind::DyLib
+                | TargetKind::CDyLib
+                | TargetKind::StaticLib
+                | TargetKind::ProcMacro
+        );
+
+        if self.all_targets {
+            return true;
+        }
+        let default = !self.lib
+            && self.bins.is_empty()
+            && self.examples.is_empty()
+            && self.tests.is_empty()
+            && self.benches.is_empty();
+        if default {
+            return is_lib || matches!(kind, TargetKind::Bin | TargetKind::Test);
+        }
+
+        match kind {
+            TargetKind::Bin => self.bins.selects(&target.name),
+            TargetKind::Example => self.examples.selects(&target.name),
+            TargetKind::Test => self.tests.selects(&target.name),
+            TargetKind::Bench => self.benches.selects(&target.name),
+            _ => is_lib && self.lib,
+        }
+    }
+}
+
+/// Cargo's feature selection flags, which decide whether a target's `required-features` are on.
+#[derive(Default)]
+struct FeatureSelection {
+    features: Vec<String>,
+    all: bool,
+    no_default: bool,
+}
+
+impl FeatureSelection {
+    fn enables(&self, package: &Package, required: &[String]) -> bool {
+        if self.all {
+            return true;
+        }
+
+        let mut todo: Vec<&str> = self
+            .features
+            .iter()
+            .filter_map(|f| match f.split_once('/') {
+                Some((pkg, feature)) => (pkg == package.name.as_ref() as &str).then_some(feature),
+                None => Some(f.as_str()),
+            })
+            .collect();
+        if !self.no_default {
+            todo.push("default");
+        }
+
+        // Close over what each feature enables in this package
+        let mut enabled = HashSet::new();
+        while let Some(f) = todo.pop() {
+            if !enabled.insert(f) {
+                continue;
+            }
+            if let Some(implied) = package.features.get(f) {
+                todo.extend(
+                    implied
+                        .iter()
+                        .map(String::as_str)
+                        .filter(|i| !i.starts_with("dep:") && !i.contains('/')),
+                );
+            }
+        }
+        required.iter().all(|r| enabled.contains(r.as_str()))
+    }
+}
+
+/// The arguments of `cargo verifopt`.
+#[derive(Default)]
+struct CargoArgs {
+    packages: Vec<String>,
+    workspace: bool,
+    targets: TargetSelection,
+    features: FeatureSelection,
+    /// Passed on to every cargo command as-is
+    cargo: Vec<String>,
+    /// After the first `--`, for `verifopt` (through `VERIFOPT_FLAGS`)
+    verifopt: Vec<String>,
+}
+
+impl CargoArgs {
+    /// `args` are the ones after `cargo-verifopt verifopt`.
+    fn parse(args: impl Iterator<Item = String>) -> Result<CargoArgs, String> {
+        let mut parsed = CargoArgs::default();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.verifopt.extend(args.by_ref());
+                break;
+            }
+
+            // `--flag=value`, `--flag value`, and for short flags `-pvalue`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ if (arg.starts_with("-p") || arg.starts_with("-F")) && arg.len() > 2 => {
+                    (arg[..2].to_string(), Some(arg[2..].to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            // A missing value mustn't swallow the next flag: `--bin --release`
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next_if(|next| !next.starts_with('-')))
+                    .ok_or_else(|| format!("`{}` takes a value", flag))
+            };
+
+            let t = &mut parsed.targets;
+            match flag.as_str() {
+                "-p" | "--package" => parsed.packages.push(value()?),
+                "--workspace" | "--all" => parsed.workspace = true,
+                "--lib" => t.lib = true,
+                "--bins" => t.bins.all = true,
+                "--bin" => t.bins.names.push(value()?),
+                "--examples" => t.examples.all = true,
+                "--example" => t.examples.names.push(value()?),
+                "--tests" => t.tests.all = true,
+                "--test" => t.tests.names.push(value()?),
+                "--benches" => t.benches.all = true,
+                "--bench" => t.benches.names.push(value()?),
+                "--all-targets" => t.all_targets = true,
+                "-F" | "--features" => {
+                    let features = value()?;
+                    parsed.features.features.extend(
+                        features
+                            .split([',', ' '])
+                            .filter(|f| !f.is_empty())
+                            .map(str::to_string),
+                    );
+                    parsed.cargo.push("--features".to_string());
+                    parsed.cargo.push(features);
+                }
+                "--all-features" => {
+                    parsed.features.all = true;
+                    parsed.cargo.push(arg);
+                }
+                "--no-default-features" => {
+                    parsed.features.no_default = true;
+                    parsed.cargo.push(arg);
+                }
+                _ => parsed.cargo.push(arg),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
 /// Read the toml associated with the current directory and
 /// recursively execute cargo for each applicable package target/workspace member in the toml
 fn call_cargo() {
@@ -88,77 +303,143 @@ fn call_cargo() {
         std::process::exit(1);
     };
 
-    // If a binary is specified, analyze this binary only.
-    if let Some(target) = get_arg_flag_value("--bin") {
-        call_cargo_on_target(&target, &TargetKind::Bin);
-        return;
+    let args = CargoArgs::parse(std::env::args().skip(2)).unwrap_or_else(|e| {
+        eprintln!("cargo-verifopt: {}", e);
+        std::process::exit(1);
+    });
+    for package in selected_packages(&metadata, &args) {
+        call_cargo_on_each_package_target(package, &metadata, &args);
     }
+}
 
-    if let Some(root) = metadata.root_package() {
-        call_cargo_on_each_package_target(root);
-        return;
+/// The packages named by `-p`, or with `--workspace` or in a virtual workspace, every member;
+/// otherwise the root package.
+fn selected_packages<'m>(metadata: &'m Metadata, args: &CargoArgs) -> Vec<&'m Package> {
+    let members = metadata.workspace_packages();
+    if !args.packages.is_empty() {
+        return args
+            .packages
+            .iter()
+            .map(|name| {
+                let package = members.iter().find(|p| p.name == *name);
+                *package.unwrap_or_else(|| {
+                    eprintln!("package `{}` is not a member of the workspace", name);
+                    std::process::exit(1);
+                })
+            })
+            .collect();
     }
 
-    // There is no root, this must be a workspace, so call_cargo_on_each_package_target on each workspace member
-    for package_id in &metadata.workspace_members {
-        let package = metadata.index(package_id);
-        call_cargo_on_each_package_target(package);
+    match metadata.root_package() {
+        Some(root) if !args.workspace => vec![root],
+        // There is no root, this must be a workspace, so analyze each workspace member
+        _ => members,
     }
 }
 
-fn call_cargo_on_each_package_target(package: &Package) {
-    let lib_only = has_arg_flag("--lib");
-    for target in &package.targets {
-        let kind = target
-            .kind
-            .first()
-            .expect("bad cargo metadata: target::kind");
-        if lib_only && *kind != TargetKind::Lib {
+/// The `verifopt.toml` that applies to `package`, as `Config::locate` finds it.
+fn package_config(package: &Package, workspace_root: &Path) -> Option<String> {
+    let package_dir = package.manifest_path.parent().unwrap().as_std_path();
+    match Config::locate(package_dir, workspace_root, &package.metadata) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn call_cargo_on_each_package_target(package: &Package, metadata: &Metadata, args: &CargoArgs) {
+    let config = package_config(package, metadata.workspace_root.as_std_path());
+
+    // The build script first: every other target's cargo command compiles it too, and once
+    // it's built (without `verifopt`), its own command would find nothing left to compile.
+    let mut targets: Vec<&Target> = package.targets.iter().collect();
+    targets.sort_by_key(|t| t.kind.first() != Some(&TargetKind::CustomBuild));
+
+    for target in targets {
+        if !args.targets.selects(target) {
+            continue;
+        }
+        if !args.features.enables(package, &target.required_features) {
+            eprintln!(
+                "cargo-verifopt: skipping `{}`, which requires the features {:?}",
+                target.name, target.required_features
+            );
             continue;
         }
-        call_cargo_on_target(&target.name, kind);
+        call_cargo_on_target(package, target, args, config.as_deref());
     }
 }
 
-fn call_cargo_on_target(target: &String, kind: &TargetKind) {
+fn call_cargo_on_target(
+    package: &Package,
+    target: &Target,
+    args: &CargoArgs,
+    config: Option<&str>,
+) {
+    let kind = target
+        .kind
+        .first()
+        .expect("bad cargo metadata: target::kind");
+
     // Build a cargo command for target
     let mut cmd =
         Command::new(std::env::var_os("CARGO").unwrap_or_else(|| OsString::from("cargo")));
     match kind {
         TargetKind::Bin => {
-            cmd.arg("build");
-            if get_arg_flag_value("--bin").is_none() {
-                cmd.arg("--bin").arg(target);
-            }
+            cmd.arg("build").arg("--bin").arg(&target.name);
         }
-        TargetKind::Lib => {
-            cmd.arg("build");
-            cmd.arg("--lib");
+        TargetKind::Example => {
+            cmd.arg("build").arg("--example").arg(&target.name);
         }
         TargetKind::Test => {
-            cmd.arg("test");
-            cmd.arg("--no-run");
+            cmd.arg("test")
+                .arg("--no-run")
+                .arg("--test")
+                .arg(&target.name);
+        }
+        TargetKind::Bench => {
+            cmd.arg("bench")
+                .arg("--no-run")
+                .arg("--bench")
+                .arg(&target.name);
+        }
+        // `check` still compiles (and runs) the build script, but leaves the package's other
+        // targets for their own commands
+        TargetKind::CustomBuild => {
+            cmd.arg("check");
+        }
+        TargetKind::Lib
+        | TargetKind::RLib
+        | TargetKind::DyLib
+        | TargetKind::CDyLib
+        | TargetKind::StaticLib
+        | TargetKind::ProcMacro => {
+            cmd.arg("build").arg("--lib");
         }
         _ => {
+            eprintln!(
+                "cargo-verifopt: skipping `{}`, a {} target",
+                target.name, kind
+            );
             return;
         }
     }
+    cmd.arg("--package").arg(package.name.as_ref() as &str);
     cmd.arg("--verbose");
-
-    let mut args = std::env::args().skip(2);
-    // Add cargo args to cmd until first `--`.
-    for arg in args.by_ref() {
-        if arg == "--" {
-            break;
-        }
-        if arg == "--lib" {
-            continue;
-        }
-        cmd.arg(arg);
-    }
+    // `cargo bench` always uses the optimized `bench` profile, and refuses `--release`
+    cmd.args(
+        args.cargo.iter().filter(|arg| {
+            *kind != TargetKind::Bench || !matches!(arg.as_str(), "--release" | "-r")
+        }),
+    );
 
     // Enable Cargo to compile the standard library from source code as part of a crate graph compilation.
-    if env::var(VERIFOPT_BUILD_STD).is_ok() {
+    let build_std = config
+        .and_then(|c| Config::parse(c, CONFIG_ENV).ok())
+        .is_some_and(|c| c.build_std);
+    if build_std || env::var(VERIFOPT_BUILD_STD).is_ok() {
         cmd.arg("-Zbuild-std");
 
         if !has_arg_flag("--target") {
@@ -167,15 +448,18 @@ fn call_cargo_on_target(target: &String, kind: &TargetKind) {
         }
     }
 
-    // Serialize the remaining args into an environment variable.
-    let args_vec: Vec<String> = args.collect();
-    if !args_vec.is_empty() {
+    // Serialize the args after `--` into an environment variable.
+    if !args.verifopt.is_empty() {
         cmd.env(
             "VERIFOPT_FLAGS",
-            serde_json::to_string(&args_vec).expect("failed to serialize args"),
+            serde_json::to_string(&args.verifopt).expect("failed to serialize args"),
         );
     }
 
+    if let Some(config) = config {
+        cmd.env(CONFIG_ENV, config);
+    }
+
     // Force cargo to recompile all dependencies with VerifOpt friendly flags
     cmd.env("RUSTFLAGS", "-Z always_encode_mir");
 
@@ -186,11 +470,21 @@ fn call_cargo_on_target(target: &String, kind: &TargetKind) {
 
     // Communicate the name of the root crate to the calls to cargo-verifopt that are invoked via
     // the RUSTC_WRAPPER setting.
-    cmd.env("VERIFOPT_CRATE", target.replace('-', "_"));
+    cmd.env("VERIFOPT_CRATE", target.name.replace('-', "_"));
 
-    // Communicate the target kind of the root crate to the calls to cargo-verifopt that are invoked via
-    // the RUSTC_WRAPPER setting.
+    // Communicate the target kind and crate types of the root crate, and its package, to the
+    // calls to cargo-verifopt that are invoked via the RUSTC_WRAPPER setting.
     cmd.env("VERIFOPT_TARGET_KIND", kind.to_string());
+    cmd.env(
+        "VERIFOPT_CRATE_TYPES",
+        target
+            .crate_types
+            .iter()
+            .map(|t| t.to_string())
+            .collect::<Vec<_>>()
+            .join(","),
+    );
+    cmd.env("VERIFOPT_PACKAGE", package.name.as_ref() as &str);
 
     // Set the tool chain to be compatible with verifopt
     if let Some(toolchain) = option_env!("RUSTUP_TOOLCHAIN") {
@@ -211,24 +505,39 @@ fn call_cargo_on_target(target: &String, kind: &TargetKind) {
 }
 
 fn call_rustc_or_verifopt() {
-    if let Some(crate_name) = get_arg_flag_value("--crate-name") {
-        if let Ok(verifopt_crate) = std::env::var("VERIFOPT_CRATE") {
-            if crate_name.eq(&verifopt_crate) {
-                if let Ok(kind) = std::env::var("VERIFOPT_TARGET_KIND") {
-                    if let Some(t) = get_arg_flag_value("--crate-type") {
-                        if kind.eq(&t) {
-                            call_verifopt();
-                            return;
-                        }
-                    } else if kind == "test" {
-                        call_verifopt();
-                        return;
-                    }
-                }
-            }
-        }
+    if is_verifopt_target() {
+        call_verifopt();
+    } else {
+        call_rustc()
+    }
+}
+
+/// Whether this rustc call compiles the target `call_cargo_on_target` was run for: the right
+/// crate name and package (every build script is `build_script_build`), and one of its crate
+/// types, or for a test or bench target, no crate type at all.
+fn is_verifopt_target() -> bool {
+    let (Some(crate_name), Ok(verifopt_crate), Ok(kind)) = (
+        get_arg_flag_value("--crate-name"),
+        std::env::var("VERIFOPT_CRATE"),
+        std::env::var("VERIFOPT_TARGET_KIND"),
+    ) else {
+        return false;
+    };
+    if crate_name != verifopt_crate {
+        return false;
+    }
+    if let Ok(package) = std::env::var("VERIFOPT_PACKAGE")
+        && std::env::var("CARGO_PKG_NAME").is_ok_and(|p| p != package)
+    {
+        return false;
+    }
+
+    match get_arg_flag_value("--crate-type") {
+        Some(t) => std::env::var("VERIFOPT_CRATE_TYPES")
+            .is_ok_and(|types| types.split(',').any(|ty| ty == t)),
+        // A test or bench target's binary: `--test` with the harness, neither flag without
+        None => kind == "test" || kind == "bench",
     }
-    call_rustc()
 }
 
 fn call_verifopt() {
@@ -325,3 +634,8 @@ fn toolchain_target() -> Option<String> {
 
     target
 }
+
+// In a directory of its own: straight in src/bin/, cargo would take it for a bin
+#[cfg(test)]
+#[path = "cargo-verifopt/cargo_verifopt_tests.rs"]
+mod cargo_verifopt_tests;
@@ -0,0 +1,90 @@
+use super::*;
+
+fn parse(args: &[&str]) -> Result<CargoArgs, String> {
+    CargoArgs::parse(args.iter().map(|a| a.to_string()))
+}
+
+fn target(name: &str, kind: &str) -> Target {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "kind": [kind],
+        "crate_types": ["bin"],
+        "src_path": format!("/pkg/src/{}.rs", name),
+        "edition": "2024",
+        "doctest": false,
+        "test": true,
+        "doc": false,
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_parse_targets() {
+    let args = parse(&[
+        "--example",
+        "tally",
+        "--bench=speed",
+        "-papp",
+        "--release",
+        "--",
+        "--cost-model",
+        "off",
+    ])
+    .unwrap();
+    assert_eq!(args.targets.examples.names, ["tally"]);
+    assert_eq!(args.targets.benches.names, ["speed"]);
+    assert_eq!(args.packages, ["app"]);
+    assert_eq!(args.cargo, ["--release"]);
+    assert_eq!(args.verifopt, ["--cost-model", "off"]);
+}
+
+#[test]
+fn test_parse_features() {
+    let args = parse(&["-F", "simd,std", "--no-default-features"]).unwrap();
+    assert_eq!(args.features.features, ["simd", "std"]);
+    assert!(args.features.no_default);
+    assert_eq!(
+        args.cargo,
+        ["--features", "simd,std", "--no-default-features"]
+    );
+}
+
+#[test]
+fn test_parse_missing_value() {
+    // Not `--release` as the bin's name
+    assert!(parse(&["--bin", "--release"]).is_err());
+    assert!(parse(&["--example"]).is_err());
+    assert!(parse(&["--test", "--", "--cost-model", "off"]).is_err());
+    assert!(parse(&["-p", "-F", "simd"]).is_err());
+    assert!(parse(&["--bench", "speed", "--release"]).is_ok());
+}
+
+#[test]
+fn test_selects_default() {
+    let selection = TargetSelection::default();
+    assert!(selection.selects(&target("app", "bin")));
+    assert!(selection.selects(&target("app", "lib")));
+    assert!(selection.selects(&target("it", "test")));
+    assert!(!selection.selects(&target("tally", "example")));
+    assert!(!selection.selects(&target("speed", "bench")));
+    assert!(!selection.selects(&target("build-script-build", "custom-build")));
+}
+
+#[test]
+fn test_selects_named() {
+    let selection = parse(&["--example", "tally", "--benches"]).unwrap().targets;
+    assert!(selection.selects(&target("tally", "example")));
+    assert!(!selection.selects(&target("other", "example")));
+    assert!(selection.selects(&target("speed", "bench")));
+    assert!(!selection.selects(&target("app", "bin")));
+    assert!(!selection.selects(&target("app", "lib")));
+    assert!(!selection.selects(&target("build-script-build", "custom-build")));
+}
+
+#[test]
+fn test_selects_all_targets() {
+    let selection = parse(&["--all-targets"]).unwrap().targets;
+    assert!(selection.selects(&target("tally", "example")));
+    assert!(selection.selects(&target("speed", "bench")));
+    assert!(selection.selects(&target("build-script-build", "custom-build")));
+}
@@ -0,0 +1,88 @@
+//! Checks runtime dispatch traces against the analysis' FSA sets.
+//!
+//! Build the crate with `cargo verifopt` and `--trace-dispatch` in
+//! `VERIFOPT_FLAGS`, run it (or its test suite) with stderr going to a file,
+//! then:
+//!
+//! ```sh
+//! verifopt-check dispatch_sets trace.log [more.log ...]
+//! ```
+//!
+//! Exits non-zero if any observed target wasn't predicted. See
+//! `monomorph::trace` for the formats and what each violation means.
+
+#![feature(rustc_private)]
+
+extern crate rustc_driver;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+
+use monomorph::trace::{self, Violation};
+
+const USAGE: &str = "Usage: verifopt-check <dispatch_sets> <trace>...";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 2 || args.iter().any(|a| a == "--help" || a == "-h") {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+
+    let sets = trace::read_sets(&args[0]).unwrap_or_else(|e| {
+        eprintln!("can't read {}: {}", args[0], e);
+        std::process::exit(2);
+    });
+
+    let mut hits = BTreeMap::new();
+    for path in &args[1..] {
+        let f = File::open(path).unwrap_or_else(|e| {
+            eprintln!("can't read {}: {}", path, e);
+            std::process::exit(2);
+        });
+        if let Err(e) = trace::read_trace(BufReader::new(f), &mut hits) {
+            eprintln!("can't read {}: {}", path, e);
+            std::process::exit(2);
+        }
+    }
+
+    let violations = trace::check(&sets, &hits);
+
+    println!(
+        "{} dyn call sites observed, {} (site, impl) pairs, {} violations",
+        hits.keys()
+            .map(|(site, _)| site)
+            .collect::<std::collections::HashSet<_>>()
+            .len(),
+        hits.len(),
+        violations.len()
+    );
+
+    for (v, n) in &violations {
+        match v {
+            Violation::NotInFsa { site, impl_path } => {
+                println!(
+                    "SOUNDNESS: {} called {} ({}x), not in FSA set",
+                    site, impl_path, n
+                )
+            }
+            Violation::NotInCha { site } => {
+                println!(
+                    "CHA MISS: {} called an impl no CHA candidate matched ({}x)",
+                    site, n
+                )
+            }
+            Violation::Unanalyzed { site, impl_path } => {
+                println!(
+                    "UNANALYZED: {} called {} ({}x), site never reached by analysis",
+                    site, impl_path, n
+                )
+            }
+        }
+    }
+
+    if !violations.is_empty() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,292 @@
+//! Differential execution of a crate's original and rewritten builds.
+//!
+//! ```sh
+//! verifopt-diff [--manifest-path <Cargo.toml>] [--tests] [-- <args>...]
+//! ```
+//!
+//! Builds the crate with `cargo verifopt --release` twice, with and without
+//! `--no-rewrite`, into `target/verifopt-diff/{original,rewritten}`, then runs
+//! each binary from both builds with `<args>` (or, with `--tests`, each test
+//! binary with `--test-threads=1` and `<args>`) and reports every difference
+//! in exit status, stdout, stderr or votrace's `calls` file, along with the
+//! functions the rewriter touched. Exits 1 on any divergence, 2 if either
+//! build fails. See `monomorph::diff`.
+
+#![feature(rustc_private)]
+
+extern crate rustc_driver;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use monomorph::config::{CONFIG_ENV, Config, STATS_FILE};
+use monomorph::diff::{self, Outcome, REWRITTEN_FILE};
+
+const USAGE: &str = "Usage: verifopt-diff [--manifest-path <Cargo.toml>] [--tests] [-- <args>...]";
+
+/// Files the analysis writes next to its `stats` file, cleared along with
+/// it before each build so a stale one can't leak into the report.
+const REPORT_FILES: [&str; 3] = ["found_ex", "notfound_ex", REWRITTEN_FILE];
+/// votrace's output in the crate dir, cleared before each run
+const CALLS_FILE: &str = "calls";
+
+struct Artifact {
+    test: bool,
+    exe: PathBuf,
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut manifest_path = None;
+    let mut tests = false;
+    let mut run_args = Vec::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--manifest-path" => manifest_path = args.next(),
+            "--tests" => tests = true,
+            "--" => {
+                run_args.extend(args.by_ref());
+            }
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => {
+                eprintln!("unexpected argument {:?}\n{}", arg, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let manifest_path = manifest_path.map(|m| {
+        Path::new(&m).canonicalize().unwrap_or_else(|e| {
+            eprintln!("bad --manifest-path {}: {}", m, e);
+            std::process::exit(2);
+        })
+    });
+    let crate_dir = match &manifest_path {
+        Some(m) => m.parent().unwrap().to_path_buf(),
+        None => std::env::current_dir().unwrap(),
+    };
+
+    let stats_files = stats_files(&crate_dir, manifest_path.as_deref());
+
+    let original = build(
+        &crate_dir,
+        manifest_path.as_deref(),
+        &stats_files,
+        "original",
+        true,
+    );
+    let rewritten = build(
+        &crate_dir,
+        manifest_path.as_deref(),
+        &stats_files,
+        "rewritten",
+        false,
+    );
+
+    let mut rewritten_fns: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for stats in &stats_files {
+        let path = diff::rewritten_path(&stats.to_string_lossy());
+        for (f, sites) in diff::read_rewritten(&path.to_string_lossy()).unwrap_or_default() {
+            rewritten_fns.entry(f).or_default().extend(sites);
+        }
+    }
+
+    let mut divergences = Vec::new();
+    let mut runs = 0;
+
+    for (key, orig) in &original {
+        if orig.test != tests {
+            continue;
+        }
+        let Some(rw) = rewritten.get(key) else {
+            println!("{}: only built without rewriting", key);
+            divergences.push(key.clone());
+            continue;
+        };
+
+        let mut exe_args = Vec::new();
+        if tests {
+            exe_args.push("--test-threads=1".to_string());
+        }
+        exe_args.extend(run_args.iter().cloned());
+
+        let a = run(&crate_dir, &orig.exe, &exe_args);
+        let b = run(&crate_dir, &rw.exe, &exe_args);
+        runs += 1;
+
+        for d in diff::compare(key, &a, &b) {
+            println!("{}", d);
+            divergences.push(key.clone());
+        }
+    }
+
+    for (key, _) in rewritten
+        .iter()
+        .filter(|(k, rw)| rw.test == tests && !original.contains_key(*k))
+    {
+        println!("{}: only built with rewriting", key);
+        divergences.push(key.clone());
+    }
+
+    println!(
+        "{} {} compared, {} rewritten fns, {} divergences",
+        runs,
+        if tests { "test binaries" } else { "binaries" },
+        rewritten_fns.len(),
+        divergences.len()
+    );
+
+    if divergences.is_empty() {
+        return;
+    }
+
+    println!("\nrewritten fns:");
+    for (f, sites) in &rewritten_fns {
+        println!("  {}", f);
+        for site in sites {
+            println!("    {}", site);
+        }
+    }
+
+    std::process::exit(1);
+}
+
+/// The `stats` file of each package `cargo verifopt` analyzes (the root
+/// package, or every member of a virtual workspace): its config's
+/// `[report] stats`, found and made absolute the way `cargo verifopt` does
+/// it, or the default, relative to the workspace root cargo runs rustc in.
+/// The rewriting build writes `REWRITTEN_FILE` next to it.
+fn stats_files(crate_dir: &Path, manifest_path: Option<&Path>) -> BTreeSet<PathBuf> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    cmd.no_deps().current_dir(crate_dir);
+    if let Some(m) = manifest_path {
+        cmd.manifest_path(m);
+    }
+    let metadata = cmd.exec().unwrap_or_else(|e| {
+        eprintln!("could not obtain cargo metadata: {}", e);
+        std::process::exit(2);
+    });
+
+    let workspace_root = metadata.workspace_root.as_std_path();
+    let packages = match metadata.root_package() {
+        Some(root) => vec![root],
+        None => metadata.workspace_packages(),
+    };
+
+    packages
+        .into_iter()
+        .map(|package| {
+            let package_dir = package.manifest_path.parent().unwrap().as_std_path();
+            let config = Config::locate(package_dir, workspace_root, &package.metadata)
+                .unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                })
+                .and_then(|text| Config::parse(&text, CONFIG_ENV).ok());
+            let stats = config
+                .and_then(|c| c.stats)
+                .unwrap_or_else(|| STATS_FILE.to_string());
+            workspace_root.join(stats)
+        })
+        .collect()
+}
+
+/// Builds the crate through the sibling `cargo-verifopt` binary into its own
+/// target dir and returns its executables by `<kind>:<target name>`, which
+/// (unlike the file names) is the same in both builds.
+fn build(
+    crate_dir: &Path,
+    manifest_path: Option<&Path>,
+    stats_files: &BTreeSet<PathBuf>,
+    variant: &str,
+    no_rewrite: bool,
+) -> BTreeMap<String, Artifact> {
+    for stats in stats_files {
+        let _ = fs::remove_file(stats);
+        for f in REPORT_FILES {
+            let _ = fs::remove_file(stats.with_file_name(f));
+        }
+    }
+
+    let mut path = std::env::current_exe().expect("current executable path invalid");
+    let extension = path.extension().map(|e| e.to_owned());
+    path.pop();
+    path.push("cargo-verifopt");
+    if let Some(ext) = extension {
+        path.set_extension(ext);
+    }
+
+    let mut cmd = Command::new(path);
+    cmd.arg("verifopt")
+        .arg("--release")
+        .arg("--message-format=json-render-diagnostics");
+    if let Some(m) = manifest_path {
+        cmd.arg("--manifest-path").arg(m);
+    }
+    if no_rewrite {
+        cmd.arg("--").arg("--no-rewrite");
+    }
+    cmd.current_dir(crate_dir)
+        .env(
+            "CARGO_TARGET_DIR",
+            crate_dir.join("target/verifopt-diff").join(variant),
+        )
+        .stdout(Stdio::piped());
+
+    let output = cmd.output().expect("could not run cargo-verifopt");
+    if !output.status.success() {
+        eprintln!("{} build failed", variant);
+        std::process::exit(2);
+    }
+
+    let mut artifacts = BTreeMap::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Ok(msg) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        if msg["reason"] != "compiler-artifact" {
+            continue;
+        }
+        let Some(exe) = msg["executable"].as_str() else {
+            continue;
+        };
+        let test = msg["profile"]["test"].as_bool().unwrap_or(false);
+        let kind = msg["target"]["kind"][0].as_str().unwrap_or("?");
+        let name = msg["target"]["name"].as_str().unwrap_or("?");
+        let key = format!("{}:{}", if test { "test" } else { kind }, name);
+        artifacts.insert(
+            key,
+            Artifact {
+                test,
+                exe: PathBuf::from(exe),
+            },
+        );
+    }
+
+    artifacts
+}
+
+fn run(crate_dir: &Path, exe: &Path, args: &[String]) -> Outcome {
+    let calls = crate_dir.join(CALLS_FILE);
+    let _ = fs::remove_file(&calls);
+
+    let output = Command::new(exe)
+        .args(args)
+        .current_dir(crate_dir)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap_or_else(|e| panic!("could not run {:?}: {}", exe, e));
+
+    Outcome {
+        status: output.status.code(),
+        stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        calls: fs::read_to_string(&calls).ok(),
+    }
+}
@@ -20,25 +20,40 @@ use log::*;
 use std::env;
 use std::io::Write;
 
+use monomorph::annotations;
+use monomorph::config::{CONFIG_ENV, Config};
 use monomorph::rewrite::{FsaCallbacks, RewriteCallbacks};
 use monomorph::util;
 use monomorph::util::options::AnalysisOptions;
 
+/// Plain rustc, for `--no-rewrite` builds.
+struct PlainCallbacks;
+
+impl rustc_driver::Callbacks for PlainCallbacks {}
+
 fn main() {
     let early_dcx =
         rustc_session::EarlyDiagCtxt::new(rustc_session::config::ErrorOutputType::default());
 
+    // The verifopt.toml `cargo verifopt` found, if any
+    let config = match env::var(CONFIG_ENV) {
+        Ok(text) => Config::parse(&text, CONFIG_ENV)
+            .unwrap_or_else(|e| early_dcx.early_fatal(e.to_string())),
+        Err(_) => Config::default(),
+    };
+
     // Initialize loggers.
     if env::var("RUSTC_LOG").is_ok() {
         rustc_driver::init_rustc_env_logger(&early_dcx);
     }
-    if env::var("VERIFOPT_LOG").is_ok() {
+    if env::var("VERIFOPT_LOG").is_ok() || config.log.is_some() {
         env_logger::Builder::new()
             .format(|buf, record| {
                 //writeln!(buf, "{}: {}", record.level(), record.args())
                 //writeln!(buf, "{}: {}", record.file().unwrap(), record.args())
                 writeln!(buf, "{}", record.args())
             })
+            .parse_filters(config.log.as_deref().unwrap_or_default())
             .parse_env(
                 env_logger::Env::new()
                     .filter("VERIFOPT_LOG")
@@ -49,6 +64,7 @@ fn main() {
 
     // Get any options specified via the VERIFOPT_FLAGS environment variable
     let mut options = AnalysisOptions::default();
+    options.apply_config(&config);
     let pta_flags = env::var("VERIFOPT_FLAGS").unwrap_or_default();
     let pta_args: Vec<String> = serde_json::from_str(&pta_flags).unwrap_or_default();
     let rustc_args = options.parse_from_args(&pta_args[..], true);
@@ -105,6 +121,11 @@ fn main() {
             rustc_command_line_arguments.push("-Z".into());
             rustc_command_line_arguments.push(always_encode_mir);
         }
+
+        // Let the `verifopt` crate's attributes (`#[verifopt::closed]`, ...)
+        // expand to tool attributes we can read. The `--no-rewrite` baseline
+        // gets them too, so both builds see the same code.
+        rustc_command_line_arguments.extend(annotations::RUSTC_FLAGS.iter().map(|f| f.to_string()));
         debug!(
             "rustc command line arguments: {:?}",
             rustc_command_line_arguments
@@ -113,6 +134,11 @@ fn main() {
         //let mut callbacks = VerifOptCallbacks::new(options);
         //let compiler = rustc_driver::RunCompiler::new(&rustc_command_line_arguments, &mut callbacks);
         //compiler.run()
+        if options.no_rewrite {
+            rustc_driver::run_compiler(&rustc_command_line_arguments, &mut PlainCallbacks);
+            return;
+        }
+
         let mut callbacks = FsaCallbacks { options };
         rustc_driver::catch_fatal_errors(|| {
             rustc_driver::run_compiler(&rustc_command_line_arguments, &mut callbacks);
@@ -0,0 +1,512 @@
+//! `Option`/`Result` combinators and the `?` operator.
+//!
+//! `Option` and `Result` values are plain `Adt` constraints (see
+//! `WrapperKind`), so the combinators' real bodies already work - but each
+//! one is a `match` whose arms are merged back together, and the closure
+//! argument goes through the generic closure path with whatever its
+//! operand resolves to. Modeling them directly instead splits the receiver
+//! by variant and feeds exactly the `Some`/`Ok` (or `Err`) payload to the
+//! closure, via `interp_callable_with`, so a `Box<dyn Trait>` that goes
+//! through `opt.map(..).unwrap()` comes out with the same type set it went
+//! in with.
+//!
+//! `?` desugars to `Try::branch` on the operand - `Continue(x)` or
+//! `Break(residual)` - and `FromResidual::from_residual` on the way out of
+//! the enclosing fn. `branch` is modeled here. `from_residual` is too when
+//! the error type doesn't change (`Option`, or the same `E`) or becomes a
+//! `Box<dyn Error>` (see `dyn_error`); otherwise its real body runs, so
+//! the `From::from` conversion is interpreted.
+//!
+//! A receiver that isn't a known `Option`/`Result` value (a parametric
+//! placeholder, or anything else we can't split) falls back to the real
+//! body, same as an unlisted method.
+
+use crate::rustc_public_bridge::IndexedVal;
+use rustc_public::mir::{LocalDecl, Operand, Place};
+use rustc_public::ty::{
+    FnDef, GenericArgKind, GenericArgs, RigidTy, Span, Ty, TyKind, VariantDef, VariantIdx,
+};
+
+use crate::Context;
+use crate::InterpPass;
+use crate::constraints::{ADTFields, Constraint, Constraints, Location, RunningConstraint, VOID};
+use crate::convert::WrapperKind;
+use crate::error::Error;
+use crate::interior::instantiated_sig;
+
+use log::debug;
+
+/// An `Option`/`Result` value split by variant. Each arm is None when the
+/// value can't be in that variant; `miss` is always empty for `Option`.
+struct Arms {
+    result: bool,
+    /// the `Some`/`Ok` payload
+    hit: Option<Constraints>,
+    /// the `Err` payload, or just "may be `None`"
+    miss: Option<Constraints>,
+}
+
+impl Arms {
+    fn hit_name(&self) -> &'static str {
+        if self.result { "Ok" } else { "Some" }
+    }
+
+    fn miss_name(&self) -> &'static str {
+        if self.result { "Err" } else { "None" }
+    }
+
+    /// What a rebuilt `miss` variant carries: `None` has no field.
+    fn miss_payload(&self) -> Option<Constraints> {
+        if self.result { self.miss.clone() } else { None }
+    }
+}
+
+impl<'a> InterpPass<'a> {
+    /// Called from `stub_higher_order`: Some(result) if this is an
+    /// `Option`/`Result` method (or `Try::branch`/
+    /// `FromResidual::from_residual` on one) modeled here.
+    pub fn stub_combinator(
+        &self,
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let path = Self::callee_path(fndef);
+        let inherent = path.contains("option::Option") || path.contains("result::Result");
+        if !inherent
+            && !path.ends_with("Try::branch")
+            && !path.ends_with("FromResidual::from_residual")
+        {
+            return None;
+        }
+        let out_ty = instantiated_sig(fndef, genargs)?.value.output();
+
+        if path.ends_with("from_residual") {
+            return self
+                .stub_from_residual(ctxt, caller_scope, term_span, local_decls, args, out_ty)
+                .map(|c| Ok(Some(c)));
+        }
+
+        self.stub_combinator_method(
+            term_span,
+            ctxt,
+            call_stack,
+            caller_scope,
+            local_decls,
+            &Self::method_name(fndef),
+            args,
+            out_ty,
+        )
+        .transpose()
+        .map(|r| r.map(Some))
+    }
+
+    /// Ok(None) if `method` isn't modeled (or its receiver can't be split),
+    /// decided before anything is interpreted.
+    fn stub_combinator_method(
+        &self,
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        method: &str,
+        args: &Vec<Operand>,
+        out_ty: Ty,
+    ) -> Result<Option<Constraints>, Error> {
+        let Some(recv_op) = args.first() else {
+            return Ok(None);
+        };
+        let Ok(mut recv_ty) = recv_op.ty(local_decls) else {
+            return Ok(None);
+        };
+        while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = recv_ty.kind() {
+            recv_ty = inner;
+        }
+        let recv = self.resolve_arg(
+            ctxt,
+            term_span,
+            caller_scope,
+            &None,
+            local_decls,
+            recv_op,
+            false,
+        );
+        let Some(arms) = self.split_arms(caller_scope, recv_ty, &recv) else {
+            return Ok(None);
+        };
+        debug!(
+            "COMBINATOR {} hit={} miss={}",
+            method,
+            arms.hit.is_some(),
+            arms.miss.is_some()
+        );
+
+        // the closure or value argument, if any
+        let arg1 = args
+            .get(1)
+            .map(|op| {
+                self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false)
+            })
+            .unwrap_or_default();
+
+        // Fn items (`.map(Some)`, `.map(Box::new)`) and fn pointers keep
+        // going through the real body, where the call is resolved the
+        // usual way; only closures are called from here.
+        let takes_fn = matches!(
+            method,
+            "map" | "map_err" | "and_then" | "unwrap_or_else" | "ok_or_else"
+        );
+        if takes_fn
+            && (arg1.inner.is_empty()
+                || !arg1
+                    .inner
+                    .iter()
+                    .all(|c| matches!(c.cfc, Some(RunningConstraint::Closure(..)))))
+        {
+            return Ok(None);
+        }
+
+        let mut out = Constraints::new();
+        match method {
+            "map" => {
+                let Some(u) = type_arg(out_ty, 0) else {
+                    return Ok(None);
+                };
+                if let Some(x) = arms.hit.clone() {
+                    let y = self.interp_callable_with(
+                        ctxt,
+                        call_stack,
+                        caller_scope,
+                        &arg1,
+                        vec![x],
+                        &u,
+                    )?;
+                    out.append(self.build_variant(caller_scope, out_ty, arms.hit_name(), Some(y)));
+                }
+                if arms.miss.is_some() {
+                    out.append(self.build_variant(
+                        caller_scope,
+                        out_ty,
+                        arms.miss_name(),
+                        arms.miss_payload(),
+                    ));
+                }
+            }
+            "map_err" if arms.result => {
+                let Some(f) = type_arg(out_ty, 1) else {
+                    return Ok(None);
+                };
+                if let Some(x) = arms.hit.clone() {
+                    out.append(self.build_variant(caller_scope, out_ty, "Ok", Some(x)));
+                }
+                if let Some(e) = arms.miss.clone() {
+                    let e = self.interp_callable_with(
+                        ctxt,
+                        call_stack,
+                        caller_scope,
+                        &arg1,
+                        vec![e],
+                        &f,
+                    )?;
+                    out.append(self.build_variant(caller_scope, out_ty, "Err", Some(e)));
+                }
+            }
+            "and_then" => {
+                if let Some(x) = arms.hit.clone() {
+                    out.append(self.interp_callable_with(
+                        ctxt,
+                        call_stack,
+                        caller_scope,
+                        &arg1,
+                        vec![x],
+                        &out_ty,
+                    )?);
+                }
+                if arms.miss.is_some() {
+                    out.append(self.build_variant(
+                        caller_scope,
+                        out_ty,
+                        arms.miss_name(),
+                        arms.miss_payload(),
+                    ));
+                }
+            }
+            "unwrap_or_else" => {
+                if let Some(x) = arms.hit.clone() {
+                    out.append(x);
+                }
+                if let Some(e) = arms.miss.clone() {
+                    let inputs = if arms.result { vec![e] } else { vec![] };
+                    out.append(self.interp_callable_with(
+                        ctxt,
+                        call_stack,
+                        caller_scope,
+                        &arg1,
+                        inputs,
+                        &out_ty,
+                    )?);
+                }
+            }
+            "unwrap_or" => {
+                if let Some(x) = arms.hit.clone() {
+                    out.append(x);
+                }
+                if arms.miss.is_some() {
+                    out.append(arg1);
+                }
+            }
+            "unwrap" | "expect" | "unwrap_unchecked" => {
+                if let Some(x) = arms.hit.clone() {
+                    out.append(x);
+                }
+            }
+            "unwrap_err" | "expect_err" if arms.result => {
+                if let Some(e) = arms.miss.clone() {
+                    out.append(e);
+                }
+            }
+            "ok_or" | "ok_or_else" if !arms.result => {
+                let Some(err_ty) = type_arg(out_ty, 1) else {
+                    return Ok(None);
+                };
+                if let Some(x) = arms.hit.clone() {
+                    out.append(self.build_variant(caller_scope, out_ty, "Ok", Some(x)));
+                }
+                if arms.miss.is_some() {
+                    let e = if method == "ok_or" {
+                        arg1
+                    } else {
+                        self.interp_callable_with(
+                            ctxt,
+                            call_stack,
+                            caller_scope,
+                            &arg1,
+                            vec![],
+                            &err_ty,
+                        )?
+                    };
+                    out.append(self.build_variant(caller_scope, out_ty, "Err", Some(e)));
+                }
+            }
+            "ok" | "err" if arms.result => {
+                let kept = if method == "ok" {
+                    &arms.hit
+                } else {
+                    &arms.miss
+                };
+                if let Some(x) = kept.clone() {
+                    out.append(self.build_variant(caller_scope, out_ty, "Some", Some(x)));
+                }
+                out.append(self.build_variant(caller_scope, out_ty, "None", None));
+            }
+            // Refs are transparent, so `Option<&T>` holds `T`'s constraints -
+            // only the Adt's own type changes.
+            "as_ref" | "as_mut" => {
+                if let Some(x) = arms.hit.clone() {
+                    out.append(self.build_variant(caller_scope, out_ty, arms.hit_name(), Some(x)));
+                }
+                if arms.miss.is_some() {
+                    out.append(self.build_variant(
+                        caller_scope,
+                        out_ty,
+                        arms.miss_name(),
+                        arms.miss_payload(),
+                    ));
+                }
+            }
+            // Strong updates through the `&mut self`, same as an assignment.
+            "take" | "replace" if !arms.result => {
+                let (Operand::Copy(place) | Operand::Move(place)) = recv_op else {
+                    return Ok(None);
+                };
+                let new = if method == "take" {
+                    self.build_variant(caller_scope, recv_ty, "None", None)
+                } else {
+                    self.build_variant(caller_scope, recv_ty, "Some", Some(arg1))
+                };
+                self.write_through_receiver(ctxt, caller_scope, place, new);
+                out.append(recv);
+            }
+            "branch" => {
+                let Some(residual_ty) = type_arg(out_ty, 0) else {
+                    return Ok(None);
+                };
+                if let Some(x) = arms.hit.clone() {
+                    out.append(self.build_variant(caller_scope, out_ty, "Continue", Some(x)));
+                }
+                if arms.miss.is_some() {
+                    let residual = self.build_variant(
+                        caller_scope,
+                        residual_ty,
+                        arms.miss_name(),
+                        arms.miss_payload(),
+                    );
+                    out.append(self.build_variant(caller_scope, out_ty, "Break", Some(residual)));
+                }
+            }
+            _ => return Ok(None),
+        }
+
+        Ok(Some(out))
+    }
+
+    /// `?`'s exit: `Option<Infallible>::None` -> `None`, and
+    /// `Result<Infallible, E>::Err(e)` -> `Err(e)` when the fn's own error
+    /// type is `E` too, or `Err(Box<dyn Error>)` when it's that (see
+    /// `box_dyn_error`). None (run the real body, which calls `From::from`)
+    /// for any other change of error type.
+    fn stub_from_residual(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        args: &Vec<Operand>,
+        out_ty: Ty,
+    ) -> Option<Constraints> {
+        let residual_op = args.first()?;
+        let residual_ty = residual_op.ty(local_decls).ok()?;
+        let residual = self.resolve_arg(
+            ctxt,
+            term_span,
+            caller_scope,
+            &None,
+            local_decls,
+            residual_op,
+            false,
+        );
+        let arms = self.split_arms(caller_scope, residual_ty, &residual)?;
+        if arms.result {
+            let (from, to) = (type_arg(residual_ty, 1)?, type_arg(out_ty, 1)?);
+            if from != to {
+                let boxed = self.box_dyn_error(caller_scope, from, arms.miss?, to)?;
+                return Some(self.build_variant(caller_scope, out_ty, "Err", Some(boxed)));
+            }
+        }
+        Some(self.build_variant(caller_scope, out_ty, arms.miss_name(), arms.miss_payload()))
+    }
+
+    /// None if `ty` isn't `Option`/`Result` or any disjunct of `value`
+    /// isn't one of its variants.
+    fn split_arms(&self, scope: &VOID, ty: Ty, value: &Constraints) -> Option<Arms> {
+        let TyKind::RigidTy(RigidTy::Adt(adtdef, _)) = ty.kind() else {
+            return None;
+        };
+        let result = match self.converter.wrapper_kind(&adtdef)? {
+            WrapperKind::Option => false,
+            WrapperKind::Result => true,
+            _ => return None,
+        };
+        let hit_idx = variant_idx(&adtdef.variants(), if result { "Ok" } else { "Some" })?;
+        let hit_ty = type_arg(ty, 0)?;
+        let miss_ty = if result { type_arg(ty, 1) } else { None };
+        let payload_fallback = |ty: Ty| {
+            let (_, constraint) =
+                self.converter
+                    .convert_ty(&Location::unknown(), &ty, Some(scope), Some(self));
+            Constraints::from(constraint)
+        };
+
+        let mut arms = Arms {
+            result,
+            hit: None,
+            miss: None,
+        };
+        for c in value.inner.iter() {
+            let Some(RunningConstraint::Adt(def, _, variant, fields)) = &c.cfc else {
+                return None;
+            };
+            if def.0 != adtdef.0 {
+                return None;
+            }
+            // a value of unknown variant could be either one
+            let hit = (*variant).is_none_or(|v| v == hit_idx);
+            let miss = (*variant).is_none_or(|v| v != hit_idx);
+            let field = fields.get(&0).cloned();
+            if hit {
+                let payload = field.clone().unwrap_or_else(|| payload_fallback(hit_ty));
+                arms.hit.get_or_insert_default().append(payload);
+            }
+            if miss {
+                let slot = arms.miss.get_or_insert_default();
+                if let Some(miss_ty) = miss_ty {
+                    slot.append(field.unwrap_or_else(|| payload_fallback(miss_ty)));
+                }
+            }
+        }
+
+        Some(arms)
+    }
+
+    /// A `ty` (an enum) value in variant `name`, carrying `payload` in
+    /// field 0. Uses `ty`'s real AdtDef/GenericArgs, like `wrap_in_option`.
+    /// Falls back to everything `ty` allows if it somehow isn't one.
+    pub(crate) fn build_variant(
+        &self,
+        scope: &VOID,
+        ty: Ty,
+        name: &str,
+        payload: Option<Constraints>,
+    ) -> Constraints {
+        let built = match ty.kind() {
+            TyKind::RigidTy(RigidTy::Adt(adtdef, genargs)) => variant_idx(&adtdef.variants(), name)
+                .map(|idx| {
+                    let fields: ADTFields = payload.into_iter().map(|p| (0, p)).collect();
+                    Constraint::new(
+                        None,
+                        Some(RunningConstraint::Adt(adtdef, genargs, Some(idx), fields)),
+                    )
+                }),
+            _ => None,
+        };
+        let constraint = built.unwrap_or_else(|| {
+            let (_, constraint) =
+                self.converter
+                    .convert_ty(&Location::unknown(), &ty, Some(scope), Some(self));
+            constraint
+        });
+        Constraints::from(constraint)
+    }
+
+    /// `Option::take`/`replace` store into the `&mut Option<T>` receiver,
+    /// the way the collection stubs write back (see `stub_insert`).
+    fn write_through_receiver(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        place: &Place,
+        new: Constraints,
+    ) {
+        self.note_cell_store(ctxt, caller_scope, place, &new);
+        self.note_heap_store(ctxt, caller_scope, place, &new);
+        ctxt.set_scoped_constraints(caller_scope, place, new, Some(self));
+    }
+}
+
+/// The `i`th type argument of an Adt type, skipping lifetimes.
+pub(crate) fn type_arg(ty: Ty, i: usize) -> Option<Ty> {
+    let TyKind::RigidTy(RigidTy::Adt(_, genargs)) = ty.kind() else {
+        return None;
+    };
+    genargs
+        .0
+        .iter()
+        .filter_map(|a| match a {
+            GenericArgKind::Type(ty) => Some(*ty),
+            _ => None,
+        })
+        .nth(i)
+}
+
+fn variant_idx(variants: &[VariantDef], name: &str) -> Option<VariantIdx> {
+    variants
+        .iter()
+        .position(|v| v.name() == name)
+        .map(VariantIdx::to_val)
+}
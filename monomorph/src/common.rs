@@ -7,9 +7,24 @@ use log::debug;
 
 use crate::constraints::VOID;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum VerifOptType {
     FlowSensitive,
+    /// CHA alone: every impl of the trait, see `baseline`
+    ClassHierarchy,
+    /// RTA: CHA's impls for types the program constructs, see `baseline`
+    RapidType,
+}
+
+impl VerifOptType {
+    /// How `stats` labels this analysis' sets
+    pub fn label(&self) -> &'static str {
+        match self {
+            VerifOptType::FlowSensitive => "FSA",
+            VerifOptType::ClassHierarchy => "CHA",
+            VerifOptType::RapidType => "RTA",
+        }
+    }
 }
 
 pub fn log_scope(scope: &VOID) {
@@ -0,0 +1,305 @@
+//! Project-level configuration: `verifopt.toml`, or a
+//! `[package.metadata.verifopt]` table in Cargo.toml.
+//!
+//! ```toml
+//! entry = ["main"]                # fns the analysis starts from (default: the crate's `main`)
+//! stubs = ["stubs/app.toml"]      # as --stubs
+//! build_std = false               # as VERIFOPT_BUILD_STD
+//! log = "info"                    # as VERIFOPT_LOG
+//!
+//! [analysis]                      # which callees the interpreter walks into
+//! exclude = ["tracing::*"]
+//!
+//! [rewrite]                       # which fns' dyn calls get rewritten
+//! exclude = ["*::tests::*"]
+//! strategy = "vcall=6,max_arms=4" # as --cost-model (default "off" rewrites everything)
+//! profile = "calls"               # as --dispatch-profile
+//!
+//! [limits]
+//! max_depth = 50                  # interpreter call depth
+//! max_rounds = 4                  # cell/heap reruns, see `interior`
+//! max_fold_rounds = 3             # see `iter_adapters`
+//! merge_widen_threshold = 50      # disjuncts before a merge widens
+//! max_scalar_set = 8              # see `scalar`
+//!
+//! [report]
+//! stats = "stats"                 # where `VOLogger` writes
+//! ```
+//!
+//! Every key is optional. `cargo verifopt` takes the first of the package's
+//! own `verifopt.toml`, its `[package.metadata.verifopt]` and the workspace
+//! root's `verifopt.toml` (see `locate`), resolves the paths in it against
+//! the directory it came from and hands it to `verifopt` in `CONFIG_ENV`.
+//! `verifopt` applies it to `AnalysisOptions` before its own flags, so a
+//! command-line flag (or `VERIFOPT_FLAGS`, or a VERIFOPT_* variable) always
+//! wins.
+//!
+//! Include/exclude patterns are matched against fn paths the way `[[stub]]`
+//! paths are (`*` matches anything); the crate's own fns' paths don't start
+//! with its name, other crates' do. A fn passes a filter if it
+//! matches some `include` (or there are none) and no `exclude`. A callee
+//! the `[analysis]` filter rejects, and no stub or built-in model covers,
+//! is treated like a `returns = "fallback"` stub: anything its return type
+//! allows, and nothing else - so, like a stub, excluding a fn that stores
+//! a trait object through an argument makes the analysis unsound.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::error::Error;
+use crate::stub_spec::glob_match;
+
+/// The config file `locate` looks for
+pub const CONFIG_FILE: &str = "verifopt.toml";
+/// How `cargo verifopt` passes the config on to `verifopt`, as TOML text
+pub const CONFIG_ENV: &str = "VERIFOPT_CONFIG";
+/// Default `[report] stats`
+pub const STATS_FILE: &str = "stats";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathFilter {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl PathFilter {
+    pub fn allows(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| glob_match(p, path)))
+            && !self.exclude.iter().any(|p| glob_match(p, path))
+    }
+}
+
+/// Analysis budgets and widening settings. These are read from deep inside
+/// the domain code (`merge`, `scalar`), which has no `InterpPass` to hang
+/// them off, so they're process-wide: `set_limits` once before the
+/// analysis, `limits()` everywhere else.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Limits {
+    pub max_depth: u32,
+    pub max_rounds: usize,
+    pub max_fold_rounds: usize,
+    pub merge_widen_threshold: usize,
+    pub max_scalar_set: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_depth: crate::interp::MAX_DEPTH,
+            max_rounds: crate::interior::MAX_CELL_ROUNDS,
+            max_fold_rounds: crate::iter_adapters::MAX_FOLD_ROUNDS,
+            merge_widen_threshold: crate::merge::MERGE_WIDEN_THRESHOLD,
+            max_scalar_set: crate::scalar::MAX_SCALAR_SET,
+        }
+    }
+}
+
+static LIMITS: OnceLock<Limits> = OnceLock::new();
+
+/// The limits the analysis runs under: what `set_limits` set, or the
+/// defaults.
+pub fn limits() -> &'static Limits {
+    LIMITS.get_or_init(Limits::default)
+}
+
+/// Only the first call has any effect, and only if `limits()` hasn't been
+/// read yet.
+pub fn set_limits(limits: Limits) {
+    let _ = LIMITS.set(limits);
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    pub entry: Vec<String>,
+    pub stubs: Vec<String>,
+    pub build_std: bool,
+    pub log: Option<String>,
+    pub analysis: PathFilter,
+    pub rewrite: PathFilter,
+    pub strategy: Option<String>,
+    pub profile: Option<String>,
+    pub limits: Limits,
+    pub stats: Option<String>,
+}
+
+impl Config {
+    pub fn parse(text: &str, origin: &str) -> Result<Config, Error> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e: toml::de::Error| Error::Config(origin.to_string(), e.to_string()))?;
+        Self::from_table(&table, origin)
+    }
+
+    pub fn from_table(table: &toml::Table, origin: &str) -> Result<Config, Error> {
+        let err = |msg: String| Error::Config(origin.to_string(), msg);
+
+        let mut config = Config::default();
+        for (key, value) in table {
+            match key.as_str() {
+                "entry" => config.entry = strings(value, key).map_err(err)?,
+                "stubs" => config.stubs = strings(value, key).map_err(err)?,
+                "build_std" => config.build_std = boolean(value, key).map_err(err)?,
+                "log" => config.log = Some(string(value, key).map_err(err)?),
+                "analysis" => {
+                    let t = section(value, key).map_err(err)?;
+                    config.analysis = filter(t, key, &[]).map_err(err)?;
+                }
+                "rewrite" => {
+                    let t = section(value, key).map_err(err)?;
+                    config.rewrite = filter(t, key, &["strategy", "profile"]).map_err(err)?;
+                    if let Some(v) = t.get("strategy") {
+                        config.strategy = Some(string(v, "rewrite.strategy").map_err(err)?);
+                    }
+                    if let Some(v) = t.get("profile") {
+                        config.profile = Some(string(v, "rewrite.profile").map_err(err)?);
+                    }
+                }
+                "limits" => {
+                    let t = section(value, key).map_err(err)?;
+                    let l = &mut config.limits;
+                    for (k, v) in t {
+                        let n = integer(v, k).map_err(err)?;
+                        match k.as_str() {
+                            "max_depth" => l.max_depth = n as u32,
+                            "max_rounds" => l.max_rounds = n,
+                            "max_fold_rounds" => l.max_fold_rounds = n,
+                            "merge_widen_threshold" => l.merge_widen_threshold = n,
+                            "max_scalar_set" => l.max_scalar_set = n,
+                            _ => return Err(err(format!("unknown key limits.{}", k))),
+                        }
+                    }
+                }
+                "report" => {
+                    let t = section(value, key).map_err(err)?;
+                    for (k, v) in t {
+                        match k.as_str() {
+                            "stats" => config.stats = Some(string(v, "report.stats").map_err(err)?),
+                            _ => return Err(err(format!("unknown key report.{}", k))),
+                        }
+                    }
+                }
+                _ => return Err(err(format!("unknown top-level key {:?}", key))),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The config for the package in `package_dir`, with `metadata` its
+    /// `package.metadata` from `cargo metadata`: the first of
+    /// `package_dir/verifopt.toml`, `metadata.verifopt` and
+    /// `workspace_root/verifopt.toml`. Returned as TOML text for
+    /// `CONFIG_ENV`, with relative paths made absolute, since `verifopt`
+    /// runs wherever cargo runs rustc.
+    pub fn locate(
+        package_dir: &Path,
+        workspace_root: &Path,
+        metadata: &serde_json::Value,
+    ) -> Result<Option<String>, Error> {
+        let read = |dir: &Path| -> Result<Option<(toml::Table, String)>, Error> {
+            let path = dir.join(CONFIG_FILE);
+            let origin = path.display().to_string();
+            if !path.is_file() {
+                return Ok(None);
+            }
+            let text = std::fs::read_to_string(&path)
+                .map_err(|e| Error::Config(origin.clone(), e.to_string()))?;
+            let table = text
+                .parse()
+                .map_err(|e: toml::de::Error| Error::Config(origin.clone(), e.to_string()))?;
+            Ok(Some((table, origin)))
+        };
+
+        let (mut table, origin, base) = if let Some((t, o)) = read(package_dir)? {
+            (t, o, package_dir)
+        } else if let Some(v) = metadata.get("verifopt") {
+            let origin = format!(
+                "[package.metadata.verifopt] in {}",
+                package_dir.join("Cargo.toml").display()
+            );
+            let t: toml::Table = serde_json::from_value(v.clone())
+                .map_err(|e| Error::Config(origin.clone(), e.to_string()))?;
+            (t, origin, package_dir)
+        } else if let Some((t, o)) = read(workspace_root)? {
+            (t, o, workspace_root)
+        } else {
+            return Ok(None);
+        };
+
+        // Check it here, where the error can point at the file
+        Self::from_table(&table, &origin)?;
+
+        let absolute = |v: &mut toml::Value| {
+            if let toml::Value::String(s) = v
+                && Path::new(s.as_str()).is_relative()
+            {
+                *s = base.join(s.as_str()).display().to_string();
+            }
+        };
+        if let Some(toml::Value::Array(stubs)) = table.get_mut("stubs") {
+            stubs.iter_mut().for_each(absolute);
+        }
+        for (section, key) in [("rewrite", "profile"), ("report", "stats")] {
+            if let Some(v) = table
+                .get_mut(section)
+                .and_then(|t| t.as_table_mut())
+                .and_then(|t| t.get_mut(key))
+            {
+                absolute(v);
+            }
+        }
+
+        Ok(Some(table.to_string()))
+    }
+}
+
+fn section<'t>(value: &'t toml::Value, key: &str) -> Result<&'t toml::Table, String> {
+    value
+        .as_table()
+        .ok_or_else(|| format!("`{}` must be a table ([{}])", key, key))
+}
+
+fn filter(table: &toml::Table, section: &str, other_keys: &[&str]) -> Result<PathFilter, String> {
+    let mut filter = PathFilter::default();
+    for (k, v) in table {
+        match k.as_str() {
+            "include" => filter.include = strings(v, k)?,
+            "exclude" => filter.exclude = strings(v, k)?,
+            _ if other_keys.contains(&k.as_str()) => {}
+            _ => return Err(format!("unknown key {}.{}", section, k)),
+        }
+    }
+    Ok(filter)
+}
+
+fn string(value: &toml::Value, key: &str) -> Result<String, String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("`{}` must be a string", key))
+}
+
+/// A list of strings, or a single one
+fn strings(value: &toml::Value, key: &str) -> Result<Vec<String>, String> {
+    match value {
+        toml::Value::String(s) => Ok(vec![s.clone()]),
+        toml::Value::Array(items) => items.iter().map(|v| string(v, key)).collect(),
+        _ => Err(format!("`{}` must be a string or a list of strings", key)),
+    }
+}
+
+fn boolean(value: &toml::Value, key: &str) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| format!("`{}` must be true or false", key))
+}
+
+fn integer(value: &toml::Value, key: &str) -> Result<usize, String> {
+    value
+        .as_integer()
+        .and_then(|n| usize::try_from(n).ok())
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("`{}` must be a positive integer", key))
+}
+
+#[cfg(test)]
+mod config_tests;
@@ -0,0 +1,149 @@
+use super::*;
+
+#[test]
+fn test_parse_full() {
+    let config = Config::parse(
+        "entry = [\"main\", \"app::run\"]\n\
+         stubs = \"stubs/app.toml\"\n\
+         build_std = true\n\
+         log = \"info\"\n\
+         \n\
+         [analysis]\n\
+         exclude = [\"tracing::*\"]\n\
+         \n\
+         [rewrite]\n\
+         include = \"app::*\"\n\
+         exclude = [\"*::tests::*\"]\n\
+         strategy = \"vcall=6,max_arms=4\"\n\
+         profile = \"calls\"\n\
+         \n\
+         [limits]\n\
+         max_depth = 10\n\
+         max_scalar_set = 3\n\
+         \n\
+         [report]\n\
+         stats = \"out/stats\"\n",
+        "test.toml",
+    )
+    .unwrap();
+    assert_eq!(config.entry, ["main", "app::run"]);
+    assert_eq!(config.stubs, ["stubs/app.toml"]);
+    assert!(config.build_std);
+    assert_eq!(config.log.as_deref(), Some("info"));
+    assert_eq!(config.analysis.exclude, ["tracing::*"]);
+    assert!(config.analysis.include.is_empty());
+    assert_eq!(config.rewrite.include, ["app::*"]);
+    assert_eq!(config.rewrite.exclude, ["*::tests::*"]);
+    assert_eq!(config.strategy.as_deref(), Some("vcall=6,max_arms=4"));
+    assert_eq!(config.profile.as_deref(), Some("calls"));
+    assert_eq!(config.limits.max_depth, 10);
+    assert_eq!(config.limits.max_scalar_set, 3);
+    assert_eq!(
+        config.limits.max_rounds,
+        Limits::default().max_rounds,
+        "unset limits keep their defaults"
+    );
+    assert_eq!(config.stats.as_deref(), Some("out/stats"));
+}
+
+#[test]
+fn test_parse_empty() {
+    assert_eq!(Config::parse("", "test.toml").unwrap(), Config::default());
+}
+
+#[test]
+fn test_parse_errors() {
+    let bad = |text: &str| matches!(Config::parse(text, "test.toml"), Err(Error::Config(..)));
+    assert!(bad("entry = "));
+    assert!(bad("entrys = [\"main\"]\n"));
+    assert!(bad("entry = 1\n"));
+    assert!(bad("entry = [\"main\", 1]\n"));
+    assert!(bad("build_std = \"yes\"\n"));
+    assert!(bad("analysis = \"tracing::*\"\n"));
+    assert!(bad("[analysis]\nstrategy = \"off\"\n"));
+    assert!(bad("[rewrite]\nprofile = 1\n"));
+    assert!(bad("[limits]\nmax_depth = 0\n"));
+    assert!(bad("[limits]\nmax_depth = -1\n"));
+    assert!(bad("[limits]\nmax_steps = 5\n"));
+    assert!(bad("[report]\nlog = \"info\"\n"));
+}
+
+#[test]
+fn test_path_filter() {
+    let all = PathFilter::default();
+    assert!(all.allows("app::main"));
+
+    let filter = PathFilter {
+        include: vec!["app::*".to_string()],
+        exclude: vec!["*::tests::*".to_string()],
+    };
+    assert!(filter.allows("app::run"));
+    assert!(!filter.allows("app::tests::run"));
+    assert!(!filter.allows("tracing::event"));
+}
+
+#[test]
+fn test_locate_metadata() {
+    let dir = Path::new("/nonexistent/pkg");
+    let metadata = serde_json::json!({
+        "verifopt": {
+            "stubs": ["stubs/app.toml", "/abs/other.toml"],
+            "report": { "stats": "out/stats" }
+        }
+    });
+    let text = Config::locate(dir, dir, &metadata).unwrap().unwrap();
+    let config = Config::parse(&text, "located").unwrap();
+    assert_eq!(
+        config.stubs,
+        ["/nonexistent/pkg/stubs/app.toml", "/abs/other.toml"]
+    );
+    assert_eq!(config.stats.as_deref(), Some("/nonexistent/pkg/out/stats"));
+
+    assert_eq!(
+        Config::locate(dir, dir, &serde_json::json!({})).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_locate_checks_metadata() {
+    let dir = Path::new("/nonexistent/pkg");
+    let metadata = serde_json::json!({ "verifopt": { "entrys": ["main"] } });
+    assert!(matches!(
+        Config::locate(dir, dir, &metadata),
+        Err(Error::Config(..))
+    ));
+}
+
+#[test]
+fn test_locate_package_file_first() {
+    let root = std::env::temp_dir().join(format!("verifopt-config-{}", std::process::id()));
+    let package = root.join("pkg");
+    std::fs::create_dir_all(&package).unwrap();
+    std::fs::write(
+        package.join(CONFIG_FILE),
+        "[rewrite]\nprofile = \"calls\"\n",
+    )
+    .unwrap();
+    std::fs::write(root.join(CONFIG_FILE), "log = \"debug\"\n").unwrap();
+    let metadata = serde_json::json!({ "verifopt": { "log": "info" } });
+
+    let text = Config::locate(&package, &root, &metadata).unwrap().unwrap();
+    let config = Config::parse(&text, "located").unwrap();
+    assert_eq!(config.log, None);
+    assert_eq!(
+        config.profile,
+        Some(package.join("calls").display().to_string())
+    );
+
+    std::fs::remove_file(package.join(CONFIG_FILE)).unwrap();
+    let text = Config::locate(&package, &root, &serde_json::json!({}))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Config::parse(&text, "located").unwrap().log.as_deref(),
+        Some("debug")
+    );
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
@@ -5,8 +5,8 @@ use rustc_public::mir::mono::Instance;
 use rustc_public::DefId;
 use rustc_public::mir::{Body, LocalDecl, Mutability, Operand, Place, ProjectionElem};
 use rustc_public::ty::{
-    AdtDef, Binder, ClosureDef, ExistentialPredicate, FnDef, GenericArgs, Span, TraitDef,
-    VariantIdx,
+    AdtDef, Binder, BoundVariableKind, ClosureDef, ExistentialPredicate, FnDef, GenericArgs, Span,
+    TermKind, TraitDef, Ty, VariantIdx,
 };
 
 use crate::merge::merge_mapvals;
@@ -403,6 +403,9 @@ impl TagProv {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RunningConstraint {
     Scalar(Option<i128>),
+    /// Every integer in `lo..=hi`: a value too spread out to list as
+    /// `Scalar` disjuncts, see `scalar`.
+    Interval(i128, i128),
     Float,
     Adt(AdtDef, GenericArgs, Option<VariantIdx>, ADTFields),
     Ptr(Box<Constraint>),
@@ -642,7 +645,9 @@ fn widen_toc(tc: &TraitObjConstraint) -> TraitObjConstraint {
 
 fn widen_rc(rc: &RunningConstraint) -> RunningConstraint {
     match rc {
-        RunningConstraint::Scalar(_) => RunningConstraint::Scalar(None),
+        RunningConstraint::Scalar(_) | RunningConstraint::Interval(..) => {
+            RunningConstraint::Scalar(None)
+        }
         RunningConstraint::Float => RunningConstraint::Float,
         RunningConstraint::Adt(def, genargs, _, _) => {
             RunningConstraint::Adt(def.clone(), genargs.clone(), None, ADTFields::new())
@@ -781,67 +786,104 @@ fn substitute_params_constraint(c: &Constraint, actual_args: &[Constraints]) ->
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// One trait of a `dyn` type: its principal trait with that trait's own
+/// generic args, the associated types the `dyn` pins down, and the
+/// higher-ranked bound vars (`for<'a>`) all of them are under - one unit,
+/// so `dyn Iterator<Item = u8>` and `dyn Iterator<Item = String>` are
+/// different trait object types.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TraitObjTy {
-    //pub bound_tys: Vec<(DefId, String)>,
-    //pub bound_regions: Vec<(DefId, String)>,
     pub def: TraitDef,
     pub genargs: GenericArgs,
+    pub assoc_bindings: Vec<AssocBinding>,
+    pub bound_vars: Vec<BoundVariableKind>,
+}
+
+/// An `Assoc = T` binding on a trait object type: `Item = u8` in
+/// `dyn Iterator<Item = u8>`, or `Output = B` in `dyn Fn(A) -> B` (where
+/// the item is `FnOnce::Output`, a supertrait's).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssocBinding {
+    /// The associated item, as `ExistentialProjection` has it
+    pub item: TraitDef,
+    pub genargs: GenericArgs,
+    /// None for a const
+    pub ty: Option<Ty>,
+}
+
+// By hand for `bound_vars`: equal trait object types always have equal
+// everything else, so leaving them out of the hash is still consistent
+impl Hash for TraitObjTy {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.def.hash(state);
+        self.genargs.hash(state);
+        self.assoc_bindings.hash(state);
+    }
 }
 
 impl TraitObjTy {
-    pub fn new_from_bound_existential(binder: &Binder<ExistentialPredicate>) -> Option<TraitObjTy> {
-        //let mut bound_tys = Vec::new();
-        //let mut bound_regions = Vec::new();
-        if !binder.bound_vars.is_empty() {
-            //debug!("handle bound vars");
-            //    for bound_var in &binder.bound_vars {
-            //        match bound_var {
-            //            BoundVariableKind::Ty(ty) => match ty {
-            //                BoundTyKind::Param(def, s) => bound_tys.push((def.0, s.clone())),
-            //                _ => {}
-            //            },
-            //            BoundVariableKind::Region(region) => match region {
-            //                BoundRegionKind::BrNamed(def, s) => bound_regions.push((def.0, s.clone())),
-            //                _ => {}
-            //            },
-            //            _ => {}
-            //        }
-            //    }
+    /// A `dyn` type's predicates (`RigidTy::Dynamic`'s) as trait object
+    /// types: the principal trait, if it has one, with every projection
+    /// attached. Auto traits (`Send`, `Sync`) don't dispatch anything.
+    pub fn new_from_existentials(preds: &[Binder<ExistentialPredicate>]) -> Vec<TraitObjTy> {
+        let mut principal = preds.iter().find_map(Self::new_from_bound_existential);
+        if let Some(principal) = principal.as_mut() {
+            for binder in preds {
+                if let ExistentialPredicate::Projection(proj) = &binder.value {
+                    principal.assoc_bindings.push(AssocBinding {
+                        item: proj.def_id,
+                        genargs: proj.generic_args.clone(),
+                        ty: match &proj.term {
+                            TermKind::Type(ty) => Some(*ty),
+                            TermKind::Const(_) => None,
+                        },
+                    });
+                }
+            }
         }
+        principal.into_iter().collect()
+    }
 
-        match binder.clone().skip_binder() {
-            ExistentialPredicate::Trait(trait_ref) => {
-                return Some(Self {
-                    def: trait_ref.def_id,
-                    genargs: trait_ref.generic_args,
-                });
-            }
-            ExistentialPredicate::Projection(proj) => {
-                return Some(Self {
-                    def: proj.def_id,
-                    genargs: proj.generic_args,
-                });
-            }
-            ExistentialPredicate::AutoTrait(_trait_def) => None,
+    /// Just the principal trait (no bindings) from one of a `dyn` type's
+    /// predicates; None for a projection or an auto trait. For a whole
+    /// `dyn` type see `new_from_existentials`.
+    pub fn new_from_bound_existential(binder: &Binder<ExistentialPredicate>) -> Option<TraitObjTy> {
+        match &binder.value {
+            ExistentialPredicate::Trait(trait_ref) => Some(Self {
+                def: trait_ref.def_id,
+                genargs: trait_ref.generic_args.clone(),
+                assoc_bindings: Vec::new(),
+                bound_vars: binder.bound_vars.clone(),
+            }),
+            ExistentialPredicate::Projection(_) | ExistentialPredicate::AutoTrait(_) => None,
         }
     }
 
     pub fn is_fn_trait(&self) -> bool {
         matches!(
             self.def.name().as_str(),
-            "std::ops::Fn"
-                | "std::ops::FnMut"
-                | "std::ops::FnOnce"
-                | "std::ops::Fn::Output"
-                | "std::ops::FnMut::Output"
-                | "std::ops::FnOnce::Output"
+            "std::ops::Fn" | "std::ops::FnMut" | "std::ops::FnOnce"
         )
     }
 
     pub fn is_universal_trait(&self) -> bool {
         matches!(self.def.name().as_str(), "core::error::Erased")
     }
+
+    pub fn is_iterator_trait(&self) -> bool {
+        matches!(self.def.name().as_str(), "std::iter::Iterator")
+    }
+
+    pub fn is_any_trait(&self) -> bool {
+        matches!(self.def.name().as_str(), "std::any::Any" | "core::any::Any")
+    }
+
+    pub fn is_error_trait(&self) -> bool {
+        matches!(
+            self.def.name().as_str(),
+            "std::error::Error" | "core::error::Error"
+        )
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -1290,6 +1332,12 @@ impl ConstraintStore {
         }
     }
 
+    /// Where a write to `place` in `scope` actually lands, following
+    /// `&mut` refs.
+    pub fn resolve_mut_place(&self, place: &Place, scope: &VOID) -> (Place, VOID) {
+        self.resolve(place.clone(), scope.clone(), true)
+    }
+
     pub fn add_ref(&mut self, from: (Place, VOID), to: (Place, VOID), bk: Mutability) {
         self.refs.insert(from, (to, bk));
     }
@@ -18,6 +18,7 @@ use crate::constraints::{
 };
 use crate::constraints::{hash_val, memoize_by_rc};
 use crate::interp::{InterpPass, TimingCat};
+use crate::scalar::{self, ScalarVal, scalar_constraints};
 use crate::sig_collect::SigVal;
 
 use indexmap::IndexSet;
@@ -37,6 +38,86 @@ pub enum WrapperKind {
     Result,
     BTreeSet,
     BTreeMap,
+    Vec,
+    VecDeque,
+    HashMap,
+    HashSet,
+}
+
+impl WrapperKind {
+    /// Collections `stdlib_stubs` summarizes as one element slot (field 0),
+    /// plus a value slot (field 1) for maps, instead of interpreting.
+    pub fn is_collection(&self) -> bool {
+        matches!(
+            self,
+            WrapperKind::BTreeSet
+                | WrapperKind::BTreeMap
+                | WrapperKind::Vec
+                | WrapperKind::VecDeque
+                | WrapperKind::HashMap
+                | WrapperKind::HashSet
+        )
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(self, WrapperKind::BTreeMap | WrapperKind::HashMap)
+    }
+}
+
+/// Interior-mutability containers, and the guards their accessors hand out,
+/// that `interior` models as one shared summary per content type instead of
+/// interpreting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellKind {
+    RefCell,
+    Cell,
+    Mutex,
+    RwLock,
+    Ref,
+    RefMut,
+    MutexGuard,
+    RwLockReadGuard,
+    RwLockWriteGuard,
+}
+
+impl CellKind {
+    pub fn is_guard(&self) -> bool {
+        matches!(
+            self,
+            CellKind::Ref
+                | CellKind::RefMut
+                | CellKind::MutexGuard
+                | CellKind::RwLockReadGuard
+                | CellKind::RwLockWriteGuard
+        )
+    }
+}
+
+/// Matched on the path after the crate name, like `wrapper_kind`. The sync
+/// types are matched on their last segment under `sync::`, since where
+/// they're defined (`sync::mutex`, `sync::poison::mutex`,
+/// `sync::nonpoison::mutex`) moves between toolchains.
+pub fn cell_kind(def: &AdtDef) -> Option<CellKind> {
+    let name = def.0.name();
+    let suffix = name.splitn(2, "::").nth(1).unwrap_or("");
+    match suffix {
+        "cell::RefCell" => return Some(CellKind::RefCell),
+        "cell::Cell" => return Some(CellKind::Cell),
+        "cell::Ref" => return Some(CellKind::Ref),
+        "cell::RefMut" => return Some(CellKind::RefMut),
+        _ => {}
+    }
+    if !suffix.starts_with("sync::") {
+        return None;
+    }
+    match suffix.rsplit("::").next() {
+        Some("Mutex") => Some(CellKind::Mutex),
+        Some("RwLock") => Some(CellKind::RwLock),
+        Some("MutexGuard") => Some(CellKind::MutexGuard),
+        Some("RwLockReadGuard") => Some(CellKind::RwLockReadGuard),
+        Some("RwLockWriteGuard") => Some(CellKind::RwLockWriteGuard),
+        _ => None,
+    }
 }
 
 pub struct RvalConverter<'a> {
@@ -58,6 +139,60 @@ pub fn is_btree_iter_suffix(suffix: &str) -> bool {
     )
 }
 
+/// Iterators over the std sequence/hash collections. Unlike the btree ones,
+/// these are only stubbed when they're our own synthetic value (see
+/// `stdlib_stubs::iter_receiver`): a real `slice::Iter` over an array still
+/// gets interpreted.
+pub fn is_std_collection_iter_suffix(suffix: &str) -> bool {
+    matches!(
+        suffix,
+        "slice::Iter"
+            | "slice::IterMut"
+            | "vec::IntoIter"
+            | "vec::Drain"
+            | "collections::vec_deque::Iter"
+            | "collections::vec_deque::IterMut"
+            | "collections::vec_deque::IntoIter"
+            | "collections::vec_deque::Drain"
+            | "collections::hash_map::Iter"
+            | "collections::hash_map::IterMut"
+            | "collections::hash_map::IntoIter"
+            | "collections::hash_map::Keys"
+            | "collections::hash_map::Values"
+            | "collections::hash_map::ValuesMut"
+            | "collections::hash_map::IntoKeys"
+            | "collections::hash_map::IntoValues"
+            | "collections::hash_map::Drain"
+            | "collections::hash_set::Iter"
+            | "collections::hash_set::IntoIter"
+            | "collections::hash_set::Drain"
+    )
+}
+
+/// The `std::iter` adapters (`iter::Map`, `iter::Filter`, `iter::Chain`,
+/// ...). Like the collection iterators, only stubbed when the value is one
+/// of our synthetic ones (see `iter_adapters`).
+pub fn is_iter_adapter_suffix(suffix: &str) -> bool {
+    suffix
+        .strip_prefix("iter::")
+        .is_some_and(|rest| !rest.contains("::"))
+}
+
+pub fn is_std_iter_suffix(suffix: &str) -> bool {
+    is_btree_iter_suffix(suffix)
+        || is_std_collection_iter_suffix(suffix)
+        || is_iter_adapter_suffix(suffix)
+}
+
+/// `core::fmt::rt::Argument`, one `format_args!` placeholder (see
+/// `fmt_args`).
+pub fn is_fmt_argument_ty(ty: &Ty) -> bool {
+    match ty.kind() {
+        TyKind::RigidTy(RigidTy::Adt(def, _)) => def.0.name().ends_with("fmt::rt::Argument"),
+        _ => false,
+    }
+}
+
 pub fn is_opaque_internal_defid(adtdef: &AdtDef) -> bool {
     let name = adtdef.0.name();
     let suffix = name.splitn(2, "::").nth(1).unwrap_or("");
@@ -75,6 +210,10 @@ pub fn is_opaque_internal_defid(adtdef: &AdtDef) -> bool {
             | "rc::Rc"
             | "collections::BTreeSet"
             | "collections::BTreeMap"
+            | "vec::Vec"
+            | "collections::VecDeque"
+            | "collections::HashMap"
+            | "collections::HashSet"
             // Pure pointer-plumbing internals: these can never structurally
             // hold a trait-object payload, so treating them as opaque 
             // is free precision to give up
@@ -256,16 +395,12 @@ impl<'a> RvalConverter<'a> {
     ) -> Constraints {
         match ty.kind() {
             TyKind::RigidTy(RigidTy::Bool | RigidTy::Int(_) | RigidTy::Uint(_)) => {
-                match alloc.read_int() {
-                    Ok(val) => Constraints::from(Constraint::new(
-                        None,
-                        Some(RunningConstraint::Scalar(Some(val))),
-                    )),
-                    Err(_) => Constraints::from(Constraint::new(
-                        None,
-                        Some(RunningConstraint::Scalar(None)),
-                    )),
-                }
+                // `read_int` doesn't know the type: extend its bits by it
+                let val = alloc
+                    .read_int()
+                    .ok()
+                    .and_then(|bits| scalar::int_value(bits as u128, ty));
+                Constraints::from(Constraint::new(None, Some(RunningConstraint::Scalar(val))))
             }
             TyKind::RigidTy(RigidTy::Adt(adtdef, genargs)) => Constraints::from(Constraint::new(
                 None,
@@ -369,10 +504,11 @@ impl<'a> RvalConverter<'a> {
     ) -> Constraint {
         match ty.kind() {
             TyKind::RigidTy(RigidTy::Bool | RigidTy::Int(_) | RigidTy::Uint(_)) => {
-                match alloc.read_int() {
-                    Ok(val) => Constraint::new(None, Some(RunningConstraint::Scalar(Some(val)))),
-                    Err(_) => Constraint::new(None, Some(RunningConstraint::Scalar(None))),
-                }
+                let val = alloc
+                    .read_int()
+                    .ok()
+                    .and_then(|bits| scalar::int_value(bits as u128, ty));
+                Constraint::new(None, Some(RunningConstraint::Scalar(val)))
             }
 
             TyKind::RigidTy(RigidTy::Adt(adtdef, genargs)) => Constraint::new(
@@ -461,6 +597,7 @@ impl<'a> RvalConverter<'a> {
             RunningConstraint::Adt(adtdef, _, _, _) => vec![(adtdef.0, cfc.clone())],
             RunningConstraint::Closure(cdef, _) => vec![(cdef.0, cfc.clone())],
             RunningConstraint::Scalar(_)
+            | RunningConstraint::Interval(..)
             | RunningConstraint::Float
             | RunningConstraint::Ptr(_)
             | RunningConstraint::FnPtr(_) => vec![],
@@ -492,7 +629,81 @@ impl<'a> RvalConverter<'a> {
         }
     }
 
-    fn convert_cast_helper(
+    /// `Box<I>`/`&I` -> `dyn Iterator` where everything being cast is one
+    /// of the synthetic iterators `stdlib_stubs`/`iter_adapters` make.
+    /// Those carry the source collection's AdtDef (see `stub_make_iter`),
+    /// which doesn't implement `Iterator`, so `convert_cast_helper` would
+    /// leave them without a trait object constraint and the `next` call
+    /// through the vtable would fall back to CHA. Label them with `I`
+    /// instead - the type the vtable really belongs to - keeping the
+    /// synthetic value as the cfc so `stub_next` can still read it.
+    fn relabel_synthetic_iter(
+        &self,
+        traitobjtys: &[TraitObjTy],
+        src_ty: Ty,
+        constraints: &Constraints,
+        span: &Location,
+    ) -> Option<Constraints> {
+        let traitobjty = traitobjtys.iter().find(|t| t.is_iterator_trait())?;
+
+        let mut pointee = src_ty;
+        loop {
+            match pointee.kind() {
+                TyKind::RigidTy(RigidTy::Ref(_, inner, _)) => pointee = inner,
+                TyKind::RigidTy(RigidTy::Adt(def, args))
+                    if self.wrapper_kind(&def) == Some(WrapperKind::Box) =>
+                {
+                    pointee = *args.0.first()?.ty()?;
+                }
+                _ => break,
+            }
+        }
+        let TyKind::RigidTy(RigidTy::Adt(iter_def, iter_args)) = pointee.kind() else {
+            return None;
+        };
+        let name = iter_def.0.name();
+        if !is_std_iter_suffix(name.splitn(2, "::").nth(1).unwrap_or("")) {
+            return None;
+        }
+
+        let mut relabeled = Constraints::new();
+        for constraint in constraints.inner.iter() {
+            // through the `Box` `stub_wrapper_new` built
+            let leaves = match &constraint.cfc {
+                Some(RunningConstraint::Adt(def, _, _, fields))
+                    if self.wrapper_kind(def) == Some(WrapperKind::Box) =>
+                {
+                    fields.get(&0)?.clone()
+                }
+                _ => Constraints::from(constraint.clone()),
+            };
+            for leaf in leaves.inner.iter() {
+                let Some(RunningConstraint::Adt(def, _, _, fields)) = &leaf.cfc else {
+                    return None;
+                };
+                if leaf.toc.is_some() || !self.wrapper_kind(def).is_some_and(|k| k.is_collection())
+                {
+                    return None;
+                }
+                let toc =
+                    TraitObjConstraint::Adt(iter_def, iter_args.clone(), None, fields.clone());
+                relabeled.push(
+                    Constraint::new(Some((traitobjty.clone(), toc)), leaf.cfc.clone()).with_prov(
+                        match span.scope {
+                            Some(s) => {
+                                TagProv::Tags([(s, span.bb, span.stmt)].into_iter().collect())
+                            }
+                            None => TagProv::Unknown,
+                        },
+                    ),
+                );
+            }
+        }
+
+        (!relabeled.inner.is_empty()).then_some(relabeled)
+    }
+
+    pub(crate) fn convert_cast_helper(
         &self,
         traitobjtys: &Vec<TraitObjTy>,
         constraints: &Constraints,
@@ -524,6 +735,21 @@ impl<'a> RvalConverter<'a> {
         for traitobjty in traitobjtys {
             for constraint in constraints.inner.iter() {
                 match constraint {
+                    // A trait object upcast (`&dyn Sub` to `&dyn Super`): the
+                    // concrete type behind it is the same, it's just
+                    // dispatched through `Super` from here on
+                    Constraint {
+                        toc: Some((from, to_)),
+                        cfc,
+                        prov,
+                    } if from.def != traitobjty.def
+                        && self.tstore.is_supertrait(&from.def.0, &traitobjty.def.0) =>
+                    {
+                        new_constraints.push(
+                            Constraint::new(Some((traitobjty.clone(), to_.clone())), cfc.clone())
+                                .with_prov(prov.clone()),
+                        );
+                    }
                     Constraint { toc: Some(_), .. } => {
                         new_constraints.push(constraint.clone());
                     }
@@ -538,9 +764,13 @@ impl<'a> RvalConverter<'a> {
                             new_constraints.push(constraint.clone());
                         } else {
                             for (defid, leaf_cfc) in &candidate_defids {
+                                // An ADT with no impl of its own may still
+                                // get one from a blanket impl
+                                let blanket = matches!(leaf_cfc, RunningConstraint::Adt(..))
+                                    && self.tstore.has_blanket_impl(&traitobjty.def.0);
                                 match self.tstore.struct_traits.get(&defid) {
                                     Some(traits) => {
-                                        if traits.contains(&traitobjty.def.0) {
+                                        if traits.contains(&traitobjty.def.0) || blanket {
                                             let new_constraint = Constraint::new(
                                                 Some((
                                                     traitobjty.clone(),
@@ -567,6 +797,7 @@ impl<'a> RvalConverter<'a> {
                                         // in our trait store
                                         if (constraint.is_cfc_closure() && traitobjty.is_fn_trait())
                                             || traitobjty.is_universal_trait()
+                                            || blanket
                                         {
                                             // Pull relevant CFC into TOC
                                             let new_constraint = Constraint::new(
@@ -627,7 +858,12 @@ impl<'a> RvalConverter<'a> {
                     self.convert_ty(span, ty, Some(cur_scope), timing);
 
                 if let Some(traitobjtys) = maybe_traitobj {
-                    self.convert_cast_helper(&traitobjtys, &prev_constraints, span)
+                    let src_ty = place.ty(local_decls).unwrap_or(*ty);
+                    match self.relabel_synthetic_iter(&traitobjtys, src_ty, &prev_constraints, span)
+                    {
+                        Some(relabeled) => relabeled,
+                        None => self.convert_cast_helper(&traitobjtys, &prev_constraints, span),
+                    }
                 } else {
                     match &prev_constraints
                         .inner
@@ -675,6 +911,10 @@ impl<'a> RvalConverter<'a> {
             // don't need to understand these types
             "collections::BTreeSet" => Some(WrapperKind::BTreeSet),
             "collections::BTreeMap" => Some(WrapperKind::BTreeMap),
+            "vec::Vec" => Some(WrapperKind::Vec),
+            "collections::VecDeque" => Some(WrapperKind::VecDeque),
+            "collections::HashMap" => Some(WrapperKind::HashMap),
+            "collections::HashSet" => Some(WrapperKind::HashSet),
             _ => None,
         };
 
@@ -704,8 +944,12 @@ impl<'a> RvalConverter<'a> {
                         match self.tstore.struct_traits.get(&adtdef.0) {
                             Some(possible_traits) => {
                                 if let Some(trait_tys) = maybe_trait_ty {
-                                    if !trait_tys.iter().all(|t| possible_traits.contains(&t.def.0))
-                                    {
+                                    // Implemented directly, or maybe through
+                                    // a blanket impl
+                                    if !trait_tys.iter().all(|t| {
+                                        possible_traits.contains(&t.def.0)
+                                            || self.tstore.has_blanket_impl(&t.def.0)
+                                    }) {
                                         return None;
                                     }
                                     return Some((
@@ -846,6 +1090,29 @@ impl<'a> RvalConverter<'a> {
             }
             AggregateKind::Array(ty) => {
                 let _g = timing.map(|p| p.timing_span(TimingCat::ConvertAggArray, cur_scope));
+                // `format_args!`'s `[rt::Argument; N]`: keep what's in it, so
+                // `fmt_args` can pair each value with its formatting fn
+                if is_fmt_argument_ty(ty) {
+                    let mut elems = Constraints::new();
+                    for op in ops {
+                        elems.append(self.convert_op(
+                            ctxt,
+                            span,
+                            local_decls,
+                            cur_scope,
+                            op,
+                            destty,
+                            timing,
+                        ));
+                    }
+                    return Constraints::from(Constraint::new(
+                        None,
+                        Some(RunningConstraint::List(Box::new(Constraint::new(
+                            None,
+                            Some(RunningConstraint::Idk(Box::new(elems))),
+                        )))),
+                    ));
+                }
                 let (_, constraint) = self.convert_ty(span, ty, Some(cur_scope), timing);
                 Constraints::from(Constraint::new(
                     None,
@@ -1018,14 +1285,7 @@ impl<'a> RvalConverter<'a> {
                     (None, Constraint::new(None, None))
                 }
                 RigidTy::Dynamic(bound_existentials, _) => {
-                    let mut traitobj_vec = Vec::new();
-                    for bound_existential in bound_existentials {
-                        if let Some(toty) =
-                            TraitObjTy::new_from_bound_existential(&bound_existential)
-                        {
-                            unique_push(&mut traitobj_vec, toty);
-                        }
-                    }
+                    let traitobj_vec = TraitObjTy::new_from_existentials(&bound_existentials);
                     (
                         Some(traitobj_vec.clone()),
                         Constraint::new(None, Some(RunningConstraint::Dynamic(traitobj_vec))),
@@ -1051,30 +1311,33 @@ impl<'a> RvalConverter<'a> {
         op2: &Operand,
         timing: Option<&InterpPass>,
     ) -> Constraints {
-        let first = {
+        // `(T, bool)`: the value on the path where it didn't overflow (the
+        // other one panics), and whether it did
+        let val_ty = match destty.kind() {
+            TyKind::RigidTy(RigidTy::Tuple(tys)) if tys.len() == 2 => tys[0],
+            _ => *destty,
+        };
+        let (val, toc) = {
             let _g = timing.map(|p| p.timing_span(TimingCat::ConvertBinop, cur_scope));
-            self.convert_binop(
+            self.convert_binop_helper(
                 ctxt,
                 span,
                 local_decls,
                 cur_scope,
-                destty,
+                &val_ty,
                 binop,
                 op1,
                 op2,
                 timing,
             )
-        }
-        .at(0)
-        .clone();
-
-        let second = Constraint::new(None, Some(RunningConstraint::Scalar(None)));
+        };
+        let (first, second) = scalar::checked(val, scalar::int_bounds(&val_ty));
 
         let constraint = Constraint::new(
             None,
             Some(RunningConstraint::Tuple(vec![
-                Constraints::from(first),
-                Constraints::from(second),
+                scalar_constraints(first.as_ref(), toc),
+                scalar_constraints(second.as_ref(), None),
             ])),
         );
         Constraints::from(constraint)
@@ -1092,225 +1355,33 @@ impl<'a> RvalConverter<'a> {
         op2: &Operand,
         timing: Option<&InterpPass>,
     ) -> Constraints {
-        let constraint = match binop {
-            BinOp::Add | BinOp::AddUnchecked => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x + y,
-                timing,
-            ),
-            BinOp::Sub | BinOp::SubUnchecked => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x - y,
-                timing,
-            ),
-            BinOp::Mul | BinOp::MulUnchecked => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x * y,
-                timing,
-            ),
-            BinOp::Div => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x / y,
-                timing,
-            ),
-            BinOp::Rem => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x % y,
-                timing,
-            ),
-            BinOp::Eq => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| {
-                    if x == y { 1 } else { 0 }
-                },
-                timing,
-            ),
-            BinOp::Lt => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| {
-                    if x < y { 1 } else { 0 }
-                },
-                timing,
-            ),
-            BinOp::Le => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| {
-                    if x <= y { 1 } else { 0 }
-                },
-                timing,
-            ),
-            BinOp::Ne => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| {
-                    if x != y { 1 } else { 0 }
-                },
-                timing,
-            ),
-            BinOp::Ge => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| {
-                    if x >= y { 1 } else { 0 }
-                },
-                timing,
-            ),
-            BinOp::Gt => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| {
-                    if x > y { 1 } else { 0 }
-                },
-                timing,
-            ),
-            // bit-level binops
-            BinOp::Shl | BinOp::ShlUnchecked => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x << y,
-                timing,
-            ),
-            BinOp::Shr | BinOp::ShrUnchecked => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x >> y,
-                timing,
-            ),
-            BinOp::BitAnd => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x & y,
-                timing,
-            ),
-            BinOp::BitOr => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x | y,
-                timing,
-            ),
-            BinOp::BitXor => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| x ^ y,
-                timing,
-            ),
-            // This binop return Ord results
-            BinOp::Cmp => self.convert_binop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op1,
-                op2,
-                |x, y| {
-                    if x < y {
-                        -1
-                    } else if x > y {
-                        1
-                    } else {
-                        0
-                    }
-                },
-                timing,
-            ),
+        match binop {
             BinOp::Offset => {
                 let (_, ty) = self.convert_ty(span, destty, Some(cur_scope), timing);
-                ty
+                Constraints::from(ty)
             }
-        };
-
-        Constraints::from(constraint)
+            _ => {
+                let (val, toc) = self.convert_binop_helper(
+                    ctxt,
+                    span,
+                    local_decls,
+                    cur_scope,
+                    destty,
+                    binop,
+                    op1,
+                    op2,
+                    timing,
+                );
+                // Wrapping (or `Cmp`'s `Ordering`, which has no bounds)
+                let val = scalar::fit(val, scalar::int_bounds(destty));
+                scalar_constraints(val.as_ref(), toc)
+            }
+        }
     }
 
+    /// Both operands' values through `binop` (see `scalar`), before any
+    /// check against the result type, and the trait-object tag one of them
+    /// carried, if it was a single disjunct.
     fn convert_binop_helper(
         &self,
         ctxt: &Context,
@@ -1318,11 +1389,11 @@ impl<'a> RvalConverter<'a> {
         local_decls: &[LocalDecl],
         cur_scope: &VOID,
         destty: &Ty,
+        binop: &BinOp,
         op1: &Operand,
         op2: &Operand,
-        f: fn(i128, i128) -> i128,
         timing: Option<&InterpPass>,
-    ) -> Constraint {
+    ) -> (Option<ScalarVal>, Option<(TraitObjTy, TraitObjConstraint)>) {
         let c_op1 = {
             let _g = timing.map(|p| p.timing_span(TimingCat::ConvertOp, cur_scope));
             self.convert_op(ctxt, span, local_decls, cur_scope, op1, destty, timing)
@@ -1331,60 +1402,25 @@ impl<'a> RvalConverter<'a> {
             let _g = timing.map(|p| p.timing_span(TimingCat::ConvertOp, cur_scope));
             self.convert_op(ctxt, span, local_decls, cur_scope, op2, destty, timing)
         };
-        if c_op1.len() != 1 || c_op2.len() != 1 {
-            return Constraint::new(None, Some(RunningConstraint::Scalar(None)));
-        }
-        match (c_op1.at(0).clone(), c_op2.at(0).clone()) {
-            (
-                Constraint {
-                    toc: None,
-                    cfc: Some(RunningConstraint::Scalar(Some(val1))),
-                    prov: _,
-                },
-                Constraint {
-                    toc: None,
-                    cfc: Some(RunningConstraint::Scalar(Some(val2))),
-                    prov: _,
-                },
-            ) => Constraint::new(None, Some(RunningConstraint::Scalar(Some(f(val1, val2))))),
-            (
-                Constraint {
-                    toc: None,
-                    cfc: Some(RunningConstraint::Scalar(Some(val1))),
-                    prov: _,
-                },
-                Constraint {
-                    toc: to,
-                    cfc: Some(RunningConstraint::Scalar(Some(val2))),
-                    prov: _,
-                },
-            ) => Constraint::new(to, Some(RunningConstraint::Scalar(Some(f(val1, val2))))),
-            (
-                Constraint {
-                    toc: to,
-                    cfc: Some(RunningConstraint::Scalar(Some(val1))),
-                    prov: _,
-                },
-                Constraint {
-                    toc: None,
-                    cfc: Some(RunningConstraint::Scalar(Some(val2))),
-                    prov: _,
-                },
-            ) => Constraint::new(to, Some(RunningConstraint::Scalar(Some(f(val1, val2))))),
-            (
-                Constraint {
-                    toc: _to1,
-                    cfc: Some(RunningConstraint::Scalar(Some(_val1))),
-                    prov: _,
-                },
-                Constraint {
-                    toc: _to2,
-                    cfc: Some(RunningConstraint::Scalar(Some(_val2))),
-                    prov: _,
-                },
-            ) => todo!(),
-            _ => Constraint::new(None, Some(RunningConstraint::Scalar(None))),
-        }
+        let sole_toc = |cs: &Constraints| match cs.len() {
+            1 => cs.at(0).toc.clone(),
+            _ => None,
+        };
+        let toc = sole_toc(&c_op1).or_else(|| sole_toc(&c_op2));
+        // By the operands' own types: a comparison's `destty` is `bool`
+        let bounds = |op: &Operand| {
+            op.ty(local_decls)
+                .ok()
+                .and_then(|ty| scalar::int_bounds(&ty))
+        };
+        let val = match (
+            ScalarVal::of_bounded(&c_op1, bounds(op1)),
+            ScalarVal::of_bounded(&c_op2, bounds(op2)),
+        ) {
+            (Some(a), Some(b)) => scalar::binop(binop, &a, &b),
+            _ => None,
+        };
+        (val, toc)
     }
 
     fn convert_unop(
@@ -1398,34 +1434,22 @@ impl<'a> RvalConverter<'a> {
         op: &Operand,
         timing: Option<&InterpPass>,
     ) -> Constraints {
-        let constraint = match unop {
-            UnOp::Neg => self.convert_unop_helper(
+        match unop {
+            UnOp::Neg | UnOp::Not => self.convert_unop_helper(
                 ctxt,
                 span,
                 local_decls,
                 cur_scope,
                 destty,
+                unop,
                 op,
-                |x| -x,
-                timing,
-            ),
-            UnOp::Not => self.convert_unop_helper(
-                ctxt,
-                span,
-                local_decls,
-                cur_scope,
-                destty,
-                op,
-                |x| !x,
                 timing,
             ),
             UnOp::PtrMetadata => {
                 let (_, ty) = self.convert_ty(span, destty, Some(cur_scope), timing);
-                ty
+                Constraints::from(ty)
             }
-        };
-
-        Constraints::from(constraint)
+        }
     }
 
     fn convert_unop_helper(
@@ -1435,29 +1459,22 @@ impl<'a> RvalConverter<'a> {
         local_decls: &[LocalDecl],
         cur_scope: &VOID,
         destty: &Ty,
+        unop: &UnOp,
         op: &Operand,
-        f: fn(i128) -> i128,
         timing: Option<&InterpPass>,
-    ) -> Constraint {
+    ) -> Constraints {
         let c_op = {
             let _g = timing.map(|p| p.timing_span(TimingCat::ConvertOp, cur_scope));
             self.convert_op(ctxt, span, local_decls, cur_scope, op, destty, timing)
         };
-        if c_op.len() != 1 {
-            return Constraint::new(None, Some(RunningConstraint::Scalar(None)));
-        }
-        match c_op.at(0).clone() {
-            Constraint {
-                toc: to,
-                cfc: Some(RunningConstraint::Scalar(Some(val))),
-                prov: _,
-            } => Constraint::new(to, Some(RunningConstraint::Scalar(Some(f(val))))),
-            Constraint {
-                toc: to,
-                cfc: _,
-                prov: _,
-            } => Constraint::new(to, Some(RunningConstraint::Scalar(None))),
-        }
+        let toc = match c_op.len() {
+            1 => c_op.at(0).toc.clone(),
+            _ => None,
+        };
+        let is_bool = matches!(destty.kind(), TyKind::RigidTy(RigidTy::Bool));
+        let val = ScalarVal::of(&c_op).and_then(|a| scalar::unop(unop, &a, is_bool));
+        let val = scalar::fit(val, scalar::int_bounds(destty));
+        scalar_constraints(val.as_ref(), toc)
     }
 
     fn is_unsafe_cell(&self, def: &AdtDef) -> bool {
@@ -0,0 +1,283 @@
+//! Per-call-site decision of whether (and how) devirtualizing is worth it.
+//!
+//! Rewriting every site with an FSA result isn't always a win: a long
+//! pointer-compare chain in straight-line code can cost more than the vtable
+//! jump it replaces (see notes/MILESTONES.md, "fallback rewrite performs worse
+//! than initial dyn call"). `CostModel::decide` weighs, in rough "cycles"
+//! units:
+//!
+//! - the vtable call: a slot load plus an indirect call (`vcall`),
+//! - a compare chain: `compare` per comparison actually executed, using the
+//!   profile's hit shares when there are any and a uniform split otherwise
+//!   (the last arm is the unconditional fallback, so it needs no compare),
+//! - a tag switch: a single `switch`, but only when a `TagPlan` exists,
+//!
+//! minus `inline_gain` for every arm whose callee is small enough to inline
+//! (weighted by how often that arm runs). Sites outside loops must win by at
+//! least `min_gain` to be worth the code growth; sites in loops take any win.
+//!
+//! The model is off by default, which rewrites every site with a target as
+//! before. `--cost-model on` turns it on with the defaults below, and
+//! `--cost-model key=value,...` turns it on with those knobs changed.
+
+extern crate rustc_middle;
+extern crate rustc_span;
+
+use rustc_middle::middle::codegen_fn_attrs::InlineAttr;
+use rustc_middle::mir::{BasicBlock, TerminatorKind};
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+use rustc_span::def_id::DefId;
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Strategy {
+    Direct,
+    TagSwitch,
+    PointerChain,
+    Leave,
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Strategy::Direct => "direct call",
+            Strategy::TagSwitch => "tag switch",
+            Strategy::PointerChain => "pointer-compare chain",
+            Strategy::Leave => "leave dyn call",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CostModel {
+    pub enabled: bool,
+    pub vcall: f64,
+    pub compare: f64,
+    pub switch: f64,
+    pub inline_gain: f64,
+    /// MIR statements + terminators at or below which a callee counts as
+    /// inlinable
+    pub inline_size: usize,
+    pub min_gain: f64,
+    /// never emit more arms than this, whatever the numbers say
+    pub max_arms: usize,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            vcall: 4.0,
+            compare: 1.0,
+            switch: 1.5,
+            inline_gain: 2.0,
+            inline_size: 30,
+            min_gain: 1.0,
+            max_arms: 8,
+        }
+    }
+}
+
+impl CostModel {
+    /// `off`, `on`, or comma-separated `key=value` overrides of the
+    /// defaults, e.g. `vcall=6,max_arms=4`, which also turn the model on.
+    pub fn parse(s: &str) -> Result<CostModel, String> {
+        let mut model = CostModel::default();
+        if s == "off" {
+            return Ok(model);
+        }
+        model.enabled = true;
+        if s == "on" {
+            return Ok(model);
+        }
+
+        for kv in s.split(',').filter(|kv| !kv.is_empty()) {
+            let Some((k, v)) = kv.split_once('=') else {
+                return Err(format!("expected key=value, got {:?}", kv));
+            };
+            let f = || v.parse::<f64>().map_err(|e| format!("{}: {}", k, e));
+            let u = || v.parse::<usize>().map_err(|e| format!("{}: {}", k, e));
+            match k {
+                "vcall" => model.vcall = f()?,
+                "compare" => model.compare = f()?,
+                "switch" => model.switch = f()?,
+                "inline_gain" => model.inline_gain = f()?,
+                "inline_size" => model.inline_size = u()?,
+                "min_gain" => model.min_gain = f()?,
+                "max_arms" => model.max_arms = u()?,
+                _ => return Err(format!("unknown cost model key {:?}", k)),
+            }
+        }
+
+        Ok(model)
+    }
+
+    pub fn decide(&self, facts: &SiteFacts) -> (Strategy, String) {
+        let n = facts.callees.len();
+
+        if !self.enabled {
+            let strategy = match n {
+                0 => Strategy::Leave,
+                1 => Strategy::Direct,
+                _ if facts.tagged => Strategy::TagSwitch,
+                _ => Strategy::PointerChain,
+            };
+            return (strategy, "cost model off".to_string());
+        }
+
+        match n {
+            0 => return (Strategy::Leave, "no targets".to_string()),
+            1 => return (Strategy::Direct, "single target".to_string()),
+            _ if n > self.max_arms => {
+                return (
+                    Strategy::Leave,
+                    format!("{} targets > max_arms ({})", n, self.max_arms),
+                );
+            }
+            _ => {}
+        }
+
+        // Hit share per arm, in the order the arms will be emitted (hottest
+        // first when there's a profile).
+        let total: u64 = facts.callees.iter().map(|c| c.hits).sum();
+        let shares: Vec<f64> = facts
+            .callees
+            .iter()
+            .map(|c| {
+                if total > 0 {
+                    c.hits as f64 / total as f64
+                } else {
+                    1.0 / n as f64
+                }
+            })
+            .collect();
+
+        let inline_gain: f64 = facts
+            .callees
+            .iter()
+            .zip(&shares)
+            .filter(|(c, _)| c.inlinable(self.inline_size))
+            .map(|(_, p)| p * self.inline_gain)
+            .sum();
+
+        let compares: f64 = shares
+            .iter()
+            .enumerate()
+            .map(|(i, p)| p * (i + 1).min(n - 1) as f64)
+            .sum();
+
+        let chain = compares * self.compare - inline_gain;
+        let switch = self.switch - inline_gain;
+        let vcall = self.vcall;
+
+        let (best, cost) = if facts.tagged && switch <= chain {
+            (Strategy::TagSwitch, switch)
+        } else {
+            (Strategy::PointerChain, chain)
+        };
+
+        let margin = if facts.in_loop { 0.0 } else { self.min_gain };
+        let strategy = if vcall - cost > margin {
+            best
+        } else {
+            Strategy::Leave
+        };
+
+        let rationale = format!(
+            "{} targets{}{}{}: vcall={:.2} chain={:.2}{} (inline gain {:.2}, need gain > {:.2})",
+            n,
+            if facts.tagged { ", tagged" } else { "" },
+            if facts.in_loop { ", in loop" } else { "" },
+            if total > 0 { ", profiled" } else { "" },
+            vcall,
+            chain,
+            if facts.tagged {
+                format!(" switch={:.2}", switch)
+            } else {
+                String::new()
+            },
+            inline_gain,
+            margin,
+        );
+
+        (strategy, rationale)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CalleeFacts {
+    /// MIR statements + terminators, if the body is available at all
+    pub size: Option<usize>,
+    pub inline_never: bool,
+    pub hits: u64,
+}
+
+impl CalleeFacts {
+    pub fn of<'tcx>(tcx: TyCtxt<'tcx>, did: DefId, hits: u64) -> CalleeFacts {
+        let size = tcx.is_mir_available(did).then(|| {
+            tcx.optimized_mir(did)
+                .basic_blocks
+                .iter()
+                .map(|bb| bb.statements.len() + 1)
+                .sum()
+        });
+
+        CalleeFacts {
+            size,
+            inline_never: matches!(tcx.codegen_fn_attrs(did).inline, InlineAttr::Never),
+            hits,
+        }
+    }
+
+    fn inlinable(&self, inline_size: usize) -> bool {
+        !self.inline_never && self.size.is_some_and(|s| s <= inline_size)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SiteFacts {
+    /// in the order the rewriter would emit them
+    pub callees: Vec<CalleeFacts>,
+    pub tagged: bool,
+    pub in_loop: bool,
+}
+
+/// Whether `bb` of `caller` can reach itself again, i.e. sits in a loop.
+/// `bb` is the analysis' block number, so it's only trusted if that block
+/// of `optimized_mir` is still the call at `site`; otherwise the site
+/// counts as outside any loop, which only makes it harder to rewrite.
+pub fn in_loop<'tcx>(tcx: TyCtxt<'tcx>, caller: DefId, bb: usize, site: Span) -> bool {
+    if !tcx.is_mir_available(caller) {
+        return false;
+    }
+
+    let bbs = &tcx.optimized_mir(caller).basic_blocks;
+    let start = BasicBlock::from_usize(bb);
+    if start.as_usize() >= bbs.len() {
+        return false;
+    }
+    let term = bbs[start].terminator();
+    if !matches!(term.kind, TerminatorKind::Call { .. }) || term.source_info.span != site {
+        return false;
+    }
+
+    let mut seen = vec![false; bbs.len()];
+    let mut stack: Vec<BasicBlock> = bbs[start].terminator().successors().collect();
+    while let Some(cur) = stack.pop() {
+        if cur == start {
+            return true;
+        }
+        if std::mem::replace(&mut seen[cur.as_usize()], true) {
+            continue;
+        }
+        stack.extend(bbs[cur].terminator().successors());
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod cost_tests;
@@ -0,0 +1,116 @@
+use super::*;
+
+fn callee(size: Option<usize>, hits: u64) -> CalleeFacts {
+    CalleeFacts {
+        size,
+        inline_never: false,
+        hits,
+    }
+}
+
+fn site(callees: Vec<CalleeFacts>, tagged: bool, in_loop: bool) -> SiteFacts {
+    SiteFacts {
+        callees,
+        tagged,
+        in_loop,
+    }
+}
+
+fn on() -> CostModel {
+    CostModel::parse("on").unwrap()
+}
+
+#[test]
+fn test_parse() {
+    assert!(!CostModel::default().enabled);
+    assert!(!CostModel::parse("off").unwrap().enabled);
+    assert!(on().enabled);
+
+    let model = CostModel::parse("vcall=6,max_arms=4").unwrap();
+    assert!(model.enabled);
+    assert_eq!(model.vcall, 6.0);
+    assert_eq!(model.max_arms, 4);
+    assert_eq!(model.compare, CostModel::default().compare);
+}
+
+#[test]
+fn test_parse_err() {
+    assert!(CostModel::parse("vcall").is_err());
+    assert!(CostModel::parse("vcall=x").is_err());
+    assert!(CostModel::parse("max_arms=1.5").is_err());
+    assert!(CostModel::parse("bogus=1").is_err());
+}
+
+#[test]
+fn test_decide_off() {
+    let model = CostModel::default();
+    let two = || vec![callee(None, 0), callee(None, 0)];
+    assert_eq!(model.decide(&site(vec![], false, false)).0, Strategy::Leave);
+    assert_eq!(
+        model.decide(&site(vec![callee(None, 0)], false, false)).0,
+        Strategy::Direct
+    );
+    assert_eq!(
+        model.decide(&site(two(), false, false)).0,
+        Strategy::PointerChain
+    );
+    assert_eq!(
+        model.decide(&site(two(), true, false)).0,
+        Strategy::TagSwitch
+    );
+}
+
+#[test]
+fn test_decide_chain_vs_switch() {
+    let model = on();
+    // two arms: one compare either way (1.0) beats the switch (1.5)
+    let two = vec![callee(None, 0), callee(None, 0)];
+    assert_eq!(
+        model.decide(&site(two, true, false)).0,
+        Strategy::PointerChain
+    );
+    // three arms: 5/3 compares on average, so the switch wins
+    let three = vec![callee(None, 0), callee(None, 0), callee(None, 0)];
+    assert_eq!(
+        model.decide(&site(three, true, false)).0,
+        Strategy::TagSwitch
+    );
+}
+
+#[test]
+fn test_decide_max_arms() {
+    let model = on();
+    let many = (0..model.max_arms + 1).map(|_| callee(None, 0)).collect();
+    assert_eq!(model.decide(&site(many, false, true)).0, Strategy::Leave);
+}
+
+#[test]
+fn test_decide_min_gain_outside_loops() {
+    // chain = 1.0, vcall = 2.0: a gain of 1.0, which isn't > min_gain
+    let model = CostModel::parse("vcall=2").unwrap();
+    let two = || vec![callee(None, 0), callee(None, 0)];
+    assert_eq!(model.decide(&site(two(), false, false)).0, Strategy::Leave);
+    assert_eq!(
+        model.decide(&site(two(), false, true)).0,
+        Strategy::PointerChain
+    );
+}
+
+#[test]
+fn test_decide_profile_and_inlining() {
+    // a cold first arm makes the chain cost ~1 compare; small callees
+    // take off inline_gain, so even vcall=1 is beaten
+    let model = CostModel::parse("vcall=1").unwrap();
+    let big = vec![callee(None, 99), callee(None, 1)];
+    assert_eq!(model.decide(&site(big, false, false)).0, Strategy::Leave);
+    let small = vec![callee(Some(5), 99), callee(Some(5), 1)];
+    assert_eq!(
+        model.decide(&site(small, false, false)).0,
+        Strategy::PointerChain
+    );
+
+    let mut never = callee(Some(5), 99);
+    never.inline_never = true;
+    assert!(!never.inlinable(model.inline_size));
+    assert!(!callee(Some(model.inline_size + 1), 0).inlinable(model.inline_size));
+}
@@ -0,0 +1,202 @@
+//! Differential execution: the original program vs. its rewritten build.
+//!
+//! `verifopt-diff` (src/bin/verifopt-diff.rs) builds a crate twice through
+//! `cargo verifopt`, once with `--no-rewrite` and once normally, into separate
+//! target dirs, runs every resulting binary (or test binary, with `--tests`)
+//! on the same arguments, and compares the two runs with `compare`: exit
+//! status, stdout, stderr and votrace's `calls` file when the program writes
+//! one. Rewriting must not change observable behavior, so any difference is a
+//! miscompile (or a nondeterministic program - see `normalize`).
+//!
+//! To point at the likely culprit, the rewriting build appends one
+//! `<fn>\t<site>` line to `REWRITTEN_FILE`, next to the `stats` file (see
+//! `rewritten_path`), per call site it edits (see `rewrite::optimized_mir`),
+//! which `read_rewritten` loads for the report.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const REWRITTEN_FILE: &str = "rewritten";
+
+/// Where the rewriting build writes `REWRITTEN_FILE`: the directory the
+/// analysis writes `stats_file` to.
+pub fn rewritten_path(stats_file: &str) -> PathBuf {
+    Path::new(stats_file).with_file_name(REWRITTEN_FILE)
+}
+
+/// Everything observable about one run of one binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// `None` if killed by a signal
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// votrace's `calls` file, if the run wrote one
+    pub calls: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stream {
+    Status,
+    Stdout,
+    Stderr,
+    Calls,
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Stream::Status => "exit status",
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+            Stream::Calls => "calls",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// which binary, e.g. `bin:shims` or `test:dispatch`
+    pub run: String,
+    pub stream: Stream,
+    /// 1-based line of the first difference; 0 for `Stream::Status`
+    pub line: usize,
+    pub original: String,
+    pub rewritten: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.stream == Stream::Status {
+            return write!(
+                f,
+                "{}: exit status {} (original) vs {} (rewritten)",
+                self.run, self.original, self.rewritten
+            );
+        }
+        write!(
+            f,
+            "{}: {} differs at line {}\n  original:  {}\n  rewritten: {}",
+            self.run, self.stream, self.line, self.original, self.rewritten
+        )
+    }
+}
+
+pub fn compare(run: &str, original: &Outcome, rewritten: &Outcome) -> Vec<Divergence> {
+    let mut out = Vec::new();
+
+    if original.status != rewritten.status {
+        let status = |s: Option<i32>| s.map_or("<signal>".to_string(), |c| c.to_string());
+        out.push(Divergence {
+            run: run.to_string(),
+            stream: Stream::Status,
+            line: 0,
+            original: status(original.status),
+            rewritten: status(rewritten.status),
+        });
+    }
+
+    let missing = "<no calls file>".to_string();
+    let streams = [
+        (
+            Stream::Stdout,
+            Some(&original.stdout),
+            Some(&rewritten.stdout),
+        ),
+        (
+            Stream::Stderr,
+            Some(&original.stderr),
+            Some(&rewritten.stderr),
+        ),
+        (
+            Stream::Calls,
+            original.calls.as_ref(),
+            rewritten.calls.as_ref(),
+        ),
+    ];
+    for (stream, a, b) in streams {
+        let (a, b) = match (a, b) {
+            (None, None) => continue,
+            (a, b) => (a.unwrap_or(&missing), b.unwrap_or(&missing)),
+        };
+        if let Some((line, a, b)) = first_diff(&normalize(a), &normalize(b)) {
+            out.push(Divergence {
+                run: run.to_string(),
+                stream,
+                line,
+                original: a,
+                rewritten: b,
+            });
+        }
+    }
+
+    out
+}
+
+/// First differing line (1-based) and both sides of it, `<eof>` for a side
+/// that ran out of lines.
+pub fn first_diff(a: &str, b: &str) -> Option<(usize, String, String)> {
+    let mut a_lines = a.lines();
+    let mut b_lines = b.lines();
+    let mut i = 0;
+    loop {
+        i += 1;
+        match (a_lines.next(), b_lines.next()) {
+            (None, None) => return None,
+            (x, y) if x == y => continue,
+            (x, y) => {
+                let eof = |l: Option<&str>| l.unwrap_or("<eof>").to_string();
+                return Some((i, eof(x), eof(y)));
+            }
+        }
+    }
+}
+
+/// Drops the parts of a run's output that differ between two identical runs
+/// anyway: libtest's `finished in 0.01s` and the thread id in panic messages
+/// (`thread 'main' (1234) panicked at`).
+pub fn normalize(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        let mut line = line.to_string();
+
+        if let Some(idx) = line.find("; finished in ") {
+            line.truncate(idx);
+        }
+
+        if line.starts_with("thread '")
+            && let Some(open) = line.find("' (")
+            && let Some(close) = line[open..].find(") panicked")
+            && line[open + 3..open + close]
+                .bytes()
+                .all(|b| b.is_ascii_digit())
+        {
+            line.replace_range(open + 1..open + close + 1, "");
+        }
+
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// `REWRITTEN_FILE` as fn -> its rewritten call sites.
+pub fn read_rewritten(path: &str) -> io::Result<BTreeMap<String, Vec<String>>> {
+    let mut fns: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for line in fs::read_to_string(path)?.lines() {
+        if let Some((f, site)) = line.split_once('\t') {
+            let sites = fns.entry(f.to_string()).or_default();
+            if !sites.iter().any(|s| s == site) {
+                sites.push(site.to_string());
+            }
+        }
+    }
+    Ok(fns)
+}
+
+#[cfg(test)]
+mod diff_tests;
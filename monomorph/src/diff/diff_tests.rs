@@ -0,0 +1,83 @@
+use super::*;
+
+fn outcome(status: Option<i32>, stdout: &str, calls: Option<&str>) -> Outcome {
+    Outcome {
+        status,
+        stdout: stdout.to_string(),
+        stderr: String::new(),
+        calls: calls.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_normalize_test_timing() {
+    assert_eq!(
+        normalize("test result: ok. 3 passed; 0 failed; finished in 0.01s"),
+        "test result: ok. 3 passed; 0 failed\n"
+    );
+}
+
+#[test]
+fn test_normalize_thread_id() {
+    assert_eq!(
+        normalize("thread 'main' (1234) panicked at src/main.rs:3:5:"),
+        "thread 'main' panicked at src/main.rs:3:5:\n"
+    );
+    // not a thread id: left alone
+    assert_eq!(
+        normalize("thread 'main' (abc) panicked at src/main.rs:3:5:"),
+        "thread 'main' (abc) panicked at src/main.rs:3:5:\n"
+    );
+}
+
+#[test]
+fn test_first_diff() {
+    assert_eq!(first_diff("a\nb\n", "a\nb\n"), None);
+    assert_eq!(
+        first_diff("a\nb\n", "a\nc\n"),
+        Some((2, "b".to_string(), "c".to_string()))
+    );
+    assert_eq!(
+        first_diff("a\n", "a\nb\n"),
+        Some((2, "<eof>".to_string(), "b".to_string()))
+    );
+}
+
+#[test]
+fn test_compare() {
+    let a = outcome(Some(0), "1\n2\n", Some("<Cat as Animal>::speak\n"));
+    assert!(compare("bin:x", &a, &a.clone()).is_empty());
+
+    let b = outcome(Some(1), "1\n3\n", None);
+    let streams: Vec<(Stream, usize)> = compare("bin:x", &a, &b)
+        .into_iter()
+        .map(|d| (d.stream, d.line))
+        .collect();
+    assert_eq!(
+        streams,
+        vec![(Stream::Status, 0), (Stream::Stdout, 2), (Stream::Calls, 1)]
+    );
+}
+
+#[test]
+fn test_rewritten_path() {
+    assert_eq!(rewritten_path("stats"), PathBuf::from("rewritten"));
+    assert_eq!(
+        rewritten_path("/tmp/out/stats.txt"),
+        PathBuf::from("/tmp/out/rewritten")
+    );
+}
+
+#[test]
+fn test_read_rewritten() {
+    let path = std::env::temp_dir().join(format!("verifopt_rewritten_{}", std::process::id()));
+    fs::write(
+        &path,
+        "main\tsrc/main.rs:3:5\nmain\tsrc/main.rs:3:5\nmain\tsrc/main.rs:9:1\nf\tsrc/lib.rs:1:1\n",
+    )
+    .unwrap();
+    let fns = read_rewritten(&path.to_string_lossy()).unwrap();
+    let _ = fs::remove_file(&path);
+    assert_eq!(fns["main"], vec!["src/main.rs:3:5", "src/main.rs:9:1"]);
+    assert_eq!(fns["f"], vec!["src/lib.rs:1:1"]);
+}
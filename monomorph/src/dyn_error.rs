@@ -0,0 +1,314 @@
+//! `Box<dyn Error>`: the std `From` conversions into it and the
+//! `downcast` family out of it.
+//!
+//! `From<E: Error> for Box<dyn Error>` (and the `+ Send + Sync` variant)
+//! is a generic std impl we'd otherwise see only through its signature -
+//! an empty `Box` Adt with every `Error` impl behind it - so each concrete
+//! error type that went in through `?` or `.into()` was lost. Modeled here
+//! as what its body does: box the value and unsize it, same as
+//! `convert_cast` does for `Box::new(e) as Box<dyn Error>`. `combinators`'
+//! `from_residual` uses the same conversion when `?` changes the error
+//! type.
+//!
+//! `downcast_ref`/`downcast_mut` on `dyn Error` and `downcast` on
+//! `Box<dyn Error>` split the receiver by concrete type: disjuncts of the
+//! requested type - the same Adt with the same generic args, so a
+//! `Wrapper<u8>` isn't a `Wrapper<u16>` - go to `Some`/`Ok`, the rest to
+//! `None`/`Err`, and anything we can't tell the type of (args with a param
+//! or region in them, say) to both. The same goes for `dyn Any` (and
+//! `Rc`/`Arc<dyn Any>::downcast`), whose `is` and `TypeId`s are in
+//! `type_id`.
+//!
+//! `From<&str>`/`From<String>` (std's private `StringError`), identity
+//! conversions, and downcasts to a non-Adt type run the real body.
+
+use rustc_public::mir::{LocalDecl, Operand};
+use rustc_public::ty::{AdtDef, FnDef, GenericArgKind, GenericArgs, RigidTy, Span, Ty, TyKind};
+
+use crate::Context;
+use crate::InterpPass;
+use crate::combinators::type_arg;
+use crate::constraints::{
+    ADTFields, Constraint, Constraints, Location, RunningConstraint, TraitObjConstraint,
+    TraitObjTy, VOID,
+};
+use crate::convert::WrapperKind;
+use crate::error::Error;
+use crate::interior::instantiated_sig;
+use crate::trait_collect::mentions_param;
+
+use log::debug;
+
+impl<'a> InterpPass<'a> {
+    /// Called from `stdlib_stub`: Some(result) for a `From`/`Into`
+    /// conversion into `Box<dyn Error>`, or a downcast out of one, that's
+    /// modeled here.
+    pub fn stub_dyn_error(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let path = Self::callee_path(fndef);
+        let method = Self::method_name(fndef);
+        let is_conversion = path.ends_with("From::from") || path.ends_with("Into::into");
+        if !is_conversion && !method.starts_with("downcast") {
+            return None;
+        }
+        let out_ty = instantiated_sig(fndef, genargs)?.value.output();
+        let arg = args.first()?;
+        let arg_ty = arg.ty(local_decls).ok()?;
+        let relevant = if is_conversion {
+            self.is_dyn_object(out_ty, TraitObjTy::is_error_trait)
+        } else {
+            self.is_dyn_object(arg_ty, |t| t.is_error_trait() || t.is_any_trait())
+        };
+        if !relevant {
+            return None;
+        }
+        let value = self.resolve_arg(
+            ctxt,
+            term_span,
+            caller_scope,
+            &None,
+            local_decls,
+            arg,
+            false,
+        );
+
+        let result = if is_conversion {
+            self.box_dyn_error(caller_scope, arg_ty, value, out_ty)?
+        } else {
+            self.stub_downcast(caller_scope, &method, value, out_ty)?
+        };
+        debug!("DYN ERROR {} -> {} disjuncts", path, result.inner.len());
+        Some(Ok(Some(result)))
+    }
+
+    /// `value` (an `E`) converted to `to`, a `Box<dyn Error ..>`: a `Box`
+    /// holding `value` labeled as that trait object. None if `to` isn't
+    /// one, or `E` is one of the conversions std routes through a private
+    /// type (or the identity `From<T> for T`).
+    pub(crate) fn box_dyn_error(
+        &self,
+        scope: &VOID,
+        from: Ty,
+        value: Constraints,
+        to: Ty,
+    ) -> Option<Constraints> {
+        if from == to || !self.is_dyn_object(to, TraitObjTy::is_error_trait) {
+            return None;
+        }
+        let TyKind::RigidTy(RigidTy::Adt(from_def, _)) = from.kind() else {
+            return None;
+        };
+        let from_name = from_def.0.name();
+        if self.converter.wrapper_kind(&from_def).is_some()
+            || from_name.ends_with("string::String")
+            || from_name.ends_with("borrow::Cow")
+        {
+            return None;
+        }
+        let TyKind::RigidTy(RigidTy::Adt(box_def, box_args)) = to.kind() else {
+            return None;
+        };
+
+        let (traitobjtys, _) =
+            self.converter
+                .convert_ty(&Location::unknown(), &to, Some(scope), Some(self));
+        let unsized_value =
+            self.converter
+                .convert_cast_helper(&traitobjtys?, &value, &Location::unknown());
+        Some(Constraints::from(Constraint::new(
+            None,
+            Some(RunningConstraint::Adt(
+                box_def,
+                box_args,
+                None,
+                ADTFields::from([(0, unsized_value)]),
+            )),
+        )))
+    }
+
+    /// `downcast_ref`/`downcast_mut` (`-> Option<&T>`) and `downcast`
+    /// (`-> Result<Box<T>, Box<dyn Error>>`, or `Rc`/`Arc` for `dyn Any`)
+    /// on `value`.
+    fn stub_downcast(
+        &self,
+        scope: &VOID,
+        method: &str,
+        value: Constraints,
+        out_ty: Ty,
+    ) -> Option<Constraints> {
+        let boxed = method == "downcast";
+        let hit_ty = if boxed {
+            type_arg(type_arg(out_ty, 0)?, 0)?
+        } else {
+            let mut ty = type_arg(out_ty, 0)?;
+            while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() {
+                ty = inner;
+            }
+            ty
+        };
+        let (hits, misses) = self.split_by_type(&value, hit_ty)?;
+
+        let (hit_name, miss_name) = if boxed {
+            ("Ok", "Err")
+        } else {
+            ("Some", "None")
+        };
+        let mut out = Constraints::new();
+        if !hits.inner.is_empty() {
+            let payload = if boxed {
+                let TyKind::RigidTy(RigidTy::Adt(box_def, box_args)) = type_arg(out_ty, 0)?.kind()
+                else {
+                    return None;
+                };
+                Constraints::from(Constraint::new(
+                    None,
+                    Some(RunningConstraint::Adt(
+                        box_def,
+                        box_args,
+                        None,
+                        ADTFields::from([(0, hits)]),
+                    )),
+                ))
+            } else {
+                hits
+            };
+            out.append(self.build_variant(scope, out_ty, hit_name, Some(payload)));
+        }
+        if !misses.inner.is_empty() {
+            let payload = boxed.then_some(misses);
+            out.append(self.build_variant(scope, out_ty, miss_name, payload));
+        }
+        Some(out)
+    }
+
+    /// A trait object value split by its concrete type: the objects that
+    /// are (or might be) a `target` - unwrapped, as a plain `target` value
+    /// - and the ones that aren't (or might not be), left as they were.
+    /// None if `target` isn't an Adt, or there's a pointer wrapper we
+    /// can't see into.
+    pub(crate) fn split_by_type(
+        &self,
+        value: &Constraints,
+        target: Ty,
+    ) -> Option<(Constraints, Constraints)> {
+        let TyKind::RigidTy(RigidTy::Adt(target_def, target_args)) = target.kind() else {
+            return None;
+        };
+        let mut hits = Constraints::new();
+        let mut misses = Constraints::new();
+        for c in value.inner.iter() {
+            // a `Box<dyn Error>` is the `Box` Adt `box_dyn_error` (or the
+            // unsizing cast) built, a `&dyn Error` the object itself
+            let (wrapper, leaves) = match &c.cfc {
+                Some(RunningConstraint::Adt(def, args, _, fields))
+                    if self
+                        .converter
+                        .wrapper_kind(def)
+                        .is_some_and(is_object_pointer) =>
+                {
+                    (Some((*def, args.clone())), fields.get(&0).cloned()?)
+                }
+                _ => (None, Constraints::from(c.clone())),
+            };
+            let mut missed = Constraints::new();
+            for leaf in leaves.inner.iter() {
+                let is_target = concrete_adt(leaf)
+                    .and_then(|(def, args)| same_type((def, &args), (target_def, &target_args)));
+                if is_target != Some(false) {
+                    hits.push(Constraint::new(None, leaf.cfc.clone()).with_prov(leaf.prov.clone()));
+                }
+                if is_target != Some(true) {
+                    missed.push(leaf.clone());
+                }
+            }
+            if missed.inner.is_empty() {
+                continue;
+            }
+            match wrapper {
+                Some((def, args)) => misses.push(Constraint::new(
+                    c.toc.clone(),
+                    Some(RunningConstraint::Adt(
+                        def,
+                        args,
+                        None,
+                        ADTFields::from([(0, missed)]),
+                    )),
+                )),
+                None => misses.append(missed),
+            }
+        }
+        Some((hits, misses))
+    }
+
+    /// `dyn Trait ..` for a trait `is_trait` accepts, behind any number of
+    /// refs and `Box`/`Rc`/`Arc`s.
+    pub(crate) fn is_dyn_object(&self, ty: Ty, is_trait: impl Fn(&TraitObjTy) -> bool) -> bool {
+        let mut ty = ty;
+        loop {
+            match ty.kind() {
+                TyKind::RigidTy(RigidTy::Ref(_, inner, _)) => ty = inner,
+                TyKind::RigidTy(RigidTy::Adt(def, args))
+                    if self
+                        .converter
+                        .wrapper_kind(&def)
+                        .is_some_and(is_object_pointer) =>
+                {
+                    match args.0.first().and_then(|a| a.ty()) {
+                        Some(inner) => ty = *inner,
+                        None => return false,
+                    }
+                }
+                TyKind::RigidTy(RigidTy::Dynamic(preds, _)) => {
+                    return preds.iter().any(|p| {
+                        TraitObjTy::new_from_bound_existential(p).is_some_and(|t| is_trait(&t))
+                    });
+                }
+                _ => return false,
+            }
+        }
+    }
+}
+
+/// The concrete type behind one trait object disjunct, if it's an Adt we
+/// know.
+pub(crate) fn concrete_adt(leaf: &Constraint) -> Option<(AdtDef, GenericArgs)> {
+    match (&leaf.toc, &leaf.cfc) {
+        (Some((_, TraitObjConstraint::Adt(def, args, ..))), _) => Some((*def, args.clone())),
+        (None, Some(RunningConstraint::Adt(def, args, ..))) => Some((*def, args.clone())),
+        _ => None,
+    }
+}
+
+/// Whether the Adt `a` is the Adt `b`: known when their defs differ, their
+/// generic args are the same, or some type arg differs with no params in
+/// it; None when what differs is a param or region an instantiation might
+/// still make equal.
+fn same_type(a: (AdtDef, &GenericArgs), b: (AdtDef, &GenericArgs)) -> Option<bool> {
+    if a.0.0 != b.0.0 {
+        return Some(false);
+    }
+    if a.1 == b.1 {
+        return Some(true);
+    }
+    let differs = a.1.0.len() == b.1.0.len()
+        && a.1.0.iter().zip(b.1.0.iter()).any(|pair| match pair {
+            (GenericArgKind::Type(x), GenericArgKind::Type(y)) => {
+                x != y && !mentions_param(x) && !mentions_param(y)
+            }
+            _ => false,
+        });
+    differs.then_some(false)
+}
+
+/// Pointers a trait object can sit behind that have a `downcast`.
+fn is_object_pointer(kind: WrapperKind) -> bool {
+    matches!(kind, WrapperKind::Box | WrapperKind::Rc | WrapperKind::Arc)
+}
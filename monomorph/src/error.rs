@@ -6,4 +6,8 @@ pub enum Error {
     RecurseLimit(u32),
     #[error("parametric summary build hit control flow driven by a parameter's value")]
     SummaryImprecise,
+    #[error("bad stub spec {0}: {1}")]
+    StubSpec(String, String),
+    #[error("bad config {0}: {1}")]
+    Config(String, String),
 }
@@ -0,0 +1,328 @@
+//! `format_args!` and `core::fmt::write`.
+//!
+//! Every `format!`/`println!`/`write!` builds one `rt::Argument` per
+//! placeholder - a type-erased pointer to the value plus its `<T as
+//! Display>::fmt` (or `Debug`, `LowerHex`, ...) transmuted to a fn pointer
+//! - packs them into an `Arguments`, and hands that to `fmt::write` with
+//! the sink as a `&mut dyn fmt::Write`. Interpreted as-is, the pairing of
+//! value and fn is lost to the erasure and the template is walked with raw
+//! pointer arithmetic, so every `Display` impl behind a placeholder falls
+//! back to CHA.
+//!
+//! Modeled instead, in our own layout (nothing but `fmt::write` reads
+//! either type, and that's stubbed here too):
+//!   - `rt::Argument::new_*::<T>(x)`: an `Argument` Adt with `x` in field
+//!     0 and the fn item `<T as Trait>::fmt` in field 1 (found in the
+//!     constructor's own body; nothing for a count or a no-op)
+//!   - `Arguments::new*`: an `Arguments` Adt with every placeholder in
+//!     field 1, where the real `args` pointer is. `convert_agg` keeps the
+//!     `[rt::Argument; N]` elements for this.
+//!   - `fmt::write(output, args)`: `Write::write_str` on each concrete
+//!     writer in `output` for the literal pieces, then each placeholder's
+//!     fn on its value and a `Formatter` whose `buf` is `output`.
+//!
+//! So `<A as Display>::fmt` runs with `A`'s constraints, and the
+//! `f.write_str(..)`s inside it dispatch on the writer that's really
+//! there. `fmt::write` runs its real body if a writer isn't a concrete
+//! type, an `Arguments` didn't come from here, or a placeholder's fn takes
+//! something we can't build a `Formatter` for.
+
+use rustc_public::mir::visit::Location as MirLocation;
+use rustc_public::mir::{LocalDecl, MirVisitor, Operand};
+use rustc_public::ty::{FnDef, GenericArgKind, GenericArgs, RigidTy, Span, Ty, TyKind};
+use rustc_public::{CrateDef, DefId};
+
+use rustc_data_structures::fx::FxHashMap as HashMap;
+use std::cell::RefCell;
+
+use crate::Context;
+use crate::InterpPass;
+use crate::constraints::{ADTFields, Constraint, Constraints, Location, RunningConstraint, VOID};
+use crate::dyn_error::concrete_adt;
+use crate::error::Error;
+use crate::interior::instantiated_sig;
+
+use log::debug;
+
+thread_local! {
+    /// `fn_item_in_body` results, by (searched fn, name suffix).
+    static FN_ITEM_IN_BODY: RefCell<HashMap<(DefId, &'static str), Option<FnDef>>> =
+        RefCell::new(HashMap::default());
+}
+
+impl<'a> InterpPass<'a> {
+    /// Called from `stub_higher_order`: Some(result) for the
+    /// `format_args!` constructors and `fmt::write`, when modeled.
+    pub fn stub_fmt(
+        &self,
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let path = Self::callee_path(fndef);
+        let method = Self::method_name(fndef);
+        let out_ty = instantiated_sig(fndef, genargs)?.value.output();
+
+        if path.contains("fmt::rt::Argument")
+            && (method.starts_with("new_") || method == "from_usize")
+        {
+            let value = match args.first() {
+                Some(op) => {
+                    self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false)
+                }
+                None => Constraints::new(),
+            };
+            let formatter = match (fn_item_in_body(fndef, "::fmt"), first_type_arg(genargs)) {
+                (Some(fmt_fn), Some(t)) => Constraints::from(Constraint::new(
+                    None,
+                    Some(RunningConstraint::FnDef(
+                        fmt_fn,
+                        GenericArgs(vec![GenericArgKind::Type(t)]),
+                    )),
+                )),
+                _ => Constraints::new(),
+            };
+            return Some(Ok(Some(adt_value(
+                out_ty,
+                ADTFields::from([(0, value), (1, formatter)]),
+            )?)));
+        }
+
+        if path.contains("fmt::Arguments") && (method.starts_with("new") || method == "from_str") {
+            let mut placeholders = Constraints::new();
+            for op in args.iter() {
+                let value =
+                    self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false);
+                collect_placeholders(&value, &mut placeholders);
+            }
+            return Some(Ok(Some(adt_value(
+                out_ty,
+                ADTFields::from([(1, placeholders)]),
+            )?)));
+        }
+
+        if path.ends_with("fmt::write") {
+            return self
+                .stub_fmt_write(
+                    term_span,
+                    ctxt,
+                    call_stack,
+                    caller_scope,
+                    local_decls,
+                    fndef,
+                    args,
+                    out_ty,
+                )
+                .transpose()
+                .map(|r| r.map(Some));
+        }
+
+        None
+    }
+
+    /// Ok(None) if `output` or `args` aren't something we can take apart,
+    /// or a placeholder's `Formatter` can't be built - decided before
+    /// anything is interpreted.
+    fn stub_fmt_write(
+        &self,
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        args: &Vec<Operand>,
+        out_ty: Ty,
+    ) -> Result<Option<Constraints>, Error> {
+        let [output_op, args_op] = &args[..] else {
+            return Ok(None);
+        };
+        let resolve = |ctxt: &Context, op: &Operand| {
+            self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false)
+        };
+        let output = resolve(ctxt, output_op);
+        let fmt_args = resolve(ctxt, args_op);
+        let Some(write_str) = fn_item_in_body(fndef, "Write::write_str") else {
+            return Ok(None);
+        };
+
+        let mut writers = Vec::new();
+        for leaf in output.inner.iter() {
+            let Some((def, genargs)) = concrete_adt(leaf) else {
+                return Ok(None);
+            };
+            let ty = Ty::from_rigid_kind(RigidTy::Adt(def, genargs));
+            writers.push((ty, Constraint::new(None, leaf.cfc.clone())));
+        }
+
+        // (value, formatting fn, its `Formatter`) per placeholder
+        let mut placeholders = Vec::new();
+        for c in fmt_args.inner.iter() {
+            let Some(RunningConstraint::Adt(_, _, _, fields)) = &c.cfc else {
+                return Ok(None);
+            };
+            let Some(entries) = fields.get(&1) else {
+                return Ok(None);
+            };
+            for entry in entries.inner.iter() {
+                let Some(RunningConstraint::Adt(_, _, _, fields)) = &entry.cfc else {
+                    return Ok(None);
+                };
+                let (Some(value), Some(fmt_fn)) = (fields.get(&0), fields.get(&1)) else {
+                    return Ok(None);
+                };
+                if fmt_fn.inner.is_empty() {
+                    continue;
+                }
+                let Some(formatter) = self.formatter_over(fmt_fn, &output) else {
+                    return Ok(None);
+                };
+                placeholders.push((value.clone(), fmt_fn.clone(), formatter));
+            }
+        }
+        debug!(
+            "FMT WRITE writers={} placeholders={}",
+            writers.len(),
+            placeholders.len()
+        );
+
+        // the literal pieces: `&str`s, nothing in them
+        let piece = Constraint::new(None, None);
+        for (writer_ty, writer) in writers {
+            let callee = Constraints::from(Constraint::new(
+                None,
+                Some(RunningConstraint::FnDef(
+                    write_str,
+                    GenericArgs(vec![GenericArgKind::Type(writer_ty)]),
+                )),
+            ));
+            self.interp_callable_with(
+                ctxt,
+                call_stack,
+                caller_scope,
+                &callee,
+                vec![Constraints::from(writer), Constraints::from(piece.clone())],
+                &out_ty,
+            )?;
+        }
+
+        for (value, fmt_fn, formatter) in placeholders {
+            self.interp_callable_with(
+                ctxt,
+                call_stack,
+                caller_scope,
+                &fmt_fn,
+                vec![value, formatter],
+                &out_ty,
+            )?;
+        }
+
+        let (_, result) = self.converter.convert_ty(
+            &Location::unknown(),
+            &out_ty,
+            Some(caller_scope),
+            Some(self),
+        );
+        Ok(Some(Constraints::from(result)))
+    }
+
+    /// A `Formatter` writing into `output`, typed after the second param
+    /// of `fmt_fn`'s (any) disjunct - `&mut Formatter<'_>`.
+    fn formatter_over(&self, fmt_fn: &Constraints, output: &Constraints) -> Option<Constraints> {
+        let Some(RunningConstraint::FnDef(def, genargs)) = &fmt_fn.inner.first()?.cfc else {
+            return None;
+        };
+        let mut ty = *instantiated_sig(def, genargs)?.value.inputs().get(1)?;
+        while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() {
+            ty = inner;
+        }
+        let TyKind::RigidTy(RigidTy::Adt(adtdef, _)) = ty.kind() else {
+            return None;
+        };
+        let buf = adtdef
+            .variants()
+            .first()?
+            .fields()
+            .iter()
+            .position(|f| f.name == "buf")?;
+        adt_value(ty, ADTFields::from([(buf, output.clone())]))
+    }
+}
+
+/// Every `rt::Argument` value in `value` (the `&[rt::Argument; N]`
+/// `Arguments::new` is given), through the array.
+fn collect_placeholders(value: &Constraints, out: &mut Constraints) {
+    for c in value.inner.iter() {
+        match &c.cfc {
+            Some(RunningConstraint::List(inner)) => {
+                collect_placeholders(&Constraints::from((**inner).clone()), out)
+            }
+            Some(RunningConstraint::Idk(inner)) => collect_placeholders(inner, out),
+            Some(RunningConstraint::Adt(def, ..))
+                if def.0.name().ends_with("fmt::rt::Argument") =>
+            {
+                out.push(c.clone())
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A value of Adt type `ty` with just `fields`.
+fn adt_value(ty: Ty, fields: ADTFields) -> Option<Constraints> {
+    let TyKind::RigidTy(RigidTy::Adt(def, genargs)) = ty.kind() else {
+        return None;
+    };
+    Some(Constraints::from(Constraint::new(
+        None,
+        Some(RunningConstraint::Adt(def, genargs, None, fields)),
+    )))
+}
+
+fn first_type_arg(genargs: &GenericArgs) -> Option<Ty> {
+    genargs.0.iter().find_map(|a| a.ty().copied())
+}
+
+/// The first fn item named `..{suffix}` that `fndef`'s (generic) body
+/// mentions - `<T as Display>::fmt` in `Argument::new_display`,
+/// `Write::write_str` in `fmt::write` - as the trait method, so it can be
+/// called with any `Self`.
+fn fn_item_in_body(fndef: &FnDef, suffix: &'static str) -> Option<FnDef> {
+    let key = (fndef.0, suffix);
+    if let Some(found) = FN_ITEM_IN_BODY.with(|cache| cache.borrow().get(&key).copied()) {
+        return found;
+    }
+    let found = fndef.body().and_then(|body| {
+        let mut finder = FnItemFinder {
+            suffix,
+            found: None,
+        };
+        finder.visit_body(&body);
+        finder.found
+    });
+    FN_ITEM_IN_BODY.with(|cache| cache.borrow_mut().insert(key, found));
+    found
+}
+
+struct FnItemFinder {
+    suffix: &'static str,
+    found: Option<FnDef>,
+}
+
+impl MirVisitor for FnItemFinder {
+    fn visit_operand(&mut self, operand: &Operand, location: MirLocation) {
+        if self.found.is_none()
+            && let Operand::Constant(constant) = operand
+            && let TyKind::RigidTy(RigidTy::FnDef(def, _)) = constant.const_.ty().kind()
+            && def.name().ends_with(self.suffix)
+        {
+            self.found = Some(def);
+        }
+        self.super_operand(operand, location);
+    }
+}
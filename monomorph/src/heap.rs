@@ -0,0 +1,474 @@
+//! Allocation-site heap objects behind `Box`/`Rc`/`Arc` and `Vec`
+//! elements.
+//!
+//! The constraint domain is by value: an `Rc` handle carries a copy of its
+//! pointee's constraints, and `ConstraintStore::refs` only aliases *places*.
+//! So two `Rc` handles to one allocation, or a `&mut` handed out by
+//! `Vec::get_mut`/`IndexMut`/`deref_mut`, don't see each other's writes -
+//! `stdlib_stubs` used to widen a `Vec`'s slot to its whole declared type
+//! whenever one was handed out, for exactly that reason.
+//!
+//! Instead, every allocating call is an abstract object, one per call site,
+//! holding everything ever stored in it (like `interior`'s cells, always a
+//! weak update, and flow-insensitive):
+//!
+//! - `Box::new`/`Rc::new`/`Arc::new`: the pointee.
+//! - `Vec::push`/`insert`/`push_back`/`push_front`, and the `Vec`/`VecDeque`
+//!   constructors `stdlib_stubs` models: the elements they put in.
+//!
+//! The pointer (or collection) value records the sites it might point to
+//! in the reserved field `HEAP_SITE_FIELD`, so they're copied, moved and
+//! merged along with it (`Rc::clone`/`Arc::clone` hand back the same
+//! value, where their real bodies would drop them). Reads - `Rc::deref`,
+//! `Vec::get`, `iter`, ... - see the objects' contents as well as the
+//! value's own. The `&mut T`s out of
+//! `Rc::get_mut`/`make_mut`, `Box::as_mut` and the `Vec` accessors are
+//! recorded in `InterpPass::heap_ptrs` (along with every local they're
+//! copied or reborrowed into), and every assignment that lands on one -
+//! directly, or in a callee through a `&mut` arg via `refs` - joins into
+//! the objects too, see `note_heap_write`. One that escapes where we can't
+//! follow it (a field, the return place) widens its objects to everything
+//! their type allows.
+//!
+//! Staleness is handled like the cells': `heap_stale` records an object
+//! growing after it was read, and `start_verifopt` reruns the analysis
+//! with the objects seeded from the previous round. A value without sites
+//! (built by a real body we interpreted) keeps the old behavior.
+
+use crate::rustc_public_bridge::IndexedVal;
+use rustc_public::mir::{LocalDecl, Mutability, Operand, Place, ProjectionElem, Rvalue};
+use rustc_public::ty::{FnDef, GenericArgs, RigidTy, Span, Ty, TyKind};
+
+use crate::Context;
+use crate::InterpPass;
+use crate::constraints::{
+    Constraint, Constraints, Location, RunningConstraint, VOID, contains_param,
+};
+use crate::convert::WrapperKind;
+use crate::error::Error;
+use crate::interior::instantiated_sig;
+
+use log::debug;
+
+/// The `ADTFields` key a `Box`/`Rc`/`Arc`/`Vec` value keeps its allocation
+/// sites under, as `Scalar(Some(site))`s. Never a real field index.
+pub const HEAP_SITE_FIELD: usize = usize::MAX;
+
+/// An allocating call, by its span's index.
+pub type AllocSite = usize;
+
+/// Everything ever stored in the objects one call site allocates.
+#[derive(Clone, Debug, Default)]
+pub struct HeapObject {
+    pub contents: Constraints,
+    /// the pointee/element type, for widening
+    pub ty: Option<Ty>,
+    /// a `&mut` into it escaped where writes through it can't be seen
+    pub widened: bool,
+    /// read at least once this round
+    pub read: bool,
+}
+
+impl<'a> InterpPass<'a> {
+    /// Called from `stdlib_stub`: Some(result) for the `Box`/`Rc`/`Arc`
+    /// methods that read or hand out a `&mut` to a pointee with known
+    /// sites, and `Rc::clone`/`Arc::clone` of one.
+    pub fn stub_heap(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let method = Self::method_name(fndef);
+        let hands_out_mut = matches!(
+            method.as_str(),
+            "get_mut" | "make_mut" | "get_mut_unchecked" | "as_mut" | "deref_mut"
+        );
+        if !hands_out_mut && !matches!(method.as_str(), "deref" | "as_ref" | "borrow" | "clone") {
+            return None;
+        }
+        let recv = args.first()?;
+        let mut ty = recv.ty(local_decls).ok()?;
+        while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() {
+            ty = inner;
+        }
+        let TyKind::RigidTy(RigidTy::Adt(adtdef, ptr_args)) = ty.kind() else {
+            return None;
+        };
+        let pointee_ty = *ptr_args.0.first()?.ty()?;
+        let kind = self.converter.wrapper_kind(&adtdef);
+        if !matches!(
+            kind,
+            Some(WrapperKind::Box | WrapperKind::Rc | WrapperKind::Arc)
+        ) || (method == "clone" && kind == Some(WrapperKind::Box))
+        {
+            return None;
+        }
+
+        let value = self.resolve_arg(
+            ctxt,
+            term_span,
+            caller_scope,
+            &None,
+            local_decls,
+            recv,
+            false,
+        );
+        let sites = heap_sites(&value)?;
+        // another handle to the same objects (the real body rebuilds the
+        // `Rc` from its pointer, dropping the sites)
+        if method == "clone" {
+            return Some(Ok(Some(value)));
+        }
+        let mut pointee = ctxt.step_field(
+            caller_scope,
+            &value,
+            &ProjectionElem::Field(0, pointee_ty),
+            Some(self),
+        );
+        pointee.append(self.heap_read(caller_scope, &sites, Some(pointee_ty)));
+        debug!("HEAP {} on {} sites", method, sites.len());
+
+        if !hands_out_mut {
+            return Some(Ok(Some(pointee)));
+        }
+        self.heap_ptr_result(sites);
+        // `Rc::get_mut`/`Arc::get_mut` -> Option<&mut T>
+        let sig = instantiated_sig(fndef, genargs)?;
+        match sig.value.output().kind() {
+            TyKind::RigidTy(RigidTy::Adt(out, _))
+                if self.converter.wrapper_kind(&out) == Some(WrapperKind::Option) =>
+            {
+                Some(Ok(Some(self.wrap_in_option(&sig, pointee)?)))
+            }
+            _ => Some(Ok(Some(pointee))),
+        }
+    }
+
+    /// A new object for the allocating call at `term_span` holding `value`
+    /// (joined in, if the call was already seen): the site to tag the
+    /// pointer with.
+    pub(crate) fn heap_alloc(
+        &self,
+        term_span: &Span,
+        ty: Option<Ty>,
+        value: Constraints,
+    ) -> AllocSite {
+        let site = term_span.to_index();
+        if ty.is_some() {
+            self.heap.borrow_mut().entry(site).or_default().ty = ty;
+        }
+        self.heap_join(site, value);
+        site
+    }
+
+    /// The union of everything in `sites`' objects; `ty` is what a widened
+    /// one holds if its allocation didn't say.
+    pub(crate) fn heap_read(
+        &self,
+        scope: &VOID,
+        sites: &[AllocSite],
+        ty: Option<Ty>,
+    ) -> Constraints {
+        let mut out = Constraints::new();
+        for site in sites {
+            let (contents, widened) = {
+                let mut heap = self.heap.borrow_mut();
+                let obj = heap.entry(*site).or_default();
+                obj.read = true;
+                (
+                    obj.contents.clone(),
+                    obj.widened.then_some(obj.ty.or(ty)).flatten(),
+                )
+            };
+            out.append(contents);
+            if let Some(ty) = widened {
+                let (_, c) =
+                    self.converter
+                        .convert_ty(&Location::unknown(), &ty, Some(scope), Some(self));
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// Records that the call being interpreted returns a `&mut` into
+    /// `sites`, for `interp_direct_call` to pick up with
+    /// `take_heap_ptr_result`.
+    pub(crate) fn heap_ptr_result(&self, sites: Vec<AllocSite>) {
+        *self.heap_ptr_result.borrow_mut() = Some(sites);
+    }
+
+    pub fn take_heap_ptr_result(&self) -> Option<Vec<AllocSite>> {
+        self.heap_ptr_result.borrow_mut().take()
+    }
+
+    /// Records a call destination that's a `&mut` into `sites`. One that
+    /// isn't a bare local (so can't be found again) widens them instead.
+    pub fn note_heap_ptr(&self, scope: &VOID, destination: &Place, sites: Vec<AllocSite>) {
+        if !destination.projection.is_empty() || destination.local == 0 {
+            self.heap_widen(&sites);
+            return;
+        }
+        let mut ptrs = self.heap_ptrs.borrow_mut();
+        let known = ptrs
+            .entry((destination.clone(), scope.clone()))
+            .or_default();
+        for site in sites {
+            if !known.contains(&site) {
+                known.push(site);
+            }
+        }
+    }
+
+    /// Called for every assignment: a heap pointer moved or copied (out of
+    /// an `Option`, say) or reborrowed into `dest` makes `dest` one too
+    /// (see `note_heap_ptr`).
+    pub fn note_heap_alias(
+        &self,
+        ctxt: &Context,
+        scope: &VOID,
+        dest: &Place,
+        dest_ty: Ty,
+        rvalue: &Rvalue,
+    ) {
+        if self.heap_ptrs.borrow().is_empty() || !has_mut_ref(dest_ty) {
+            return;
+        }
+        let src = match rvalue {
+            // a copy of something *behind* the pointer isn't the pointer
+            Rvalue::Use(Operand::Copy(p) | Operand::Move(p))
+                if !p.projection.contains(&ProjectionElem::Deref) =>
+            {
+                p
+            }
+            Rvalue::Ref(_, _, p) => p,
+            _ => return,
+        };
+        if let Some(sites) = self.heap_ptr_of(ctxt, scope, src) {
+            self.note_heap_ptr(scope, dest, sites);
+        }
+    }
+
+    /// The sites of a heap pointer passed to a call that returns one
+    /// (`dest_ty`) without being a stub that says where it points
+    /// (`Option::unwrap`, `expect`, ...): it could be one of those.
+    pub fn heap_ptr_args(
+        &self,
+        ctxt: &Context,
+        scope: &VOID,
+        args: &[Operand],
+        dest_ty: Ty,
+    ) -> Option<Vec<AllocSite>> {
+        if self.heap_ptrs.borrow().is_empty() || !has_mut_ref(dest_ty) {
+            return None;
+        }
+        let mut sites = Vec::new();
+        for op in args {
+            if let Operand::Copy(p) | Operand::Move(p) = op
+                && let Some(found) = self.heap_ptr_of(ctxt, scope, p)
+            {
+                for site in found {
+                    if !sites.contains(&site) {
+                        sites.push(site);
+                    }
+                }
+            }
+        }
+        (!sites.is_empty()).then_some(sites)
+    }
+
+    /// Called for every assignment before it's written: if `place` is
+    /// (something inside) the pointee of a heap pointer, also join the
+    /// value into its objects.
+    pub fn note_heap_write(
+        &self,
+        ctxt: &Context,
+        scope: &VOID,
+        place: &Place,
+        value: &Constraints,
+    ) {
+        if place.projection.first() != Some(&ProjectionElem::Deref)
+            || self.heap_ptrs.borrow().is_empty()
+        {
+            return;
+        }
+        let Some(sites) = self.heap_ptr_of(ctxt, scope, place) else {
+            return;
+        };
+        let (resolved, _) = ctxt.cstore.resolve_mut_place(place, scope);
+        let proj: Vec<ProjectionElem> = resolved
+            .projection
+            .iter()
+            .filter(|e| !matches!(e, ProjectionElem::Deref))
+            .cloned()
+            .collect();
+        debug!("HEAP: write through heap pointer {:?}", place);
+        for site in sites {
+            self.heap_write(ctxt, scope, site, &proj, value.clone());
+        }
+    }
+
+    /// Same as `note_heap_write`, for a stub that updated what its
+    /// (pointer) receiver `recv` points to by writing `value` to `recv`
+    /// itself (see `stub_std_collection`).
+    pub fn note_heap_store(&self, ctxt: &Context, scope: &VOID, recv: &Place, value: &Constraints) {
+        if !recv.projection.is_empty() {
+            return;
+        }
+        let pointee = Place {
+            local: recv.local,
+            projection: vec![ProjectionElem::Deref],
+        };
+        self.note_heap_write(ctxt, scope, &pointee, value);
+    }
+
+    /// Whether any object grew after being read this round, so the round
+    /// has to be rerun (see the module docs).
+    pub fn heap_stale(&self) -> bool {
+        *self.heap_stale.borrow()
+    }
+
+    // ---------- the objects ----------
+
+    fn heap_ptr_of(&self, ctxt: &Context, scope: &VOID, place: &Place) -> Option<Vec<AllocSite>> {
+        let base = Place {
+            local: place.local,
+            projection: vec![],
+        };
+        let (resolved, rscope) = ctxt.cstore.resolve_mut_place(&base, scope);
+        let resolved = Place {
+            local: resolved.local,
+            projection: vec![],
+        };
+        self.heap_ptrs.borrow().get(&(resolved, rscope)).cloned()
+    }
+
+    fn heap_join(&self, site: AllocSite, value: Constraints) {
+        // Same as `cell_join`: a `Param` placeholder can't outlive the
+        // summary being built.
+        if contains_param(&value) {
+            if let Some(tainted) = self.summary_build_taint_stack.borrow_mut().last_mut() {
+                *tainted = true;
+            }
+            return;
+        }
+        let mut heap = self.heap.borrow_mut();
+        let obj = heap.entry(site).or_default();
+        let before = obj.contents.inner.len();
+        obj.contents.append(value);
+        if obj.read && obj.contents.inner.len() != before {
+            *self.heap_stale.borrow_mut() = true;
+        }
+    }
+
+    fn heap_widen(&self, sites: &[AllocSite]) {
+        let mut heap = self.heap.borrow_mut();
+        for site in sites {
+            let obj = heap.entry(*site).or_default();
+            if !obj.widened {
+                obj.widened = true;
+                if obj.read {
+                    *self.heap_stale.borrow_mut() = true;
+                }
+            }
+        }
+    }
+
+    /// Weak write of `value` at `proj` inside every value in the object.
+    fn heap_write(
+        &self,
+        ctxt: &Context,
+        scope: &VOID,
+        site: AllocSite,
+        proj: &[ProjectionElem],
+        value: Constraints,
+    ) {
+        if proj.is_empty() || contains_param(&value) {
+            self.heap_join(site, value);
+            return;
+        }
+
+        let cur = self.heap.borrow().get(&site).map(|o| o.contents.clone());
+        let Some(cur) = cur.filter(|c| !c.inner.is_empty()) else {
+            self.heap_widen(&[site]);
+            return;
+        };
+
+        let mut slot = cur.clone();
+        for elem in proj {
+            if matches!(elem, ProjectionElem::Field(..)) {
+                slot = ctxt.step_field(scope, &slot, elem, Some(self));
+            }
+        }
+        slot.append(value);
+        let mut updated = cur.clone();
+        updated.write_field(proj.to_vec(), slot);
+
+        let mut heap = self.heap.borrow_mut();
+        let obj = heap.entry(site).or_default();
+        if updated != obj.contents {
+            obj.contents = updated;
+            if obj.read {
+                *self.heap_stale.borrow_mut() = true;
+            }
+        }
+    }
+}
+
+/// The sites every disjunct of a pointer/collection value might point to,
+/// or None if one of them has none we know of.
+pub(crate) fn heap_sites(value: &Constraints) -> Option<Vec<AllocSite>> {
+    let mut sites = Vec::new();
+    for c in value.inner.iter() {
+        let Some(RunningConstraint::Adt(_, _, _, fields)) = &c.cfc else {
+            return None;
+        };
+        let tags = fields.get(&HEAP_SITE_FIELD)?;
+        for tag in tags.inner.iter() {
+            let Some(RunningConstraint::Scalar(Some(site))) = &tag.cfc else {
+                return None;
+            };
+            let site = *site as AllocSite;
+            if !sites.contains(&site) {
+                sites.push(site);
+            }
+        }
+    }
+    (!sites.is_empty()).then_some(sites)
+}
+
+/// A `&mut` somewhere in `ty`: the pointer itself, or inside an `Option`,
+/// `Result`, tuple, ...
+fn has_mut_ref(ty: Ty) -> bool {
+    match ty.kind() {
+        TyKind::RigidTy(RigidTy::Ref(_, _, Mutability::Mut)) => true,
+        TyKind::RigidTy(RigidTy::Adt(_, args)) => args
+            .0
+            .iter()
+            .any(|a| a.ty().is_some_and(|t| has_mut_ref(*t))),
+        TyKind::RigidTy(RigidTy::Tuple(tys)) => tys.iter().any(|t| has_mut_ref(*t)),
+        _ => false,
+    }
+}
+
+/// `value` with `site` added to every Adt disjunct's sites.
+pub(crate) fn tag_site(value: &Constraints, site: AllocSite) -> Constraints {
+    let tag = Constraint::new(None, Some(RunningConstraint::Scalar(Some(site as i128))));
+    let mut out = Constraints::new();
+    for c in value.inner.iter() {
+        let mut c = c.clone();
+        if let Some(RunningConstraint::Adt(_, _, _, fields)) = &mut c.cfc {
+            fields
+                .entry(HEAP_SITE_FIELD)
+                .or_insert_with(Constraints::new)
+                .push(tag.clone());
+        }
+        out.push(c);
+    }
+    out
+}
@@ -0,0 +1,552 @@
+//! Interior mutability: `RefCell`, `Cell`, `Mutex` and `RwLock`.
+//!
+//! The constraint domain is by value - `Rc::clone`/`Arc::clone` copy the
+//! pointee's constraints - and a write through `&RefCell<T>` is a write
+//! through a *shared* reference, which `ConstraintStore::refs` deliberately
+//! doesn't follow (only `&mut` aliasing is tracked there). So a cell's
+//! contents don't live in the cell's own place at all: there is one
+//! flow-insensitive `SharedCell` per content type `T` on `InterpPass::cells`,
+//! which every cell of that type - and so every alias of any of them - reads
+//! and writes:
+//!
+//! - `new(x)` joins `x` in. Other constructors (`default`, `From`, `clone`)
+//!   reach it through their real bodies.
+//! - `set`/`replace` join the new value in. With one summary per type this
+//!   is always a weak update: nothing already there can be dropped.
+//!   `replace`, `get`, `into_inner`, and `deref`/`deref_mut` on a guard read.
+//! - `borrow`/`borrow_mut`/`lock`/`read`/`write` (and their `try_`s) just
+//!   hand out a guard: dereferencing it reads the summary for the guard's
+//!   `T`, so the guard value itself doesn't need to carry anything.
+//! - the `&mut T` out of `deref_mut` (or `RefCell::get_mut`/`Cell::get_mut`)
+//!   is recorded in `InterpPass::cell_ptrs`, and every assignment that lands
+//!   on it - `*guard = x`, `guard.field = x`, or the same from a callee
+//!   through a `&mut` arg, via `refs` - joins into the summary, see
+//!   `note_cell_write`. `mem::replace`/`mem::swap` on one are modeled
+//!   directly, since their bodies write through a raw pointer.
+//! - anything else that could store a `T` we can't see (`replace_with`,
+//!   `RefMut::map`, `Mutex::get_mut`, `as_ptr`, ...) widens the summary to
+//!   everything `T`'s type allows.
+//!
+//! Being flow-insensitive, a read can be interpreted before a write that
+//! happens earlier at runtime - the state-machine pattern, where `main`
+//! dispatches on a state that some callee replaced on the previous loop
+//! iteration. `cells_stale` records whenever a summary grows after it was
+//! read, and `start_verifopt` then reruns the analysis with the summaries
+//! seeded from the previous round until they settle, or gives up after
+//! `MAX_CELL_ROUNDS` and falls back to CHA at every call site.
+
+use rustc_public::mir::{LocalDecl, Operand, Place, ProjectionElem};
+use rustc_public::ty::{FnDef, GenericArgKind, GenericArgs, PolyFnSig, RigidTy, Span, Ty, TyKind};
+
+use crate::Context;
+use crate::InterpPass;
+use crate::constraints::{
+    ADTFields, Constraint, Constraints, Location, RunningConstraint, VOID, contains_param,
+};
+use crate::convert::{CellKind, cell_kind};
+use crate::error::Error;
+
+use log::debug;
+
+/// Rounds of the whole analysis `start_verifopt` runs before giving up on
+/// the cell summaries settling, by default (`[limits] max_rounds`, see
+/// `config`).
+pub const MAX_CELL_ROUNDS: usize = 4;
+
+/// Accessors that only hand out a guard (or a `Result` holding one).
+const GUARD_ACCESSORS: [&str; 10] = [
+    "borrow",
+    "borrow_mut",
+    "try_borrow",
+    "try_borrow_mut",
+    "lock",
+    "try_lock",
+    "read",
+    "write",
+    "try_read",
+    "try_write",
+];
+
+/// Everything ever stored in any cell of one content type.
+#[derive(Clone, Debug, Default)]
+pub struct SharedCell {
+    pub contents: Constraints,
+    /// something we don't model could have stored any `T`
+    pub widened: bool,
+    /// read at least once this round
+    pub read: bool,
+}
+
+impl<'a> InterpPass<'a> {
+    /// Called from `stdlib_stub`: Some(result) if this is a cell/guard
+    /// method we model, or `mem::replace`/`mem::swap` through a pointer
+    /// into a cell.
+    pub fn stub_interior(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let path = Self::callee_path(fndef);
+        if path.ends_with("mem::replace") || path.ends_with("mem::swap") {
+            return self.stub_mem_on_cell(ctxt, caller_scope, term_span, local_decls, &path, args);
+        }
+
+        let method = Self::method_name(fndef);
+        if method == "new" {
+            return self.stub_cell_new(ctxt, caller_scope, term_span, local_decls, fndef, args);
+        }
+
+        let (kind, ty) = receiver_cell(args, local_decls)?;
+        debug!("INTERIOR {:?}<{:?}>::{}", kind, ty, method);
+
+        let fallback = || self.retty_fallback_from_poly(fndef.fn_sig());
+        let is_sync = matches!(kind, CellKind::Mutex | CellKind::RwLock);
+
+        let result = match (kind.is_guard(), method.as_str()) {
+            // Their real bodies only go through the methods modeled here
+            // (`take` is `replace(Default::default())`, `clone` is
+            // `new(self.get())`/`new(self.borrow().clone())`).
+            (false, "take" | "clone" | "default") => return None,
+
+            // Same-type cells share one summary already.
+            (_, "drop") | (false, "swap") => Ok(Some(Constraints::new())),
+
+            (true, "deref" | "deref_mut") => Ok(Some(self.cell_read(caller_scope, ty))),
+
+            (false, m) if GUARD_ACCESSORS.contains(&m) => fallback(),
+
+            // The `&mut T` `get_mut` returns is registered as a cell
+            // pointer by `interp_direct_call`, see `cell_ptr_target`.
+            (false, "get" | "into_inner" | "get_mut") if !is_sync => {
+                let mut out = self.cell_read(caller_scope, ty);
+                out.append(self.receiver_initial(ctxt, caller_scope, term_span, local_decls, args));
+                Ok(Some(out))
+            }
+
+            (false, "set" | "replace") => {
+                let old = self.cell_read(caller_scope, ty);
+                if let Some(op) = args.get(1) {
+                    let new = self.resolve_arg(
+                        ctxt,
+                        term_span,
+                        caller_scope,
+                        &None,
+                        local_decls,
+                        op,
+                        false,
+                    );
+                    self.cell_join(ty, new);
+                }
+                match method.as_str() {
+                    "set" => Ok(Some(Constraints::new())),
+                    _ if is_sync => fallback(),
+                    _ => Ok(Some(old)),
+                }
+            }
+
+            _ => {
+                // Anything else could only store a `T` through a non-scalar
+                // argument (a closure, a value) or a pointer it hands out.
+                let may_store = method.contains("mut")
+                    || method.contains("ptr")
+                    || args.iter().skip(1).any(|op| !is_scalar(op, local_decls));
+                if may_store {
+                    self.cell_widen(ty);
+                }
+                // `Ref::map` and friends: a guard onto some part `U` of
+                // the `T`, whose summary nothing would ever write to.
+                if let Some(sig) = instantiated_sig(fndef, genargs)
+                    && let Some((_, out_ty)) = output_cell(&sig)
+                    && out_ty != ty
+                {
+                    self.cell_widen(out_ty);
+                }
+                debug!("INTERIOR: unmodeled {}, widen={}", method, may_store);
+                fallback()
+            }
+        };
+        Some(result)
+    }
+
+    /// `RefCell::new(x)`/`Cell::new(x)`/`Mutex::new(x)`/`RwLock::new(x)`:
+    /// joins `x` into the summary for its type and returns a cell with no
+    /// fields of its own.
+    fn stub_cell_new(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let TyKind::RigidTy(RigidTy::Adt(adtdef, _)) = fndef.fn_sig().value.output().kind() else {
+            return None;
+        };
+        if cell_kind(&adtdef).is_none_or(|k| k.is_guard()) {
+            return None;
+        }
+        let op = args.first()?;
+        let ty = op.ty(local_decls).ok()?;
+
+        let value = self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false);
+        self.cell_join(ty, value);
+
+        let genargs = GenericArgs(vec![GenericArgKind::Type(ty)]);
+        Some(Ok(Some(Constraints::from(Constraint::new(
+            None,
+            Some(RunningConstraint::Adt(
+                adtdef,
+                genargs,
+                None,
+                ADTFields::new(),
+            )),
+        )))))
+    }
+
+    /// `mem::replace(dest, src)`/`mem::swap(a, b)` where a pointer argument
+    /// is one `cell_ptrs` knows; None (interpret as usual) otherwise.
+    fn stub_mem_on_cell(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        path: &str,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let cell_of = |op: Option<&Operand>| match op {
+            Some(Operand::Copy(place) | Operand::Move(place)) => {
+                self.cell_ptr_of(ctxt, caller_scope, place)
+            }
+            _ => None,
+        };
+        let a = cell_of(args.first());
+        let b = cell_of(args.get(1));
+        if a.is_none() && b.is_none() {
+            return None;
+        }
+        let (Some(a_op), Some(b_op)) = (args.first(), args.get(1)) else {
+            return None;
+        };
+        let a_val = self.resolve_arg(
+            ctxt,
+            term_span,
+            caller_scope,
+            &None,
+            local_decls,
+            a_op,
+            false,
+        );
+        let b_val = self.resolve_arg(
+            ctxt,
+            term_span,
+            caller_scope,
+            &None,
+            local_decls,
+            b_op,
+            false,
+        );
+
+        if path.ends_with("mem::replace") {
+            let ty = a?;
+            let old = self.cell_read(caller_scope, ty);
+            self.cell_join(ty, b_val);
+            return Some(Ok(Some(old)));
+        }
+
+        // swap: each side ends up with the other's value
+        if let Some(ty) = a {
+            self.cell_join(ty, b_val);
+        }
+        if let Some(ty) = b {
+            self.cell_join(ty, a_val);
+        }
+        for (op, this, other) in [(a_op, a, b), (b_op, b, a)] {
+            if this.is_none()
+                && let Some(ty) = other
+                && let Operand::Copy(place) | Operand::Move(place) = op
+            {
+                let target = Place {
+                    local: place.local,
+                    projection: vec![ProjectionElem::Deref],
+                };
+                let read = self.cell_read(caller_scope, ty);
+                ctxt.set_scoped_constraints(caller_scope, &target, read, Some(self));
+            }
+        }
+        Some(Ok(Some(Constraints::new())))
+    }
+
+    /// The content type, if this call's return value is a `&mut` into a
+    /// cell: `deref_mut` on a write guard, or `get_mut` on a `RefCell`/
+    /// `Cell`. `interp_direct_call` records its destination in `cell_ptrs`.
+    pub fn cell_ptr_target(
+        &self,
+        fndef: &FnDef,
+        args: &Vec<Operand>,
+        local_decls: &[LocalDecl],
+    ) -> Option<Ty> {
+        let (kind, ty) = receiver_cell(args, local_decls)?;
+        let method = Self::method_name(fndef);
+        match (kind, method.as_str()) {
+            (CellKind::RefMut | CellKind::MutexGuard | CellKind::RwLockWriteGuard, "deref_mut")
+            | (CellKind::RefCell | CellKind::Cell, "get_mut") => Some(ty),
+            _ => None,
+        }
+    }
+
+    /// Records a call destination `cell_ptr_target` picked out. One that
+    /// isn't a bare local (so can't be found again by `cell_ptr_of`)
+    /// widens the cell instead.
+    pub fn note_cell_ptr(&self, scope: &VOID, destination: &Place, ty: Ty) {
+        if !destination.projection.is_empty() {
+            self.cell_widen(ty);
+            return;
+        }
+        self.cell_ptrs
+            .borrow_mut()
+            .insert((destination.clone(), scope.clone()), ty);
+    }
+
+    /// Called for every assignment before it's written: if `place` is
+    /// (something inside) the pointee of a cell pointer, also join the
+    /// value into that cell's summary.
+    pub fn note_cell_write(
+        &self,
+        ctxt: &Context,
+        scope: &VOID,
+        place: &Place,
+        value: &Constraints,
+    ) {
+        if place.projection.first() != Some(&ProjectionElem::Deref)
+            || self.cell_ptrs.borrow().is_empty()
+        {
+            return;
+        }
+        let (resolved, rscope) = ctxt.cstore.resolve_mut_place(place, scope);
+        let base = Place {
+            local: resolved.local,
+            projection: vec![],
+        };
+        let Some(ty) = self.cell_ptrs.borrow().get(&(base, rscope)).copied() else {
+            return;
+        };
+
+        let proj: Vec<ProjectionElem> = resolved
+            .projection
+            .iter()
+            .filter(|e| !matches!(e, ProjectionElem::Deref))
+            .cloned()
+            .collect();
+        debug!(
+            "INTERIOR: write through cell pointer {:?} ({:?})",
+            place, ty
+        );
+        self.cell_write(ctxt, scope, ty, &proj, value.clone());
+    }
+
+    /// Same as `note_cell_write`, for a stub that updated what its
+    /// (pointer) receiver `recv` points to by writing `value` to `recv`
+    /// itself (see `stub_insert`).
+    pub fn note_cell_store(&self, ctxt: &Context, scope: &VOID, recv: &Place, value: &Constraints) {
+        if !recv.projection.is_empty() {
+            return;
+        }
+        let pointee = Place {
+            local: recv.local,
+            projection: vec![ProjectionElem::Deref],
+        };
+        self.note_cell_write(ctxt, scope, &pointee, value);
+    }
+
+    /// Whether any cell summary grew after being read this round, so the
+    /// round has to be rerun (see the module docs).
+    pub fn cells_stale(&self) -> bool {
+        *self.cells_stale.borrow()
+    }
+
+    // ---------- the summaries ----------
+
+    fn cell_ptr_of(&self, ctxt: &Context, scope: &VOID, place: &Place) -> Option<Ty> {
+        let base = Place {
+            local: place.local,
+            projection: vec![],
+        };
+        let (resolved, rscope) = ctxt.cstore.resolve_mut_place(&base, scope);
+        let resolved = Place {
+            local: resolved.local,
+            projection: vec![],
+        };
+        self.cell_ptrs.borrow().get(&(resolved, rscope)).copied()
+    }
+
+    fn cell_read(&self, scope: &VOID, ty: Ty) -> Constraints {
+        let (mut out, widened) = {
+            let mut cells = self.cells.borrow_mut();
+            let cell = cells.entry(ty).or_default();
+            cell.read = true;
+            (cell.contents.clone(), cell.widened)
+        };
+        if widened {
+            let (_, c) =
+                self.converter
+                    .convert_ty(&Location::unknown(), &ty, Some(scope), Some(self));
+            out.push(c);
+        }
+        out
+    }
+
+    fn cell_join(&self, ty: Ty, value: Constraints) {
+        // A parametric summary is being built: a `Param` placeholder
+        // escaping into a summary that outlives the build would be
+        // meaningless, so give up on the function summary instead.
+        if contains_param(&value) {
+            if let Some(tainted) = self.summary_build_taint_stack.borrow_mut().last_mut() {
+                *tainted = true;
+            }
+            return;
+        }
+        let mut cells = self.cells.borrow_mut();
+        let cell = cells.entry(ty).or_default();
+        let before = cell.contents.inner.len();
+        cell.contents.append(value);
+        if cell.read && cell.contents.inner.len() != before {
+            *self.cells_stale.borrow_mut() = true;
+        }
+    }
+
+    fn cell_widen(&self, ty: Ty) {
+        let mut cells = self.cells.borrow_mut();
+        let cell = cells.entry(ty).or_default();
+        if !cell.widened {
+            cell.widened = true;
+            if cell.read {
+                *self.cells_stale.borrow_mut() = true;
+            }
+        }
+    }
+
+    /// Weak write of `value` at `proj` inside every value in the summary:
+    /// the slot becomes old slot + `value`.
+    fn cell_write(
+        &self,
+        ctxt: &Context,
+        scope: &VOID,
+        ty: Ty,
+        proj: &[ProjectionElem],
+        value: Constraints,
+    ) {
+        if proj.is_empty() || contains_param(&value) {
+            self.cell_join(ty, value);
+            return;
+        }
+
+        let cur = self.cells.borrow().get(&ty).map(|c| c.contents.clone());
+        let Some(cur) = cur.filter(|c| !c.inner.is_empty()) else {
+            // nothing to write a field of: no idea what this is a part of
+            self.cell_widen(ty);
+            return;
+        };
+
+        let mut slot = cur.clone();
+        for elem in proj {
+            if matches!(elem, ProjectionElem::Field(..)) {
+                slot = ctxt.step_field(scope, &slot, elem, Some(self));
+            }
+        }
+        slot.append(value);
+        let mut updated = cur.clone();
+        updated.write_field(proj.to_vec(), slot);
+
+        let mut cells = self.cells.borrow_mut();
+        let cell = cells.entry(ty).or_default();
+        if updated != cell.contents {
+            cell.contents = updated;
+            if cell.read {
+                *self.cells_stale.borrow_mut() = true;
+            }
+        }
+    }
+
+    /// A receiver cell that wasn't made by `stub_cell_new` (a `static`'s,
+    /// or one built by some real body) can still hold contents of its own:
+    /// everything reachable from its fields.
+    fn receiver_initial(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        args: &Vec<Operand>,
+    ) -> Constraints {
+        let mut out = Constraints::new();
+        let Some(op) = args.first() else {
+            return out;
+        };
+        let recv = self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false);
+        for c in recv.inner.iter() {
+            if let Some(RunningConstraint::Adt(adtdef, _, _, fields)) = &c.cfc
+                && cell_kind(adtdef).is_some()
+            {
+                for field in fields.values() {
+                    out.append(ctxt.flatten_all(field, caller_scope, Some(self)));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// The cell (or guard) `args[0]` is, or points to, and its content type.
+fn receiver_cell(args: &[Operand], local_decls: &[LocalDecl]) -> Option<(CellKind, Ty)> {
+    let mut ty = args.first()?.ty(local_decls).ok()?;
+    while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() {
+        ty = inner;
+    }
+    cell_and_content(ty)
+}
+
+fn output_cell(sig: &PolyFnSig) -> Option<(CellKind, Ty)> {
+    cell_and_content(sig.value.output())
+}
+
+fn cell_and_content(ty: Ty) -> Option<(CellKind, Ty)> {
+    let TyKind::RigidTy(RigidTy::Adt(adtdef, genargs)) = ty.kind() else {
+        return None;
+    };
+    let kind = cell_kind(&adtdef)?;
+    // skip the guards' lifetime
+    let content = genargs.0.iter().find_map(|a| match a {
+        GenericArgKind::Type(ty) => Some(*ty),
+        _ => None,
+    })?;
+    Some((kind, content))
+}
+
+/// The callee's signature with this call's generic args substituted in.
+pub(crate) fn instantiated_sig(fndef: &FnDef, genargs: &GenericArgs) -> Option<PolyFnSig> {
+    Ty::from_rigid_kind(RigidTy::FnDef(*fndef, genargs.clone()))
+        .kind()
+        .fn_sig()
+}
+
+pub(crate) fn is_scalar(op: &Operand, local_decls: &[LocalDecl]) -> bool {
+    op.ty(local_decls).is_ok_and(|ty| {
+        matches!(
+            ty.kind(),
+            TyKind::RigidTy(
+                RigidTy::Bool
+                    | RigidTy::Char
+                    | RigidTy::Int(_)
+                    | RigidTy::Uint(_)
+                    | RigidTy::Float(_)
+            )
+        )
+    })
+}
@@ -25,6 +25,7 @@ use log::{debug, error};
 
 use crate::Context;
 use crate::common::{log_call_stack, log_scope};
+use crate::config::{PathFilter, limits};
 use crate::constraints::{
     ADTFields, ArgSet, Constraint, ConstraintStore, Constraints, EnclosingScopes, Location, MapKey,
     MapValue, RunningConstraint, SummaryKey, TagProv, TraitObjConstraint, TraitObjTy, VOID,
@@ -33,14 +34,18 @@ use crate::constraints::{
 use crate::constraints::{unique_append, unique_push};
 use crate::convert::RvalConverter;
 use crate::error::Error;
+use crate::heap::{AllocSite, HeapObject};
+use crate::interior::SharedCell;
 use crate::merge::Merge;
 use crate::sig_collect::{SigStore, SigVal};
-use crate::trait_collect::TraitStore;
+use crate::stub_spec::StubSpecs;
+use crate::trait_collect::{SelfTyPat, TraitStore, mentions_param};
 use crate::wto::BBDeps;
 use indexmap::IndexSet;
 use std::rc::Weak;
 
-const MAX_DEPTH: u32 = 50;
+/// Default `[limits] max_depth`, see `config`
+pub const MAX_DEPTH: u32 = 50;
 
 /// Cache key for `virtual_call_memo` - the call site (caller function's
 /// DefId + basic block, same pair already used for `dispatch_cha`) plus
@@ -58,6 +63,27 @@ pub struct InterpPass<'a> {
     pub sigstore: &'a SigStore,
     pub tstore: &'a TraitStore,
     pub converter: RvalConverter<'a>,
+    /// `[[stub]]` specs consulted by `stdlib_stub`, see `stub_spec`
+    pub stub_specs: StubSpecs,
+    /// `[analysis]` in verifopt.toml: callees it rejects aren't
+    /// interpreted, see `stdlib_stub`
+    pub analysis_filter: PathFilter,
+
+    /// RefCell/Cell/Mutex/RwLock contents, one summary per content type;
+    /// see `interior`
+    pub cells: RefCell<HashMap<Ty, SharedCell>>,
+    /// `&mut T`s into a cell's contents, and the `T`
+    pub cell_ptrs: RefCell<HashMap<(Place, VOID), Ty>>,
+    /// some cell summary grew after it was read this round
+    pub cells_stale: RefCell<bool>,
+    /// `Box`/`Rc`/`Arc`/`Vec` allocation-site objects; see `heap`
+    pub heap: RefCell<HashMap<AllocSite, HeapObject>>,
+    /// `&mut`s into heap objects, and their sites
+    pub heap_ptrs: RefCell<HashMap<(Place, VOID), Vec<AllocSite>>>,
+    /// set by a stub whose result is a `&mut` into these sites
+    pub heap_ptr_result: RefCell<Option<Vec<AllocSite>>>,
+    /// some heap object grew after it was read this round
+    pub heap_stale: RefCell<bool>,
 
     pub dispatch_targets:
         RefCell<ImHashMap<(DefId, usize), (Span, Vec<(DefId, Option<GenericArgs>)>)>>,
@@ -416,6 +442,15 @@ impl<'a> InterpPass<'a> {
             sigstore,
             tstore,
             converter: RvalConverter::new(tstore),
+            stub_specs: StubSpecs::shipped(),
+            analysis_filter: PathFilter::default(),
+            cells: HashMap::new().into(),
+            cell_ptrs: HashMap::new().into(),
+            cells_stale: false.into(),
+            heap: HashMap::new().into(),
+            heap_ptrs: HashMap::new().into(),
+            heap_ptr_result: None.into(),
+            heap_stale: false.into(),
             dispatch_targets: ImHashMap::new().into(),
             dispatch_cha: ImHashMap::new().into(),
             dispatch_tags: ImHashMap::new().into(),
@@ -503,6 +538,8 @@ impl<'a> InterpPass<'a> {
         let start_scope = (start_instance, GenericArgs(vec![]));
         let mut call_stack = vec![start_scope.clone()];
 
+        // A previous entry point's run may have left its key behind
+        self.key_stack.borrow_mut().clear();
         self.key_stack
             .borrow_mut()
             .push((start_scope.clone(), ArgSet::new(&[])));
@@ -937,13 +974,7 @@ impl<'a> InterpPass<'a> {
         match ty.kind() {
             TyKind::RigidTy(rigidty) => match rigidty {
                 RigidTy::Dynamic(trait_vec, _) => {
-                    let mut desttys = Vec::new();
-                    for trait_ in trait_vec {
-                        if let Some(toty) = TraitObjTy::new_from_bound_existential(&trait_) {
-                            unique_push(&mut desttys, toty);
-                        }
-                    }
-                    return Some(desttys);
+                    return Some(TraitObjTy::new_from_existentials(&trait_vec));
                 }
                 RigidTy::Adt(_def, genargs) => {
                     for genarg in genargs.0 {
@@ -1198,6 +1229,10 @@ impl<'a> InterpPass<'a> {
                 );
                 //debug!("FINAL CONSTRAINTS: {:?}", final_constraints);
 
+                self.note_cell_write(ctxt, cur_scope, place, &final_constraints);
+                self.note_heap_write(ctxt, cur_scope, place, &final_constraints);
+                self.note_heap_alias(ctxt, cur_scope, place, dest_ty, rvalue);
+
                 let mut write_proj = place.projection.as_slice();
                 while let [ProjectionElem::Deref, rest @ ..] = write_proj {
                     write_proj = rest;
@@ -1420,6 +1455,8 @@ impl<'a> InterpPass<'a> {
         for constraint in constraints.inner.iter() {
             match constraint.cfc {
                 Some(RunningConstraint::Scalar(Some(num))) => nums.push(num.try_into().unwrap()),
+                // Too many to list
+                Some(RunningConstraint::Interval(..)) => return None,
                 _ => {}
             }
         }
@@ -1606,6 +1643,7 @@ impl<'a> InterpPass<'a> {
                 call_stack,
                 cur_scope,
                 local_decls,
+                bb,
                 sigval,
                 args,
             ),
@@ -1638,22 +1676,54 @@ impl<'a> InterpPass<'a> {
         }
     }
 
+    /// A call through a `fn` pointer we don't know the value of: any fn of
+    /// its signature whose address is taken, see `sig_collect`
     fn interp_fn_ptr(
         &self,
-        _term_span: &Span,
-        _ctxt: &mut Context,
-        _call_stack: &mut Vec<VOID>,
-        _cur_scope: &VOID,
-        _local_decls: &[LocalDecl],
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        cur_scope: &VOID,
+        local_decls: &[LocalDecl],
+        bb: usize,
         sigval: &SigVal,
-        _args: &Vec<Operand>,
+        args: &Vec<Operand>,
     ) -> Result<Option<Constraints>, Error> {
-        //debug!(
-        //    "interp_fn_ptr: falling back for sigval with output {:?}",
-        //    sigval.output
-        //);
+        let targets = self.sigstore.lookup(sigval);
+        debug!("interp_fn_ptr: {} candidates", targets.len());
+        if targets.is_empty() {
+            return self.retty_fallback_from_sigval(sigval);
+        }
 
-        self.retty_fallback_from_sigval(sigval)
+        let mut ret_constraints = Constraints::new();
+        let mut fallback = false;
+        for target in &targets {
+            match target {
+                RunningConstraint::FnDef(fndef, genargs) => {
+                    if let Some(c) = self.interp_fn_def(
+                        term_span,
+                        ctxt,
+                        call_stack,
+                        cur_scope,
+                        local_decls,
+                        bb,
+                        *fndef,
+                        genargs,
+                        args,
+                    )? {
+                        ret_constraints.append(c);
+                    }
+                }
+                // Called through the pointer, a closure takes its arguments
+                // untupled and without itself, which `interp_closure`
+                // doesn't expect
+                _ => fallback = true,
+            }
+        }
+        if fallback && let Some(c) = self.retty_fallback_from_sigval(sigval)? {
+            ret_constraints.append(c);
+        }
+        Ok(Some(ret_constraints))
     }
 
     fn interp_closure(
@@ -1709,6 +1779,106 @@ impl<'a> InterpPass<'a> {
         }
     }
 
+    /// Calls a closure value with already-resolved argument constraints
+    /// instead of operands in the caller's frame, for stubs that model a
+    /// higher-order std fn themselves (see `combinators`). Fn items that
+    /// resolve to a body are called the same way (see `fmt_args`).
+    /// Anything else (fn pointers, unresolvable items) falls back to
+    /// `out_ty`, the callee's return type at the stub's call.
+    pub(crate) fn interp_callable_with(
+        &self,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        callee: &Constraints,
+        inputs: Vec<Constraints>,
+        out_ty: &Ty,
+    ) -> Result<Constraints, Error> {
+        let fallback = || {
+            let (_, constraint) = self.converter.convert_ty(
+                &Location::unknown(),
+                out_ty,
+                Some(caller_scope),
+                Some(self),
+            );
+            Constraints::from(constraint)
+        };
+
+        if callee.inner.is_empty() {
+            return Ok(fallback());
+        }
+
+        let mut out = Constraints::new();
+        for constraint in callee.inner.iter() {
+            let (new_scope, first_arg) = match &constraint.cfc {
+                Some(RunningConstraint::Closure(cdef, genargs)) if cdef.body().is_some() => {
+                    let closure_kind = self.get_closure_kind(genargs);
+                    let instance =
+                        Instance::resolve_closure(*cdef, genargs, closure_kind).unwrap();
+                    // same layout `resolve_args` uses for a closure: _1 is
+                    // the environment, the (untupled) args follow
+                    ((instance, genargs.clone()), 2)
+                }
+                Some(RunningConstraint::FnDef(fndef, genargs)) => {
+                    match Instance::resolve(*fndef, genargs) {
+                        Ok(instance)
+                            if matches!(instance.kind, InstanceKind::Item) && instance.has_body() =>
+                        {
+                            ((instance, genargs.clone()), 1)
+                        }
+                        _ => {
+                            out.append(fallback());
+                            continue;
+                        }
+                    }
+                }
+                Some(RunningConstraint::Param(..)) => {
+                    if let Some(tainted) = self.summary_build_taint_stack.borrow_mut().last_mut() {
+                        *tainted = true;
+                    }
+                    out.append(fallback());
+                    continue;
+                }
+                _ => {
+                    out.append(fallback());
+                    continue;
+                }
+            };
+
+            if call_stack.contains(&new_scope) {
+                out.append(fallback());
+                continue;
+            }
+            let body = self.get_body(&new_scope);
+
+            let mut new_ctxt = Context::empty();
+            for (i, cs) in inputs.iter().enumerate() {
+                let place = Place {
+                    local: i + first_arg,
+                    projection: vec![],
+                };
+                new_ctxt.cstore.cmap.insert(
+                    MapKey::Var(place),
+                    Box::new(MapValue::Constraints(cs.clone())),
+                );
+            }
+            self.seed_callee_store(ctxt, caller_scope, &new_scope, new_ctxt);
+
+            let key = (new_scope.clone(), ArgSet::new(&inputs));
+            self.prepare_call(call_stack, &key);
+            match self.visit_body(ctxt, call_stack, &new_scope, &body) {
+                Ok(Some(r)) => out.append(r),
+                Ok(None) => out.append(fallback()),
+                Err(e) => {
+                    self.prepare_return(call_stack);
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(out)
+    }
+
     fn get_closure_kind(&self, genargs: &GenericArgs) -> ClosureKind {
         if genargs.0.is_empty() {
             panic!("no closure kind in genargs (empty)");
@@ -1737,6 +1907,14 @@ impl<'a> InterpPass<'a> {
     ) -> Result<Option<Constraints>, Error> {
         let dest_ty = destination.ty(local_decls).unwrap();
         let maybe_trait_destty = self.contains_dyn(&dest_ty);
+        let cell_ptr = match co.const_.ty().kind() {
+            TyKind::RigidTy(RigidTy::FnDef(fndef, _)) => {
+                self.cell_ptr_target(&fndef, args, local_decls)
+            }
+            _ => None,
+        };
+        let heap_ptr_args = self.heap_ptr_args(ctxt, cur_scope, args, dest_ty);
+        self.take_heap_ptr_result();
         let ret_constraints = match co.const_.ty().kind() {
             TyKind::RigidTy(rigid_ty) => match rigid_ty {
                 RigidTy::FnDef(fndef, genargs) => self.interp_fn_def(
@@ -1787,6 +1965,12 @@ impl<'a> InterpPass<'a> {
                     constraints.clone(),
                     Some(self),
                 );
+                if let Some(ty) = cell_ptr {
+                    self.note_cell_ptr(cur_scope, destination, ty);
+                }
+                if let Some(sites) = self.take_heap_ptr_result().or(heap_ptr_args) {
+                    self.note_heap_ptr(cur_scope, destination, sites);
+                }
 
                 //debug!("\n\n####### RETURNED VAL (CONSTRAINTS): {:?}", constraints);
 
@@ -1850,21 +2034,48 @@ impl<'a> InterpPass<'a> {
         log_scope(cur_scope);
 
         // checking for recursive stack depths of > 50
-        if *self.rec_depth.borrow() > MAX_DEPTH {
-            return Err(Error::RecurseLimit(MAX_DEPTH));
+        if *self.rec_depth.borrow() > limits().max_depth {
+            return Err(Error::RecurseLimit(limits().max_depth));
         }
 
         let stdlib_result = {
             let _g = self.timing_span(TimingCat::InterpFnDefStdlibStub, cur_scope);
-            self.stdlib_stub(
-                ctxt,
-                cur_scope,
-                term_span,
-                local_decls,
-                &fndef,
-                genargs,
-                args,
-            )
+            // `[[stub]]`s first, so one can override any built-in model
+            match self
+                .stub_spec_call(
+                    ctxt,
+                    cur_scope,
+                    term_span,
+                    local_decls,
+                    &fndef,
+                    genargs,
+                    args,
+                )
+                .or_else(|| {
+                    self.stub_higher_order(
+                        term_span,
+                        ctxt,
+                        call_stack,
+                        cur_scope,
+                        local_decls,
+                        &fndef,
+                        genargs,
+                        args,
+                    )
+                }) {
+                Some(result) => Some(result),
+                None => self
+                    .stdlib_stub(
+                        ctxt,
+                        cur_scope,
+                        term_span,
+                        local_decls,
+                        &fndef,
+                        genargs,
+                        args,
+                    )
+                    .or_else(|| self.analysis_excluded(&fndef)),
+            }
         };
         if let Some(result) = stdlib_result {
             return result;
@@ -2422,7 +2633,18 @@ impl<'a> InterpPass<'a> {
             args,
             is_closure,
         );
+        self.seed_callee_store(ctxt, caller_scope, callee_scope, new_ctxt);
+    }
 
+    /// Merges a callee's freshly resolved argument store into whatever its
+    /// scope already holds, bumping its epoch if that widened anything.
+    fn seed_callee_store(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        callee_scope: &VOID,
+        new_ctxt: Context,
+    ) {
         // Merge new substore into existing substore at this scopeId
         let store;
         let mut widened = false;
@@ -2739,6 +2961,7 @@ impl<'a> InterpPass<'a> {
             local_decls,
             &trait_defid,
             &fndef.0,
+            genargs,
             args,
         );
         drop(_timing_guard);
@@ -2822,6 +3045,7 @@ impl<'a> InterpPass<'a> {
             local_decls,
             &trait_defid,
             &fndef.0,
+            genargs,
             args,
             fsa_empty,
         );
@@ -2873,6 +3097,7 @@ impl<'a> InterpPass<'a> {
         local_decls: &[LocalDecl],
         trait_defid: &DefId,
         assoc_fn_defid: &DefId,
+        call_site_genargs: &GenericArgs,
         args: &Vec<Operand>,
         fsa_empty: bool,
     ) -> TagPlan {
@@ -2901,7 +3126,8 @@ impl<'a> InterpPass<'a> {
             };
 
             let (_is_closure, defids) = self.resolve_defid(term_span, trait_defid, &c);
-            let impls = self.get_impls_from_defids(assoc_fn_defid, &defids, true);
+            let impls =
+                self.get_impls_from_defids(assoc_fn_defid, &defids, call_site_genargs, true);
             if impls.len() != 1 {
                 return TagPlan::Poisoned;
             }
@@ -2936,7 +3162,7 @@ impl<'a> InterpPass<'a> {
         TagPlan::Tagged(out)
     }
 
-    fn get_trait_defid(&self, assoc_fn_defid: &DefId) -> DefId {
+    pub(crate) fn get_trait_defid(&self, assoc_fn_defid: &DefId) -> DefId {
         // Get trait that this function is associated with
         match self.tstore.assoc_fn_traits.get(assoc_fn_defid) {
             Some(trait_defid) => *trait_defid,
@@ -2964,6 +3190,7 @@ impl<'a> InterpPass<'a> {
         &self,
         assoc_fn_defid: &DefId,
         constraint_defids: &Vec<(DefId, Option<GenericArgs>)>,
+        call_site_genargs: &GenericArgs,
         _fsa: bool,
     ) -> Vec<(DefId, Option<GenericArgs>)> {
         // CHA-collected defid genargs are None, while FSA-collected defid genargs might be Some().
@@ -3006,6 +3233,10 @@ impl<'a> InterpPass<'a> {
                     if FnDef(*defid).body().is_some() {
                         // This is a callable item, push the impl defid
                         unique_push(&mut assoc_fn_impls, (*defid, genargs.clone()));
+                    } else if let Some(found) =
+                        self.resolve_blanket_impl(assoc_fn_defid, defid, genargs, call_site_genargs)
+                    {
+                        unique_push(&mut assoc_fn_impls, found);
                     }
                 }
             }
@@ -3014,21 +3245,220 @@ impl<'a> InterpPass<'a> {
         assoc_fn_impls
     }
 
-    fn get_impls_cha(
+    /// The fn a blanket impl (`impl<T: Foo> Bar for T`) provides for the ADT
+    /// `defid` that reached this `dyn Bar` call site: rustc's own resolution
+    /// picks the impl and instantiates it, and fails if the impl's bounds
+    /// don't hold for this type after all.
+    fn resolve_blanket_impl(
+        &self,
+        assoc_fn_defid: &DefId,
+        defid: &DefId,
+        genargs: &Option<GenericArgs>,
+        call_site_genargs: &GenericArgs,
+    ) -> Option<(DefId, Option<GenericArgs>)> {
+        let trait_defid = self.get_trait_defid(assoc_fn_defid);
+        if !self.tstore.has_blanket_impl(&trait_defid) {
+            return None;
+        }
+        let self_ty = match genargs {
+            Some(g) => AdtDef(*defid).ty_with_args(g),
+            None => AdtDef(*defid).ty(),
+        };
+        if mentions_param(&self_ty) {
+            return None;
+        }
+
+        // Self replaces the `dyn Bar` in the call's own args - the first
+        // Type arg, after any leading lifetimes
+        let mut args = call_site_genargs.0.clone();
+        match args
+            .iter_mut()
+            .find(|arg| matches!(arg, GenericArgKind::Type(_)))
+        {
+            Some(arg) => *arg = GenericArgKind::Type(self_ty),
+            None => args.push(GenericArgKind::Type(self_ty)),
+        }
+        let instance = Instance::resolve(FnDef(*assoc_fn_defid), &GenericArgs(args)).ok()?;
+        debug!(
+            "resolve_blanket_impl: {:?} for {:?} -> {:?}",
+            assoc_fn_defid, self_ty, instance
+        );
+        Some((instance.def.def_id(), Some(instance.args())))
+    }
+
+    /// CHA's candidates among `trait_other_impls`: every non-ADT impl's fn,
+    /// blanket impls' still generic (the rewrite only matches its DefId
+    /// against vtable entries, and interpreting one without its
+    /// instantiation falls back to its signature, see
+    /// `simulate_static_calls`).
+    fn get_other_impls_cha(
+        &self,
+        assoc_fn_defid: &DefId,
+        trait_defid: &DefId,
+        call_site_genargs: &GenericArgs,
+    ) -> Vec<(DefId, Option<GenericArgs>)> {
+        let mut impls = Vec::new();
+        let Some(others) = self.tstore.trait_other_impls.get(trait_defid) else {
+            return impls;
+        };
+        for other in others {
+            if !Self::other_impl_params_compatible(call_site_genargs, &other.args) {
+                continue;
+            }
+            for (impl_fn, decl) in &other.assoc_fns {
+                if decl != assoc_fn_defid {
+                    continue;
+                }
+                if impl_fn != decl {
+                    unique_push(&mut impls, (*impl_fn, None));
+                } else if let SelfTyPat::Concrete(self_ty) = &other.self_ty {
+                    // Inherited default method, generic over Self - as in
+                    // `get_impls_from_defids`, only with a concrete one
+                    unique_push(
+                        &mut impls,
+                        (*decl, Some(GenericArgs(vec![GenericArgKind::Type(*self_ty)]))),
+                    );
+                }
+            }
+        }
+        impls
+    }
+
+    /// `trait_params_compatible` for a `trait_other_impls` entry, whose Self
+    /// is no ADT: Self is the TraitRef's first arg, and an arg with the
+    /// impl's own params in it could be instantiated to anything.
+    fn other_impl_params_compatible(
+        call_site_genargs: &GenericArgs,
+        impl_genargs: &GenericArgs,
+    ) -> bool {
+        call_site_genargs.0.len() == impl_genargs.0.len()
+            && call_site_genargs
+                .0
+                .iter()
+                .zip(impl_genargs.0.iter())
+                .skip(1)
+                .all(|(call_arg, impl_arg)| {
+                    call_arg == impl_arg
+                        || matches!(impl_arg, GenericArgKind::Type(t) if mentions_param(t))
+                })
+    }
+
+    /// Whether an FSA receiver disjunct is a value whose type the cast to
+    /// `dyn Trait` couldn't tag (only ADTs and closures are), so it could be
+    /// any of `trait_other_impls`.
+    fn is_untyped_receiver(c: &Constraint) -> bool {
+        if c.toc.is_some() {
+            return false;
+        }
+        match &c.cfc {
+            Some(RunningConstraint::Ptr(inner)) => Self::is_untyped_receiver(inner),
+            Some(
+                RunningConstraint::Scalar(_)
+                | RunningConstraint::Interval(..)
+                | RunningConstraint::Float
+                | RunningConstraint::Tuple(_)
+                | RunningConstraint::List(_)
+                | RunningConstraint::FnPtr(_)
+                | RunningConstraint::FnDef(..),
+            ) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn get_impls_cha(
         &self,
         //callee_scope: &VOID,
         assoc_fn_defid: &DefId,
         trait_defid: &DefId,
         call_site_genargs: &GenericArgs,
+    ) -> Vec<(DefId, Option<GenericArgs>)> {
+        self.get_impls_cha_among(assoc_fn_defid, trait_defid, call_site_genargs, |_| true)
+    }
+
+    /// `get_impls_cha`, with only the implementing types `keep` accepts
+    /// (RTA's instantiated types, see `baseline`). Impls for types that
+    /// aren't ADTs or closures are always kept.
+    pub(crate) fn get_impls_cha_among(
+        &self,
+        assoc_fn_defid: &DefId,
+        trait_defid: &DefId,
+        call_site_genargs: &GenericArgs,
+        keep: impl Fn(&DefId) -> bool,
     ) -> Vec<(DefId, Option<GenericArgs>)> {
         debug!("\n\nGETTING CHA IMPLS");
-        let constraint_defids = self.get_cha_tyconstraint_defids(&trait_defid, call_site_genargs);
+        let mut constraint_defids =
+            self.get_cha_tyconstraint_defids(&trait_defid, call_site_genargs);
+        constraint_defids.retain(|(defid, _)| keep(defid));
         //debug!(
         //    "constraint defids ({:?} total): {:?}",
         //    constraint_defids.len(),
         //    constraint_defids
         //);
-        self.get_impls_from_defids(assoc_fn_defid, &constraint_defids, false)
+        let mut impls = self.get_impls_from_defids(
+            assoc_fn_defid,
+            &constraint_defids,
+            call_site_genargs,
+            false,
+        );
+        unique_append(
+            &mut impls,
+            self.get_other_impls_cha(assoc_fn_defid, trait_defid, call_site_genargs),
+        );
+        impls.retain(|(impl_fn, genargs)| {
+            Self::assoc_bindings_compatible(assoc_fn_defid, call_site_genargs, impl_fn, genargs)
+        });
+        impls
+    }
+
+    /// Whether `impl_fn` (with `genargs`, as `get_impls_from_defids` has it)
+    /// can be what a call through the call site's `dyn` runs, given the
+    /// associated types the `dyn` binds (`Item = u8`, `Output = B`): with
+    /// every associated type normalized, the impl's signature has to be the
+    /// call site's everywhere but the receiver. True whenever there is
+    /// nothing to compare, or either side doesn't resolve.
+    fn assoc_bindings_compatible(
+        assoc_fn_defid: &DefId,
+        call_site_genargs: &GenericArgs,
+        impl_fn: &DefId,
+        genargs: &Option<GenericArgs>,
+    ) -> bool {
+        let Some(dyn_ty) = call_site_genargs.0.iter().find_map(|arg| arg.ty()) else {
+            return true;
+        };
+        let TyKind::RigidTy(RigidTy::Dynamic(preds, _)) = dyn_ty.kind() else {
+            return true;
+        };
+        if TraitObjTy::new_from_existentials(&preds)
+            .iter()
+            .all(|toty| toty.assoc_bindings.is_empty())
+        {
+            return true;
+        }
+
+        let sig_tail = |instance: Instance| -> Option<Vec<Ty>> {
+            let abi = instance.fn_abi().ok()?;
+            Some(
+                abi.args
+                    .iter()
+                    .skip(1)
+                    .chain(std::iter::once(&abi.ret))
+                    .map(|arg| arg.ty)
+                    .collect(),
+            )
+        };
+        let expected = Instance::resolve(FnDef(*assoc_fn_defid), call_site_genargs)
+            .ok()
+            .and_then(sig_tail);
+        let actual = Instance::resolve(
+            FnDef(*impl_fn),
+            genargs.as_ref().unwrap_or(&GenericArgs(vec![])),
+        )
+        .ok()
+        .and_then(sig_tail);
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => expected == actual,
+            _ => true,
+        }
     }
 
     fn get_cha_tyconstraint_defids(
@@ -3096,6 +3526,7 @@ impl<'a> InterpPass<'a> {
         local_decls: &[LocalDecl],
         trait_defid: &DefId,
         assoc_fn_defid: &DefId,
+        call_site_genargs: &GenericArgs,
         args: &Vec<Operand>,
     ) -> (bool, bool, Vec<(DefId, Option<GenericArgs>)>) {
         debug!("\n\nGETTING FSA IMPLS");
@@ -3111,11 +3542,20 @@ impl<'a> InterpPass<'a> {
         //    constraint_defids.len(),
         //    constraint_defids
         //);
-        (
-            is_closure,
-            receiver_is_param,
-            self.get_impls_from_defids(assoc_fn_defid, &constraint_defids, true),
-        )
+        let mut impls =
+            self.get_impls_from_defids(assoc_fn_defid, &constraint_defids, call_site_genargs, true);
+        if tyconstraints.inner.iter().any(Self::is_untyped_receiver) {
+            unique_append(
+                &mut impls,
+                self.get_other_impls_cha(assoc_fn_defid, trait_defid, call_site_genargs),
+            );
+        }
+        if !is_closure {
+            impls.retain(|(impl_fn, genargs)| {
+                Self::assoc_bindings_compatible(assoc_fn_defid, call_site_genargs, impl_fn, genargs)
+            });
+        }
+        (is_closure, receiver_is_param, impls)
     }
 
     fn get_traitobj_place(&self, args: &Vec<Operand>) -> Place {
@@ -3197,7 +3637,11 @@ impl<'a> InterpPass<'a> {
                 cfc: _,
                 prov: _,
             } => {
-                if *trait_defid != toc_.0.def.0 {
+                // A `dyn Sub`'s vtable has all of `Super`'s methods too, so
+                // a call to one of those dispatches on it as well
+                if *trait_defid != toc_.0.def.0
+                    && !self.tstore.is_supertrait(&toc_.0.def.0, trait_defid)
+                {
                     return (false, vec![]);
                 }
 
@@ -3229,14 +3673,28 @@ impl<'a> InterpPass<'a> {
                         (true, vec![(cdef.0, Some(genargs.clone()))])
                     }
                 }
-                RunningConstraint::Scalar(_) | RunningConstraint::Float => (false, vec![]),
+                RunningConstraint::Scalar(_)
+                | RunningConstraint::Interval(..)
+                | RunningConstraint::Float => (false, vec![]),
                 RunningConstraint::Dynamic(tys) => {
                     match tys.iter().find(|ty| ty.def.0 == *trait_defid) {
                         Some(matching_ty) => (
                             false,
                             self.get_cha_tyconstraint_defids(trait_defid, &matching_ty.genargs),
                         ),
-                        None => (false, vec![]),
+                        // A supertrait method on a `dyn Sub`: whatever's
+                        // behind it implements `Sub`, so those are the
+                        // candidates, each with its own impl of the supertrait
+                        None => match tys
+                            .iter()
+                            .find(|ty| self.tstore.is_supertrait(&ty.def.0, trait_defid))
+                        {
+                            Some(sub_ty) => (
+                                false,
+                                self.get_cha_tyconstraint_defids(&sub_ty.def.0, &sub_ty.genargs),
+                            ),
+                            None => (false, vec![]),
+                        },
                     }
                 }
                 RunningConstraint::Ptr(box c) => self.resolve_defid(term_span, trait_defid, c),
@@ -3269,18 +3727,19 @@ impl<'a> InterpPass<'a> {
         //debug!("\nRESOLVE ADT HELPER");
 
         let mut resvec = Vec::new();
-        match self.tstore.struct_traits.get(&adtdef.0) {
-            // Does this ADT implement the desired trait? If so, add to vec
-            Some(traits) => {
-                if traits.contains(trait_defid) {
-                    if genargs.0.is_empty() {
-                        unique_push(&mut resvec, (adtdef.0, None));
-                    } else {
-                        unique_push(&mut resvec, (adtdef.0, Some(genargs.clone())));
-                    }
-                }
+        // Does this ADT implement the desired trait? If so, add to vec - or
+        // might it, through a blanket impl (see `resolve_blanket_impl`)
+        let implements = self
+            .tstore
+            .struct_traits
+            .get(&adtdef.0)
+            .is_some_and(|traits| traits.contains(trait_defid));
+        if implements || self.tstore.has_blanket_impl(trait_defid) {
+            if genargs.0.is_empty() {
+                unique_push(&mut resvec, (adtdef.0, None));
+            } else {
+                unique_push(&mut resvec, (adtdef.0, Some(genargs.clone())));
             }
-            None => {}
         }
 
         // Search in fields (in addition to genargs) b/c constraints are already there + don't need
@@ -3475,15 +3934,42 @@ impl<'a> InterpPass<'a> {
 
                     let _timing_guard =
                         self.timing_span(TimingCat::TermSimulateStdlibStub, cur_scope);
-                    let stub_attempt = self.stdlib_stub(
-                        ctxt,
-                        cur_scope,
-                        term_span,
-                        local_decls,
-                        &fndef,
-                        &genargs,
-                        args,
-                    );
+                    // `[[stub]]`s first, so one can override any built-in model
+                    let stub_attempt = match self
+                        .stub_spec_call(
+                            ctxt,
+                            cur_scope,
+                            term_span,
+                            local_decls,
+                            &fndef,
+                            &genargs,
+                            args,
+                        )
+                        .or_else(|| {
+                            self.stub_higher_order(
+                                term_span,
+                                ctxt,
+                                call_stack,
+                                cur_scope,
+                                local_decls,
+                                &fndef,
+                                &genargs,
+                                args,
+                            )
+                        }) {
+                        Some(result) => Some(result),
+                        None => self
+                            .stdlib_stub(
+                                ctxt,
+                                cur_scope,
+                                term_span,
+                                local_decls,
+                                &fndef,
+                                &genargs,
+                                args,
+                            )
+                            .or_else(|| self.analysis_excluded(&fndef)),
+                    };
                     match stub_attempt {
                         Some(stub_result) => {
                             drop(_timing_guard);
@@ -3985,7 +4471,8 @@ impl<'a> InterpPass<'a> {
                         let discr_vals = discr_vals_uninit.write_filled(0);
 
                         // Populate byte-map with possible branch values, based on constraints
-                        self.set_bytemap(&constraints, targets, discr_vals);
+                        let discr_ty = place.ty(local_decls).ok();
+                        self.set_bytemap(&constraints, targets, discr_ty.as_ref(), discr_vals);
 
                         self.prune_switchint_targets(
                             bb,
@@ -4004,12 +4491,23 @@ impl<'a> InterpPass<'a> {
         Ok(None)
     }
 
+    /// Arm values are compared as `discr_ty` reads them (see
+    /// `scalar::int_value`); an arm whose value can't be read is kept.
     fn set_bytemap(
         &self,
         constraints: &Constraints,
         targets: &SwitchTargets,
+        discr_ty: Option<&Ty>,
         discr_vals: &mut [u8],
     ) {
+        let arm_vals: Vec<Option<i128>> = targets
+            .branches()
+            .map(|(val, _bb)| match discr_ty {
+                Some(ty) => crate::scalar::int_value(val, ty),
+                None => i128::try_from(val).ok(),
+            })
+            .collect();
+
         if constraints.is_empty() {
             // Increment all branch counters (since no statically-known discr value)
             for (i, _) in targets.branches().enumerate() {
@@ -4029,10 +4527,14 @@ impl<'a> InterpPass<'a> {
                     if let Some(num) = num_opt {
                         // Increment matching branch counters
                         let mut set = false;
-                        for (i, (val, _bb)) in targets.branches().enumerate() {
-                            if *num == <u128 as TryInto<i128>>::try_into(val).unwrap() {
-                                discr_vals[usize::try_from(i).unwrap()] += 1;
-                                set = true;
+                        for (i, val) in arm_vals.iter().enumerate() {
+                            match val {
+                                Some(val) if val == num => {
+                                    discr_vals[i] += 1;
+                                    set = true;
+                                }
+                                Some(_) => {}
+                                None => discr_vals[i] += 1,
                             }
                         }
                         if !set {
@@ -4046,6 +4548,28 @@ impl<'a> InterpPass<'a> {
                         discr_vals[discr_vals.len() - 1] += 1;
                     }
                 }
+                Constraint {
+                    toc: _,
+                    cfc: Some(RunningConstraint::Interval(lo, hi)),
+                    prov: _,
+                } => {
+                    // Increment the branch counters in range, and otherwise
+                    // unless those branches cover the whole range
+                    let mut covered = 0;
+                    for (i, val) in arm_vals.iter().enumerate() {
+                        match val {
+                            Some(val) if *lo <= *val && *val <= *hi => {
+                                discr_vals[i] += 1;
+                                covered += 1;
+                            }
+                            Some(_) => {}
+                            None => discr_vals[i] += 1,
+                        }
+                    }
+                    if hi.checked_sub(*lo).is_none_or(|d| covered <= d) {
+                        discr_vals[discr_vals.len() - 1] += 1;
+                    }
+                }
                 _ => {
                     // Increment all branch counters (since no statically-known discr value)
                     for (i, _) in targets.branches().enumerate() {
@@ -4302,7 +4826,7 @@ impl<'a> InterpPass<'a> {
             }
         }
 
-        if *self.rec_depth.borrow() > MAX_DEPTH {
+        if *self.rec_depth.borrow() > limits().max_depth {
             *self.rec_depth.borrow_mut() -= 1;
             self.incomplete.borrow_mut().insert(cur_scope.clone());
             return Ok(retval);
@@ -0,0 +1,412 @@
+//! Iterator adapter chains over the synthetic iterators `stdlib_stubs`
+//! makes for the std collections.
+//!
+//! A synthetic iterator is a value of the collection's own AdtDef with the
+//! element constraints in field 0 (see `stub_make_iter`), and `stub_next`
+//! just reads them back. Each adapter here turns one into another synthetic
+//! iterator with a new field 0, so `handlers.iter().filter(..).map(|h|
+//! h.as_ref())` yields exactly what the closures return, and `next` on the
+//! resulting `Map<Filter<Iter<..>>>` is stubbed the same way
+//! (`iter_receiver` accepts the adapter types too).
+//!
+//! Closures run once, eagerly, when the adapter is built - on every element
+//! at once, through `interp_callable_with`. An adapter that's never driven
+//! still counts its closure's effects, which can only add constraints.
+//! Nothing is called over an empty element set: those closures never run.
+//!
+//! `collect` into one of the std collections builds a synthetic collection
+//! (the `fresh_collection_constraint` layout); `for_each` and `fold` run
+//! their closure, `fold` until the accumulator stops growing. Everything
+//! else falls through to the real body: a receiver that isn't synthetic, a
+//! fn item or pointer instead of a closure, a `chain`/`zip`/`flat_map`
+//! over something we can't read elements out of, `collect` into anything
+//! else.
+//!
+//! Boxing one of these as a `dyn Iterator` labels it with its real type
+//! (see `relabel_synthetic_iter`), so `next` through the vtable is
+//! dispatched - and devirtualized - like any other trait object call.
+
+use crate::rustc_public_bridge::IndexedVal;
+use rustc_public::mir::{LocalDecl, Operand, ProjectionElem};
+use rustc_public::ty::{FnDef, GenericArgs, RigidTy, Span, Ty, TyKind};
+
+use crate::Context;
+use crate::InterpPass;
+use crate::config::limits;
+use crate::constraints::{ADTFields, Constraint, Constraints, Location, RunningConstraint, VOID};
+use crate::convert::{WrapperKind, is_std_iter_suffix};
+use crate::error::Error;
+use crate::interior::instantiated_sig;
+use crate::stdlib_stubs::IterRecv;
+
+use log::debug;
+
+/// Rounds of `fold`'s closure before the accumulator is widened to its
+/// type instead, by default (`[limits] max_fold_rounds`, see `config`).
+pub const MAX_FOLD_ROUNDS: usize = 3;
+
+/// Adapters that hand the receiver's elements through unchanged.
+const PASS_THROUGH: [&str; 10] = [
+    "rev", "take", "skip", "cloned", "copied", "fuse", "peekable", "step_by", "by_ref", "cycle",
+];
+
+/// Same, but with a closure that sees each element (and whose result
+/// doesn't become one).
+const INSPECTING: [&str; 4] = ["filter", "inspect", "take_while", "skip_while"];
+
+impl<'a> InterpPass<'a> {
+    /// Called from `stub_higher_order`: Some(result) for an `Iterator`
+    /// adapter or consumer on one of our synthetic iterators that's modeled
+    /// here.
+    pub fn stub_iter_adapter(
+        &self,
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        if !Self::callee_path(fndef).contains("Iterator::") {
+            return None;
+        }
+        let recv = self.iter_receiver(ctxt, caller_scope, local_decls, args)?;
+        let out_ty = instantiated_sig(fndef, genargs)?.value.output();
+
+        self.stub_iter_method(
+            term_span,
+            ctxt,
+            call_stack,
+            caller_scope,
+            local_decls,
+            &Self::method_name(fndef),
+            &recv,
+            args,
+            out_ty,
+        )
+        .transpose()
+        .map(|r| r.map(Some))
+    }
+
+    /// Ok(None) if `method` isn't modeled. For `filter_map`/`flat_map` that
+    /// can only be told from what the closure returned, in which case the
+    /// real body calls it again - harmless, just redundant.
+    fn stub_iter_method(
+        &self,
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        method: &str,
+        recv: &IterRecv,
+        args: &Vec<Operand>,
+        out_ty: Ty,
+    ) -> Result<Option<Constraints>, Error> {
+        let Some(cur) =
+            ctxt.get_constraints(caller_scope, local_decls, &recv.place, false, Some(self))
+        else {
+            return Ok(None);
+        };
+        let elem = ctxt.step_field(caller_scope, &cur, &recv.elem_field, Some(self));
+        debug!("ITER ADAPTER {} elems={}", method, elem.inner.len());
+
+        // the receiver with its elements replaced
+        let with_elem = |elem: Constraints| {
+            let mut out = cur.clone();
+            out.write_field(vec![recv.elem_field.clone()], elem);
+            out
+        };
+        let closure = |ctxt: &Context, idx: usize| {
+            self.closure_arg(ctxt, term_span, caller_scope, local_decls, args.get(idx)?)
+        };
+        let other = |ctxt: &Context| -> Option<Constraints> {
+            let op = args.get(1)?;
+            let value =
+                self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false);
+            self.iter_elems(ctxt, caller_scope, op.ty(local_decls).ok()?, &value)
+        };
+
+        let result = match method {
+            m if PASS_THROUGH.contains(&m) => cur.clone(),
+            "enumerate" => {
+                let idx =
+                    Constraints::from(Constraint::new(None, Some(RunningConstraint::Scalar(None))));
+                with_elem(Constraints::from(Constraint::new(
+                    None,
+                    Some(RunningConstraint::Tuple(vec![idx, elem])),
+                )))
+            }
+            m if INSPECTING.contains(&m) => {
+                let Some((f, ret)) = closure(ctxt, 1) else {
+                    return Ok(None);
+                };
+                if !elem.inner.is_empty() {
+                    self.interp_callable_with(
+                        ctxt,
+                        call_stack,
+                        caller_scope,
+                        &f,
+                        vec![elem],
+                        &ret,
+                    )?;
+                }
+                cur.clone()
+            }
+            "map" | "filter_map" | "flat_map" => {
+                let Some((f, ret)) = closure(ctxt, 1) else {
+                    return Ok(None);
+                };
+                let mapped = if elem.inner.is_empty() {
+                    Constraints::new()
+                } else {
+                    let out = self.interp_callable_with(
+                        ctxt,
+                        call_stack,
+                        caller_scope,
+                        &f,
+                        vec![elem],
+                        &ret,
+                    )?;
+                    let elems = match method {
+                        "map" => Some(out),
+                        "filter_map" => self.some_payloads(&out),
+                        _ => self.iter_elems(ctxt, caller_scope, ret, &out),
+                    };
+                    let Some(elems) = elems else {
+                        return Ok(None);
+                    };
+                    elems
+                };
+                with_elem(mapped)
+            }
+            "chain" => {
+                let Some(rest) = other(ctxt) else {
+                    return Ok(None);
+                };
+                let mut all = elem;
+                all.append(rest);
+                with_elem(all)
+            }
+            "zip" => {
+                let Some(rest) = other(ctxt) else {
+                    return Ok(None);
+                };
+                with_elem(Constraints::from(Constraint::new(
+                    None,
+                    Some(RunningConstraint::Tuple(vec![elem, rest])),
+                )))
+            }
+            "for_each" => {
+                let Some((f, ret)) = closure(ctxt, 1) else {
+                    return Ok(None);
+                };
+                if !elem.inner.is_empty() {
+                    self.interp_callable_with(
+                        ctxt,
+                        call_stack,
+                        caller_scope,
+                        &f,
+                        vec![elem],
+                        &ret,
+                    )?;
+                }
+                self.type_fallback(caller_scope, out_ty)
+            }
+            "fold" => {
+                let Some((f, ret)) = closure(ctxt, 2) else {
+                    return Ok(None);
+                };
+                let Some(init_op) = args.get(1) else {
+                    return Ok(None);
+                };
+                let mut acc = self.resolve_arg(
+                    ctxt,
+                    term_span,
+                    caller_scope,
+                    &None,
+                    local_decls,
+                    init_op,
+                    false,
+                );
+                if !elem.inner.is_empty() {
+                    let mut settled = false;
+                    for _ in 0..limits().max_fold_rounds {
+                        let before = acc.inner.len();
+                        let next = self.interp_callable_with(
+                            ctxt,
+                            call_stack,
+                            caller_scope,
+                            &f,
+                            vec![acc.clone(), elem.clone()],
+                            &ret,
+                        )?;
+                        acc.append(next);
+                        if acc.inner.len() == before {
+                            settled = true;
+                            break;
+                        }
+                    }
+                    if !settled {
+                        acc.append(self.type_fallback(caller_scope, out_ty));
+                    }
+                }
+                acc
+            }
+            "collect" => match self.collect_into(out_ty, elem) {
+                Some(collected) => collected,
+                None => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(result))
+    }
+
+    /// The closure at `op` and its return type, or None if it isn't a
+    /// closure (or might not be one).
+    fn closure_arg(
+        &self,
+        ctxt: &Context,
+        term_span: &Span,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        op: &Operand,
+    ) -> Option<(Constraints, Ty)> {
+        let ty = op.ty(local_decls).ok()?;
+        if !matches!(ty.kind(), TyKind::RigidTy(RigidTy::Closure(..))) {
+            return None;
+        }
+        let ret = ty.kind().fn_sig()?.value.output();
+        let f = self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false);
+        let all_closures = !f.inner.is_empty()
+            && f.inner
+                .iter()
+                .all(|c| matches!(c.cfc, Some(RunningConstraint::Closure(..))));
+        all_closures.then_some((f, ret))
+    }
+
+    /// The elements of `value` (of type `ty`), an `IntoIterator` we have a
+    /// model of: one of our synthetic iterators, a std collection, or an
+    /// `Option`.
+    fn iter_elems(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        ty: Ty,
+        value: &Constraints,
+    ) -> Option<Constraints> {
+        let mut ty = ty;
+        while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() {
+            ty = inner;
+        }
+        let TyKind::RigidTy(RigidTy::Adt(adtdef, _)) = ty.kind() else {
+            return None;
+        };
+        let name = adtdef.0.name();
+        let is_iter = is_std_iter_suffix(name.splitn(2, "::").nth(1).unwrap_or(""));
+        let kind = self.converter.wrapper_kind(&adtdef);
+
+        if kind == Some(WrapperKind::Option) {
+            return self.some_payloads(value);
+        }
+        if !is_iter && !kind.is_some_and(|k| k.is_collection()) {
+            return None;
+        }
+
+        let key = ProjectionElem::Field(0, Ty::bool_ty());
+        let mut elems = Constraints::new();
+        for c in value.inner.iter() {
+            let Some(RunningConstraint::Adt(def, _, _, _)) = &c.cfc else {
+                return None;
+            };
+            let c_kind = self.converter.wrapper_kind(def)?;
+            if !c_kind.is_collection() {
+                return None;
+            }
+            let one = Constraints::from(c.clone());
+            let keys = ctxt.step_field(caller_scope, &one, &key, Some(self));
+            // a map itself (not an iterator over it) yields `(key, value)`
+            if !is_iter && c_kind.is_map() {
+                let vals = ctxt.step_field(
+                    caller_scope,
+                    &one,
+                    &ProjectionElem::Field(1, Ty::bool_ty()),
+                    Some(self),
+                );
+                elems.push(Constraint::new(
+                    None,
+                    Some(RunningConstraint::Tuple(vec![keys, vals])),
+                ));
+            } else {
+                elems.append(keys);
+            }
+        }
+        Some(elems)
+    }
+
+    /// What's in the `Some`s of an `Option` value, or None if any disjunct
+    /// isn't one we can read.
+    fn some_payloads(&self, value: &Constraints) -> Option<Constraints> {
+        let mut payloads = Constraints::new();
+        for c in value.inner.iter() {
+            let Some(RunningConstraint::Adt(def, _, variant, fields)) = &c.cfc else {
+                return None;
+            };
+            if self.converter.wrapper_kind(def) != Some(WrapperKind::Option) {
+                return None;
+            }
+            let is_none = variant.is_some_and(|v| {
+                def.variants()
+                    .iter()
+                    .position(|var| var.name() == "None")
+                    .is_some_and(|none| v.to_index() == none)
+            });
+            if is_none {
+                continue;
+            }
+            payloads.append(fields.get(&0)?.clone());
+        }
+        Some(payloads)
+    }
+
+    /// `collect` into a std collection: a fresh synthetic one holding
+    /// `elem` (split into keys and values for a map).
+    fn collect_into(&self, out_ty: Ty, elem: Constraints) -> Option<Constraints> {
+        let TyKind::RigidTy(RigidTy::Adt(adtdef, genargs)) = out_ty.kind() else {
+            return None;
+        };
+        let kind = self.converter.wrapper_kind(&adtdef)?;
+        if !kind.is_collection() {
+            return None;
+        }
+
+        let fields: ADTFields = if kind.is_map() {
+            let (mut keys, mut vals) = (Constraints::new(), Constraints::new());
+            for c in elem.inner.iter() {
+                let Some(RunningConstraint::Tuple(kv)) = &c.cfc else {
+                    return None;
+                };
+                keys.append(kv.first()?.clone());
+                vals.append(kv.get(1)?.clone());
+            }
+            ADTFields::from([(0, keys), (1, vals)])
+        } else {
+            ADTFields::from([(0, elem)])
+        };
+
+        Some(Constraints::from(Constraint::new(
+            None,
+            Some(RunningConstraint::Adt(adtdef, genargs, None, fields)),
+        )))
+    }
+
+    fn type_fallback(&self, scope: &VOID, ty: Ty) -> Constraints {
+        let (_, constraint) =
+            self.converter
+                .convert_ty(&Location::unknown(), &ty, Some(scope), Some(self));
+        Constraints::from(constraint)
+    }
+}
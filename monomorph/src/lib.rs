@@ -3,60 +3,76 @@
 #![feature(box_patterns)]
 
 //extern crate rustc_hir;
-//extern crate rustc_middle;
 extern crate rustc_data_structures;
 extern crate rustc_index;
+extern crate rustc_middle;
 extern crate rustc_public;
 extern crate rustc_public_bridge;
 
 //use rustc_public::CrateDef;
-use rustc_public::DefId;
+use rustc_public::mir::TerminatorKind;
 use rustc_public::mir::mono::Instance;
-use rustc_public::ty::{GenericArgs, Span};
-use std::collections::HashMap;
+use rustc_public::ty::{FnDef, GenericArgs, RigidTy, Span, TyKind};
+use rustc_public::{DefId, ItemKind};
+use std::collections::{HashMap, HashSet};
 
 use log::debug;
 
+pub mod annotations;
+pub mod baseline;
+pub mod combinators;
 pub mod common;
+pub mod config;
 pub mod constraints;
 pub mod convert;
+pub mod cost;
+pub mod diff;
+pub mod dyn_error;
 pub mod error;
+pub mod fmt_args;
+pub mod heap;
+pub mod interior;
 pub mod interp;
+pub mod iter_adapters;
 pub mod logger;
 pub mod merge;
+pub mod profile;
 //pub mod projection;
 pub mod rewrite;
+pub mod scalar;
 pub mod sig_collect;
 pub mod stdlib_stubs;
+pub mod stub_spec;
+pub mod trace;
 pub mod trait_collect;
+pub mod type_id;
 pub mod util;
 pub mod wto;
 
+use crate::common::VerifOptType;
+use crate::config::{limits, set_limits};
 use crate::constraints::Context;
+use crate::heap::HeapObject;
+use crate::interior::SharedCell;
 use crate::interp::{InterpPass, TagPlan};
 use crate::logger::VOLogger;
-use crate::sig_collect::{SigCollectPass, SigStore};
-use crate::trait_collect::{TraitCollectPass, TraitStore};
+use crate::sig_collect::SigStore;
+use crate::stub_spec::StubSpecs;
+use crate::trait_collect::{TraitCollectPass, TraitStore, is_exported};
 use crate::util::options::AnalysisOptions;
 
 pub fn start_verifopt(
-    _options: AnalysisOptions,
+    options: AnalysisOptions,
 ) -> (
+    HashMap<(DefId, usize), (Span, Vec<(DefId, Option<GenericArgs>)>)>,
     HashMap<(DefId, usize), (Span, Vec<(DefId, Option<GenericArgs>)>)>,
     HashMap<(DefId, usize), TagPlan>,
+    HashSet<(DefId, usize)>,
 ) {
-    // TODO make log filename a cmdline option
-    let f_filename = "found_ex";
-    let nf_filename = "notfound_ex";
-    let mut logger = VOLogger::new(f_filename, nf_filename);
-
-    let entry_fn_opt = rustc_public::entry_fn();
-    if entry_fn_opt.is_none() {
-        panic!("no entry function");
-    }
+    let mut logger = VOLogger::new(&options.stats_file);
+    set_limits(options.limits.clone());
 
-    let entry_fn = entry_fn_opt.unwrap();
-    let entry_instance = Instance::try_from(entry_fn).unwrap();
+    let entry_instances = entry_instances(&options.entry_funcs);
 
     // Collect trait metadata
     debug!("\n\nTRAIT PASS");
@@ -64,24 +80,36 @@ pub fn start_verifopt(
     let trait_collect = TraitCollectPass::new();
     trait_collect.run(&mut tstore);
 
-    // Collect function signatures for indirect calls
-    debug!("\n\nSIG PASS");
-    let mut sigstore = SigStore::new();
-    let sig_collect = SigCollectPass::new(&tstore);
-    sig_collect.run(&mut sigstore);
-
-    // Abstractly Interpret MIR
-    debug!("\n\nINTERP PASS");
-    let mut ctxt = Context::empty();
-    let interp = InterpPass::new(&sigstore, &tstore);
-    let _ = interp.run(&mut ctxt, entry_instance);
+    let sigstore = SigStore::new();
+    let interp = match options.verifopt_type {
+        VerifOptType::FlowSensitive => {
+            // Abstractly Interpret MIR (collecting the fns whose address is
+            // taken once a call through a `fn` pointer needs them)
+            debug!("\n\nINTERP PASS");
+            interp_fsa(&options, &entry_instances, &sigstore, &tstore)
+        }
+        style => {
+            debug!("\n\nBASELINE PASS ({})", style.label());
+            let interp = InterpPass::new(&sigstore, &tstore);
+            interp.run_baseline(style);
+            interp
+        }
+    };
+    // Still not settled: every result could have missed a cell's (or heap
+    // object's) contents.
+    let cells_settled = !interp.cells_stale() && !interp.heap_stale();
 
     let incomplete = &interp.incomplete.borrow();
     let confirmed: HashMap<Span, bool> = interp
         .dependencies
         .borrow()
         .iter()
-        .map(|(&s, ds)| (s, !ds.iter().any(|d| incomplete.contains(d))))
+        .map(|(&s, ds)| {
+            (
+                s,
+                cells_settled && !ds.iter().any(|d| incomplete.contains(d)),
+            )
+        })
         .collect();
 
     let cha = &interp.dispatch_cha.borrow();
@@ -123,7 +151,149 @@ pub fn start_verifopt(
     // attempt, so it isn't the cost that migration was about avoiding.
     let cha_std: HashMap<(DefId, usize), (Span, Vec<(DefId, Option<GenericArgs>)>)> =
         cha.iter().map(|(k, v)| (*k, v.clone())).collect();
-    let _ = logger.log_stats(&fsa, &cha_std);
+    let _ = logger.log_stats(&fsa, &cha_std, options.verifopt_type);
+    let _ = logger.log_trait_classes(&tstore);
+
+    // A binary has nothing downstream of it, so every trait is closed there
+    let open = if rustc_public::entry_fn().is_some() {
+        HashSet::new()
+    } else {
+        cha_std
+            .keys()
+            .filter(|(defid, bb)| {
+                !dispatched_trait(&interp, *defid, *bb).is_some_and(|t| tstore.is_closed(&t))
+            })
+            .copied()
+            .collect()
+    };
+
+    (fsa, cha_std, tags, open)
+}
+
+/// The trait of the dyn call that ends `bb` of `defid`'s body.
+fn dispatched_trait(interp: &InterpPass, defid: DefId, bb: usize) -> Option<DefId> {
+    let body = FnDef(defid).body()?;
+    let TerminatorKind::Call { func, .. } = &body.blocks.get(bb)?.terminator.kind else {
+        return None;
+    };
+    let TyKind::RigidTy(RigidTy::FnDef(method, _)) = func.ty(body.locals()).ok()?.kind() else {
+        return None;
+    };
+    Some(interp.get_trait_defid(&method.0))
+}
+
+/// FSA: interpret from each entry, rerunning until the cell and heap
+/// summaries settle (or `max_rounds`)
+fn interp_fsa<'a>(
+    options: &AnalysisOptions,
+    entry_instances: &[Instance],
+    sigstore: &'a SigStore,
+    tstore: &'a TraitStore,
+) -> InterpPass<'a> {
+    let stub_specs = match StubSpecs::load(&options.stub_files) {
+        Ok(specs) => specs,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // RefCell/Cell/Mutex/RwLock contents and heap objects are
+    // flow-insensitive summaries, so a read can miss a write interpreted
+    // after it: rerun, seeded with the previous round's summaries, until no
+    // summary grows after being read (see `interior` and `heap`).
+    let mut cells = HashMap::new();
+    let mut heap = HashMap::new();
+    let mut round = 1;
+    loop {
+        let mut ctxt = Context::empty();
+        let mut interp = InterpPass::new(sigstore, tstore);
+        interp.stub_specs = stub_specs.clone();
+        interp.analysis_filter = options.analysis_filter.clone();
+        interp.cells = cells.into();
+        interp.heap = heap.into();
+        for &entry_instance in entry_instances {
+            let _ = interp.run(&mut ctxt, entry_instance);
+        }
+
+        let stale = interp.cells_stale() || interp.heap_stale();
+        if !stale || round == limits().max_rounds {
+            break interp;
+        }
+        round += 1;
+        debug!(
+            "\n\nINTERP PASS: cell/heap summaries changed, round {}",
+            round
+        );
+        heap = interp
+            .heap
+            .take()
+            .into_iter()
+            .map(|(site, obj)| (site, HeapObject { read: false, ..obj }))
+            .collect();
+        cells = interp
+            .cells
+            .take()
+            .into_iter()
+            .map(|(ty, cell)| {
+                (
+                    ty,
+                    SharedCell {
+                        read: false,
+                        ..cell
+                    },
+                )
+            })
+            .collect();
+    }
+}
+
+/// The fns named by `--entry-func`/`entry` (a full path, or any suffix of
+/// one after a `::`), or without any, the crate's `main` - or for a library,
+/// which has none, every non-generic fn it exports. A `--test` build
+/// (a test or bench harness) always starts from the `main` the harness
+/// generates, which runs every test. verifopt.toml's `entry` applies to all
+/// of a package's targets, so a name one of them doesn't have is skipped.
+fn entry_instances(names: &[String]) -> Vec<Instance> {
+    let harness = rustc_middle::ty::tls::with(|tcx| tcx.sess.opts.test);
+
+    let items = rustc_public::all_local_items();
+    let named: Vec<Instance> = names
+        .iter()
+        .filter(|_| !harness)
+        .filter_map(|name| {
+            let suffix = format!("::{}", name);
+            let Some(item) = items
+                .iter()
+                .find(|item| item.name() == *name || item.name().ends_with(&suffix))
+            else {
+                eprintln!("verifopt: no entry function {:?} here, skipping it", name);
+                return None;
+            };
+            Instance::try_from(*item)
+                .inspect_err(|_| {
+                    eprintln!(
+                        "verifopt: entry function {:?} is generic, skipping it",
+                        name
+                    )
+                })
+                .ok()
+        })
+        .collect();
+    if !named.is_empty() {
+        return named;
+    }
 
-    (fsa, tags)
+    if let Some(entry_fn) = rustc_public::entry_fn() {
+        return vec![Instance::try_from(entry_fn).unwrap()];
+    }
+    let exported: Vec<Instance> = items
+        .iter()
+        .filter(|item| item.kind() == ItemKind::Fn && is_exported(item.0))
+        .filter_map(|item| Instance::try_from(*item).ok())
+        .collect();
+    if exported.is_empty() {
+        eprintln!("verifopt: no entry function or exported fn to start from");
+    }
+    exported
 }
@@ -1,6 +1,9 @@
 use rustc_public::DefId;
 use rustc_public::ty::{GenericArgs, Span};
 
+use crate::common::VerifOptType;
+use crate::trait_collect::{TraitClass, TraitStore};
+
 use std::collections::HashMap;
 use std::fs::{File, OpenOptions};
 use std::io::{Error, Write};
@@ -10,11 +13,11 @@ pub struct VOLogger {
 }
 
 impl VOLogger {
-    pub fn new<'a>(_f_filename: &'a str, _nf_filename: &'a str) -> VOLogger {
+    pub fn new(stats_filename: &str) -> VOLogger {
         let stats_file = OpenOptions::new()
             .append(true)
             .create(true)
-            .open("stats")
+            .open(stats_filename)
             .expect("should be able to open file");
 
         Self { stats_file }
@@ -24,7 +27,9 @@ impl VOLogger {
         &mut self,
         dispatch_targets: &HashMap<(DefId, usize), (Span, Vec<(DefId, Option<GenericArgs>)>)>,
         dispatch_cha: &HashMap<(DefId, usize), (Span, Vec<(DefId, Option<GenericArgs>)>)>,
+        style: VerifOptType,
     ) -> Result<(), Error> {
+        let label = style.label();
         let mut diff = Vec::new();
         let mut same = Vec::new();
 
@@ -49,10 +54,11 @@ impl VOLogger {
         for (span, cha, fsa) in &diff {
             write!(
                 &mut self.stats_file,
-                "Span: {:?}\nCHA ({}): {:?}\nFSA ({}): {:?}\n\n",
+                "Span: {:?}\nCHA ({}): {:?}\n{} ({}): {:?}\n\n",
                 span,
                 cha.len(),
                 cha,
+                label,
                 fsa.len(),
                 fsa
             )?;
@@ -62,10 +68,11 @@ impl VOLogger {
         for (span, cha, fsa) in &same {
             write!(
                 &mut self.stats_file,
-                "Span: {:?}\nCHA ({}): {:?}\nFSA ({}): {:?}\n",
+                "Span: {:?}\nCHA ({}): {:?}\n{} ({}): {:?}\n",
                 span,
                 cha.len(),
                 cha,
+                label,
                 fsa.len(),
                 fsa
             )?;
@@ -73,4 +80,46 @@ impl VOLogger {
 
         Ok(())
     }
+
+    /// Appends whether each of this crate's traits is closed to downstream
+    /// impls (see `trait_collect::TraitClass`), sorted by path. Like
+    /// `log_decisions`, no line starts with `Span:`.
+    pub fn log_trait_classes(&mut self, tstore: &TraitStore) -> Result<(), Error> {
+        let mut classes: Vec<(String, &TraitClass)> = tstore
+            .trait_classes
+            .iter()
+            .map(|(defid, class)| (defid.name(), class))
+            .collect();
+        classes.sort_by(|a, b| a.0.cmp(&b.0));
+
+        write!(&mut self.stats_file, "--TRAIT CLASSES--\n")?;
+        for (name, class) in classes {
+            write!(
+                &mut self.stats_file,
+                "Trait: {}\nClass: {}\n\n",
+                name, class
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Appends the cost model's per-call-site choice (see `cost::CostModel`)
+    /// after the CHA/FSA stats. Lines deliberately don't start with `Span:`,
+    /// which is what the test harness keys its stats parsing on.
+    pub fn log_decisions(
+        &mut self,
+        decisions: &[(String, crate::cost::Strategy, String)],
+    ) -> Result<(), Error> {
+        write!(&mut self.stats_file, "--DEVIRT DECISIONS--\n")?;
+        for (site, strategy, why) in decisions {
+            write!(
+                &mut self.stats_file,
+                "Site: {}\nDecision: {}\nWhy: {}\n\n",
+                site, strategy, why
+            )?;
+        }
+
+        Ok(())
+    }
 }
@@ -1,3 +1,4 @@
+use crate::config::limits;
 use crate::constraints::VOID;
 use crate::constraints::unique_append;
 use crate::constraints::{ConstraintStore, Constraints, EnclosingScopes, MapValue};
@@ -7,7 +8,8 @@ use rustc_public::mir::Place;
 
 use log::debug;
 
-const MERGE_WIDEN_THRESHOLD: usize = 50;
+/// Default `[limits] merge_widen_threshold`, see `config`
+pub const MERGE_WIDEN_THRESHOLD: usize = 50;
 
 fn merge_constraints(
     cur_constraints: &Constraints,
@@ -19,6 +21,7 @@ fn merge_constraints(
         timing.map(|(pass, scope)| pass.timing_span(TimingCat::TermMergeConstraintsAppend, scope));
     merged.append(new_constraints.clone());
     drop(append_guard);
+    let merged = crate::scalar::widen_scalars(cur_constraints, merged);
     debug!(
         "merge_constraints: merged_disjuncts={}",
         crate::constraints::constraints_size(&merged)
@@ -28,7 +31,7 @@ fn merge_constraints(
         merged.inner.len()
     );
     //debug!("MERGED CONSTRAINTS: {:?}", merged);
-    if merged.inner.len() > MERGE_WIDEN_THRESHOLD {
+    if merged.inner.len() > limits().merge_widen_threshold {
         debug!("merge_constraints: WIDENING");
         let widen_guard = timing
             .map(|(pass, scope)| pass.timing_span(TimingCat::TermMergeConstraintsWiden, scope));
@@ -0,0 +1,147 @@
+//! Observed dispatch counts, used by the rewriter to order devirtualized arms.
+//!
+//! A profile is a plain text file, one entry per line, in any of three forms
+//! (fields are tab-separated since impl labels contain spaces):
+//!
+//! ```text
+//! <impl>                       one hit, e.g. a line of votrace's `calls` file
+//! <count>\t<impl>              `count` hits, at whatever call site
+//! <count>\t<site>\t<impl>      `count` hits at one call site
+//! ```
+//!
+//! `<impl>` is the impl fn's path as `tcx.def_path_str` prints it, which is
+//! the same `<Cat as Animal>::speak` shape votrace's `#[trace]` records for
+//! impls at the crate root (it writes the impl header as spelled, so an impl
+//! in a module needs its path qualified to match). Only whitespace is
+//! ignored when comparing: two `Foo`s in different modules stay apart.
+//! `<site>` is `file:line:col` of the dyn call (see `site_key`); a site in the
+//! profile matches a call site whose key is the same, or ends in `/` and
+//! then the same, so relative paths work. Lines that are empty or start with
+//! `#` are skipped.
+//!
+//! Per-site counts win over site-agnostic ones when both exist.
+
+use rustc_public::ty::Span;
+
+use log::warn;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+/// Arms hit on at most this percent of a call site's profiled calls get a
+/// cold-path hint.
+const COLD_PERCENT: u64 = 1;
+
+#[derive(Debug, Default, Clone)]
+pub struct Profile {
+    sites: HashMap<(String, String), u64>,
+    impls: HashMap<String, u64>,
+}
+
+impl Profile {
+    pub fn from_file(path: &str) -> io::Result<Profile> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> Profile {
+        let mut profile = Profile::default();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').map(str::trim).collect();
+            match fields[..] {
+                [f] => *profile.impls.entry(normalize(f)).or_default() += 1,
+                [count, f] => match count.parse::<u64>() {
+                    Ok(n) => *profile.impls.entry(normalize(f)).or_default() += n,
+                    Err(_) => warn!("profile: bad count in {:?}, skipping", line),
+                },
+                [count, site, f] => match count.parse::<u64>() {
+                    Ok(n) => {
+                        *profile
+                            .sites
+                            .entry((site.to_string(), normalize(f)))
+                            .or_default() += n
+                    }
+                    Err(_) => warn!("profile: bad count in {:?}, skipping", line),
+                },
+                _ => warn!("profile: can't parse {:?}, skipping", line),
+            }
+        }
+
+        profile
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty() && self.impls.is_empty()
+    }
+
+    /// Hits of `impl_path` at the call site `site` (a `site_key`).
+    pub fn count(&self, site: &str, impl_path: &str) -> u64 {
+        let impl_path = normalize(impl_path);
+
+        let per_site = self
+            .sites
+            .iter()
+            .filter(|((s, f), _)| same_site(site, s) && *f == impl_path)
+            .map(|(_, n)| *n)
+            .max();
+
+        per_site.unwrap_or_else(|| self.impls.get(&impl_path).copied().unwrap_or(0))
+    }
+
+    /// Sorts `candidates` (impl paths, in the analysis' order) hottest first
+    /// and returns, per candidate in that new order, whether it's cold. Ties
+    /// keep their original relative order, so an empty profile is a no-op.
+    pub fn order<T>(&self, site: &str, candidates: Vec<(T, String)>) -> Vec<(T, bool)> {
+        let mut counted: Vec<(T, u64)> = candidates
+            .into_iter()
+            .map(|(t, path)| {
+                let n = self.count(site, &path);
+                (t, n)
+            })
+            .collect();
+        counted.sort_by(|a, b| b.1.cmp(&a.1));
+
+        let total: u64 = counted.iter().map(|(_, n)| n).sum();
+
+        counted
+            .into_iter()
+            .map(|(t, n)| (t, total > 0 && n * 100 <= total * COLD_PERCENT))
+            .collect()
+    }
+}
+
+/// `file:line:col` of a call site, the key profile sites are matched against.
+pub fn site_key(span: &Span) -> String {
+    let lines = span.get_lines();
+    format!(
+        "{}:{}:{}",
+        span.get_filename(),
+        lines.start_line,
+        lines.start_col
+    )
+}
+
+/// Whether the profile's `file:line:col` key `profiled` names the call site
+/// `site`: the whole key, with `profiled`'s file allowed to be a trailing
+/// part of `site`'s path (`src/main.rs:3:5` for `/abs/src/main.rs:3:5`).
+fn same_site(site: &str, profiled: &str) -> bool {
+    site == profiled
+        || site
+            .strip_suffix(profiled)
+            .is_some_and(|prefix| prefix.ends_with('/'))
+}
+
+/// Whitespace differs between `def_path_str` and `quote!`-stringified types
+/// (`< Cat as Animal >` vs `<Cat as Animal>`), so compare with it dropped.
+fn normalize(path: &str) -> String {
+    path.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+#[cfg(test)]
+mod profile_tests;
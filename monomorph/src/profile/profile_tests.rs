@@ -0,0 +1,85 @@
+use super::*;
+
+#[test]
+fn test_parse_forms() {
+    let profile = Profile::parse(
+        "# comment\n\
+         \n\
+         <Cat as Animal>::speak\n\
+         <Cat as Animal>::speak\n\
+         5\t<Dog as Animal>::speak\n\
+         7\tsrc/main.rs:10:5\t<Dog as Animal>::speak\n",
+    );
+    assert_eq!(
+        profile.count("src/main.rs:1:1", "<Cat as Animal>::speak"),
+        2
+    );
+    assert_eq!(
+        profile.count("src/main.rs:1:1", "<Dog as Animal>::speak"),
+        5
+    );
+    assert_eq!(
+        profile.count("src/main.rs:10:5", "<Dog as Animal>::speak"),
+        7
+    );
+}
+
+#[test]
+fn test_parse_skips_bad_lines() {
+    let profile = Profile::parse("x\t<Cat as Animal>::speak\na\tb\tc\td\n");
+    assert!(profile.is_empty());
+}
+
+#[test]
+fn test_whitespace_ignored() {
+    let profile = Profile::parse("3\t< Cat as Animal >::speak\n");
+    assert_eq!(
+        profile.count("src/main.rs:1:1", "<Cat as Animal>::speak"),
+        3
+    );
+}
+
+#[test]
+fn test_modules_kept_apart() {
+    let profile = Profile::parse("3\t<a::Foo as T>::f\n");
+    assert_eq!(profile.count("src/main.rs:1:1", "<a::Foo as T>::f"), 3);
+    assert_eq!(profile.count("src/main.rs:1:1", "<b::Foo as T>::f"), 0);
+    assert_eq!(profile.count("src/main.rs:1:1", "<Foo as T>::f"), 0);
+}
+
+#[test]
+fn test_site_matches_whole_key() {
+    let profile = Profile::parse("4\ta.rs:1:2\t<Cat as Animal>::speak\n");
+    assert_eq!(profile.count("a.rs:1:2", "<Cat as Animal>::speak"), 4);
+    assert_eq!(profile.count("/src/a.rs:1:2", "<Cat as Animal>::speak"), 4);
+    assert_eq!(profile.count("ba.rs:1:2", "<Cat as Animal>::speak"), 0);
+    assert_eq!(profile.count("a.rs:11:2", "<Cat as Animal>::speak"), 0);
+}
+
+#[test]
+fn test_order_hottest_first_and_cold() {
+    // Fish 97%, the other three 1% each, as in rewrites' skew4sf
+    let profile = Profile::parse("97\tFish\n1\tCat\n1\tDog\n1\tBird\n");
+    let candidates = ["Cat", "Dog", "Bird", "Fish"]
+        .into_iter()
+        .map(|n| (n, n.to_string()))
+        .collect();
+    assert_eq!(
+        profile.order("src/main.rs:1:1", candidates),
+        vec![
+            ("Fish", false),
+            ("Cat", true),
+            ("Dog", true),
+            ("Bird", true)
+        ]
+    );
+}
+
+#[test]
+fn test_order_empty_profile_is_noop() {
+    let candidates = vec![(1, "B".to_string()), (2, "A".to_string())];
+    assert_eq!(
+        Profile::default().order("src/main.rs:1:1", candidates),
+        vec![(1, false), (2, false)]
+    );
+}
@@ -9,9 +9,10 @@ extern crate rustc_span;
 use rustc_data_structures::smallvec::SmallVec;
 use rustc_index::IndexVec;
 use rustc_middle::mir::{
-    BasicBlock, BasicBlockData, BinOp, Body, CastKind, CoercionSource, Const, ConstOperand, Local,
-    LocalDecl, Mutability, Operand, Place, ProjectionElem, Rvalue, SourceInfo, Statement,
-    StatementKind, SwitchTargets, Terminator, TerminatorKind, UnOp,
+    BasicBlock, BasicBlockData, BinOp, Body, BorrowKind, CallSource, CastKind, CoercionSource,
+    Const, ConstOperand, ConstValue, Local, LocalDecl, MutBorrowKind, Mutability, Operand, Place,
+    ProjectionElem, Rvalue, SourceInfo, Statement, StatementKind, SwitchTargets, Terminator,
+    TerminatorKind, UnOp, UnwindAction,
 };
 use rustc_span::def_id::{DefPathHash, LocalDefId};
 
@@ -23,17 +24,27 @@ use rustc_middle::ty::adjustment::PointerCoercion;
 use rustc_middle::ty::{
     AssocKind, FnDef, GenericArg, Instance, List, Ty, TyCtxt, TyKind, TypingEnv, VtblEntry,
 };
-use rustc_public::{DefId, rustc_internal};
-use rustc_span::Span;
+use rustc_public::{CrateDef, DefId, rustc_internal};
+use rustc_span::source_map::Spanned;
+use rustc_span::{Span, Symbol};
 
+use std::fs::OpenOptions;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::Mutex;
 use std::sync::OnceLock;
 
+use crate::annotations;
+use crate::common::VerifOptType;
+use crate::cost::{CalleeFacts, SiteFacts, Strategy, in_loop};
+use crate::diff::rewritten_path;
 use crate::interp::TagPlan;
+use crate::logger::VOLogger;
+use crate::profile::{Profile, site_key};
 use crate::start_verifopt;
+use crate::trace::{SETS_FILE, UNKNOWN_IMPL, trace_line, write_sets};
 use crate::util::options::AnalysisOptions;
 
 #[derive(Default)]
@@ -48,6 +59,16 @@ pub struct Store {
             DefPathHash, /* impl fn */
         )>,
     >,
+    /// (fn, bb, impl fn) arms the profile says are rarely taken
+    pub cold: HashSet<(DefPathHash, usize, DefPathHash)>,
+    /// `core::intrinsics::cold_path`, if any arm is cold
+    pub cold_path: Option<DefPathHash>,
+    /// instrument dyn calls (see trace.rs) rather than devirtualize them
+    pub trace: bool,
+    /// CHA candidates per call site, only filled in for `trace`
+    pub cha: HashMap<(DefPathHash, usize), Vec<DefPathHash>>,
+    /// `diff::REWRITTEN_FILE`, next to the stats file
+    pub rewritten_file: PathBuf,
 }
 
 static STORE: OnceLock<Mutex<Store>> = OnceLock::new();
@@ -63,9 +84,21 @@ pub struct FsaCallbacks {
 impl Callbacks for FsaCallbacks {
     fn after_analysis<'tcx>(&mut self, _compiler: &Compiler, tcx: TyCtxt<'tcx>) -> Compilation {
         let _ = rustc_internal::run(tcx, || {
-            let (targets, tags) = start_verifopt(self.options.clone());
+            let (targets, cha, tags, open) = start_verifopt(self.options.clone());
+
+            // A failed `assert_targets!` fails the build: rustc stops once
+            // this returns, so nothing gets rewritten either. They state
+            // what FSA finds, so a baseline run doesn't check them.
+            if self.options.verifopt_type == VerifOptType::FlowSensitive {
+                let assertions = annotations::find_assertions(tcx);
+                annotations::check_assertions(tcx, &assertions, &targets);
+                if tcx.dcx().has_errors().is_some() {
+                    return;
+                }
+            }
 
             let mut store = store().lock().unwrap();
+            store.rewritten_file = rewritten_path(&self.options.stats_file);
 
             let to_hash = |did| -> Option<DefPathHash> {
                 std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
@@ -75,22 +108,156 @@ impl Callbacks for FsaCallbacks {
                 .ok()
             };
 
-            for ((defid, bb), (_, ts)) in targets {
+            let to_path = |did| -> String { tcx.def_path_str(rustc_internal::internal(tcx, did)) };
+
+            if self.options.trace_dispatch {
+                store.trace = true;
+
+                for ((defid, bb), (_, ts)) in &cha {
+                    let Some(hash) = to_hash(*defid) else {
+                        continue;
+                    };
+                    let t_hashes = ts.iter().filter_map(|(did, _)| to_hash(*did)).collect();
+                    store.cha.insert((hash, *bb), t_hashes);
+                }
+
+                // Keyed by source location, so instances of the same generic
+                // fn share (and union) one set, as they share one trace site.
+                let mut sets: BTreeMap<String, Vec<String>> = BTreeMap::new();
+                for (span, ts) in targets.values() {
+                    let set = sets.entry(site_key(span)).or_default();
+                    for (did, _) in ts {
+                        let path = to_path(*did);
+                        if !set.contains(&path) {
+                            set.push(path);
+                        }
+                    }
+                }
+                if let Err(e) = write_sets(SETS_FILE, &sets) {
+                    eprintln!("couldn't write {}: {}", SETS_FILE, e);
+                }
+
+                return;
+            }
+
+            let profile = match &self.options.profile {
+                Some(path) => Profile::from_file(path)
+                    .inspect_err(|e| eprintln!("can't read profile {:?} ({}), ignoring", path, e))
+                    .unwrap_or_default(),
+                None => Profile::default(),
+            };
+
+            // Hottest first. Candidates the profile never saw keep the
+            // analysis' order, so without a profile this changes nothing.
+            let order = |site: &str, dids: Vec<DefId>| -> Vec<(DefId, bool)> {
+                if profile.is_empty() {
+                    return dids.into_iter().map(|did| (did, false)).collect();
+                }
+                let candidates = dids.into_iter().map(|did| (did, to_path(did))).collect();
+                profile.order(site, candidates)
+            };
+
+            let sites: HashMap<(DefId, usize), String> = targets
+                .iter()
+                .map(|(key, (span, _))| (*key, site_key(span)))
+                .collect();
+
+            let tagged: HashSet<(DefId, usize)> = tags
+                .iter()
+                .filter(|(_, plan)| matches!(plan, TagPlan::Tagged(s) if !s.is_empty()))
+                .map(|(key, _)| *key)
+                .collect();
+
+            let mut strategies: HashMap<(DefId, usize), Strategy> = HashMap::default();
+            let mut report: Vec<(String, Strategy, String)> = Vec::new();
+
+            for ((defid, bb), (span, ts)) in targets {
                 let Some(hash) = to_hash(defid) else {
                     continue;
                 };
 
-                let t_hashes: Vec<DefPathHash> =
-                    ts.iter().filter_map(|(did, _)| to_hash(*did)).collect();
+                let site = &sites[&(defid, bb)];
+
+                if annotations::no_devirt(tcx, rustc_internal::internal(tcx, defid), bb) {
+                    report.push((
+                        site.clone(),
+                        Strategy::Leave,
+                        "opted out by #[verifopt::no_devirt]".to_string(),
+                    ));
+                    strategies.insert((defid, bb), Strategy::Leave);
+                    continue;
+                }
+
+                // Every strategy ends in an unconditional call to the last
+                // candidate, which a downstream impl would fall into
+                if open.contains(&(defid, bb)) {
+                    report.push((
+                        site.clone(),
+                        Strategy::Leave,
+                        "open trait in a library, see --TRAIT CLASSES--".to_string(),
+                    ));
+                    strategies.insert((defid, bb), Strategy::Leave);
+                    continue;
+                }
+
+                if !self.options.rewrite_filter.allows(&defid.name()) {
+                    report.push((
+                        site.clone(),
+                        Strategy::Leave,
+                        "excluded by [rewrite] in verifopt.toml".to_string(),
+                    ));
+                    strategies.insert((defid, bb), Strategy::Leave);
+                    continue;
+                }
+
+                let ordered = order(site, ts.iter().map(|(did, _)| *did).collect());
+
+                let facts = SiteFacts {
+                    callees: ordered
+                        .iter()
+                        .map(|(did, _)| {
+                            let hits = profile.count(site, &to_path(*did));
+                            CalleeFacts::of(tcx, rustc_internal::internal(tcx, *did), hits)
+                        })
+                        .collect(),
+                    tagged: tagged.contains(&(defid, bb)),
+                    in_loop: in_loop(
+                        tcx,
+                        rustc_internal::internal(tcx, defid),
+                        bb,
+                        rustc_internal::internal(tcx, span),
+                    ),
+                };
+                let (strategy, why) = self.options.cost_model.decide(&facts);
+                report.push((site.clone(), strategy, why));
+                strategies.insert((defid, bb), strategy);
+
+                if strategy == Strategy::Leave {
+                    continue;
+                }
+
+                let mut t_hashes: Vec<DefPathHash> = Vec::new();
+                for (did, cold) in ordered {
+                    let Some(t_hash) = to_hash(did) else {
+                        continue;
+                    };
+                    if cold {
+                        store.cold.insert((hash, bb, t_hash));
+                    }
+                    t_hashes.push(t_hash);
+                }
 
                 store.targets.insert((hash, bb), t_hashes);
             }
 
             for ((defid, bb), plan) in tags {
-                let TagPlan::Tagged(sites) = plan else {
+                if strategies.get(&(defid, bb)) != Some(&Strategy::TagSwitch) {
+                    continue;
+                }
+                let TagPlan::Tagged(plan_sites) = plan else {
                     continue;
                 };
-                if sites.is_empty() {
+                if plan_sites.is_empty() {
                     continue;
                 }
 
@@ -98,22 +265,42 @@ impl Callbacks for FsaCallbacks {
                     continue;
                 };
 
-                let mut next: u64 = 0;
-                let mut assigned: HashMap<DefId, u64> = HashMap::default();
+                // Tags handed out hottest impl first, so tag 0 is the arm most
+                // worth having up front.
+                let mut impls: Vec<DefId> = Vec::new();
+                for (.., did) in &plan_sites {
+                    if !impls.contains(did) {
+                        impls.push(*did);
+                    }
+                }
+                let site = sites.get(&(defid, bb)).cloned().unwrap_or_default();
+                let assigned: HashMap<DefId, u64> = order(&site, impls)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(tag, (did, _))| (did, tag as u64))
+                    .collect();
 
-                let entry: Vec<(usize, usize, u64, DefPathHash)> = sites
+                let entry: Vec<(usize, usize, u64, DefPathHash)> = plan_sites
                     .iter()
-                    .filter_map(|(bb, stmt, did)| {
-                        let tag = *assigned.entry(*did).or_insert_with(|| {
-                            next += 1;
-                            next - 1
-                        });
-                        to_hash(*did).map(|h| (*bb, *stmt, tag, h))
-                    })
+                    .filter_map(|(bb, stmt, did)| Some((*bb, *stmt, assigned[did], to_hash(*did)?)))
                     .collect();
 
                 store.tags.insert((hash, bb), entry);
             }
+
+            report.sort();
+            let mut logger = VOLogger::new(&self.options.stats_file);
+            if let Err(e) = logger.log_decisions(&report) {
+                eprintln!("couldn't write devirtualization decisions: {}", e);
+            }
+
+            if !store.cold.is_empty() {
+                store.cold_path = rustc_public::find_crates("core")
+                    .iter()
+                    .flat_map(|krate| krate.fn_defs())
+                    .find(|f| f.name() == "core::intrinsics::cold_path")
+                    .and_then(|f| to_hash(f.def_id()));
+            }
         });
 
         Compilation::Stop
@@ -158,6 +345,10 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
     let hash = tcx.def_path_hash(def_id.to_def_id());
     let store = store().lock().unwrap();
 
+    if store.trace {
+        return instrument_dispatch(tcx, default, hash, &store);
+    }
+
     let edits: Vec<(usize, Edit)> = default
         .basic_blocks
         .indices()
@@ -187,6 +378,8 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
         return default;
     }
 
+    record_rewrites(tcx, def_id, default, &edits, &store.rewritten_file);
+
     let mut body = default.clone();
 
     dump_body(tcx, &body, "before");
@@ -194,6 +387,13 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
     let local_decls = body.local_decls.clone();
     let mut bbs = body.basic_blocks_mut().to_owned();
 
+    // Arms the profile says are rarely taken start with a `cold_path()` call,
+    // so codegen lays them out of line.
+    let cold_path = store.cold_path;
+    let is_cold = |bb_idx: usize, impl_hash: DefPathHash| -> bool {
+        cold_path.is_some() && store.cold.contains(&(hash, bb_idx, impl_hash))
+    };
+
     for (bb_idx, edit) in edits {
         let bb = BasicBlock::from_usize(bb_idx);
 
@@ -260,134 +460,27 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
             }
 
             Edit::Pointers(hashes) => {
-                let op = args[0].node.clone();
-
-                let recv_ty = op.ty(&local_decls, tcx); // &dyn X
-                let pointee_ty = recv_ty.builtin_deref(true).unwrap(); // dyn X
-
-                // <dyn X as X>
-                let trait_ref = match pointee_ty.kind() {
-                    TyKind::Dynamic(preds, _) => {
-                        let principal = preds.principal().unwrap();
-                        principal.with_self_ty(tcx, pointee_ty).skip_binder()
-                    }
-                    _ => panic!(),
-                };
-
-                let pointee_trait = tcx.require_lang_item(rustc_hir::LangItem::PointeeTrait, span);
-                let metadata_assoc = tcx
-                    .associated_items(pointee_trait)
-                    .in_definition_order()
-                    .find(|it| matches!(it.kind, AssocKind::Type { .. }))
-                    .unwrap()
-                    .def_id;
-
-                // <dyn X as Pointee>::Metadata
-                let proj =
-                    Ty::new_projection(tcx, metadata_assoc, tcx.mk_args(&[pointee_ty.into()]));
-
-                let meta_ty = match tcx
-                    .try_normalize_erasing_regions(TypingEnv::fully_monomorphized(), proj)
-                {
-                    Ok(ty) => ty, // DynMetadata<dyn X>
-                    Err(_) => continue,
-                };
-                let raw_ptr_ty = Ty::new_ptr(tcx, tcx.types.unit, Mutability::Not); // *const ()
-
-                // DynMetadata<dyn X>
-                let meta_place = Place::from(body.local_decls.push(LocalDecl::new(meta_ty, span)));
-                bbs[bb].statements.push(Statement::new(
-                    source_info,
-                    StatementKind::Assign(Box::new((
-                        meta_place,
-                        Rvalue::UnaryOp(UnOp::PtrMetadata, op),
-                    ))),
-                ));
-
-                // raw *const ()
-                let vt_ptr_place =
-                    Place::from(body.local_decls.push(LocalDecl::new(raw_ptr_ty, span)));
-                bbs[bb].statements.push(Statement::new(
+                let Some((slot_fn_place, fn_ptr_ty, slot_stmts)) = vtable_slot(
+                    tcx,
+                    &mut body,
                     source_info,
-                    StatementKind::Assign(Box::new((
-                        vt_ptr_place,
-                        Rvalue::Cast(CastKind::Transmute, Operand::Move(meta_place), raw_ptr_ty),
-                    ))),
-                ));
-
-                let entries = tcx.vtable_entries(trait_ref);
-                let slot_idx = entries
-                    .iter()
-                    .position(|e| {
-                        matches!(
-                            e, VtblEntry::Method(inst) if inst.def_id() == defid
-                        )
-                    })
-                    .unwrap();
-
-                let VtblEntry::Method(vtable_instance) = &entries[slot_idx] else {
+                    args[0].node.clone(),
+                    &local_decls,
+                    defid,
+                    span,
+                ) else {
                     continue;
                 };
-
-                let fn_abi_ty = vtable_instance.ty(tcx, TypingEnv::fully_monomorphized());
-                let fn_sig = fn_abi_ty.fn_sig(tcx);
-                let fn_ptr_ty = Ty::new_fn_ptr(tcx, fn_sig);
-
-                let vt_typed_ty = Ty::new_ptr(tcx, fn_ptr_ty, Mutability::Not);
-
-                // *const (fn ptr)
-                let vt_slots_place =
-                    Place::from(body.local_decls.push(LocalDecl::new(vt_typed_ty, span)));
-                bbs[bb].statements.push(Statement::new(
-                    source_info,
-                    StatementKind::Assign(Box::new((
-                        vt_slots_place,
-                        Rvalue::Cast(CastKind::PtrToPtr, Operand::Copy(vt_ptr_place), vt_typed_ty),
-                    ))),
-                ));
-
-                let op = Box::new(ConstOperand {
-                    span: span,
-                    user_ty: None,
-                    const_: Const::from_usize(tcx, slot_idx.try_into().unwrap()),
-                });
-
-                // vtable as slots + slot idx
-                let slot_ptr_loc = body.local_decls.push(LocalDecl::new(vt_typed_ty, span));
-                let slot_ptr_place = Place::from(slot_ptr_loc);
-
-                bbs[bb].statements.push(Statement::new(
-                    source_info,
-                    StatementKind::Assign(Box::new((
-                        slot_ptr_place,
-                        Rvalue::BinaryOp(
-                            BinOp::Offset,
-                            Box::new((Operand::Copy(vt_slots_place), Operand::Constant(op))),
-                        ),
-                    ))),
-                ));
-
-                let deref_place = Place {
-                    local: slot_ptr_loc,
-                    projection: tcx.mk_place_elems(&[ProjectionElem::Deref]),
-                };
-
-                // loaded fn
-                let slot_fn_place =
-                    Place::from(body.local_decls.push(LocalDecl::new(fn_ptr_ty, span)));
-                bbs[bb].statements.push(Statement::new(
-                    source_info,
-                    StatementKind::Assign(Box::new((
-                        slot_fn_place,
-                        Rvalue::Use(Operand::Copy(deref_place)),
-                    ))),
-                ));
+                bbs[bb].statements.extend(slot_stmts);
 
                 let mut fallback = None;
                 let n = hashes.len();
 
-                for (i, &hash) in hashes.iter().enumerate() {
-                    let (fnc, self_ty) = fn_op(tcx, hash, gen_args, span).unwrap();
+                // `hashes` is hottest first: walk it coldest first so the
+                // coldest candidate becomes the unconditional fallback and the
+                // hottest ends up as the first comparison in `bb`.
+                for (i, &impl_hash) in hashes.iter().rev().enumerate() {
+                    let (fnc, self_ty) = fn_op(tcx, impl_hash, gen_args, span).unwrap();
 
                     let (recv, new_stmts) = narrow_dyn(
                         tcx,
@@ -416,44 +509,32 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
                         }),
                         false,
                     ));
+                    let call_bb = match cold_path {
+                        Some(cold_path) if is_cold(bb_idx, impl_hash) => cold_block(
+                            tcx,
+                            &mut body,
+                            &mut bbs,
+                            cold_path,
+                            call_bb,
+                            source_info,
+                            span,
+                        ),
+                        _ => call_bb,
+                    };
 
                     let Some(fallback_bb) = fallback else {
                         fallback = Some(call_bb);
                         continue;
                     };
 
-                    let cand_ptr_place =
-                        Place::from(body.local_decls.push(LocalDecl::new(fn_ptr_ty, span)));
-                    bbs[bb].statements.push(Statement::new(
-                        source_info,
-                        StatementKind::Assign(Box::new((
-                            cand_ptr_place,
-                            Rvalue::Cast(
-                                CastKind::PointerCoercion(
-                                    PointerCoercion::ReifyFnPointer(Safety::Unsafe),
-                                    CoercionSource::AsCast,
-                                ),
-                                fnc.clone(),
-                                fn_ptr_ty,
-                            ),
-                        ))),
-                    ));
-
-                    let eq_place =
-                        Place::from(body.local_decls.push(LocalDecl::new(tcx.types.bool, span)));
-
-                    let eq_stmt = Statement::new(
+                    let (eq_place, eq_stmts) = slot_is(
+                        tcx,
+                        &mut body,
                         source_info,
-                        StatementKind::Assign(Box::new((
-                            eq_place,
-                            Rvalue::BinaryOp(
-                                BinOp::Eq,
-                                Box::new((
-                                    Operand::Copy(slot_fn_place),
-                                    Operand::Copy(cand_ptr_place),
-                                )),
-                            ),
-                        ))),
+                        slot_fn_place,
+                        fnc,
+                        fn_ptr_ty,
+                        span,
                     );
 
                     let new_term = Terminator {
@@ -465,11 +546,11 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
                     };
 
                     if i == n - 1 {
-                        bbs[bb].statements.push(eq_stmt);
+                        bbs[bb].statements.extend(eq_stmts);
                         bbs[bb].terminator = Some(new_term);
                     } else {
                         fallback = Some(bbs.push(BasicBlockData::new_stmts(
-                            vec![eq_stmt],
+                            eq_stmts,
                             Some(new_term),
                             false,
                         )));
@@ -493,10 +574,27 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
                     continue;
                 }
 
+                // One arm per impl, tag 0 (the hottest, see `FsaCallbacks`) first.
+                let mut impls: Vec<(u64, DefPathHash)> = Vec::new();
+                for (_, _, tag, impl_hash) in &sites {
+                    if !impls.contains(&(*tag, *impl_hash)) {
+                        impls.push((*tag, *impl_hash));
+                    }
+                }
+                impls.sort_by_key(|(tag, _)| *tag);
+
                 let tag_local = body.local_decls.push(LocalDecl::new(tcx.types.usize, span));
 
-                for (bb_idx, stmt_idx, tag, _) in &sites {
-                    let cb = BasicBlock::from_usize(*bb_idx);
+                // Back to front, so inserting after one cast doesn't shift the
+                // statement index of a later cast in the same block.
+                let mut local_sites: Vec<(usize, usize, u64)> = sites
+                    .iter()
+                    .map(|(bb, stmt, tag, _)| (*bb, *stmt, *tag))
+                    .collect();
+                local_sites.sort_by_key(|(bb, stmt, _)| (*bb, *stmt));
+
+                for (site_bb, stmt_idx, tag) in local_sites.into_iter().rev() {
+                    let cb = BasicBlock::from_usize(site_bb);
 
                     bbs[cb].statements.insert(
                         stmt_idx + 1,
@@ -507,7 +605,7 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
                                 Rvalue::Use(Operand::Constant(Box::new(ConstOperand {
                                     span,
                                     user_ty: None,
-                                    const_: Const::from_usize(tcx, *tag),
+                                    const_: Const::from_usize(tcx, tag),
                                 }))),
                             ))),
                         ),
@@ -519,7 +617,7 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
 
                 let mut arms = Vec::new();
 
-                for (_, _, tag, impl_hash) in &sites {
+                for (tag, impl_hash) in &impls {
                     let (fnc, self_ty) = fn_op(tcx, *impl_hash, gen_args, span).unwrap();
                     let (recv, stmts) = narrow_dyn(
                         tcx,
@@ -549,6 +647,12 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
                         }),
                         false,
                     ));
+                    let cb = match cold_path {
+                        Some(cold_path) if is_cold(bb_idx, *impl_hash) => {
+                            cold_block(tcx, &mut body, &mut bbs, cold_path, cb, source_info, span)
+                        }
+                        _ => cb,
+                    };
                     arms.push((*tag as u128, cb));
                 }
 
@@ -570,6 +674,397 @@ fn optimized_mir<'tcx>(tcx: TyCtxt<'tcx>, def_id: LocalDefId) -> &'tcx Body<'tcx
     tcx.arena.alloc(body)
 }
 
+/// Appends a `<fn>\t<site>` line per edited call site to
+/// `path` (`diff::REWRITTEN_FILE`), for `verifopt-diff` to report against.
+fn record_rewrites<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    def_id: LocalDefId,
+    body: &Body<'tcx>,
+    edits: &[(usize, Edit)],
+    path: &Path,
+) {
+    let Ok(mut f) = OpenOptions::new().create(true).append(true).open(path) else {
+        return;
+    };
+
+    let fn_path = tcx.def_path_str(def_id.to_def_id());
+    for (bb, _) in edits {
+        let span = body.basic_blocks[BasicBlock::from_usize(*bb)]
+            .terminator()
+            .source_info
+            .span;
+        let _ = writeln!(f, "{}\t{}", fn_path, site_key_of(tcx, span));
+    }
+}
+
+/// `--trace-dispatch`: in front of every dyn call, find which CHA candidate
+/// the receiver's vtable belongs to and write a `trace::trace_line` for it to
+/// stderr, then make the original call. Nothing is devirtualized.
+fn instrument_dispatch<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    default: &'tcx Body<'tcx>,
+    hash: DefPathHash,
+    store: &Store,
+) -> &'tcx Body<'tcx> {
+    let dyn_calls: Vec<usize> = default
+        .basic_blocks
+        .iter_enumerated()
+        .filter_map(|(bb, data)| {
+            let TerminatorKind::Call { func, args, .. } = &data.terminator().kind else {
+                return None;
+            };
+            let Operand::Constant(c) = func else {
+                return None;
+            };
+            let FnDef(defid, gen_args) = c.const_.ty().kind() else {
+                return None;
+            };
+            tcx.trait_of_assoc(*defid)?;
+            let recv_ty = args.first()?.node.ty(&default.local_decls, tcx);
+            let pointee_ty = recv_ty.builtin_deref(true)?;
+            (matches!(pointee_ty.kind(), TyKind::Dynamic(..))
+                && gen_args.types().next() == Some(pointee_ty))
+            .then_some(bb.as_usize())
+        })
+        .collect();
+
+    if dyn_calls.is_empty() {
+        return default;
+    }
+
+    let mut body = default.clone();
+    let local_decls = body.local_decls.clone();
+    let mut bbs = body.basic_blocks_mut().to_owned();
+
+    for bb_idx in dyn_calls {
+        let bb = BasicBlock::from_usize(bb_idx);
+
+        let term = bbs[bb].terminator().clone();
+        let TerminatorKind::Call {
+            func, args, unwind, ..
+        } = &term.kind
+        else {
+            continue;
+        };
+        let Operand::Constant(c) = func else {
+            continue;
+        };
+        let FnDef(method, gen_args) = c.const_.ty().kind() else {
+            continue;
+        };
+        let source_info = term.source_info;
+        let span = source_info.span;
+        let site = site_key_of(tcx, span);
+
+        let recv = args[0].node.clone();
+        let Some(pointee_ty) = recv.ty(&local_decls, tcx).builtin_deref(true) else {
+            continue;
+        };
+
+        let Some((recv_vt, recv_vt_stmts)) =
+            vtable_ptr(tcx, &mut body, source_info, recv, pointee_ty, span)
+        else {
+            continue;
+        };
+
+        // the original dyn call, now reached from whichever trace arm ran
+        let call_bb = bbs.push(BasicBlockData::new_stmts(vec![], Some(term.clone()), false));
+
+        let Some(unknown_bb) = trace_block(
+            tcx,
+            &mut body,
+            &mut bbs,
+            &trace_line(&site, UNKNOWN_IMPL),
+            call_bb,
+            *unwind,
+            source_info,
+            span,
+        ) else {
+            continue;
+        };
+
+        // (trace block, impl fn, self type) per CHA candidate
+        let mut candidates = Vec::new();
+        for impl_hash in store.cha.get(&(hash, bb_idx)).into_iter().flatten() {
+            let Ok((fnc, self_ty)) = fn_op(tcx, *impl_hash, gen_args, span) else {
+                continue;
+            };
+            let impl_path = tcx.def_path_str(tcx.def_path_hash_to_def_id(*impl_hash).unwrap());
+            let Some(log_bb) = trace_block(
+                tcx,
+                &mut body,
+                &mut bbs,
+                &trace_line(&site, &impl_path),
+                call_bb,
+                *unwind,
+                source_info,
+                span,
+            ) else {
+                continue;
+            };
+            candidates.push((log_bb, fnc, self_ty));
+        }
+
+        // A receiver whose vtable is none of the candidates' - the same
+        // type's vtable emitted again in another codegen unit, say - is
+        // still told apart by the fn in its method slot, so only a call to
+        // a fn no candidate has is logged as `UNKNOWN_IMPL`.
+        let mut fallback = unknown_bb;
+        if let Some((slot_fn, fn_ptr_ty, slot_stmts)) = vtable_slot(
+            tcx,
+            &mut body,
+            source_info,
+            args[0].node.clone(),
+            &local_decls,
+            *method,
+            span,
+        ) {
+            for (log_bb, fnc, _) in candidates.iter().rev() {
+                let (eq_place, stmts) = slot_is(
+                    tcx,
+                    &mut body,
+                    source_info,
+                    slot_fn,
+                    fnc.clone(),
+                    fn_ptr_ty,
+                    span,
+                );
+                fallback = bbs.push(BasicBlockData::new_stmts(
+                    stmts,
+                    Some(Terminator {
+                        source_info,
+                        kind: TerminatorKind::SwitchInt {
+                            discr: Operand::Copy(eq_place),
+                            targets: SwitchTargets::static_if(1, *log_bb, fallback),
+                        },
+                    }),
+                    false,
+                ));
+            }
+            fallback = bbs.push(BasicBlockData::new_stmts(
+                slot_stmts,
+                Some(Terminator {
+                    source_info,
+                    kind: TerminatorKind::Goto { target: fallback },
+                }),
+                false,
+            ));
+        }
+
+        let mut arms = Vec::new();
+        for (log_bb, _, self_ty) in candidates {
+            let Some((cand_vt, stmts)) =
+                concrete_vtable_ptr(tcx, &mut body, source_info, self_ty, pointee_ty, span)
+            else {
+                continue;
+            };
+            arms.push((log_bb, cand_vt, stmts));
+        }
+
+        bbs[bb].statements.extend(recv_vt_stmts);
+
+        let n = arms.len();
+        if n == 0 {
+            bbs[bb].terminator = Some(Terminator {
+                source_info,
+                kind: TerminatorKind::Goto { target: fallback },
+            });
+            continue;
+        }
+
+        for (i, (log_bb, cand_vt, mut stmts)) in arms.into_iter().rev().enumerate() {
+            let eq_place = Place::from(body.local_decls.push(LocalDecl::new(tcx.types.bool, span)));
+            stmts.push(Statement::new(
+                source_info,
+                StatementKind::Assign(Box::new((
+                    eq_place,
+                    Rvalue::BinaryOp(
+                        BinOp::Eq,
+                        Box::new((Operand::Copy(recv_vt), Operand::Move(cand_vt))),
+                    ),
+                ))),
+            ));
+
+            let new_term = Terminator {
+                source_info,
+                kind: TerminatorKind::SwitchInt {
+                    discr: Operand::Copy(eq_place),
+                    targets: SwitchTargets::static_if(1, log_bb, fallback),
+                },
+            };
+
+            if i == n - 1 {
+                bbs[bb].statements.extend(stmts);
+                bbs[bb].terminator = Some(new_term);
+            } else {
+                fallback = bbs.push(BasicBlockData::new_stmts(stmts, Some(new_term), false));
+            }
+        }
+    }
+
+    *body.basic_blocks_mut() = bbs;
+
+    tcx.arena.alloc(body)
+}
+
+/// Same `file:line:col` as `profile::site_key` computes from the stable MIR
+/// span during analysis, so trace lines match `dispatch_sets` entries.
+fn site_key_of<'tcx>(tcx: TyCtxt<'tcx>, span: Span) -> String {
+    let sm = tcx.sess.source_map();
+    let (_, line, col, _, _) = sm.span_to_location_info(span);
+    let file = sm.filename_for_diagnostics(&sm.span_to_filename(span));
+    format!("{}:{}:{}", file, line, col)
+}
+
+/// Blocks writing `line` to stderr, via `std::io::stderr()` and
+/// `<Stderr as Write>::write_all`, then jumping to `target`. The write's
+/// `io::Result` is `unwrap`ped: a trace line that silently failed to get
+/// written would hide exactly the dispatch `verifopt-check` is looking for.
+fn trace_block<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &mut Body<'tcx>,
+    bbs: &mut IndexVec<BasicBlock, BasicBlockData<'tcx>>,
+    line: &str,
+    target: BasicBlock,
+    unwind: UnwindAction,
+    si: SourceInfo,
+    span: Span,
+) -> Option<BasicBlock> {
+    let stderr_did = tcx.get_diagnostic_item(Symbol::intern("io_stderr"))?;
+    let write_did = tcx.get_diagnostic_item(Symbol::intern("IoWrite"))?;
+    let write_all_did = tcx
+        .associated_items(write_did)
+        .in_definition_order()
+        .find(|it| it.name().as_str() == "write_all")?
+        .def_id;
+
+    let stderr_ty = tcx
+        .fn_sig(stderr_did)
+        .instantiate_identity()
+        .skip_binder()
+        .output();
+    let write_all_args = tcx.mk_args(&[stderr_ty.into()]);
+    let res_ty = tcx.instantiate_bound_regions_with_erased(
+        tcx.fn_sig(write_all_did)
+            .instantiate(tcx, write_all_args)
+            .output(),
+    );
+
+    let TyKind::Adt(result_def, result_args) = res_ty.kind() else {
+        return None;
+    };
+    let unwrap_did = tcx
+        .inherent_impls(result_def.did())
+        .iter()
+        .flat_map(|imp| tcx.associated_items(*imp).in_definition_order())
+        .find(|it| it.name().as_str() == "unwrap")?
+        .def_id;
+
+    let fn_const = |ty: Ty<'tcx>| {
+        Operand::Constant(Box::new(ConstOperand {
+            span,
+            user_ty: None,
+            const_: Const::zero_sized(ty),
+        }))
+    };
+
+    let handle = Place::from(body.local_decls.push(LocalDecl::new(stderr_ty, span)));
+    let handle_ref_ty = Ty::new_mut_ref(tcx, tcx.lifetimes.re_erased, stderr_ty);
+    let handle_ref = Place::from(body.local_decls.push(LocalDecl::new(handle_ref_ty, span)));
+    let res = Place::from(body.local_decls.push(LocalDecl::new(res_ty, span)));
+    let unit = Place::from(body.local_decls.push(LocalDecl::new(tcx.types.unit, span)));
+
+    let bytes_ty = Ty::new_imm_ref(
+        tcx,
+        tcx.lifetimes.re_static,
+        Ty::new_slice(tcx, tcx.types.u8),
+    );
+    let alloc_id = tcx.allocate_bytes_dedup(line.as_bytes(), 0);
+    let bytes = Operand::Constant(Box::new(ConstOperand {
+        span,
+        user_ty: None,
+        const_: Const::Val(
+            ConstValue::Slice {
+                alloc_id,
+                meta: line.len() as u64,
+            },
+            bytes_ty,
+        ),
+    }));
+
+    let unwrap_bb = bbs.push(BasicBlockData::new_stmts(
+        vec![],
+        Some(Terminator {
+            source_info: si,
+            kind: TerminatorKind::Call {
+                func: fn_const(Ty::new_fn_def(tcx, unwrap_did, result_args)),
+                args: Box::new([Spanned {
+                    node: Operand::Move(res),
+                    span,
+                }]),
+                destination: unit,
+                target: Some(target),
+                unwind,
+                call_source: CallSource::Normal,
+                fn_span: span,
+            },
+        }),
+        false,
+    ));
+
+    let write_bb = bbs.push(BasicBlockData::new_stmts(
+        vec![Statement::new(
+            si,
+            StatementKind::Assign(Box::new((
+                handle_ref,
+                Rvalue::Ref(
+                    tcx.lifetimes.re_erased,
+                    BorrowKind::Mut {
+                        kind: MutBorrowKind::Default,
+                    },
+                    handle,
+                ),
+            ))),
+        )],
+        Some(Terminator {
+            source_info: si,
+            kind: TerminatorKind::Call {
+                func: fn_const(Ty::new_fn_def(tcx, write_all_did, write_all_args)),
+                args: Box::new([
+                    Spanned {
+                        node: Operand::Move(handle_ref),
+                        span,
+                    },
+                    Spanned { node: bytes, span },
+                ]),
+                destination: res,
+                target: Some(unwrap_bb),
+                unwind,
+                call_source: CallSource::Normal,
+                fn_span: span,
+            },
+        }),
+        false,
+    ));
+
+    Some(bbs.push(BasicBlockData::new_stmts(
+        vec![],
+        Some(Terminator {
+            source_info: si,
+            kind: TerminatorKind::Call {
+                func: fn_const(Ty::new_fn_def(tcx, stderr_did, tcx.mk_args(&[]))),
+                args: Box::new([]),
+                destination: handle,
+                target: Some(write_bb),
+                unwind,
+                call_source: CallSource::Normal,
+                fn_span: span,
+            },
+        }),
+        false,
+    )))
+}
+
 fn fn_op<'tcx>(
     tcx: TyCtxt<'tcx>,
     hash: DefPathHash,
@@ -642,17 +1137,300 @@ fn narrow_dyn<'tcx>(
         si,
         StatementKind::Assign(Box::new((
             out,
-            Rvalue::Ref(
-                tcx.lifetimes.re_erased,
-                rustc_middle::mir::BorrowKind::Shared,
-                deref,
-            ),
+            Rvalue::Ref(tcx.lifetimes.re_erased, BorrowKind::Shared, deref),
         ))),
     ));
 
     (out, stmts)
 }
 
+/// A block that calls `core::intrinsics::cold_path()` and then jumps to
+/// `target`, marking everything reachable only through it as unlikely.
+fn cold_block<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &mut Body<'tcx>,
+    bbs: &mut IndexVec<BasicBlock, BasicBlockData<'tcx>>,
+    cold_path: DefPathHash,
+    target: BasicBlock,
+    si: SourceInfo,
+    span: Span,
+) -> BasicBlock {
+    let cold_did = tcx.def_path_hash_to_def_id(cold_path).unwrap();
+    let func = Operand::Constant(Box::new(ConstOperand {
+        span,
+        user_ty: None,
+        const_: Const::zero_sized(tcx.type_of(cold_did).instantiate_identity()),
+    }));
+
+    let unit = Place::from(body.local_decls.push(LocalDecl::new(tcx.types.unit, span)));
+
+    bbs.push(BasicBlockData::new_stmts(
+        vec![],
+        Some(Terminator {
+            source_info: si,
+            kind: TerminatorKind::Call {
+                func,
+                args: Box::new([]),
+                destination: unit,
+                target: Some(target),
+                unwind: UnwindAction::Unreachable,
+                call_source: CallSource::Normal,
+                fn_span: span,
+            },
+        }),
+        false,
+    ))
+}
+
+/// `<dyn X as Pointee>::Metadata`, i.e. `DynMetadata<dyn X>`.
+fn dyn_metadata_ty<'tcx>(tcx: TyCtxt<'tcx>, pointee_ty: Ty<'tcx>, span: Span) -> Option<Ty<'tcx>> {
+    let pointee_trait = tcx.require_lang_item(rustc_hir::LangItem::PointeeTrait, span);
+    let metadata_assoc = tcx
+        .associated_items(pointee_trait)
+        .in_definition_order()
+        .find(|it| matches!(it.kind, AssocKind::Type { .. }))
+        .unwrap()
+        .def_id;
+
+    let proj = Ty::new_projection(tcx, metadata_assoc, tcx.mk_args(&[pointee_ty.into()]));
+
+    tcx.try_normalize_erasing_regions(TypingEnv::fully_monomorphized(), proj)
+        .ok()
+}
+
+/// Vtable pointer of the fat pointer `ptr` (to `pointee_ty`, a `dyn X`), as
+/// a plain `*const ()` so it can be compared with `BinOp::Eq`.
+fn vtable_ptr<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &mut Body<'tcx>,
+    si: SourceInfo,
+    ptr: Operand<'tcx>,
+    pointee_ty: Ty<'tcx>,
+    span: Span,
+) -> Option<(Place<'tcx>, Vec<Statement<'tcx>>)> {
+    let meta_ty = dyn_metadata_ty(tcx, pointee_ty, span)?;
+    let raw_ptr_ty = Ty::new_ptr(tcx, tcx.types.unit, Mutability::Not);
+
+    // the receiver is still needed by the call itself
+    let ptr = match ptr {
+        Operand::Move(p) => Operand::Copy(p),
+        o => o,
+    };
+
+    let mut stmts = Vec::new();
+
+    let meta = Place::from(body.local_decls.push(LocalDecl::new(meta_ty, span)));
+    stmts.push(Statement::new(
+        si,
+        StatementKind::Assign(Box::new((meta, Rvalue::UnaryOp(UnOp::PtrMetadata, ptr)))),
+    ));
+
+    let vt = Place::from(body.local_decls.push(LocalDecl::new(raw_ptr_ty, span)));
+    stmts.push(Statement::new(
+        si,
+        StatementKind::Assign(Box::new((
+            vt,
+            Rvalue::Cast(CastKind::Transmute, Operand::Move(meta), raw_ptr_ty),
+        ))),
+    ));
+
+    Some((vt, stmts))
+}
+
+/// The fn pointer in the receiver `recv`'s vtable slot for the trait method
+/// `method`, and that fn pointer's type. None if `recv` isn't a pointer to a
+/// `dyn X`, or the method has no slot in its vtable.
+fn vtable_slot<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &mut Body<'tcx>,
+    si: SourceInfo,
+    recv: Operand<'tcx>,
+    local_decls: &IndexVec<Local, LocalDecl<'tcx>>,
+    method: rustc_span::def_id::DefId,
+    span: Span,
+) -> Option<(Place<'tcx>, Ty<'tcx>, Vec<Statement<'tcx>>)> {
+    let pointee_ty = recv.ty(local_decls, tcx).builtin_deref(true)?; // dyn X
+
+    // <dyn X as X>
+    let TyKind::Dynamic(preds, _) = pointee_ty.kind() else {
+        return None;
+    };
+    let trait_ref = preds
+        .principal()?
+        .with_self_ty(tcx, pointee_ty)
+        .skip_binder();
+
+    // A `dyn Sub`'s vtable lays out its supertraits' methods too, with
+    // `TraitVPtr` entries for upcasting in between, so a supertrait method
+    // called on one is found here at its index in that flattened layout. A
+    // method with no slot (`where Self: Sized`, say) keeps the vtable call.
+    let entries = tcx.vtable_entries(trait_ref);
+    let slot_idx = entries
+        .iter()
+        .position(|e| matches!(e, VtblEntry::Method(inst) if inst.def_id() == method))?;
+    let VtblEntry::Method(vtable_instance) = &entries[slot_idx] else {
+        return None;
+    };
+
+    let (vt_ptr_place, mut stmts) = vtable_ptr(tcx, body, si, recv, pointee_ty, span)?;
+
+    let fn_abi_ty = vtable_instance.ty(tcx, TypingEnv::fully_monomorphized());
+    let fn_sig = fn_abi_ty.fn_sig(tcx);
+    let fn_ptr_ty = Ty::new_fn_ptr(tcx, fn_sig);
+
+    let vt_typed_ty = Ty::new_ptr(tcx, fn_ptr_ty, Mutability::Not);
+
+    // *const (fn ptr)
+    let vt_slots_place = Place::from(body.local_decls.push(LocalDecl::new(vt_typed_ty, span)));
+    stmts.push(Statement::new(
+        si,
+        StatementKind::Assign(Box::new((
+            vt_slots_place,
+            Rvalue::Cast(CastKind::PtrToPtr, Operand::Copy(vt_ptr_place), vt_typed_ty),
+        ))),
+    ));
+
+    let op = Box::new(ConstOperand {
+        span: span,
+        user_ty: None,
+        const_: Const::from_usize(tcx, slot_idx.try_into().unwrap()),
+    });
+
+    // vtable as slots + slot idx
+    let slot_ptr_loc = body.local_decls.push(LocalDecl::new(vt_typed_ty, span));
+    stmts.push(Statement::new(
+        si,
+        StatementKind::Assign(Box::new((
+            Place::from(slot_ptr_loc),
+            Rvalue::BinaryOp(
+                BinOp::Offset,
+                Box::new((Operand::Copy(vt_slots_place), Operand::Constant(op))),
+            ),
+        ))),
+    ));
+
+    let deref_place = Place {
+        local: slot_ptr_loc,
+        projection: tcx.mk_place_elems(&[ProjectionElem::Deref]),
+    };
+
+    // loaded fn
+    let slot_fn_place = Place::from(body.local_decls.push(LocalDecl::new(fn_ptr_ty, span)));
+    stmts.push(Statement::new(
+        si,
+        StatementKind::Assign(Box::new((
+            slot_fn_place,
+            Rvalue::Use(Operand::Copy(deref_place)),
+        ))),
+    ));
+
+    Some((slot_fn_place, fn_ptr_ty, stmts))
+}
+
+/// `fnc` (an impl fn's `FnDef` constant) reified to `fn_ptr_ty` and compared
+/// against the loaded vtable slot `slot_fn`: the `bool` place, and the
+/// statements computing it.
+fn slot_is<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &mut Body<'tcx>,
+    si: SourceInfo,
+    slot_fn: Place<'tcx>,
+    fnc: Operand<'tcx>,
+    fn_ptr_ty: Ty<'tcx>,
+    span: Span,
+) -> (Place<'tcx>, Vec<Statement<'tcx>>) {
+    let cand_ptr_place = Place::from(body.local_decls.push(LocalDecl::new(fn_ptr_ty, span)));
+    let eq_place = Place::from(body.local_decls.push(LocalDecl::new(tcx.types.bool, span)));
+
+    let stmts = vec![
+        Statement::new(
+            si,
+            StatementKind::Assign(Box::new((
+                cand_ptr_place,
+                Rvalue::Cast(
+                    CastKind::PointerCoercion(
+                        PointerCoercion::ReifyFnPointer(Safety::Unsafe),
+                        CoercionSource::AsCast,
+                    ),
+                    fnc,
+                    fn_ptr_ty,
+                ),
+            ))),
+        ),
+        Statement::new(
+            si,
+            StatementKind::Assign(Box::new((
+                eq_place,
+                Rvalue::BinaryOp(
+                    BinOp::Eq,
+                    Box::new((Operand::Copy(slot_fn), Operand::Copy(cand_ptr_place))),
+                ),
+            ))),
+        ),
+    ];
+
+    (eq_place, stmts)
+}
+
+/// Vtable pointer for `self_ty` as a `dyn X` (`pointee_ty`), without needing
+/// a value of that type: unsize a null `*const self_ty` and read its metadata.
+fn concrete_vtable_ptr<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    body: &mut Body<'tcx>,
+    si: SourceInfo,
+    self_ty: Ty<'tcx>,
+    pointee_ty: Ty<'tcx>,
+    span: Span,
+) -> Option<(Place<'tcx>, Vec<Statement<'tcx>>)> {
+    if !self_ty.is_sized(tcx, TypingEnv::fully_monomorphized()) {
+        return None;
+    }
+
+    let thin_ty = Ty::new_ptr(tcx, self_ty, Mutability::Not);
+    let fat_ty = Ty::new_ptr(tcx, pointee_ty, Mutability::Not);
+
+    let mut stmts = Vec::new();
+
+    let thin = Place::from(body.local_decls.push(LocalDecl::new(thin_ty, span)));
+    stmts.push(Statement::new(
+        si,
+        StatementKind::Assign(Box::new((
+            thin,
+            Rvalue::Cast(
+                CastKind::PointerWithExposedProvenance,
+                Operand::Constant(Box::new(ConstOperand {
+                    span,
+                    user_ty: None,
+                    const_: Const::from_usize(tcx, 0),
+                })),
+                thin_ty,
+            ),
+        ))),
+    ));
+
+    let fat = Place::from(body.local_decls.push(LocalDecl::new(fat_ty, span)));
+    stmts.push(Statement::new(
+        si,
+        StatementKind::Assign(Box::new((
+            fat,
+            Rvalue::Cast(
+                CastKind::PointerCoercion(PointerCoercion::Unsize, CoercionSource::Implicit),
+                Operand::Move(thin),
+                fat_ty,
+            ),
+        ))),
+    ));
+
+    let (vt, vt_stmts) = vtable_ptr(tcx, body, si, Operand::Move(fat), pointee_ty, span)?;
+    stmts.extend(vt_stmts);
+
+    Some((vt, stmts))
+}
+
+/// Every unsizing cast in this body that defines `local` at the end of
+/// `bb_idx`, walking back through plain copies/moves. None if some
+/// definition is anything else - a fn argument, a call's return, a load
+/// through a projection - since the tag local can't follow it there.
 fn find_casts<'tcx>(
     bbs: &IndexVec<BasicBlock, BasicBlockData<'tcx>>,
     preds: &IndexVec<BasicBlock, SmallVec<[BasicBlock; 4]>>,
@@ -693,6 +1471,11 @@ fn find_casts<'tcx>(
 
     let mut out = HashSet::new();
     for p in ps {
+        if let TerminatorKind::Call { destination, .. } = &bbs[*p].terminator().kind
+            && destination.local == local
+        {
+            return None;
+        }
         out.extend(find_casts(bbs, preds, p.index(), local, seen)?);
     }
 
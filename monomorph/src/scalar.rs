@@ -0,0 +1,446 @@
+//! The integer domain behind `RunningConstraint::Scalar`/`Interval`.
+//!
+//! An integer (or `bool`, or `char`) value is a set of disjuncts: each
+//! `Scalar(Some(n))` is one value it might be, `Interval(lo, hi)` every
+//! value in `lo..=hi`, and `Scalar(None)` any value at all. `ScalarVal` is
+//! the same thing with the disjuncts folded together, so the transfer
+//! functions in `convert_binop`/`convert_unop` can compute on it:
+//!
+//! - while both operands are small sets, pointwise - `n % 3` over
+//!   `{4, 5, 6}` is `{1, 2, 0}`, exactly.
+//! - otherwise on the operands' hulls, with the usual interval rules. `%`
+//!   and `&` by a non-negative bound, and the comparisons, give a bounded
+//!   result even when the other side is unknown, which is what matters for
+//!   a selector: `match rand() % 3 { .. }` still only takes three arms.
+//!
+//! A set of more than `MAX_SCALAR_SET` values is kept as its hull, and a
+//! range of at most that many values as a set, so `set_bytemap` sees
+//! singleton disjuncts whenever there are few enough to list. A result that
+//! can leave the destination type's range (wrapping arithmetic, a `!` on an
+//! unsigned) is unknown rather than wrong, and the checked ops' overflow
+//! flag is decided from the same test.
+//!
+//! Loop counters would otherwise grow one value per iteration until
+//! `MERGE_WIDEN_THRESHOLD`: `widen_scalars` moves a bound that grew across a
+//! merge straight to the end of the domain instead, so `0..n` settles on
+//! `[0, i128::MAX]` after a couple of iterations.
+
+use std::collections::BTreeSet;
+
+use rustc_public::mir::{BinOp, UnOp};
+use rustc_public::ty::{IntTy, RigidTy, Ty, TyKind, UintTy};
+
+use crate::config::limits;
+use crate::constraints::{
+    Constraint, Constraints, RunningConstraint, TraitObjConstraint, TraitObjTy,
+};
+
+/// Most distinct values a scalar is tracked as before it becomes its hull,
+/// by default (`[limits] max_scalar_set`, see `config`).
+pub const MAX_SCALAR_SET: usize = 8;
+
+/// Most operand pairs a binop is computed pointwise over.
+const MAX_SCALAR_PRODUCT: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScalarVal {
+    Set(BTreeSet<i128>),
+    /// Inclusive; never `[i128::MIN, i128::MAX]` (that is `None`).
+    Range(i128, i128),
+}
+
+impl ScalarVal {
+    /// Folds `cs`'s disjuncts together; None if any of them could be any
+    /// value, or isn't a scalar at all.
+    pub fn of(cs: &Constraints) -> Option<ScalarVal> {
+        let mut set = BTreeSet::new();
+        let mut hull: Option<(i128, i128)> = None;
+        for c in cs.inner.iter() {
+            match c.cfc {
+                Some(RunningConstraint::Scalar(Some(n))) => {
+                    set.insert(n);
+                }
+                Some(RunningConstraint::Interval(lo, hi)) => {
+                    hull = Some(hull.map_or((lo, hi), |(l, h)| (l.min(lo), h.max(hi))));
+                }
+                _ => return None,
+            }
+        }
+        match hull {
+            Some((lo, hi)) => {
+                let lo = set.first().map_or(lo, |&n| n.min(lo));
+                let hi = set.last().map_or(hi, |&n| n.max(hi));
+                ScalarVal::Range(lo, hi).normalize()
+            }
+            None if set.is_empty() => None,
+            None => ScalarVal::Set(set).normalize(),
+        }
+    }
+
+    /// `of`, except that an unknown value is still somewhere in its type's
+    /// `bounds` (from `int_bounds`), when it has any.
+    pub fn of_bounded(cs: &Constraints, bounds: Option<(i128, i128)>) -> Option<ScalarVal> {
+        ScalarVal::of(cs).or_else(|| {
+            let (lo, hi) = bounds?;
+            ScalarVal::range(lo, hi)
+        })
+    }
+
+    fn range(lo: i128, hi: i128) -> Option<ScalarVal> {
+        ScalarVal::Range(lo, hi).normalize()
+    }
+
+    fn bools(values: &[bool]) -> Option<ScalarVal> {
+        ScalarVal::Set(values.iter().map(|&b| b as i128).collect()).normalize()
+    }
+
+    fn normalize(self) -> Option<ScalarVal> {
+        match self {
+            ScalarVal::Set(set) if set.len() > limits().max_scalar_set => {
+                ScalarVal::range(*set.first().unwrap(), *set.last().unwrap())
+            }
+            ScalarVal::Range(i128::MIN, i128::MAX) => None,
+            ScalarVal::Range(lo, hi)
+                if hi
+                    .checked_sub(lo)
+                    .is_some_and(|d| d < limits().max_scalar_set as i128) =>
+            {
+                Some(ScalarVal::Set((lo..=hi).collect()))
+            }
+            v => Some(v),
+        }
+    }
+
+    pub fn hull(&self) -> (i128, i128) {
+        match self {
+            ScalarVal::Set(set) => (*set.first().unwrap(), *set.last().unwrap()),
+            ScalarVal::Range(lo, hi) => (*lo, *hi),
+        }
+    }
+
+    fn within(&self, (lo, hi): (i128, i128)) -> bool {
+        let (l, h) = self.hull();
+        lo <= l && h <= hi
+    }
+
+    /// The values that are also in `lo..=hi`, if there are any.
+    fn clamp(&self, (lo, hi): (i128, i128)) -> Option<ScalarVal> {
+        match self {
+            ScalarVal::Set(set) => {
+                let set: BTreeSet<i128> = set.range(lo..=hi).copied().collect();
+                (!set.is_empty()).then_some(ScalarVal::Set(set))
+            }
+            ScalarVal::Range(l, h) => {
+                let (l, h) = ((*l).max(lo), (*h).min(hi));
+                if l <= h { ScalarVal::range(l, h) } else { None }
+            }
+        }
+    }
+
+    pub fn to_constraints(&self, toc: Option<(TraitObjTy, TraitObjConstraint)>) -> Constraints {
+        match self {
+            ScalarVal::Set(set) => Constraints::from_vec(
+                set.iter()
+                    .map(|&n| {
+                        Constraint::new(toc.clone(), Some(RunningConstraint::Scalar(Some(n))))
+                    })
+                    .collect(),
+            ),
+            ScalarVal::Range(lo, hi) => Constraints::from(Constraint::new(
+                toc,
+                Some(RunningConstraint::Interval(*lo, *hi)),
+            )),
+        }
+    }
+}
+
+/// `v`'s constraints, or the unknown scalar for None.
+pub fn scalar_constraints(
+    v: Option<&ScalarVal>,
+    toc: Option<(TraitObjTy, TraitObjConstraint)>,
+) -> Constraints {
+    match v {
+        Some(v) => v.to_constraints(toc),
+        None => Constraints::from(Constraint::new(toc, Some(RunningConstraint::Scalar(None)))),
+    }
+}
+
+/// The values a `bool`/`char`/integer type can hold (`usize` as 64 bits).
+pub fn int_bounds(ty: &Ty) -> Option<(i128, i128)> {
+    match ty.kind() {
+        TyKind::RigidTy(RigidTy::Bool) => return Some((0, 1)),
+        TyKind::RigidTy(RigidTy::Char) => return Some((0, char::MAX as i128)),
+        _ => {}
+    }
+    Some(match int_width(ty)? {
+        (128, true) => (i128::MIN, i128::MAX),
+        (128, false) => (0, i128::MAX),
+        (bits, true) => (-(1i128 << (bits - 1)), (1i128 << (bits - 1)) - 1),
+        (bits, false) => (0, (1i128 << bits) - 1),
+    })
+}
+
+/// Size in bits and signedness of a `bool`/`char`/integer type (`usize` as
+/// 64 bits).
+fn int_width(ty: &Ty) -> Option<(u32, bool)> {
+    Some(match ty.kind() {
+        TyKind::RigidTy(RigidTy::Bool) => (8, false),
+        TyKind::RigidTy(RigidTy::Char) => (32, false),
+        TyKind::RigidTy(RigidTy::Int(int)) => match int {
+            IntTy::I8 => (8, true),
+            IntTy::I16 => (16, true),
+            IntTy::I32 => (32, true),
+            IntTy::I64 | IntTy::Isize => (64, true),
+            IntTy::I128 => (128, true),
+        },
+        TyKind::RigidTy(RigidTy::Uint(uint)) => match uint {
+            UintTy::U8 => (8, false),
+            UintTy::U16 => (16, false),
+            UintTy::U32 => (32, false),
+            UintTy::U64 | UintTy::Usize => (64, false),
+            UintTy::U128 => (128, false),
+        },
+        _ => return None,
+    })
+}
+
+/// The value a `ty` whose bits are the low bits of `bits` holds: how a
+/// `SwitchInt` arm's value or a constant's bytes read as a number. A
+/// negative arm is a huge `u128` until it's sign-extended. None if `ty`
+/// isn't an integer, or the value doesn't fit in an i128.
+pub fn int_value(bits: u128, ty: &Ty) -> Option<i128> {
+    let (width, signed) = int_width(ty)?;
+    extend(bits, width, signed)
+}
+
+/// The low `width` bits of `bits`, sign- or zero-extended.
+fn extend(bits: u128, width: u32, signed: bool) -> Option<i128> {
+    let shift = 128 - width;
+    if signed {
+        Some(((bits << shift) as i128) >> shift)
+    } else {
+        i128::try_from((bits << shift) >> shift).ok()
+    }
+}
+
+/// `x op y`, or None where Rust would panic (or the result doesn't fit in
+/// an i128).
+fn apply(op: &BinOp, x: i128, y: i128) -> Option<i128> {
+    match op {
+        BinOp::Add | BinOp::AddUnchecked => x.checked_add(y),
+        BinOp::Sub | BinOp::SubUnchecked => x.checked_sub(y),
+        BinOp::Mul | BinOp::MulUnchecked => x.checked_mul(y),
+        BinOp::Div => x.checked_div(y),
+        BinOp::Rem => x.checked_rem(y),
+        BinOp::Shl | BinOp::ShlUnchecked => {
+            let shifted = x.checked_shl(u32::try_from(y).ok()?)?;
+            (shifted >> y == x).then_some(shifted)
+        }
+        BinOp::Shr | BinOp::ShrUnchecked => x.checked_shr(u32::try_from(y).ok()?),
+        BinOp::BitAnd => Some(x & y),
+        BinOp::BitOr => Some(x | y),
+        BinOp::BitXor => Some(x ^ y),
+        BinOp::Eq => Some((x == y) as i128),
+        BinOp::Ne => Some((x != y) as i128),
+        BinOp::Lt => Some((x < y) as i128),
+        BinOp::Le => Some((x <= y) as i128),
+        BinOp::Gt => Some((x > y) as i128),
+        BinOp::Ge => Some((x >= y) as i128),
+        BinOp::Cmp => Some(x.cmp(&y) as i128),
+        BinOp::Offset => None,
+    }
+}
+
+/// `a op b` over every value either side might be, before any check
+/// against the destination type.
+pub fn binop(op: &BinOp, a: &ScalarVal, b: &ScalarVal) -> Option<ScalarVal> {
+    if let (ScalarVal::Set(xs), ScalarVal::Set(ys)) = (a, b)
+        && xs.len() * ys.len() <= MAX_SCALAR_PRODUCT
+    {
+        let mut out = BTreeSet::new();
+        for &x in xs {
+            for &y in ys {
+                out.insert(apply(op, x, y)?);
+            }
+        }
+        return ScalarVal::Set(out).normalize();
+    }
+
+    let (alo, ahi) = a.hull();
+    let (blo, bhi) = b.hull();
+    let corners = |f: fn(i128, i128) -> Option<i128>| {
+        let cs = [f(alo, blo)?, f(alo, bhi)?, f(ahi, blo)?, f(ahi, bhi)?];
+        ScalarVal::range(*cs.iter().min()?, *cs.iter().max()?)
+    };
+    match op {
+        BinOp::Add | BinOp::AddUnchecked => {
+            ScalarVal::range(alo.checked_add(blo)?, ahi.checked_add(bhi)?)
+        }
+        BinOp::Sub | BinOp::SubUnchecked => {
+            ScalarVal::range(alo.checked_sub(bhi)?, ahi.checked_sub(blo)?)
+        }
+        BinOp::Mul | BinOp::MulUnchecked => corners(i128::checked_mul),
+        // Monotone in each argument as long as the divisor can't be zero
+        BinOp::Div if blo > 0 || bhi < 0 => corners(i128::checked_div),
+        BinOp::Rem if blo > 0 || bhi < 0 => {
+            let m = blo.unsigned_abs().max(bhi.unsigned_abs()) - 1;
+            let m = i128::try_from(m).ok()?;
+            let lo = if alo >= 0 { 0 } else { (-m).max(alo) };
+            let hi = if ahi <= 0 { 0 } else { m.min(ahi) };
+            ScalarVal::range(lo, hi)
+        }
+        BinOp::BitAnd if alo >= 0 || blo >= 0 => {
+            let hi = match (alo >= 0, blo >= 0) {
+                (true, true) => ahi.min(bhi),
+                (true, false) => ahi,
+                _ => bhi,
+            };
+            ScalarVal::range(0, hi)
+        }
+        BinOp::BitOr | BinOp::BitXor if alo >= 0 && blo >= 0 => {
+            let top = ahi.max(bhi);
+            let bits = 128 - top.leading_zeros();
+            ScalarVal::range(
+                0,
+                if bits >= 127 {
+                    i128::MAX
+                } else {
+                    (1i128 << bits) - 1
+                },
+            )
+        }
+        BinOp::Shr | BinOp::ShrUnchecked if alo >= 0 && blo >= 0 && bhi < 128 => {
+            ScalarVal::range(alo >> bhi, ahi >> blo)
+        }
+        BinOp::Eq | BinOp::Ne => {
+            let can_eq = !(ahi < blo || bhi < alo);
+            let can_ne = !(alo == ahi && blo == bhi && alo == blo);
+            let (can_true, can_false) = match op {
+                BinOp::Eq => (can_eq, can_ne),
+                _ => (can_ne, can_eq),
+            };
+            let out: Vec<bool> = [(false, can_false), (true, can_true)]
+                .into_iter()
+                .filter_map(|(b, can)| can.then_some(b))
+                .collect();
+            ScalarVal::bools(&out)
+        }
+        BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge | BinOp::Cmp => {
+            // Every pair is on one side of the comparison if the hulls are.
+            let mut out = BTreeSet::new();
+            for (x, y) in [(alo, bhi), (ahi, blo)] {
+                out.insert(apply(op, x, y)?);
+            }
+            if out.len() > 1 || !(ahi < blo || bhi < alo || (alo == ahi && blo == bhi)) {
+                out.extend(match op {
+                    BinOp::Cmp => vec![-1, 0, 1],
+                    _ => vec![0, 1],
+                });
+            }
+            ScalarVal::Set(out).normalize()
+        }
+        _ => None,
+    }
+}
+
+/// `op a`, where `is_bool` says `!` is logical rather than bitwise.
+pub fn unop(op: &UnOp, a: &ScalarVal, is_bool: bool) -> Option<ScalarVal> {
+    let f = |x: i128| match op {
+        UnOp::Neg => x.checked_neg(),
+        UnOp::Not if is_bool => Some(x ^ 1),
+        UnOp::Not => Some(!x),
+        UnOp::PtrMetadata => None,
+    };
+    match a {
+        ScalarVal::Set(xs) => {
+            ScalarVal::Set(xs.iter().map(|&x| f(x)).collect::<Option<_>>()?).normalize()
+        }
+        // `-` and `!` are both decreasing (and a bool is never a range)
+        ScalarVal::Range(lo, hi) => ScalarVal::range(f(*hi)?, f(*lo)?),
+    }
+}
+
+/// `v` if it fits in `bounds` (when there are any), otherwise unknown.
+pub fn fit(v: Option<ScalarVal>, bounds: Option<(i128, i128)>) -> Option<ScalarVal> {
+    match bounds {
+        Some(bounds) => v.filter(|v| v.within(bounds)),
+        None => v,
+    }
+}
+
+/// A checked op's `(value, overflowed)`: the value on the path where it
+/// didn't overflow (the other one panics), and the flag decided whenever
+/// the value is all in or all out of range.
+pub fn checked(
+    v: Option<ScalarVal>,
+    bounds: Option<(i128, i128)>,
+) -> (Option<ScalarVal>, Option<ScalarVal>) {
+    let (Some(v), Some(bounds)) = (v, bounds) else {
+        return (None, ScalarVal::bools(&[false, true]));
+    };
+    let (lo, hi) = v.hull();
+    let flag = if v.within(bounds) {
+        ScalarVal::bools(&[false])
+    } else if hi < bounds.0 || bounds.1 < lo {
+        ScalarVal::bools(&[true])
+    } else {
+        ScalarVal::bools(&[false, true])
+    };
+    (v.clamp(bounds), flag)
+}
+
+/// Folds a merge's plain scalar disjuncts back into one value, widening a
+/// bound of the hull that grew since `cur` to the end of the domain - only
+/// once the merged value is a range, since a set can only grow
+/// `MAX_SCALAR_SET` times before it becomes one. Next to an unknown scalar
+/// the plain disjuncts say nothing, and are dropped.
+pub fn widen_scalars(cur: &Constraints, merged: Constraints) -> Constraints {
+    let is_plain = |c: &Constraint| {
+        c.toc.is_none()
+            && matches!(
+                c.cfc,
+                Some(RunningConstraint::Scalar(Some(_)) | RunningConstraint::Interval(..))
+            )
+    };
+    let plain = |cs: &Constraints| {
+        Constraints::from_vec(cs.inner.iter().filter(|c| is_plain(c)).cloned().collect())
+    };
+    let rest = || {
+        Constraints::from_vec(
+            merged
+                .inner
+                .iter()
+                .filter(|c| !is_plain(c))
+                .cloned()
+                .collect(),
+        )
+    };
+    let unknown = merged
+        .inner
+        .iter()
+        .any(|c| c.toc.is_none() && matches!(c.cfc, Some(RunningConstraint::Scalar(None))));
+    if unknown {
+        return rest();
+    }
+    let new = plain(&merged);
+    let Some(v) = ScalarVal::of(&new).filter(|v| matches!(v, ScalarVal::Range(..))) else {
+        return merged;
+    };
+
+    let (mut lo, mut hi) = v.hull();
+    if let Some(old) = ScalarVal::of(&plain(cur)) {
+        let (old_lo, old_hi) = old.hull();
+        if lo < old_lo {
+            lo = i128::MIN;
+        }
+        if hi > old_hi {
+            hi = i128::MAX;
+        }
+    }
+    let mut out = rest();
+    out.append(scalar_constraints(ScalarVal::range(lo, hi).as_ref(), None));
+    out
+}
+
+#[cfg(test)]
+mod scalar_tests;
@@ -0,0 +1,142 @@
+use super::*;
+
+fn set(values: &[i128]) -> ScalarVal {
+    ScalarVal::Set(values.iter().copied().collect())
+}
+
+fn interval(lo: i128, hi: i128) -> Constraint {
+    Constraint::new(None, Some(RunningConstraint::Interval(lo, hi)))
+}
+
+fn scalar(n: Option<i128>) -> Constraint {
+    Constraint::new(None, Some(RunningConstraint::Scalar(n)))
+}
+
+#[test]
+fn test_binop_pointwise() {
+    assert_eq!(
+        binop(&BinOp::Rem, &set(&[4, 5, 6]), &set(&[3])),
+        Some(set(&[0, 1, 2]))
+    );
+    assert_eq!(
+        binop(&BinOp::Mul, &set(&[0, 1]), &set(&[2])),
+        Some(set(&[0, 2]))
+    );
+    assert_eq!(binop(&BinOp::Add, &set(&[i128::MAX]), &set(&[1])), None);
+    assert_eq!(binop(&BinOp::Div, &set(&[4]), &set(&[0, 2])), None);
+}
+
+#[test]
+fn test_binop_hull() {
+    let wide = ScalarVal::Range(0, 1000);
+    assert_eq!(binop(&BinOp::Rem, &wide, &set(&[3])), Some(set(&[0, 1, 2])));
+    assert_eq!(binop(&BinOp::BitAnd, &wide, &set(&[1])), Some(set(&[0, 1])));
+    assert_eq!(
+        binop(&BinOp::Add, &wide, &set(&[1])),
+        Some(ScalarVal::Range(1, 1001))
+    );
+    assert_eq!(binop(&BinOp::Div, &wide, &ScalarVal::Range(-1, 10)), None);
+}
+
+#[test]
+fn test_binop_comparisons() {
+    let low = ScalarVal::Range(0, 10);
+    assert_eq!(
+        binop(&BinOp::Lt, &low, &ScalarVal::Range(20, 30)),
+        Some(set(&[1]))
+    );
+    assert_eq!(
+        binop(&BinOp::Lt, &low, &ScalarVal::Range(5, 30)),
+        Some(set(&[0, 1]))
+    );
+    assert_eq!(
+        binop(&BinOp::Eq, &low, &ScalarVal::Range(20, 30)),
+        Some(set(&[0]))
+    );
+    assert_eq!(
+        binop(&BinOp::Ne, &low, &ScalarVal::Range(20, 30)),
+        Some(set(&[1]))
+    );
+}
+
+#[test]
+fn test_binop_unknown_operand() {
+    // `env::args().count() % 3`: the count is any `usize`
+    let unknown = Constraints::from(scalar(None));
+    let n = ScalarVal::of_bounded(&unknown, Some((0, u64::MAX as i128))).unwrap();
+    assert_eq!(n, ScalarVal::Range(0, u64::MAX as i128));
+    assert_eq!(binop(&BinOp::Rem, &n, &set(&[3])), Some(set(&[0, 1, 2])));
+    assert_eq!(
+        binop(&BinOp::BitAnd, &n, &set(&[3])),
+        Some(set(&[0, 1, 2, 3]))
+    );
+    assert_eq!(binop(&BinOp::Lt, &n, &set(&[-1])), Some(set(&[0])));
+
+    let i32_bounds = (i32::MIN as i128, i32::MAX as i128);
+    let i = ScalarVal::of_bounded(&unknown, Some(i32_bounds)).unwrap();
+    assert_eq!(
+        binop(&BinOp::Rem, &i, &set(&[3])),
+        Some(set(&[-2, -1, 0, 1, 2]))
+    );
+
+    // Not an integer, or an `i128` that could be anything
+    assert_eq!(ScalarVal::of_bounded(&unknown, None), None);
+    assert_eq!(
+        ScalarVal::of_bounded(&unknown, Some((i128::MIN, i128::MAX))),
+        None
+    );
+    // A known value keeps its own
+    let known = Constraints::from(scalar(Some(5)));
+    assert_eq!(
+        ScalarVal::of_bounded(&known, Some((0, 255))),
+        Some(set(&[5]))
+    );
+}
+
+#[test]
+fn test_widen_grown_bound() {
+    let cur = Constraints::from(interval(0, 20));
+    let merged = Constraints::from(interval(0, 21));
+    assert_eq!(
+        ScalarVal::of(&widen_scalars(&cur, merged)),
+        Some(ScalarVal::Range(0, i128::MAX))
+    );
+}
+
+#[test]
+fn test_widen_keeps_sets() {
+    let cur = Constraints::from_vec(vec![scalar(Some(0)), scalar(Some(1))]);
+    let merged = Constraints::from_vec(vec![scalar(Some(0)), scalar(Some(1)), scalar(Some(2))]);
+    assert_eq!(
+        ScalarVal::of(&widen_scalars(&cur, merged)),
+        Some(set(&[0, 1, 2]))
+    );
+}
+
+#[test]
+fn test_widen_drops_next_to_unknown() {
+    let cur = Constraints::from(interval(0, 20));
+    let merged = Constraints::from_vec(vec![interval(0, 21), scalar(None)]);
+    let widened = widen_scalars(&cur, merged);
+    assert_eq!(widened.inner.len(), 1);
+    assert_eq!(ScalarVal::of(&widened), None);
+}
+
+#[test]
+fn test_extend_signed() {
+    // `-2isize` as a `SwitchInt` arm holds it
+    assert_eq!(extend(-2i64 as u64 as u128, 64, true), Some(-2));
+    assert_eq!(extend(0xff, 8, true), Some(-1));
+    assert_eq!(extend(0x7f, 8, true), Some(127));
+    // read back zero- or sign-extended past the type's bits
+    assert_eq!(extend(-1i128 as u128, 32, true), Some(-1));
+    assert_eq!(extend(-1i128 as u128, 128, true), Some(-1));
+}
+
+#[test]
+fn test_extend_unsigned() {
+    assert_eq!(extend(0xff, 8, false), Some(255));
+    assert_eq!(extend(-1i128 as u128, 8, false), Some(255));
+    assert_eq!(extend(u64::MAX as u128, 64, false), Some(u64::MAX as i128));
+    assert_eq!(extend(1 << 127, 128, false), None);
+}
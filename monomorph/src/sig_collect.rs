@@ -1,17 +1,43 @@
+//! Indirect calls through `fn` pointers.
+//!
+//! A call through a `fn` pointer whose value we've lost (it came from a
+//! field, an argument, a container) can only reach a fn whose address the
+//! program takes somewhere. The first time the interpreter meets such a
+//! call, `SigCollectPass` walks every body the program can reach
+//! (`for_each_reachable_body`) and records in `SigStore` each fn whose
+//! address it takes (see `escapes`):
+//! - a fn item or closure cast to a `fn` pointer
+//!   (`ReifyFnPointer`/`ClosureFnPointer`)
+//! - a fn item used as a value rather than called
+//! - a `fn` pointer in a constant, or in the memory, statics and vtables
+//!   it points to (`GlobalAlloc::Function` provenance), which is how a
+//!   `const`'s or a `static`'s pointers show up
+//!
+//! A call through a pointer then interprets the recorded fns of its
+//! signature (`interp_fn_ptr`) rather than the thousands of std fns that
+//! happen to share it. A program that never makes such a call never walks
+//! anything, and a signature is indexed when a call first looks it up.
+
 use rustc_data_structures::fx::FxHashMap as HashMap;
-//use rustc_data_structures::fx::FxHashSet as HashSet;
+use rustc_data_structures::fx::FxHashSet as HashSet;
+use rustc_middle::mir::mono::MonoItem;
 use rustc_public::DefId;
-//use rustc_public::mir::mono::Instance;
+use rustc_public::mir::alloc::{AllocId, GlobalAlloc};
+use rustc_public::mir::mono::Instance;
+use rustc_public::mir::{
+    Body, CastKind, LocalDecl, Operand, PointerCoercion, Rvalue, StatementKind, TerminatorKind,
+};
+use rustc_public::rustc_internal;
 use rustc_public::ty::{
-    BoundRegionKind, BoundTyKind, BoundVariableKind, FnDef, ForeignItemKind, PolyFnSig, Ty,
+    Allocation, Binder, BoundRegionKind, BoundTyKind, BoundVariableKind, ClosureKind, ConstantKind,
+    ExistentialPredicate, ExistentialProjection, ExistentialTraitRef, FnSig, GenericArgKind,
+    GenericArgs, PolyFnSig, Prov, Region, RegionKind, RigidTy, TermKind, Ty, TyKind,
 };
 
-//use log::debug;
-use std::panic;
+use log::debug;
+use std::cell::{Cell, RefCell};
 
-use crate::TraitStore;
-use crate::common::log_mir;
-use crate::convert::RvalConverter;
+use crate::constraints::RunningConstraint;
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub struct SigVal {
@@ -58,115 +84,431 @@ impl SigVal {
             output,
         }
     }
+
+    /// The key `SigStore` indexes by
+    pub fn erased(&self) -> SigVal {
+        Self {
+            bound_tys: self.bound_tys.clone(),
+            bound_regions: Vec::new(),
+            inputs: self.inputs.iter().copied().map(erase_regions).collect(),
+            output: erase_regions(self.output),
+        }
+    }
 }
 
-// If have inputs/outputs, will probably have generics that get resolved later
+/// The fns whose address the program takes, by signature: what a call
+/// through a `fn` pointer we don't have the value of can reach.
 pub struct SigStore {
-    pub sigs: HashMap<SigVal, Vec<FnDef>>,
+    /// `SigCollectPass` has run
+    collected: Cell<bool>,
+    /// recorded (with the signature they're called through) but not
+    /// indexed yet
+    pending: RefCell<Vec<(PolyFnSig, RunningConstraint)>>,
+    sigs: RefCell<HashMap<SigVal, Vec<RunningConstraint>>>,
 }
 
 impl SigStore {
     pub fn new() -> SigStore {
         Self {
-            sigs: HashMap::default(),
+            collected: Cell::new(false),
+            pending: Vec::new().into(),
+            sigs: HashMap::default().into(),
         }
     }
+
+    /// Records that `target` (a `FnDef` or `Closure`) can be called
+    /// through a `fn` pointer of signature `sig`
+    pub fn record(&self, sig: PolyFnSig, target: RunningConstraint) {
+        self.pending.borrow_mut().push((sig, target));
+    }
+
+    /// The recorded fns a `fn` pointer of signature `sig` can point to
+    pub fn lookup(&self, sig: &SigVal) -> Vec<RunningConstraint> {
+        if !self.collected.replace(true) {
+            debug!("\n\nSIG PASS");
+            SigCollectPass::new().run(self);
+        }
+        let pending = std::mem::take(&mut *self.pending.borrow_mut());
+        let mut sigs = self.sigs.borrow_mut();
+        for (fn_sig, target) in pending {
+            let targets = sigs
+                .entry(SigVal::new_from_poly(&fn_sig).erased())
+                .or_default();
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        sigs.get(&sig.erased()).cloned().unwrap_or_default()
+    }
 }
 
-pub struct SigCollectPass<'a> {
-    pub converter: RvalConverter<'a>,
+pub struct SigCollectPass;
+
+impl SigCollectPass {
+    pub fn new() -> SigCollectPass {
+        Self
+    }
+
+    pub fn run(&self, sigstore: &SigStore) {
+        let mut bodies = 0;
+        walk_reachable(|_, _, escapes| {
+            bodies += 1;
+            for escape in escapes {
+                if let Escape::FnPtr { sig, target, .. } = escape {
+                    debug!("sig pass: address taken: {:?}", target);
+                    sigstore.record(sig.clone(), target.clone());
+                }
+            }
+        });
+        debug!("sig pass: scanned {} reachable bodies", bodies);
+    }
 }
 
-impl<'a> SigCollectPass<'a> {
-    pub fn new(tstore: &'a TraitStore) -> SigCollectPass<'a> {
-        Self {
-            converter: RvalConverter::new(tstore),
+/// A fn a body makes callable other than by calling it
+enum Escape {
+    /// Its address is taken: a `fn` pointer of signature `sig` can call
+    /// `target`, which runs `instance`
+    FnPtr {
+        sig: PolyFnSig,
+        target: RunningConstraint,
+        instance: Option<Instance>,
+    },
+    /// A vtable method (or drop glue), or a `fn` pointer to something that
+    /// isn't a fn item (a shim): reachable, but not a fn pointer target
+    /// `interp_fn_ptr` would look up
+    Other(Instance),
+}
+
+impl Escape {
+    fn instance(&self) -> Option<Instance> {
+        match self {
+            Escape::FnPtr { instance, .. } => *instance,
+            Escape::Other(instance) => Some(*instance),
         }
     }
+}
 
-    pub fn run(&self, sigstore: &mut SigStore) {
-        //debug!("FUNC SIG PASS");
-        //self.test();
-        self.collect_function_sigs(sigstore);
-        //debug!("DONE FUNC SIG PASS");
+/// The fns `body` makes callable other than by calling them
+fn escapes(body: &Body) -> Vec<Escape> {
+    let mut escapes = Vec::new();
+    let locals = body.locals();
+    for block in &body.blocks {
+        for stmt in &block.statements {
+            if let StatementKind::Assign(_, rvalue) = &stmt.kind {
+                rvalue_escapes(&mut escapes, locals, rvalue);
+            }
+        }
+        // The callee itself isn't a use of its address, its arguments are
+        if let TerminatorKind::Call { args, .. } = &block.terminator.kind {
+            for arg in args {
+                value_escapes(&mut escapes, arg);
+            }
+        }
     }
+    escapes
+}
 
-    /*
-    fn test(&self) {
-        let krates: Vec<_> = rustc_public::all_local_items();
-            //.into_iter()
-            //.filter_map(|item| Instance::try_from(item).ok())
-            //.collect();
-        for krate in krates {
-            debug!("\nkrate?: {:?}", krate); //instance.name(), instance);
+fn rvalue_escapes(escapes: &mut Vec<Escape>, locals: &[LocalDecl], rvalue: &Rvalue) {
+    match rvalue {
+        Rvalue::Cast(CastKind::PointerCoercion(PointerCoercion::Unsize), op, ty) => {
+            // The vtable the cast builds, if it's to a trait object
+            if let Ok(op_ty) = op.ty(locals)
+                && let Some(vtable) = unsize_vtable(op_ty, *ty)
+            {
+                AllocFns::new(escapes).vtable(&vtable);
+            }
+            value_escapes(escapes, op);
+        }
+        // `ReifyFnPointer` or `ClosureFnPointer`
+        Rvalue::Cast(CastKind::PointerCoercion(_), op, fn_ptr_ty) => {
+            let (Ok(op_ty), Some(sig)) = (op.ty(locals), fn_ptr_ty.kind().fn_sig()) else {
+                return;
+            };
+            let (target, instance) = match op_ty.kind() {
+                TyKind::RigidTy(RigidTy::FnDef(def, genargs)) => (
+                    RunningConstraint::FnDef(def, genargs.clone()),
+                    Instance::resolve_for_fn_ptr(def, &genargs).ok(),
+                ),
+                TyKind::RigidTy(RigidTy::Closure(def, genargs)) => (
+                    RunningConstraint::Closure(def, genargs.clone()),
+                    Instance::resolve_closure(def, &genargs, ClosureKind::FnOnce).ok(),
+                ),
+                _ => return,
+            };
+            escapes.push(Escape::FnPtr {
+                sig,
+                target,
+                instance,
+            });
+        }
+        Rvalue::Use(op) | Rvalue::Cast(_, op, _) | Rvalue::Repeat(op, _) => {
+            value_escapes(escapes, op)
+        }
+        Rvalue::Aggregate(_, ops) => {
+            for op in ops {
+                value_escapes(escapes, op);
+            }
         }
-        panic!("done")
+        _ => {}
     }
-    */
+}
 
-    fn collect_function_sigs(&self, sigstore: &mut SigStore) {
-        let process = std::env::var("VERIFOPT_EXT_CRATES")
-            .map(|e| e == "1")
-            .unwrap_or(false);
-        if !process {
-            return;
+/// A fn item constant used as a value (passed as an `impl Fn`, stored in
+/// a struct) rather than called, or any constant holding `fn` pointers:
+/// a `FnPtr` one, or one pointing to memory, a static or a vtable that
+/// does
+fn value_escapes(escapes: &mut Vec<Escape>, op: &Operand) {
+    let Operand::Constant(constant) = op else {
+        return;
+    };
+    let ty = constant.const_.ty();
+    if let TyKind::RigidTy(RigidTy::FnDef(def, genargs)) = ty.kind()
+        && let Some(sig) = ty.kind().fn_sig()
+    {
+        escapes.push(Escape::FnPtr {
+            sig,
+            target: RunningConstraint::FnDef(def, genargs.clone()),
+            instance: Instance::resolve(def, &genargs).ok(),
+        });
+    }
+    if let ConstantKind::Allocated(alloc) = constant.const_.kind() {
+        AllocFns::new(escapes).memory(alloc, false);
+    }
+}
+
+/// The vtable of `from`'s pointee for `to`'s trait object pointee, if
+/// casting `from` to `to` builds one: `&Cat` to `&dyn Animal`, or
+/// `Box<Cat>` to `Box<dyn Animal>`
+fn unsize_vtable(from: Ty, to: Ty) -> Option<GlobalAlloc> {
+    let (TyKind::RigidTy(from_kind), TyKind::RigidTy(to_kind)) = (from.kind(), to.kind()) else {
+        return None;
+    };
+    match (from_kind, to_kind) {
+        // `dyn Sub` to `dyn Super` reuses (part of) the vtable it had
+        (RigidTy::Dynamic(..), _) => None,
+        (_, RigidTy::Dynamic(preds, _)) => {
+            let principal = preds.iter().find_map(|pred| match &pred.value {
+                ExistentialPredicate::Trait(trait_ref) => Some(Binder {
+                    value: trait_ref.clone(),
+                    bound_vars: pred.bound_vars.clone(),
+                }),
+                _ => None,
+            });
+            Some(GlobalAlloc::VTable(from, principal))
         }
+        (RigidTy::Ref(_, from, _), RigidTy::Ref(_, to, _))
+        | (RigidTy::RawPtr(from, _), RigidTy::RawPtr(to, _)) => unsize_vtable(from, to),
+        // `Box`, `Rc`, `Arc`, ...: the type argument that changes
+        (RigidTy::Adt(_, from_args), RigidTy::Adt(_, to_args)) => from_args
+            .0
+            .iter()
+            .zip(to_args.0.iter())
+            .find_map(|args| match args {
+                (GenericArgKind::Type(from), GenericArgKind::Type(to)) if from != to => {
+                    unsize_vtable(*from, *to)
+                }
+                _ => None,
+            }),
+        _ => None,
+    }
+}
 
-        let mut all_crates = rustc_public::external_crates().clone();
-        all_crates.insert(0, rustc_public::local_crate());
-        //let len = all_crates.len();
-        for (_i, krate) in all_crates.into_iter().enumerate() {
-            //debug!("krate#{:?} ({:?}/{:?})", i, i + 1, len);
-            //debug!("krate: {:?}", krate);
+/// Collects the fns an allocation points to into `escapes`: through its
+/// `fn` pointers, and the memory, statics and vtables it points to.
+struct AllocFns<'e> {
+    escapes: &'e mut Vec<Escape>,
+    seen: HashSet<AllocId>,
+}
 
-            // Non-crate-local FnDefs
-            for fndef in krate.fn_defs() {
-                //debug!("NOT FOREIGN");
-                self.process_fndef(sigstore, &fndef);
-            }
+impl<'e> AllocFns<'e> {
+    fn new(escapes: &'e mut Vec<Escape>) -> AllocFns<'e> {
+        Self {
+            escapes,
+            seen: HashSet::default(),
+        }
+    }
 
-            // Foreign FnDefs
-            for foreign_mod in krate.foreign_modules() {
-                for foreign_item in foreign_mod.module().items() {
-                    match foreign_item.kind() {
-                        ForeignItemKind::Fn(fndef) => {
-                            //debug!("FOREIGN");
-                            self.process_fndef(sigstore, &fndef);
-                        }
-                        _ => {}
+    /// `in_vtable`: `alloc` is a vtable's, so its fns are methods rather
+    /// than `fn` pointers
+    fn memory(&mut self, alloc: &Allocation, in_vtable: bool) {
+        for (_offset, Prov(id)) in alloc.provenance.ptrs.iter() {
+            if !self.seen.insert(*id) {
+                continue;
+            }
+            match GlobalAlloc::from(*id) {
+                GlobalAlloc::Function(instance) if in_vtable => {
+                    self.escapes.push(Escape::Other(instance))
+                }
+                GlobalAlloc::Function(instance) => self.function(instance),
+                GlobalAlloc::Memory(alloc) => self.memory(&alloc, in_vtable),
+                GlobalAlloc::Static(def) => {
+                    if let Ok(alloc) = def.eval_initializer() {
+                        self.memory(&alloc, false);
                     }
                 }
+                vtable @ GlobalAlloc::VTable(..) => self.vtable(&vtable),
+                _ => {}
             }
         }
     }
 
-    fn process_fndef(&self, sigstore: &mut SigStore, fndef: &FnDef) {
-        let sig = fndef.fn_sig();
-        //debug!("fndef: {:?}", fndef);
-        let sigval = SigVal::new_from_poly(&sig);
-        //debug!("sigval: {:?}", sigval);
-        match sigstore.sigs.get_mut(&sigval) {
-            Some(fn_vec) => {
-                if !fn_vec.contains(&fndef) {
-                    fn_vec.push(*fndef);
-                }
+    fn vtable(&mut self, vtable: &GlobalAlloc) {
+        if let Some(id) = vtable.vtable_allocation()
+            && self.seen.insert(id)
+            && let GlobalAlloc::Memory(alloc) = GlobalAlloc::from(id)
+        {
+            self.memory(&alloc, true);
+        }
+    }
+
+    /// A `fn` pointer to `instance`
+    fn function(&mut self, instance: Instance) {
+        let ty = instance.ty();
+        let escape = match (ty.kind(), ty.kind().fn_sig()) {
+            (TyKind::RigidTy(RigidTy::FnDef(def, genargs)), Some(sig)) => Escape::FnPtr {
+                sig,
+                target: RunningConstraint::FnDef(def, genargs),
+                instance: Some(instance),
+            },
+            _ => Escape::Other(instance),
+        };
+        self.escapes.push(escape);
+    }
+}
+
+/// Calls `f` on the body of every fn the program can run: rustc's mono
+/// items for this crate (which covers vtable methods and closures), and,
+/// since those leave out upstream fns that aren't generic, everything
+/// reachable from them through direct calls, `fn` pointers and vtables.
+pub fn for_each_reachable_body(mut f: impl FnMut(&Instance, &Body)) {
+    walk_reachable(|instance, body, _| f(instance, body));
+}
+
+/// `for_each_reachable_body`, also handing `f` each body's `escapes`
+fn walk_reachable(mut f: impl FnMut(&Instance, &Body, &[Escape])) {
+    let mut worklist: Vec<Instance> = rustc_middle::ty::tls::with(|tcx| {
+        tcx.collect_and_partition_mono_items(())
+            .codegen_units
+            .iter()
+            .flat_map(|cgu| cgu.items().keys())
+            .filter_map(|item| match item {
+                MonoItem::Fn(instance) => Some(rustc_internal::stable(*instance)),
+                _ => None,
+            })
+            .collect()
+    });
+    let mut seen: HashSet<Instance> = worklist.iter().copied().collect();
+
+    while let Some(instance) = worklist.pop() {
+        if !instance.has_body() {
+            continue;
+        }
+        let Some(body) = instance.body() else {
+            continue;
+        };
+        for block in &body.blocks {
+            if let TerminatorKind::Call { func, .. } = &block.terminator.kind
+                && let Ok(func_ty) = func.ty(body.locals())
+                && let TyKind::RigidTy(RigidTy::FnDef(def, genargs)) = func_ty.kind()
+                && let Ok(callee) = Instance::resolve(def, &genargs)
+                && seen.insert(callee)
+            {
+                worklist.push(callee);
             }
-            None => {
-                sigstore.sigs.insert(sigval, vec![*fndef]);
+        }
+        let escapes = escapes(&body);
+        for escaped in escapes.iter().filter_map(Escape::instance) {
+            if seen.insert(escaped) {
+                worklist.push(escaped);
             }
         }
+        f(&instance, &body, &escapes);
+    }
+}
 
-        // FIXME false -> true if want to print func bodies
-        if fndef.has_body() && false {
-            //debug!("HAS BODY");
-            match panic::catch_unwind(|| fndef.body()) {
-                Ok(body) => {
-                    log_mir(&body.unwrap());
-                }
-                _ => {} //debug!("ERROR GETTING BODY"),
-            }
-        } else {
-            //debug!("NO BODY");
+/// `ty` with every region erased, so that signatures differing only in
+/// how their lifetimes are bound or named compare equal
+fn erase_regions(ty: Ty) -> Ty {
+    let TyKind::RigidTy(rigid) = ty.kind() else {
+        return ty;
+    };
+    let erased = || Region {
+        kind: RegionKind::ReErased,
+    };
+    let rigid = match rigid {
+        RigidTy::Ref(_, ty, m) => RigidTy::Ref(erased(), erase_regions(ty), m),
+        RigidTy::RawPtr(ty, m) => RigidTy::RawPtr(erase_regions(ty), m),
+        RigidTy::Slice(ty) => RigidTy::Slice(erase_regions(ty)),
+        RigidTy::Array(ty, len) => RigidTy::Array(erase_regions(ty), len),
+        RigidTy::Tuple(tys) => RigidTy::Tuple(tys.into_iter().map(erase_regions).collect()),
+        RigidTy::Adt(def, genargs) => RigidTy::Adt(def, erase_arg_regions(genargs)),
+        RigidTy::FnPtr(sig) => RigidTy::FnPtr(erase_binder_regions(sig, |sig| FnSig {
+            inputs_and_output: sig
+                .inputs_and_output
+                .into_iter()
+                .map(erase_regions)
+                .collect(),
+            ..sig
+        })),
+        RigidTy::Dynamic(preds, _) => RigidTy::Dynamic(
+            preds
+                .into_iter()
+                .map(|pred| erase_binder_regions(pred, erase_pred_regions))
+                .collect(),
+            erased(),
+        ),
+        _ => return ty,
+    };
+    Ty::from_rigid_kind(rigid)
+}
+
+/// `binder` with `erase` applied to its value, and without the regions it
+/// binds: `for<'a> fn(&'a u8)` and `fn(&u8)` become the same type
+fn erase_binder_regions<T>(binder: Binder<T>, erase: impl FnOnce(T) -> T) -> Binder<T> {
+    Binder {
+        value: erase(binder.value),
+        bound_vars: binder
+            .bound_vars
+            .into_iter()
+            .filter(|var| !matches!(var, BoundVariableKind::Region(_)))
+            .collect(),
+    }
+}
+
+fn erase_pred_regions(pred: ExistentialPredicate) -> ExistentialPredicate {
+    match pred {
+        ExistentialPredicate::Trait(trait_ref) => {
+            ExistentialPredicate::Trait(ExistentialTraitRef {
+                generic_args: erase_arg_regions(trait_ref.generic_args),
+                ..trait_ref
+            })
+        }
+        ExistentialPredicate::Projection(proj) => {
+            ExistentialPredicate::Projection(ExistentialProjection {
+                generic_args: erase_arg_regions(proj.generic_args),
+                term: match proj.term {
+                    TermKind::Type(ty) => TermKind::Type(erase_regions(ty)),
+                    term => term,
+                },
+                ..proj
+            })
         }
+        auto @ ExistentialPredicate::AutoTrait(_) => auto,
     }
 }
+
+fn erase_arg_regions(genargs: GenericArgs) -> GenericArgs {
+    GenericArgs(
+        genargs
+            .0
+            .into_iter()
+            .map(|arg| match arg {
+                GenericArgKind::Lifetime(_) => GenericArgKind::Lifetime(Region {
+                    kind: RegionKind::ReErased,
+                }),
+                GenericArgKind::Type(ty) => GenericArgKind::Type(erase_regions(ty)),
+                other => other,
+            })
+            .collect(),
+    )
+}
@@ -7,32 +7,90 @@ use rustc_public::ty::{
 use crate::Context;
 use crate::InterpPass;
 use crate::constraints::{
-    ADTFields, Constraint, Constraints, RunningConstraint, VOID, adt_field_idx,
+    ADTFields, Constraint, Constraints, Location, RunningConstraint, TraitObjTy, VOID,
+    adt_field_idx,
 };
 use crate::convert::WrapperKind;
 use crate::error::Error;
+use crate::heap::{heap_sites, tag_site};
+use crate::interior::is_scalar;
 
 use log::debug;
 
 /// Everything we need about a call's receiver to decide whether (and how)
-/// to stub it as a BTreeSet/BTreeMap method.
+/// to stub it as a collection method.
 struct CollectionRecv {
     place: Place,
     adtdef: AdtDef,
     genargs: GenericArgs,
-    kind: WrapperKind,                 // BTreeSet or BTreeMap
-    key_field: ProjectionElem,         // field 0: the Set's element, or the Map's key
-    val_field: Option<ProjectionElem>, // field 1: only present for BTreeMap
+    kind: WrapperKind,                 // see `WrapperKind::is_collection`
+    key_field: ProjectionElem,         // field 0: the element, or the Map's key
+    val_field: Option<ProjectionElem>, // field 1: only present for maps
 }
 
 /// Same idea, but for one of *our own fabricated* iterator values
-/// (Iter/IntoIter/Keys/Values over a BTreeSet/BTreeMap).
-struct IterRecv {
-    place: Place,
-    elem_field: ProjectionElem,
+/// (Iter/IntoIter/Keys/Values/Drain/... over a stubbed collection, or an
+/// adapter over one of those, see `iter_adapters`).
+pub(crate) struct IterRecv {
+    pub(crate) place: Place,
+    pub(crate) elem_field: ProjectionElem,
+    /// over a BTreeSet/BTreeMap, where an unknown method is still a panic
+    pub(crate) btree: bool,
 }
 
 impl<'a> InterpPass<'a> {
+    /// Entry point for the stubs that call back into the program - closure
+    /// arguments to `Option`/`Result` combinators (see `combinators`) and
+    /// to iterator adapters (see `iter_adapters`), and the formatting fns
+    /// behind `format_args!` (see `fmt_args`) - so unlike `stdlib_stub`
+    /// they get the call stack. Tried before it.
+    pub fn stub_higher_order(
+        &self,
+        term_span: &Span,
+        ctxt: &mut Context,
+        call_stack: &mut Vec<VOID>,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        if let Some(result) = self.stub_combinator(
+            term_span,
+            ctxt,
+            call_stack,
+            caller_scope,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+        ) {
+            return Some(result);
+        }
+        if let Some(result) = self.stub_iter_adapter(
+            term_span,
+            ctxt,
+            call_stack,
+            caller_scope,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+        ) {
+            return Some(result);
+        }
+        self.stub_fmt(
+            term_span,
+            ctxt,
+            call_stack,
+            caller_scope,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+        )
+    }
+
     /// Entry point: returns Some(result) if this call is a std
     /// collection/iterator method we're modeling with a summary instead of
     /// interpreting its real body.
@@ -70,6 +128,60 @@ impl<'a> InterpPass<'a> {
             return Some(self.retty_fallback_from_poly(fndef.fn_sig()));
         }
 
+        // RefCell/Cell/Mutex/RwLock and their guards, see `interior`.
+        if let Some(result) = self.stub_interior(
+            ctxt,
+            caller_scope,
+            term_span,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+        ) {
+            return Some(result);
+        }
+
+        // `From` into `Box<dyn Error>`, and `downcast*` out of it or a
+        // `dyn Any`, see `dyn_error`.
+        if let Some(result) = self.stub_dyn_error(
+            ctxt,
+            caller_scope,
+            term_span,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+        ) {
+            return Some(result);
+        }
+
+        // `TypeId`s and `<dyn Any>::is`, see `type_id`.
+        if let Some(result) = self.stub_type_id(
+            ctxt,
+            caller_scope,
+            term_span,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+        ) {
+            return Some(result);
+        }
+
+        // Reads of and `&mut`s into a `Box`/`Rc`/`Arc` pointee with known
+        // allocation sites, see `heap`.
+        if let Some(result) = self.stub_heap(
+            ctxt,
+            caller_scope,
+            term_span,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+        ) {
+            return Some(result);
+        }
+
         if self.is_wrapper_new(fndef) {
             return Some(Ok(self.stub_wrapper_new(
                 ctxt,
@@ -106,7 +218,7 @@ impl<'a> InterpPass<'a> {
         // through the same receiver-identification and handler below,
         // since neither has an actual collection to key off of yet.
         if method == "from_iter" || method == "from_sorted_iter" {
-            if let Some(recv) = self.from_iter_collection_recv(genargs) {
+            if let Some(recv) = self.from_iter_collection_recv(fndef, genargs) {
                 return Some(Ok(self.stub_from_iter(
                     ctxt,
                     caller_scope,
@@ -116,15 +228,35 @@ impl<'a> InterpPass<'a> {
                     args,
                 )));
             }
-            // Not a BTreeMap/BTreeSet from_iter (e.g. Vec::from_iter, or
-            // some unrelated FromIterator impl) - fall through normally.
+            // Not a collection from_iter (e.g. String::from_iter, or some
+            // unrelated FromIterator impl) - fall through normally.
+        }
+
+        // Constructors of the std sequence/hash collections have the same
+        // shape as `from_iter`: no receiver, the collection is the return
+        // value. Left unstubbed, `Vec::new` is harmless, but `vec![..]`'s
+        // `into_vec` and `From<[T; N]>` would be interpreted down through
+        // RawVec, which is opaque (see `is_opaque_internal_defid`).
+        if let Some((recv, seeded)) = self.collection_ctor_recv(fndef, genargs, &method) {
+            return Some(Ok(self.stub_collection_ctor(
+                ctxt,
+                caller_scope,
+                term_span,
+                local_decls,
+                &recv,
+                args,
+                seeded,
+            )));
         }
 
-        // Iterator methods (currently just `next`) take priority, since an
-        // Iter<...> value is never also a BTreeSet/BTreeMap.
-        if let Some(recv) = self.iter_receiver(local_decls, args) {
+        // Iterator methods (just `next`/`into_iter` here, the adapters are
+        // in `iter_adapters`) take priority, since an Iter<...> value is
+        // never also a collection.
+        if let Some(recv) = self.iter_receiver(ctxt, caller_scope, local_decls, args) {
             let result = match method.as_str() {
-                "next" => self.stub_next(ctxt, caller_scope, local_decls, fndef, &recv),
+                "next" | "next_back" => {
+                    self.stub_next(ctxt, caller_scope, local_decls, fndef, &recv)
+                }
                 // Every Iterator blanket-impls IntoIterator::into_iter as
                 // identity (`fn into_iter(self) -> Self { self }`) - a
                 // `for` loop over something already produced by `.iter()`/
@@ -134,6 +266,10 @@ impl<'a> InterpPass<'a> {
                 "into_iter" => {
                     ctxt.get_constraints(caller_scope, local_decls, &recv.place, false, Some(self))
                 }
+                // `count`/`sum`/`position`/... on one of the std collection
+                // iterators (the adapters went to `stub_iter_adapter`): the
+                // signature's type is a sound answer.
+                _ if !recv.btree => return Some(self.retty_fallback_from_poly(fndef.fn_sig())),
                 _ => panic!(
                     "stdlib_stub: no summary for iterator method {} - add one",
                     method
@@ -142,7 +278,20 @@ impl<'a> InterpPass<'a> {
             return Some(Ok(result));
         }
 
-        let recv = self.collection_receiver(local_decls, args)?;
+        let recv = self.collection_receiver(ctxt, caller_scope, local_decls, args)?;
+
+        if !matches!(recv.kind, WrapperKind::BTreeSet | WrapperKind::BTreeMap) {
+            return Some(self.stub_std_collection(
+                ctxt,
+                caller_scope,
+                term_span,
+                local_decls,
+                fndef,
+                &recv,
+                args,
+                &method,
+            ));
+        }
 
         let result = match method.as_str() {
             "new" | "default" => self.stub_constructor(&recv),
@@ -172,48 +321,137 @@ impl<'a> InterpPass<'a> {
 
     // ---------- receiver identification ----------
 
-    /// Returns None if the receiver isn't a BTreeSet/BTreeMap (or there's no
-    /// receiver at all), in which case the caller should fall through to normal
-    /// interpretation.
+    /// Returns None if the receiver isn't a collection we summarize (or
+    /// there's no receiver at all), in which case the caller should fall
+    /// through to normal interpretation.
     fn collection_receiver(
         &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
         local_decls: &[LocalDecl],
         args: &Vec<Operand>,
     ) -> Option<CollectionRecv> {
         let place = self.receiver_place(args)?;
+        if let Some(recv) = self.slice_receiver(ctxt, caller_scope, local_decls, &place) {
+            return Some(recv);
+        }
+
         let (adtdef, genargs) = self.receiver_adt(local_decls, &place)?;
         let kind = self.converter.wrapper_kind(&adtdef)?;
-        if !matches!(kind, WrapperKind::BTreeSet | WrapperKind::BTreeMap) {
+        if !kind.is_collection() {
             return None;
         }
 
+        Some(Self::collection_recv(place, adtdef, genargs, kind))
+    }
+
+    fn collection_recv(
+        place: Place,
+        adtdef: AdtDef,
+        genargs: GenericArgs,
+        kind: WrapperKind,
+    ) -> CollectionRecv {
         let key_field = ProjectionElem::Field(0, genargs.0[0].expect_ty().clone());
-        let val_field = matches!(kind, WrapperKind::BTreeMap)
+        let val_field = kind
+            .is_map()
             .then(|| ProjectionElem::Field(1, genargs.0[1].expect_ty().clone()));
 
-        Some(CollectionRecv {
+        CollectionRecv {
             place,
             adtdef,
             genargs,
             kind,
             key_field,
             val_field,
+        }
+    }
+
+    /// `&[T]`/`&mut [T]` receivers whose value is one of our synthetic
+    /// `Vec`/`VecDeque` constraints, i.e. a slice that came out of the
+    /// `deref`/`as_slice` stub - which is how `v.iter()`, `v.first()`,
+    /// `v[i]`, ... on a `Vec` actually reach their callee. Slices of anything
+    /// else (arrays, mostly) are still interpreted normally.
+    fn slice_receiver(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        place: &Place,
+    ) -> Option<CollectionRecv> {
+        let elem_ty = match place.ty(local_decls).ok()?.kind() {
+            TyKind::RigidTy(RigidTy::Ref(_, inner_ty, _)) => match inner_ty.kind() {
+                TyKind::RigidTy(RigidTy::Slice(elem_ty)) => elem_ty,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let cur = ctxt.get_constraints(caller_scope, local_decls, place, false, Some(self))?;
+        let (adtdef, genargs) = cur.inner.iter().find_map(|c| match &c.cfc {
+            Some(RunningConstraint::Adt(adtdef, genargs, _, _))
+                if matches!(
+                    self.converter.wrapper_kind(adtdef),
+                    Some(WrapperKind::Vec | WrapperKind::VecDeque)
+                ) =>
+            {
+                Some((adtdef.clone(), genargs.clone()))
+            }
+            _ => None,
+        })?;
+
+        // Field types from the slice, not the constraint: a constraint made
+        // by `stub_collection_ctor` carries the ctor's generic signature.
+        Some(CollectionRecv {
+            place: place.clone(),
+            adtdef,
+            genargs,
+            kind: WrapperKind::Vec,
+            key_field: ProjectionElem::Field(0, elem_ty),
+            val_field: None,
         })
     }
 
     /// Recognizes our own synthetic iterator values by the real iterator
-    /// ADT's name suffix (Iter/IntoIter/Keys/Values over btree::map/set).
+    /// ADT's name suffix (Iter/IntoIter/Keys/Values over btree::map/set,
+    /// the std collections' iterators, the `std::iter` adapters), or by a
+    /// `&mut dyn Iterator` receiver whose every value is one of ours (see
+    /// `relabel_synthetic_iter`).
     /// NOTE: this is *not* a general "wrapper" concept for resolve_adt_helper
     /// - it only matters to our own stubs - so it's kept local here rather
     /// than added to `wrapper_kind` in convert.rs.
-    fn iter_receiver(&self, local_decls: &[LocalDecl], args: &Vec<Operand>) -> Option<IterRecv> {
+    pub(crate) fn iter_receiver(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        args: &Vec<Operand>,
+    ) -> Option<IterRecv> {
         let place = self.receiver_place(args)?;
+        if self.is_dyn_synthetic_iter(ctxt, caller_scope, local_decls, &place) {
+            return Some(IterRecv {
+                place,
+                elem_field: ProjectionElem::Field(0, Ty::bool_ty()),
+                btree: false,
+            });
+        }
         let (adtdef, _genargs) = self.receiver_adt(local_decls, &place)?;
 
         let name = adtdef.0.name();
         let suffix = name.splitn(2, "::").nth(1).unwrap_or("");
-        if !crate::convert::is_btree_iter_suffix(suffix) {
-            return None;
+        let btree = crate::convert::is_btree_iter_suffix(suffix);
+        if !btree {
+            if !crate::convert::is_std_collection_iter_suffix(suffix)
+                && !crate::convert::is_iter_adapter_suffix(suffix)
+            {
+                return None;
+            }
+            // A `slice::Iter` over an array is a real one, with a pointer in
+            // field 0 - only take over iterators `stub_collection_iter` made,
+            // which carry the collection's AdtDef rather than their own.
+            let cur = ctxt.get_constraints(caller_scope, local_decls, &place, false, Some(self))?;
+            if !self.is_synthetic_collection(&cur) {
+                return None;
+            }
         }
 
         // Field 0 of our fabricated Iter value carries the element
@@ -223,9 +461,41 @@ impl<'a> InterpPass<'a> {
         Some(IterRecv {
             place,
             elem_field: ProjectionElem::Field(0, Ty::bool_ty()),
+            btree,
         })
     }
 
+    fn is_dyn_synthetic_iter(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        local_decls: &[LocalDecl],
+        place: &Place,
+    ) -> bool {
+        let Ok(ty) = place.ty(local_decls) else {
+            return false;
+        };
+        let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() else {
+            return false;
+        };
+        let TyKind::RigidTy(RigidTy::Dynamic(preds, _)) = inner.kind() else {
+            return false;
+        };
+        if !preds.iter().any(|p| {
+            TraitObjTy::new_from_bound_existential(p).is_some_and(|t| t.is_iterator_trait())
+        }) {
+            return false;
+        }
+        ctxt.get_constraints(caller_scope, local_decls, place, false, Some(self))
+            .is_some_and(|cur| {
+                !cur.inner.is_empty()
+                    && cur.inner.iter().all(|c| {
+                        c.toc.is_some()
+                            && self.is_synthetic_collection(&Constraints::from(c.clone()))
+                    })
+            })
+    }
+
     /// Same idea as `collection_receiver`, but for `from_iter`/
     /// `from_sorted_iter`: there's no existing BTreeMap/BTreeSet value to
     /// find in `args` (see the comment at the dispatch site), so this
@@ -246,24 +516,33 @@ impl<'a> InterpPass<'a> {
     /// 0 here is the concrete implementing type - confirmed directly
     /// against a real `BTreeSet<Cow<'_,[u8]>>::from_iter` call's logged
     /// scope tuple, where this was `BTreeSet<Cow<'_,[u8]>, Global>`.
-    fn from_iter_collection_recv(&self, genargs: &GenericArgs) -> Option<CollectionRecv> {
-        let self_ty = match genargs.0.first()? {
-            GenericArgKind::Type(ty) => ty,
-            _ => return None,
+    ///
+    /// The same goes for the std collections' constructors, except that
+    /// inherent ones (`Vec::new`, `HashMap::with_capacity`) have the impl's
+    /// generics rather than `Self` at index 0 - but, not being trait fns,
+    /// their declared return type already is the collection.
+    fn from_iter_collection_recv(
+        &self,
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+    ) -> Option<CollectionRecv> {
+        let output_ty = fndef.fn_sig().value.output();
+        let self_ty = match output_ty.kind() {
+            TyKind::RigidTy(RigidTy::Adt(..)) => output_ty,
+            _ => match genargs.0.first()? {
+                GenericArgKind::Type(ty) => ty.clone(),
+                _ => return None,
+            },
         };
         let (adtdef, genargs) = match self_ty.kind() {
             TyKind::RigidTy(RigidTy::Adt(adtdef, genargs)) => (adtdef, genargs),
             _ => return None,
         };
         let kind = self.converter.wrapper_kind(&adtdef)?;
-        if !matches!(kind, WrapperKind::BTreeSet | WrapperKind::BTreeMap) {
+        if !kind.is_collection() {
             return None;
         }
 
-        let key_field = ProjectionElem::Field(0, genargs.0[0].expect_ty().clone());
-        let val_field = matches!(kind, WrapperKind::BTreeMap)
-            .then(|| ProjectionElem::Field(1, genargs.0[1].expect_ty().clone()));
-
         // `stub_from_iter` never reads or writes `recv.place` - unlike
         // insert/get_like/make_iter, which all update or read an existing
         // `self`, `from_iter` builds a fresh value with nothing to look up
@@ -273,14 +552,39 @@ impl<'a> InterpPass<'a> {
             projection: vec![],
         };
 
-        Some(CollectionRecv {
-            place,
-            adtdef,
-            genargs,
-            kind,
-            key_field,
-            val_field,
-        })
+        Some(Self::collection_recv(place, adtdef, genargs, kind))
+    }
+
+    /// `Vec`/`VecDeque`/`HashMap`/`HashSet` constructors, plus whether the
+    /// first argument is where the initial elements come from
+    /// (`From<[T; N]>`, `into_vec`, `to_vec`) rather than a capacity or
+    /// hasher.
+    fn collection_ctor_recv(
+        &self,
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        method: &str,
+    ) -> Option<(CollectionRecv, bool)> {
+        let seeded = matches!(method, "from" | "into_vec" | "to_vec" | "to_vec_in");
+        let empty = matches!(
+            method,
+            "new"
+                | "new_in"
+                | "default"
+                | "with_capacity"
+                | "with_capacity_in"
+                | "with_hasher"
+                | "with_capacity_and_hasher"
+        );
+        if !seeded && !empty {
+            return None;
+        }
+
+        let recv = self.from_iter_collection_recv(fndef, genargs)?;
+        if matches!(recv.kind, WrapperKind::BTreeSet | WrapperKind::BTreeMap) {
+            return None;
+        }
+        Some((recv, seeded))
     }
 
     /// Pulls the first argument's Place out of an Operand, if it has one.
@@ -331,7 +635,30 @@ impl<'a> InterpPass<'a> {
     /// triggers when the name ends in '>', so every method without its own
     /// generic parameter (`insert`, `get`, `iter`, `next`, ...) is
     /// completely unaffected.
-    fn method_name(fndef: &FnDef) -> String {
+    pub(crate) fn method_name(fndef: &FnDef) -> String {
+        let path = Self::callee_path(fndef);
+        path.rsplit("::").next().unwrap_or(&path).to_string()
+    }
+
+    /// A called function's full name minus any trailing `::<..>` generic
+    /// args (see `method_name`) - what `[[stub]]` paths are matched against.
+    /// A callee verifopt.toml's `[analysis]` filter rejects (see `config`)
+    /// returns anything its type allows, like a `returns = "fallback"` stub.
+    /// Asked only once no stub or built-in model applied, so excluding std
+    /// doesn't lose `Box::new` and friends.
+    pub(crate) fn analysis_excluded(
+        &self,
+        fndef: &FnDef,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let path = Self::callee_path(fndef);
+        if self.analysis_filter.allows(&path) {
+            return None;
+        }
+        debug!("ANALYSIS EXCLUDED: {}", path);
+        Some(self.retty_fallback_from_poly(fndef.fn_sig()))
+    }
+
+    pub(crate) fn callee_path(fndef: &FnDef) -> String {
         let full = fndef.0.name();
 
         let mut trimmed = full.as_str();
@@ -357,7 +684,7 @@ impl<'a> InterpPass<'a> {
             }
         }
 
-        trimmed.rsplit("::").next().unwrap_or(trimmed).to_string()
+        trimmed.to_string()
     }
 
     // ---------- collection method handlers ----------
@@ -418,6 +745,8 @@ impl<'a> InterpPass<'a> {
             _ => unreachable!(),
         }
 
+        self.note_cell_store(ctxt, caller_scope, &recv.place, &cur);
+        self.note_heap_store(ctxt, caller_scope, &recv.place, &cur);
         ctxt.set_scoped_constraints(caller_scope, &recv.place, cur, Some(self));
         Some(Constraints::new())
     }
@@ -439,11 +768,8 @@ impl<'a> InterpPass<'a> {
     /// resolving it generically via `resolve_arg` + `flatten_all` sidesteps
     /// needing to understand any specific iterator/adapter shape.
     ///
-    /// Deliberately approximate for BTreeMap: a flattened bag doesn't
-    /// distinguish which piece came from a key vs. a value, so both slots
-    /// get the same flattened blob rather than a precise per-item split.
-    /// Acceptable here - FSA only needs to know *what's reachable*, not
-    /// which key maps to which value.
+    /// For a map, the `(K, V)` pairs are split per slot (see
+    /// `seed_slots`), so the key slot only gets what was in key position.
     fn stub_from_iter(
         &self,
         ctxt: &mut Context,
@@ -459,16 +785,46 @@ impl<'a> InterpPass<'a> {
             }
             None => Constraints::new(),
         };
-        let flattened = ctxt.flatten_all(&iterable, caller_scope, Some(self));
-
         let mut cur = Constraints::from(self.fresh_collection_constraint(recv));
-        cur.write_field(vec![recv.key_field.clone()], flattened.clone());
-        if let Some(val_field) = &recv.val_field {
-            cur.write_field(vec![val_field.clone()], flattened);
-        }
+        self.seed_slots(ctxt, caller_scope, recv, &mut cur, &iterable);
         Some(cur)
     }
 
+    /// Fills a fresh collection's slots from `source`, what `from_iter` or
+    /// a seeded constructor was passed, and returns the key (element)
+    /// slot's contents. A map's key slot gets what's reachable from the
+    /// first half of each `(K, V)` pair in `source`, its value slot the
+    /// second half; if no pair is found (an iterator we can't see into),
+    /// both get everything.
+    fn seed_slots(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        recv: &CollectionRecv,
+        cur: &mut Constraints,
+        source: &Constraints,
+    ) -> Constraints {
+        let flattened = ctxt.flatten_all(source, caller_scope, Some(self));
+        let Some(val_field) = &recv.val_field else {
+            cur.write_field(vec![recv.key_field.clone()], flattened.clone());
+            return flattened;
+        };
+
+        let mut keys = Constraints::new();
+        let mut vals = Constraints::new();
+        let found = collect_pairs(source, &mut |k, v| {
+            keys.append(ctxt.flatten_all(k, caller_scope, Some(self)));
+            vals.append(ctxt.flatten_all(v, caller_scope, Some(self)));
+        });
+        if !found {
+            keys = flattened.clone();
+            vals = flattened;
+        }
+        cur.write_field(vec![recv.key_field.clone()], keys.clone());
+        cur.write_field(vec![val_field.clone()], vals);
+        keys
+    }
+
     /// `get`/`first`/`last` all return `Option<...>` wrapping the element -
     /// read the element slot, then wrap it the same way `next()` does.
     /// BTreeSet::get returns the element itself; BTreeMap::get returns the
@@ -560,7 +916,11 @@ impl<'a> InterpPass<'a> {
     /// straight from the callee's own signature (rather than fabricating
     /// one) so that anything downstream that keys off Option's identity -
     /// e.g. `wrapper_kind` - still recognizes it correctly.
-    fn wrap_in_option(&self, sig: &PolyFnSig, inner: Constraints) -> Option<Constraints> {
+    pub(crate) fn wrap_in_option(
+        &self,
+        sig: &PolyFnSig,
+        inner: Constraints,
+    ) -> Option<Constraints> {
         let output_ty = sig.value.output();
         let (adtdef, genargs) = match output_ty.kind() {
             TyKind::RigidTy(RigidTy::Adt(adtdef, genargs)) => (adtdef, genargs),
@@ -598,6 +958,376 @@ impl<'a> InterpPass<'a> {
         )
     }
 
+    // ---------- Vec/VecDeque/HashMap/HashSet handlers ----------
+
+    /// Summaries for `Vec`/`VecDeque`/`HashMap`/`HashSet` (and slices
+    /// borrowed from a `Vec`). Same synthetic layout as the btree stubs -
+    /// element (or key) slot in field 0, value slot in field 1 for maps - but
+    /// every write is a weak update (`add_to_slot`), since one slot stands
+    /// for all of the collection's elements at once.
+    ///
+    /// Anything that hands out `&mut` access to elements (`get_mut`,
+    /// `iter_mut`, `IndexMut`, `entry`, `retain` on maps, ...) widens the slot
+    /// to whatever its declared type allows: writes through the returned
+    /// reference never come back through here to be tracked. Except for a
+    /// `Vec`/`VecDeque` whose elements all came from known allocation sites
+    /// (`push`es and the modeled constructors, see `heap`): there a `&mut`
+    /// to one element (or the slice) is tracked instead, and reads see the
+    /// sites' objects. Methods not listed are handled conservatively instead
+    /// of panicking like the btree stubs do, by assuming every non-scalar
+    /// argument ends up inside.
+    fn stub_std_collection(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        recv: &CollectionRecv,
+        args: &Vec<Operand>,
+        method: &str,
+    ) -> Result<Option<Constraints>, Error> {
+        let sig = fndef.fn_sig();
+        let mut cur = ctxt
+            .get_constraints(caller_scope, local_decls, &recv.place, false, Some(self))
+            .unwrap_or_else(|| Constraints::from(self.fresh_collection_constraint(recv)));
+
+        let arg = |ctxt: &Context, idx: usize| -> Constraints {
+            match args.get(idx) {
+                Some(op) => {
+                    self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false)
+                }
+                None => Constraints::new(),
+            }
+        };
+        // what `get` returns: the element, or the map's value
+        let elem_field = recv.val_field.as_ref().unwrap_or(&recv.key_field);
+        let is_seq = matches!(recv.kind, WrapperKind::Vec | WrapperKind::VecDeque);
+        let elem_ty = match &recv.key_field {
+            ProjectionElem::Field(_, ty) => Some(*ty),
+            _ => None,
+        };
+        let sites = if is_seq { heap_sites(&cur) } else { None };
+        if let Some(sites) = &sites {
+            let stored = self.heap_read(caller_scope, sites, elem_ty);
+            self.add_to_slot(ctxt, caller_scope, &mut cur, &recv.key_field, stored);
+        }
+
+        let (result, modified) = match method {
+            "push" | "push_back" | "push_front" => {
+                let elem = arg(ctxt, 1);
+                let site = self.heap_alloc(term_span, elem_ty, elem.clone());
+                cur = tag_site(&cur, site);
+                self.add_to_slot(ctxt, caller_scope, &mut cur, &recv.key_field, elem);
+                (Ok(Some(Constraints::new())), true)
+            }
+            "insert" => match &recv.val_field {
+                // HashMap::insert(k, v) -> Option<V>, the value it replaced
+                Some(val_field) => {
+                    let old = ctxt.step_field(caller_scope, &cur, val_field, Some(self));
+                    let (key, val) = (arg(ctxt, 1), arg(ctxt, 2));
+                    self.add_to_slot(ctxt, caller_scope, &mut cur, &recv.key_field, key);
+                    self.add_to_slot(ctxt, caller_scope, &mut cur, val_field, val);
+                    (self.return_elem(&sig, old), true)
+                }
+                // Vec/VecDeque::insert(index, element), HashSet::insert(value)
+                None => {
+                    let elem = arg(ctxt, if is_seq { 2 } else { 1 });
+                    if is_seq {
+                        let site = self.heap_alloc(term_span, elem_ty, elem.clone());
+                        cur = tag_site(&cur, site);
+                    }
+                    self.add_to_slot(ctxt, caller_scope, &mut cur, &recv.key_field, elem);
+                    (self.retty_fallback_from_poly(sig), true)
+                }
+            },
+            "extend" | "append" | "extend_from_slice" | "extend_from_within" | "resize"
+            | "resize_with" => {
+                self.absorb_args(
+                    ctxt,
+                    caller_scope,
+                    term_span,
+                    local_decls,
+                    recv,
+                    &mut cur,
+                    args,
+                );
+                (self.retty_fallback_from_poly(sig), true)
+            }
+
+            "get_mut" | "get_unchecked_mut" | "first_mut" | "last_mut" | "front_mut"
+            | "back_mut" | "index_mut" => {
+                match &sites {
+                    Some(sites) => self.heap_ptr_result(sites.clone()),
+                    None => self.widen_slot(ctxt, caller_scope, &mut cur, elem_field),
+                }
+                let elem = ctxt.step_field(caller_scope, &cur, elem_field, Some(self));
+                (self.return_elem(&sig, elem), sites.is_none())
+            }
+            "iter_mut" | "values_mut" => {
+                self.widen_slot(ctxt, caller_scope, &mut cur, elem_field);
+                let iter = self.stub_collection_iter(ctxt, caller_scope, recv, &cur, method);
+                (Ok(Some(iter)), true)
+            }
+            // the slice keeps the sites, so its own accessors are tracked
+            // too; this covers a built-in `slice[i] = x`
+            "deref_mut" | "as_mut_slice" | "as_mut" | "borrow_mut" | "make_contiguous" => {
+                match &sites {
+                    Some(sites) => self.heap_ptr_result(sites.clone()),
+                    None => self.widen_slot(ctxt, caller_scope, &mut cur, elem_field),
+                }
+                (Ok(Some(cur.clone())), sites.is_none())
+            }
+            "entry" => {
+                // whatever `or_insert`/`and_modify`/... end up writing
+                let key = arg(ctxt, 1);
+                self.add_to_slot(ctxt, caller_scope, &mut cur, &recv.key_field, key);
+                self.widen_slot(ctxt, caller_scope, &mut cur, elem_field);
+                (self.retty_fallback_from_poly(sig), true)
+            }
+            // the closure gets `&mut V` for maps, `&mut T` for retain_mut
+            "retain" | "retain_mut" if recv.kind.is_map() || method == "retain_mut" => {
+                self.widen_slot(ctxt, caller_scope, &mut cur, elem_field);
+                (self.retty_fallback_from_poly(sig), true)
+            }
+
+            "get" | "get_unchecked" | "first" | "last" | "front" | "back" | "pop" | "pop_front"
+            | "pop_back" | "remove" | "swap_remove" | "swap_remove_front" | "swap_remove_back"
+            | "take" | "index" => {
+                let elem = ctxt.step_field(caller_scope, &cur, elem_field, Some(self));
+                (self.return_elem(&sig, elem), false)
+            }
+            "iter" | "into_iter" | "drain" | "keys" | "values" | "into_keys" | "into_values" => {
+                let iter = self.stub_collection_iter(ctxt, caller_scope, recv, &cur, method);
+                (Ok(Some(iter)), false)
+            }
+            "deref" | "as_slice" | "as_ref" | "borrow" | "clone" => (Ok(Some(cur.clone())), false),
+            "len"
+            | "is_empty"
+            | "capacity"
+            | "contains"
+            | "contains_key"
+            | "clear"
+            | "truncate"
+            | "reserve"
+            | "reserve_exact"
+            | "shrink_to"
+            | "shrink_to_fit"
+            | "retain"
+            | "sort"
+            | "sort_unstable"
+            | "sort_by"
+            | "sort_by_key"
+            | "sort_unstable_by"
+            | "sort_unstable_by_key"
+            | "reverse"
+            | "swap"
+            | "dedup"
+            | "dedup_by"
+            | "dedup_by_key"
+            | "rotate_left"
+            | "rotate_right"
+            | "eq"
+            | "ne"
+            | "hash"
+            | "fmt"
+            | "hasher" => (self.retty_fallback_from_poly(sig), false),
+
+            _ => {
+                debug!(
+                    "stdlib_stub: no summary for {:?}::{}, assuming its args flow in",
+                    recv.adtdef, method
+                );
+                self.absorb_args(
+                    ctxt,
+                    caller_scope,
+                    term_span,
+                    local_decls,
+                    recv,
+                    &mut cur,
+                    args,
+                );
+                if method.contains("mut") {
+                    self.widen_slot(ctxt, caller_scope, &mut cur, elem_field);
+                }
+                (self.retty_fallback_from_poly(sig), true)
+            }
+        };
+
+        if modified {
+            self.note_cell_store(ctxt, caller_scope, &recv.place, &cur);
+            self.note_heap_store(ctxt, caller_scope, &recv.place, &cur);
+            ctxt.set_scoped_constraints(caller_scope, &recv.place, cur, Some(self));
+        }
+        result
+    }
+
+    /// `Vec::new()`, `HashMap::with_capacity(n)`, `Vec::from([a, b])`, `vec![a, b]`
+    /// (`into_vec`), ... - a fresh synthetic collection, holding whatever
+    /// the source argument reaches when `seeded`.
+    fn stub_collection_ctor(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        recv: &CollectionRecv,
+        args: &Vec<Operand>,
+        seeded: bool,
+    ) -> Option<Constraints> {
+        let mut cur = Constraints::from(self.fresh_collection_constraint(recv));
+        let mut stored = Constraints::new();
+        if seeded {
+            let source = match args.get(0) {
+                Some(op) => {
+                    self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false)
+                }
+                None => Constraints::new(),
+            };
+            stored = self.seed_slots(ctxt, caller_scope, recv, &mut cur, &source);
+        }
+
+        // A `Vec`/`VecDeque`'s elements are this call site's heap object
+        // (see `heap`). The field types here are the ctor's generic ones, so
+        // the object's type is left to the accesses that read it.
+        if matches!(recv.kind, WrapperKind::Vec | WrapperKind::VecDeque) {
+            let site = self.heap_alloc(term_span, None, stored);
+            cur = tag_site(&cur, site);
+        }
+        Some(cur)
+    }
+
+    /// The synthetic iterator for `iter`/`drain`/`keys`/... - like
+    /// `stub_make_iter`, except `keys`/`values` carry just that slot rather
+    /// than the `(key, value)` pair.
+    fn stub_collection_iter(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        recv: &CollectionRecv,
+        cur: &Constraints,
+        method: &str,
+    ) -> Constraints {
+        let key = ctxt.step_field(caller_scope, cur, &recv.key_field, Some(self));
+        let elem = match &recv.val_field {
+            None => key,
+            Some(_) if matches!(method, "keys" | "into_keys") => key,
+            Some(val_field) => {
+                let val = ctxt.step_field(caller_scope, cur, val_field, Some(self));
+                if matches!(method, "values" | "values_mut" | "into_values") {
+                    val
+                } else {
+                    Constraints::from(Constraint::new(
+                        None,
+                        Some(RunningConstraint::Tuple(vec![key, val])),
+                    ))
+                }
+            }
+        };
+
+        let fields: ADTFields = ADTFields::from([(adt_field_idx(&recv.key_field), elem)]);
+        Constraints::from(Constraint::new(
+            None,
+            Some(RunningConstraint::Adt(
+                recv.adtdef.clone(),
+                recv.genargs.clone(),
+                None,
+                fields,
+            )),
+        ))
+    }
+
+    /// Weak update: `new` joins whatever the slot already holds.
+    pub(crate) fn add_to_slot(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        cur: &mut Constraints,
+        field: &ProjectionElem,
+        new: Constraints,
+    ) {
+        let mut slot = ctxt.step_field(caller_scope, cur, field, Some(self));
+        slot.append(new);
+        cur.write_field(vec![field.clone()], slot);
+    }
+
+    /// Adds everything the slot's declared type could hold, the same
+    /// widening `retty_fallback_from_poly` does for return values.
+    fn widen_slot(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        cur: &mut Constraints,
+        field: &ProjectionElem,
+    ) {
+        let ProjectionElem::Field(_, ty) = field else {
+            return;
+        };
+        let (_, widened) =
+            self.converter
+                .convert_ty(&Location::unknown(), ty, Some(caller_scope), Some(self));
+        self.add_to_slot(ctxt, caller_scope, cur, field, Constraints::from(widened));
+    }
+
+    /// Everything reachable from the non-receiver, non-scalar arguments,
+    /// into every slot.
+    fn absorb_args(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        recv: &CollectionRecv,
+        cur: &mut Constraints,
+        args: &Vec<Operand>,
+    ) {
+        for op in args.iter().skip(1) {
+            if is_scalar(op, local_decls) {
+                continue;
+            }
+
+            let resolved =
+                self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false);
+            let flattened = ctxt.flatten_all(&resolved, caller_scope, Some(self));
+            self.add_to_slot(ctxt, caller_scope, cur, &recv.key_field, flattened.clone());
+            if let Some(val_field) = &recv.val_field {
+                self.add_to_slot(ctxt, caller_scope, cur, val_field, flattened);
+            }
+        }
+    }
+
+    /// `elem` shaped like the callee's return type: wrapped for
+    /// `Option<T>`, the signature's own answer for `bool` (`HashSet::remove`),
+    /// and as-is otherwise (`Vec::remove`, `Index::index`).
+    fn return_elem(
+        &self,
+        sig: &PolyFnSig,
+        elem: Constraints,
+    ) -> Result<Option<Constraints>, Error> {
+        match sig.value.output().kind() {
+            TyKind::RigidTy(RigidTy::Adt(adtdef, _))
+                if self.converter.wrapper_kind(&adtdef) == Some(WrapperKind::Option) =>
+            {
+                match self.wrap_in_option(sig, elem) {
+                    Some(wrapped) => Ok(Some(wrapped)),
+                    None => self.retty_fallback_from_poly(sig.clone()),
+                }
+            }
+            TyKind::RigidTy(RigidTy::Bool) => self.retty_fallback_from_poly(sig.clone()),
+            _ => Ok(Some(elem)),
+        }
+    }
+
+    fn is_synthetic_collection(&self, cur: &Constraints) -> bool {
+        cur.inner.iter().any(|c| match &c.cfc {
+            Some(RunningConstraint::Adt(adtdef, _, _, _)) => self
+                .converter
+                .wrapper_kind(adtdef)
+                .is_some_and(|k| k.is_collection()),
+            _ => false,
+        })
+    }
+
     // ---------- precondition-check / UB-check helpers ----------
 
     /// True for the compiler-generated "precondition check" / UB-check
@@ -671,10 +1401,42 @@ impl<'a> InterpPass<'a> {
             _ => return None,
         };
 
+        // the pointee is also this call site's heap object, see `heap`
+        let pointee_ty = genargs.0.first().and_then(|a| a.ty()).copied();
+        let site = self.heap_alloc(term_span, pointee_ty, inner.clone());
+
         let fields: ADTFields = ADTFields::from([(0, inner)]);
-        Some(Constraints::from(Constraint::new(
-            None,
-            Some(RunningConstraint::Adt(adtdef, genargs, None, fields)),
-        )))
+        Some(tag_site(
+            &Constraints::from(Constraint::new(
+                None,
+                Some(RunningConstraint::Adt(adtdef, genargs, None, fields)),
+            )),
+            site,
+        ))
+    }
+}
+
+/// Calls `f` with the two halves of every 2-tuple in `source`, looking
+/// through pointers, lists and Adt fields (an array, a `Vec`, an iterator
+/// adapter's state) but not into the pairs themselves. False if there
+/// were none.
+fn collect_pairs(source: &Constraints, f: &mut impl FnMut(&Constraints, &Constraints)) -> bool {
+    let mut found = false;
+    for c in source.inner.iter() {
+        found |= match &c.cfc {
+            Some(RunningConstraint::Tuple(parts)) if parts.len() == 2 => {
+                f(&parts[0], &parts[1]);
+                true
+            }
+            Some(RunningConstraint::Ptr(inner) | RunningConstraint::List(inner)) => {
+                collect_pairs(&Constraints::from((**inner).clone()), f)
+            }
+            Some(RunningConstraint::Idk(inner)) => collect_pairs(inner, f),
+            Some(RunningConstraint::Adt(_, _, _, fields)) => fields
+                .values()
+                .fold(false, |found, field| collect_pairs(field, f) || found),
+            _ => false,
+        };
     }
+    found
 }
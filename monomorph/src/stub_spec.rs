@@ -0,0 +1,412 @@
+//! Declarative stubs, loaded at startup, for functions the interpreter
+//! shouldn't (or can't) walk into.
+//!
+//! A stub file is TOML, a list of `[[stub]]` tables:
+//!
+//! ```toml
+//! [[stub]]
+//! path = "*::registry::Registry::register"
+//! effects = ["insert arg1 into arg0"]
+//! returns = "fallback"
+//!
+//! [[stub]]
+//! path = "*::registry::Registry::lookup"
+//! returns = "some elem arg0"
+//! ```
+//!
+//! `path` is matched against the callee's full name (as `FnDef::name()`
+//! prints it, minus any trailing `::<..>` generic args), where `*` matches
+//! any run of characters. Files given with `--stubs` are tried first, in
+//! order, then the shipped defaults in `stubs/std.toml`; the first matching
+//! `[[stub]]` wins, so a user file can override a default.
+//!
+//! `returns` is an expression over the call's argument constraints:
+//!
+//! - `fallback` - everything the declared return type allows (the default)
+//! - `unit` - nothing
+//! - `argN`, or `identity` for `arg0`
+//! - `elem X` - field 0 of `X`, which is where the built-in collection stubs
+//!   keep their elements; `field K X` for any other field
+//! - `flatten X` - everything reachable from `X`, whatever its layout
+//! - `some X` - `X` wrapped in the `Some` of the declared `Option` return type
+//!
+//! `effects` run before `returns` is evaluated, each one of:
+//!
+//! - `insert X into argN` - joins `X` into field 0 of what `argN` points to
+//!   (`insert X into field K argN` for another field). Weak, like the
+//!   built-in collection stubs: nothing already there is dropped.
+//!
+//! Stubs don't need to be precise, only sound: a `returns` that can miss a
+//! trait object the real function could return makes the analysis unsound.
+//! When in doubt, `flatten` or `fallback`.
+
+use rustc_public::mir::{LocalDecl, Operand, ProjectionElem};
+use rustc_public::ty::{FnDef, GenericArgs, Span, Ty};
+
+use crate::Context;
+use crate::InterpPass;
+use crate::constraints::{Constraints, VOID};
+use crate::error::Error;
+
+use log::debug;
+
+use std::fs;
+
+const SHIPPED: &str = include_str!("../stubs/std.toml");
+const SHIPPED_NAME: &str = "stubs/std.toml";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expr {
+    Fallback,
+    Unit,
+    Arg(usize),
+    Field(usize, Box<Expr>),
+    Flatten(Box<Expr>),
+    Some(Box<Expr>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Insert {
+        value: Expr,
+        arg: usize,
+        field: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct StubSpec {
+    pub path: String,
+    pub effects: Vec<Effect>,
+    pub returns: Expr,
+    /// file it came from, for debug output
+    pub origin: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StubSpecs {
+    specs: Vec<StubSpec>,
+}
+
+impl StubSpecs {
+    /// Just the shipped defaults.
+    pub fn shipped() -> StubSpecs {
+        StubSpecs {
+            specs: parse(SHIPPED, SHIPPED_NAME).expect("shipped stubs/std.toml is malformed"),
+        }
+    }
+
+    /// `files` in order, then the shipped defaults.
+    pub fn load(files: &[String]) -> Result<StubSpecs, Error> {
+        let mut specs = Vec::new();
+        for file in files {
+            let text = fs::read_to_string(file)
+                .map_err(|e| Error::StubSpec(file.clone(), e.to_string()))?;
+            specs.extend(parse(&text, file)?);
+        }
+        specs.extend(Self::shipped().specs);
+        Ok(StubSpecs { specs })
+    }
+
+    pub fn find(&self, name: &str) -> Option<&StubSpec> {
+        self.specs.iter().find(|s| glob_match(&s.path, name))
+    }
+}
+
+pub fn parse(text: &str, origin: &str) -> Result<Vec<StubSpec>, Error> {
+    let err = |msg: String| Error::StubSpec(origin.to_string(), msg);
+
+    let table: toml::Table = text
+        .parse()
+        .map_err(|e: toml::de::Error| err(e.to_string()))?;
+
+    let mut specs = Vec::new();
+    for (key, value) in &table {
+        if key != "stub" {
+            return Err(err(format!("unknown top-level key {:?}", key)));
+        }
+        let Some(stubs) = value.as_array() else {
+            return Err(err(
+                "`stub` must be an array of tables ([[stub]])".to_string()
+            ));
+        };
+
+        for stub in stubs {
+            let Some(stub) = stub.as_table() else {
+                return Err(err(
+                    "`stub` must be an array of tables ([[stub]])".to_string()
+                ));
+            };
+            let Some(path) = stub.get("path").and_then(|p| p.as_str()) else {
+                return Err(err("[[stub]] without a `path`".to_string()));
+            };
+            let ctx = |msg: String| err(format!("{}: {}", path, msg));
+
+            if let Some(k) = stub
+                .keys()
+                .find(|k| !matches!(k.as_str(), "path" | "returns" | "effects"))
+            {
+                return Err(ctx(format!("unknown key {:?}", k)));
+            }
+
+            let returns = match stub.get("returns") {
+                None => Expr::Fallback,
+                Some(r) => {
+                    let r = r
+                        .as_str()
+                        .ok_or_else(|| ctx("`returns` must be a string".into()))?;
+                    parse_expr(r).map_err(ctx)?
+                }
+            };
+
+            let mut effects = Vec::new();
+            if let Some(es) = stub.get("effects") {
+                let es = es
+                    .as_array()
+                    .ok_or_else(|| ctx("`effects` must be an array of strings".into()))?;
+                for e in es {
+                    let e = e
+                        .as_str()
+                        .ok_or_else(|| ctx("`effects` must be an array of strings".into()))?;
+                    effects.push(parse_effect(e).map_err(ctx)?);
+                }
+            }
+
+            specs.push(StubSpec {
+                path: path.to_string(),
+                effects,
+                returns,
+                origin: origin.to_string(),
+            });
+        }
+    }
+
+    Ok(specs)
+}
+
+pub fn parse_expr(s: &str) -> Result<Expr, String> {
+    let tokens: Vec<&str> = s.split_whitespace().collect();
+    let (expr, rest) = expr_tokens(&tokens)?;
+    if !rest.is_empty() {
+        return Err(format!("trailing {:?} in {:?}", rest.join(" "), s));
+    }
+    Ok(expr)
+}
+
+fn parse_effect(s: &str) -> Result<Effect, String> {
+    let tokens: Vec<&str> = s.split_whitespace().collect();
+    let Some((&"insert", rest)) = tokens.split_first() else {
+        return Err(format!(
+            "unknown effect {:?}, expected `insert X into argN`",
+            s
+        ));
+    };
+    let (value, rest) = expr_tokens(rest)?;
+    let Some((&"into", rest)) = rest.split_first() else {
+        return Err(format!("expected `into` in {:?}", s));
+    };
+    let (field, rest) = match rest {
+        ["field", k, rest @ ..] => (
+            k.parse::<usize>()
+                .map_err(|_| format!("bad field index {:?} in {:?}", k, s))?,
+            rest,
+        ),
+        _ => (0, rest),
+    };
+    let arg = match rest {
+        [a] => arg_index(a).ok_or_else(|| format!("expected argN, got {:?} in {:?}", a, s))?,
+        _ => return Err(format!("expected a single argN after `into` in {:?}", s)),
+    };
+    Ok(Effect::Insert { value, arg, field })
+}
+
+fn expr_tokens<'t>(tokens: &'t [&'t str]) -> Result<(Expr, &'t [&'t str]), String> {
+    let Some((first, rest)) = tokens.split_first() else {
+        return Err("expected an expression".to_string());
+    };
+    let boxed = |rest| expr_tokens(rest).map(|(e, r)| (Box::new(e), r));
+
+    match *first {
+        "fallback" => Ok((Expr::Fallback, rest)),
+        "unit" => Ok((Expr::Unit, rest)),
+        "identity" => Ok((Expr::Arg(0), rest)),
+        "elem" => boxed(rest).map(|(e, r)| (Expr::Field(0, e), r)),
+        "flatten" => boxed(rest).map(|(e, r)| (Expr::Flatten(e), r)),
+        "some" => boxed(rest).map(|(e, r)| (Expr::Some(e), r)),
+        "field" => {
+            let Some((k, rest)) = rest.split_first() else {
+                return Err("expected a field index after `field`".to_string());
+            };
+            let k = k
+                .parse::<usize>()
+                .map_err(|_| format!("bad field index {:?}", k))?;
+            boxed(rest).map(|(e, r)| (Expr::Field(k, e), r))
+        }
+        t => match arg_index(t) {
+            Some(n) => Ok((Expr::Arg(n), rest)),
+            None => Err(format!("unknown expression {:?}", t)),
+        },
+    }
+}
+
+fn arg_index(t: &str) -> Option<usize> {
+    t.strip_prefix("arg")?.parse().ok()
+}
+
+/// `*` matches any run of characters (including `::`), everything else
+/// literally.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // no `*` at all
+        return rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+impl<'a> InterpPass<'a> {
+    /// Some(result) if a `[[stub]]` covers the callee. Tried before
+    /// `stub_higher_order` and `stdlib_stub`, so a stub file can override
+    /// any built-in model.
+    pub fn stub_spec_call(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let spec = self.stub_specs.find(&Self::callee_path(fndef))?;
+        Some(self.apply_stub_spec(
+            ctxt,
+            caller_scope,
+            term_span,
+            local_decls,
+            fndef,
+            genargs,
+            args,
+            spec,
+        ))
+    }
+
+    /// Applies `spec` to a call: runs its effects, then evaluates `returns`.
+    pub fn apply_stub_spec(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        _genargs: &GenericArgs,
+        args: &Vec<Operand>,
+        spec: &StubSpec,
+    ) -> Result<Option<Constraints>, Error> {
+        debug!(
+            "STUB SPEC {} ({}) for {}",
+            spec.path,
+            spec.origin,
+            fndef.0.name()
+        );
+
+        for effect in &spec.effects {
+            match effect {
+                Effect::Insert { value, arg, field } => {
+                    let Some(Operand::Copy(place) | Operand::Move(place)) = args.get(*arg) else {
+                        debug!("stub spec: arg{} isn't a place, skipping insert", arg);
+                        continue;
+                    };
+                    let new = self
+                        .eval_spec_expr(
+                            ctxt,
+                            caller_scope,
+                            term_span,
+                            local_decls,
+                            fndef,
+                            args,
+                            value,
+                        )?
+                        .unwrap_or_default();
+                    let Some(mut cur) =
+                        ctxt.get_constraints(caller_scope, local_decls, place, false, Some(self))
+                    else {
+                        continue;
+                    };
+                    let field = ProjectionElem::Field(*field, Ty::bool_ty());
+                    self.add_to_slot(ctxt, caller_scope, &mut cur, &field, new);
+                    self.note_cell_store(ctxt, caller_scope, place, &cur);
+                    self.note_heap_store(ctxt, caller_scope, place, &cur);
+                    ctxt.set_scoped_constraints(caller_scope, place, cur, Some(self));
+                }
+            }
+        }
+
+        self.eval_spec_expr(
+            ctxt,
+            caller_scope,
+            term_span,
+            local_decls,
+            fndef,
+            args,
+            &spec.returns,
+        )
+    }
+
+    fn eval_spec_expr(
+        &self,
+        ctxt: &Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        args: &Vec<Operand>,
+        expr: &Expr,
+    ) -> Result<Option<Constraints>, Error> {
+        let eval = |e: &Expr| -> Result<Constraints, Error> {
+            Ok(self
+                .eval_spec_expr(ctxt, caller_scope, term_span, local_decls, fndef, args, e)?
+                .unwrap_or_default())
+        };
+
+        let result = match expr {
+            Expr::Fallback => return self.retty_fallback_from_poly(fndef.fn_sig()),
+            Expr::Unit => Constraints::new(),
+            Expr::Arg(n) => match args.get(*n) {
+                Some(op) => {
+                    self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false)
+                }
+                None => Constraints::new(),
+            },
+            Expr::Field(k, e) => ctxt.step_field(
+                caller_scope,
+                &eval(e)?,
+                &ProjectionElem::Field(*k, Ty::bool_ty()),
+                Some(self),
+            ),
+            Expr::Flatten(e) => ctxt.flatten_all(&eval(e)?, caller_scope, Some(self)),
+            Expr::Some(e) => match self.wrap_in_option(&fndef.fn_sig(), eval(e)?) {
+                Some(wrapped) => wrapped,
+                // return type isn't an Option after all
+                None => return self.retty_fallback_from_poly(fndef.fn_sig()),
+            },
+        };
+        Ok(Some(result))
+    }
+}
+
+#[cfg(test)]
+mod stub_spec_tests;
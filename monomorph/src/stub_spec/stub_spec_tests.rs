@@ -0,0 +1,181 @@
+use super::*;
+
+#[test]
+fn test_shipped() {
+    let specs = StubSpecs::shipped();
+    let returns = |name: &str| specs.find(name).map(|s| s.returns.clone());
+    let elem_arg0 = Expr::Field(0, Box::new(Expr::Arg(0)));
+
+    assert_eq!(returns("core::convert::identity"), Some(Expr::Arg(0)));
+    assert_eq!(returns("std::mem::drop"), Some(Expr::Unit));
+    assert_eq!(returns("std::mem::forget"), Some(Expr::Unit));
+    assert_eq!(
+        returns("std::mem::ManuallyDrop::<T>::into_inner"),
+        Some(elem_arg0.clone())
+    );
+    assert_eq!(
+        returns("std::pin::Pin::<Ptr>::into_inner"),
+        Some(elem_arg0.clone())
+    );
+    assert_eq!(
+        returns("std::pin::Pin::<&'a T>::get_ref"),
+        Some(elem_arg0.clone())
+    );
+    assert_eq!(returns("std::time::Instant::now"), Some(Expr::Fallback));
+    assert_eq!(returns("std::thread::sleep"), Some(Expr::Unit));
+
+    assert_eq!(returns("std::hint::black_box"), None);
+    assert_eq!(returns("core::ptr::drop_in_place"), None);
+    assert_eq!(returns("std::pin::Pin::<Ptr>::into_inner_unchecked"), None);
+    assert_eq!(returns("alloc::vec::Vec::<T, A>::push"), None);
+}
+
+#[test]
+fn test_load_user_file_first() {
+    let dir = std::env::temp_dir().join(format!("stub_spec_tests_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("stubs.toml");
+    fs::write(
+        &file,
+        "[[stub]]\npath = \"*::mem::drop\"\nreturns = \"flatten arg0\"\n",
+    )
+    .unwrap();
+
+    let specs = StubSpecs::load(&[file.display().to_string()]).unwrap();
+    let drop = specs.find("std::mem::drop").unwrap();
+    assert_eq!(drop.returns, Expr::Flatten(Box::new(Expr::Arg(0))));
+    assert_eq!(drop.origin, file.display().to_string());
+    // the shipped ones are still there behind it
+    assert_eq!(
+        specs.find("core::convert::identity").map(|s| &s.origin[..]),
+        Some(SHIPPED_NAME)
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+    assert!(matches!(
+        StubSpecs::load(&[file.display().to_string()]),
+        Err(Error::StubSpec(..))
+    ));
+}
+
+#[test]
+fn test_parse_expr() {
+    assert_eq!(parse_expr("fallback"), Ok(Expr::Fallback));
+    assert_eq!(parse_expr("unit"), Ok(Expr::Unit));
+    assert_eq!(parse_expr("identity"), Ok(Expr::Arg(0)));
+    assert_eq!(parse_expr("arg2"), Ok(Expr::Arg(2)));
+    assert_eq!(
+        parse_expr("some elem arg0"),
+        Ok(Expr::Some(Box::new(Expr::Field(0, Box::new(Expr::Arg(0))))))
+    );
+    assert_eq!(
+        parse_expr("flatten field 3 arg1"),
+        Ok(Expr::Flatten(Box::new(Expr::Field(
+            3,
+            Box::new(Expr::Arg(1))
+        ))))
+    );
+}
+
+#[test]
+fn test_parse_expr_errors() {
+    assert!(parse_expr("").is_err());
+    assert!(parse_expr("elem").is_err());
+    assert!(parse_expr("field x arg0").is_err());
+    assert!(parse_expr("argx").is_err());
+    assert!(parse_expr("arg0 arg1").is_err());
+}
+
+#[test]
+fn test_parse_effect() {
+    assert_eq!(
+        parse_effect("insert arg1 into arg0"),
+        Ok(Effect::Insert {
+            value: Expr::Arg(1),
+            arg: 0,
+            field: 0,
+        })
+    );
+    assert_eq!(
+        parse_effect("insert flatten arg2 into field 1 arg0"),
+        Ok(Effect::Insert {
+            value: Expr::Flatten(Box::new(Expr::Arg(2))),
+            arg: 0,
+            field: 1,
+        })
+    );
+    assert!(parse_effect("remove arg1 from arg0").is_err());
+    assert!(parse_effect("insert arg1 arg0").is_err());
+    assert!(parse_effect("insert arg1 into self").is_err());
+}
+
+#[test]
+fn test_parse() {
+    let specs = parse(
+        "[[stub]]\n\
+         path = \"*::registry::Registry::register\"\n\
+         effects = [\"insert arg1 into arg0\"]\n\
+         \n\
+         [[stub]]\n\
+         path = \"*::registry::Registry::lookup\"\n\
+         returns = \"some elem arg0\"\n",
+        "test.toml",
+    )
+    .unwrap();
+    assert_eq!(specs.len(), 2);
+    assert_eq!(specs[0].returns, Expr::Fallback);
+    assert_eq!(specs[0].effects.len(), 1);
+    assert!(specs[1].effects.is_empty());
+    assert_eq!(specs[1].origin, "test.toml");
+}
+
+#[test]
+fn test_parse_errors() {
+    let bad = |text: &str| matches!(parse(text, "test.toml"), Err(Error::StubSpec(..)));
+    assert!(bad("[stub]\npath = \"f\"\n"));
+    assert!(bad("[[stubs]]\npath = \"f\"\n"));
+    assert!(bad("[[stub]]\nreturns = \"unit\"\n"));
+    assert!(bad("[[stub]]\npath = \"f\"\nreturn = \"unit\"\n"));
+    assert!(bad("[[stub]]\npath = \"f\"\nreturns = \"nothing\"\n"));
+    assert!(bad(
+        "[[stub]]\npath = \"f\"\neffects = \"insert arg1 into arg0\"\n"
+    ));
+}
+
+#[test]
+fn test_find_first_match_wins() {
+    let specs = StubSpecs {
+        specs: parse(
+            "[[stub]]\n\
+             path = \"app::opaque::wrap\"\n\
+             returns = \"identity\"\n\
+             \n\
+             [[stub]]\n\
+             path = \"*opaque::*\"\n\
+             returns = \"unit\"\n",
+            "test.toml",
+        )
+        .unwrap(),
+    };
+    assert_eq!(
+        specs.find("app::opaque::wrap").map(|s| &s.returns),
+        Some(&Expr::Arg(0))
+    );
+    assert_eq!(
+        specs.find("app::opaque::pick").map(|s| &s.returns),
+        Some(&Expr::Unit)
+    );
+    assert!(specs.find("app::main").is_none());
+}
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("std::mem::drop", "std::mem::drop"));
+    assert!(!glob_match("std::mem::drop", "std::mem::drop_in_place"));
+    assert!(glob_match("*::mem::drop", "core::mem::drop"));
+    assert!(glob_match("*", ""));
+    assert!(glob_match("a*b*c", "a::x::b::y::c"));
+    assert!(!glob_match("a*b*c", "a::c::b"));
+    assert!(glob_match("*Registry::*", "app::Registry::lookup"));
+    assert!(!glob_match("*Registry::*", "app::Registry"));
+}
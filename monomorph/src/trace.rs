@@ -0,0 +1,123 @@
+//! Runtime dispatch tracing, to check FSA results against what actually runs.
+//!
+//! With `--trace-dispatch`, the analysis run writes every call site's FSA set
+//! to `dispatch_sets`, and instead of devirtualizing, the rewriter
+//! instruments every dyn call: before the vtable jump, the receiver's vtable
+//! is compared against each CHA candidate's, and one
+//!
+//! ```text
+//! VERIFOPT_TRACE\t<site>\t<impl>
+//! ```
+//!
+//! line is written straight to stderr (bypassing test harness capture) for
+//! whichever matched. A vtable matching none of them (the same type's vtable
+//! emitted again in another codegen unit, say) gets its method slot compared
+//! against each candidate's impl fn instead, and only a fn none of them has
+//! is logged as `?`. `<site>` is `profile::site_key`'s
+//! `file:line:col` and `<impl>` the impl fn's `def_path_str`, the same
+//! strings `dispatch_sets` uses. Program behavior is otherwise unchanged -
+//! nothing gets devirtualized in this mode, since an unsound FSA result would
+//! make the rewritten call itself wrong.
+//!
+//! `verifopt-check dispatch_sets <trace>...` (src/bin/verifopt-check.rs) then
+//! reports every observed `(site, impl)` the analysis didn't predict.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+
+pub const TRACE_PREFIX: &str = "VERIFOPT_TRACE";
+pub const SETS_FILE: &str = "dispatch_sets";
+
+/// Logged in place of an impl when neither the receiver's vtable nor the fn
+/// in its method slot matched any CHA candidate's.
+pub const UNKNOWN_IMPL: &str = "?";
+
+pub fn trace_line(site: &str, impl_path: &str) -> String {
+    format!("{}\t{}\t{}\n", TRACE_PREFIX, site, impl_path)
+}
+
+/// One `site\timpl` line per FSA target, sorted, so the file diffs cleanly.
+pub fn write_sets(path: &str, sets: &BTreeMap<String, Vec<String>>) -> io::Result<()> {
+    let mut f = File::create(path)?;
+    for (site, impls) in sets {
+        let mut impls = impls.clone();
+        impls.sort();
+        for impl_path in impls {
+            writeln!(f, "{}\t{}", site, impl_path)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn read_sets(path: &str) -> io::Result<HashMap<String, HashSet<String>>> {
+    let mut sets: HashMap<String, HashSet<String>> = HashMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        if let Some((site, impl_path)) = line.split_once('\t') {
+            sets.entry(site.to_string())
+                .or_default()
+                .insert(impl_path.to_string());
+        }
+    }
+    Ok(sets)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Violation {
+    /// observed target missing from the site's FSA set - an FSA soundness bug
+    NotInFsa { site: String, impl_path: String },
+    /// the called fn is none of the CHA candidates' - CHA (or trait
+    /// collection) missed an impl
+    NotInCha { site: String },
+    /// a dyn call ran at a site the analysis never reached
+    Unanalyzed { site: String, impl_path: String },
+}
+
+/// Observed `(site, impl)` hit counts from one or more trace logs; anything
+/// not starting with `TRACE_PREFIX` (the program's own stderr) is ignored.
+pub fn read_trace<R: BufRead>(r: R, hits: &mut BTreeMap<(String, String), u64>) -> io::Result<()> {
+    for line in r.lines() {
+        let line = line?;
+        let mut fields = line.split('\t');
+        if fields.next() != Some(TRACE_PREFIX) {
+            continue;
+        }
+        let (Some(site), Some(impl_path)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        *hits
+            .entry((site.to_string(), impl_path.to_string()))
+            .or_default() += 1;
+    }
+    Ok(())
+}
+
+pub fn check(
+    sets: &HashMap<String, HashSet<String>>,
+    hits: &BTreeMap<(String, String), u64>,
+) -> Vec<(Violation, u64)> {
+    let mut out = Vec::new();
+
+    for ((site, impl_path), n) in hits {
+        let violation = match sets.get(site) {
+            None => Violation::Unanalyzed {
+                site: site.clone(),
+                impl_path: impl_path.clone(),
+            },
+            Some(_) if impl_path == UNKNOWN_IMPL => Violation::NotInCha { site: site.clone() },
+            Some(fsa) if !fsa.contains(impl_path) => Violation::NotInFsa {
+                site: site.clone(),
+                impl_path: impl_path.clone(),
+            },
+            Some(_) => continue,
+        };
+        out.push((violation, *n));
+    }
+
+    out.sort();
+    out
+}
+
+#[cfg(test)]
+mod trace_tests;
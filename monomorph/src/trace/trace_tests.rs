@@ -0,0 +1,107 @@
+use super::*;
+
+fn sets(entries: &[(&str, &str)]) -> HashMap<String, HashSet<String>> {
+    let mut sets: HashMap<String, HashSet<String>> = HashMap::new();
+    for (site, impl_path) in entries {
+        sets.entry(site.to_string())
+            .or_default()
+            .insert(impl_path.to_string());
+    }
+    sets
+}
+
+fn hits(log: &str) -> BTreeMap<(String, String), u64> {
+    let mut hits = BTreeMap::new();
+    read_trace(log.as_bytes(), &mut hits).unwrap();
+    hits
+}
+
+#[test]
+fn test_read_trace() {
+    let log = format!(
+        "{}program output\n{}{}",
+        trace_line("src/main.rs:3:5", "<Cat as Animal>::speak"),
+        trace_line("src/main.rs:3:5", "<Cat as Animal>::speak"),
+        trace_line("src/main.rs:9:1", UNKNOWN_IMPL),
+    );
+    let hits = hits(&log);
+    assert_eq!(
+        hits.get(&("src/main.rs:3:5".into(), "<Cat as Animal>::speak".into())),
+        Some(&2)
+    );
+    assert_eq!(
+        hits.get(&("src/main.rs:9:1".into(), UNKNOWN_IMPL.into())),
+        Some(&1)
+    );
+    assert_eq!(hits.len(), 2);
+}
+
+#[test]
+fn test_check_predicted() {
+    let sets = sets(&[
+        ("src/main.rs:3:5", "<Cat as Animal>::speak"),
+        ("src/main.rs:3:5", "<Dog as Animal>::speak"),
+    ]);
+    let hits = hits(&trace_line("src/main.rs:3:5", "<Dog as Animal>::speak"));
+    assert!(check(&sets, &hits).is_empty());
+}
+
+#[test]
+fn test_check_violations() {
+    let sets = sets(&[("src/main.rs:3:5", "<Cat as Animal>::speak")]);
+    let log = [
+        trace_line("src/main.rs:3:5", "<Dog as Animal>::speak"),
+        trace_line("src/main.rs:3:5", UNKNOWN_IMPL),
+        trace_line("src/main.rs:7:1", "<Cat as Animal>::speak"),
+    ]
+    .concat();
+    assert_eq!(
+        check(&sets, &hits(&log)),
+        vec![
+            (
+                Violation::NotInFsa {
+                    site: "src/main.rs:3:5".into(),
+                    impl_path: "<Dog as Animal>::speak".into(),
+                },
+                1
+            ),
+            (
+                Violation::NotInCha {
+                    site: "src/main.rs:3:5".into(),
+                },
+                1
+            ),
+            (
+                Violation::Unanalyzed {
+                    site: "src/main.rs:7:1".into(),
+                    impl_path: "<Cat as Animal>::speak".into(),
+                },
+                1
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_write_read_sets() {
+    let path = std::env::temp_dir().join(format!("verifopt_sets_{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut written = BTreeMap::new();
+    written.insert(
+        "src/main.rs:3:5".to_string(),
+        vec![
+            "<Dog as Animal>::speak".to_string(),
+            "<Cat as Animal>::speak".to_string(),
+        ],
+    );
+    write_sets(path, &written).unwrap();
+    let read = read_sets(path).unwrap();
+    let _ = std::fs::remove_file(path);
+    assert_eq!(
+        read,
+        sets(&[
+            ("src/main.rs:3:5", "<Cat as Animal>::speak"),
+            ("src/main.rs:3:5", "<Dog as Animal>::speak"),
+        ])
+    );
+}
@@ -1,18 +1,101 @@
+extern crate rustc_middle;
+
 use rustc_data_structures::fx::FxHashMap as HashMap;
 use rustc_public::ty::{
-    AssocContainer, AssocKind, FnDef, GenericArgs, ImplDef, ImplTrait, RigidTy, TyKind,
+    AssocContainer, AssocKind, ClauseKind, ExistentialPredicate, FnDef, GenericArgKind,
+    GenericArgs, ImplDef, ImplTrait, PredicateKind, RigidTy, TermKind, TraitDef, Ty, TyKind,
 };
-use rustc_public::{CrateDefItems, DefId};
+use rustc_public::{CrateDefItems, DefId, rustc_internal};
+
+use std::fmt;
+
+use crate::annotations;
 
 //use log::debug;
 
 pub struct TraitVal {}
 
+/// The Self type of an impl that isn't for an ADT, which `trait_structs`
+/// has no `DefId` to key by.
+#[derive(Debug, Clone)]
+pub enum SelfTyPat {
+    /// Mentions none of the impl's own params: `i32`, `&str`, `[u8]`,
+    /// `(A, B)`, `fn(u8)`, `dyn Other`.
+    Concrete(Ty),
+    /// Mentions some: `impl<T: Foo> Bar for T`, `for &T`, `for [T]`. Which
+    /// types it covers depends on bounds we don't check, so it's only ever
+    /// instantiated against a type that is already known to reach a call
+    /// site through a `dyn Bar` (see `InterpPass::resolve_blanket_impl`).
+    Blanket(Ty),
+    /// One rustc_public couldn't translate (`kind()` panicked on it). Kept
+    /// so CHA still has the impl's fns.
+    Unknown(Ty),
+}
+
+/// One impl for a non-ADT Self type.
+#[derive(Debug, Clone)]
+pub struct OtherImpl {
+    pub self_ty: SelfTyPat,
+    /// The impl's TraitRef's own GenericArgs, as in `trait_structs`
+    pub args: GenericArgs,
+    /// (AssocFnImpl, AssocFnDecl), with the trait's defaults filled in, as
+    /// in `struct_assoc_fns`
+    pub assoc_fns: Vec<(DefId, DefId)>,
+}
+
+/// Whether crates downstream of this one can add impls of a trait. Only a
+/// closed trait's implementors are all in `trait_structs` and
+/// `trait_other_impls` when this crate is a library, so only its call sites
+/// are rewritten there; an open one's keep the vtable call (see
+/// `start_verifopt`). For a binary every trait is effectively closed:
+/// nothing is downstream of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraitClass {
+    Open,
+    /// Not nameable outside this crate: private, `pub(crate)`, or `pub`
+    /// but in a module that isn't
+    Private,
+    /// `pub`, but with a supertrait that's closed itself (the sealed trait
+    /// pattern, `pub trait Sealed: private::Sealed`)
+    SealedBySupertrait(DefId),
+    /// `pub`, but with a method (the `DefId`) that has no default and whose
+    /// signature names a type that isn't nameable outside this crate, so no
+    /// other crate can write it
+    SealedBySignature(DefId),
+    /// `#[verifopt::closed]`: the user says so (see `annotations`)
+    Asserted,
+}
+
+impl TraitClass {
+    pub fn is_closed(&self) -> bool {
+        !matches!(self, TraitClass::Open)
+    }
+}
+
+impl fmt::Display for TraitClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TraitClass::Open => write!(f, "open"),
+            TraitClass::Private => write!(f, "closed (private)"),
+            TraitClass::SealedBySupertrait(sup) => {
+                write!(f, "closed (sealed by supertrait {})", sup.name())
+            }
+            TraitClass::SealedBySignature(method) => {
+                write!(f, "closed (sealed by signature of {})", method.name())
+            }
+            TraitClass::Asserted => write!(f, "closed (#[verifopt::closed])"),
+        }
+    }
+}
+
 pub struct TraitStore {
     // HashMap<Struct, Vec<Trait>>
     pub struct_traits: HashMap<DefId, Vec<DefId>>,
     // (CHA/RTA) HashMap<Trait, Vec<(Struct, TraitRef's own GenericArgs)>>
     pub trait_structs: HashMap<DefId, Vec<(DefId, GenericArgs)>>,
+    // (CHA/RTA) HashMap<Trait, Vec<impl for a primitive/ref/slice/tuple/fn
+    // ptr/dyn/blanket Self type>>
+    pub trait_other_impls: HashMap<DefId, Vec<OtherImpl>>,
     // HashMap<AssocFnDecl, Trait>
     pub assoc_fn_traits: HashMap<DefId, DefId>,
     // HashMap<(Struct, AssocFnDecl), Vec<AssocFnImpl>>
@@ -21,6 +104,10 @@ pub struct TraitStore {
     pub trait_fns: HashMap<DefId, Vec<DefId>>,
     // HashMap<Trait, Vec<AssocFnImpl>>
     pub default_impls: HashMap<DefId, Vec<DefId>>,
+    // HashMap<Trait, Vec<Supertrait, transitively>>
+    pub trait_supers: HashMap<DefId, Vec<DefId>>,
+    // HashMap<Trait, TraitClass>, for this crate's traits only
+    pub trait_classes: HashMap<DefId, TraitClass>,
 }
 
 impl TraitStore {
@@ -28,12 +115,151 @@ impl TraitStore {
         Self {
             struct_traits: HashMap::default(),
             trait_structs: HashMap::default(),
+            trait_other_impls: HashMap::default(),
             assoc_fn_traits: HashMap::default(),
             struct_assoc_fns: HashMap::default(),
             trait_fns: HashMap::default(),
             default_impls: HashMap::default(),
+            trait_supers: HashMap::default(),
+            trait_classes: HashMap::default(),
+        }
+    }
+
+    /// Whether no crate downstream of this one can implement `trait_defid`
+    /// (see `TraitClass`). Another crate's trait is open as far as this one
+    /// can tell.
+    pub fn is_closed(&self, trait_defid: &DefId) -> bool {
+        self.trait_classes
+            .get(trait_defid)
+            .is_some_and(TraitClass::is_closed)
+    }
+
+    /// Whether `sup` is a supertrait of `sub`, directly or further up, so a
+    /// `dyn sub` can be upcast to a `dyn sup` and its vtable has `sup`'s
+    /// methods in it. Traits from other crates aren't in `trait_supers`
+    /// (only this crate's trait decls are walked), so ask rustc for those.
+    pub fn is_supertrait(&self, sub: &DefId, sup: &DefId) -> bool {
+        match self.trait_supers.get(sub) {
+            Some(supers) => supers.contains(sup),
+            None => supertraits_of(&TraitDef(*sub)).contains(sup),
         }
     }
+
+    /// Whether some impl of `trait_defid` is for a Self type with the
+    /// impl's own params in it, so an ADT can implement it with no entry in
+    /// `struct_traits`.
+    pub fn has_blanket_impl(&self, trait_defid: &DefId) -> bool {
+        self.trait_other_impls
+            .get(trait_defid)
+            .is_some_and(|impls| {
+                impls
+                    .iter()
+                    .any(|i| matches!(i.self_ty, SelfTyPat::Blanket(_)))
+            })
+    }
+}
+
+/// Whether `ty` mentions a generic param (or anything else only an
+/// instantiation pins down, like a projection).
+pub fn mentions_param(ty: &Ty) -> bool {
+    let args_mention = |args: &GenericArgs| {
+        args.0.iter().any(|arg| match arg {
+            GenericArgKind::Type(t) => mentions_param(t),
+            _ => false,
+        })
+    };
+    match ty.kind() {
+        TyKind::Param(_) | TyKind::Alias(..) | TyKind::Bound(..) => true,
+        TyKind::RigidTy(rty) => match rty {
+            RigidTy::Ref(_, t, _)
+            | RigidTy::RawPtr(t, _)
+            | RigidTy::Slice(t)
+            | RigidTy::Array(t, _) => mentions_param(&t),
+            RigidTy::Tuple(ts) => ts.iter().any(mentions_param),
+            RigidTy::Adt(_, args)
+            | RigidTy::FnDef(_, args)
+            | RigidTy::Closure(_, args)
+            | RigidTy::Coroutine(_, args, ..) => args_mention(&args),
+            RigidTy::FnPtr(sig) => sig.value.inputs_and_output.iter().any(mentions_param),
+            RigidTy::Dynamic(preds, _) => preds.iter().any(|pred| match &pred.value {
+                ExistentialPredicate::Trait(trait_ref) => args_mention(&trait_ref.generic_args),
+                ExistentialPredicate::Projection(proj) => {
+                    args_mention(&proj.generic_args)
+                        || matches!(&proj.term, TermKind::Type(t) if mentions_param(t))
+                }
+                ExistentialPredicate::AutoTrait(_) => false,
+            }),
+            _ => false,
+        },
+    }
+}
+
+/// Every supertrait of `trait_def`, transitively: the trait bounds on its
+/// `Self` (`trait Sub: Super` is `where Self: Super`), and theirs.
+pub fn supertraits_of(trait_def: &TraitDef) -> Vec<DefId> {
+    let mut supers: Vec<DefId> = Vec::new();
+    let mut worklist = vec![*trait_def];
+    while let Some(cur) = worklist.pop() {
+        for (pred, _span) in TraitDef::declaration(&cur).predicates_of().predicates {
+            let PredicateKind::Clause(ClauseKind::Trait(trait_pred)) = pred else {
+                continue;
+            };
+            let on_self = matches!(
+                trait_pred.trait_ref.self_ty().kind(),
+                TyKind::Param(p) if p.index == 0
+            );
+            let sup = trait_pred.trait_ref.def_id;
+            if on_self && sup != *trait_def && !supers.contains(&sup.0) {
+                supers.push(sup.0);
+                worklist.push(sup);
+            }
+        }
+    }
+    supers
+}
+
+/// Whether this crate's item `def_id` can be named from outside it: `pub`
+/// all the way up, or re-exported.
+pub(crate) fn is_exported(def_id: DefId) -> bool {
+    rustc_middle::ty::tls::with(|tcx| {
+        let did = rustc_internal::internal(tcx, def_id);
+        did.as_local()
+            .is_none_or(|local| tcx.effective_visibilities(()).is_exported(local))
+    })
+}
+
+/// Whether this crate's trait `def_id` is annotated `#[verifopt::closed]`.
+fn is_asserted_closed(def_id: DefId) -> bool {
+    rustc_middle::ty::tls::with(|tcx| {
+        annotations::has_attr(
+            tcx,
+            rustc_internal::internal(tcx, def_id),
+            annotations::CLOSED,
+        )
+    })
+}
+
+/// Whether `ty` names an ADT of this crate's that isn't exported.
+fn mentions_private_adt(ty: &Ty) -> bool {
+    match ty.kind() {
+        TyKind::RigidTy(rty) => match rty {
+            RigidTy::Adt(adtdef, args) => {
+                !is_exported(adtdef.0)
+                    || args.0.iter().any(|arg| match arg {
+                        GenericArgKind::Type(t) => mentions_private_adt(t),
+                        _ => false,
+                    })
+            }
+            RigidTy::Ref(_, t, _)
+            | RigidTy::RawPtr(t, _)
+            | RigidTy::Slice(t)
+            | RigidTy::Array(t, _) => mentions_private_adt(&t),
+            RigidTy::Tuple(ts) => ts.iter().any(mentions_private_adt),
+            RigidTy::FnPtr(sig) => sig.value.inputs_and_output.iter().any(mentions_private_adt),
+            _ => false,
+        },
+        _ => false,
+    }
 }
 
 pub struct TraitCollectPass;
@@ -46,6 +272,7 @@ impl TraitCollectPass {
     pub fn run(&self, tstore: &mut TraitStore) {
         //debug!("\nDEFAULTS\n");
         self.collect_default_impls(tstore);
+        self.classify_traits(tstore);
         //debug!("\nOTHER IMPLS\n");
         self.collect_rest_impls(tstore);
     }
@@ -104,6 +331,11 @@ impl TraitCollectPass {
                 }
             }
 
+            let supers = supertraits_of(&trait_def);
+            if !supers.is_empty() {
+                tstore.trait_supers.insert(trait_def.0, supers);
+            }
+
             match tstore.default_impls.get(&trait_def.0) {
                 Some(_) => panic!("already set defaults for trait {:?}", &trait_def.0),
                 None => {
@@ -114,6 +346,52 @@ impl TraitCollectPass {
         }
     }
 
+    /// Fills in `trait_classes`: first whether each trait is closed on its
+    /// own (private, or a required method only this crate can write), then
+    /// whether one of its supertraits is, which a downstream impl would have
+    /// to implement as well.
+    fn classify_traits(&self, tstore: &mut TraitStore) {
+        for trait_def in rustc_public::all_trait_decls() {
+            let class = if is_asserted_closed(trait_def.0) {
+                TraitClass::Asserted
+            } else if !is_exported(trait_def.0) {
+                TraitClass::Private
+            } else {
+                let sealing_method = trait_def.associated_items().into_iter().find(|item| {
+                    matches!(item.kind, AssocKind::Fn { .. })
+                        && !FnDef(item.def_id.0).has_body()
+                        && FnDef(item.def_id.0)
+                            .fn_sig()
+                            .value
+                            .inputs_and_output
+                            .iter()
+                            .any(mentions_private_adt)
+                });
+                match sealing_method {
+                    Some(item) => TraitClass::SealedBySignature(item.def_id.0),
+                    None => TraitClass::Open,
+                }
+            };
+            tstore.trait_classes.insert(trait_def.0, class);
+        }
+
+        // `trait_supers` is transitive already, so one step is enough
+        let mut sealed = Vec::new();
+        for (trait_defid, class) in &tstore.trait_classes {
+            if class.is_closed() {
+                continue;
+            }
+            let closed_super = tstore
+                .trait_supers
+                .get(trait_defid)
+                .and_then(|supers| supers.iter().find(|sup| tstore.is_closed(sup)));
+            if let Some(sup) = closed_super {
+                sealed.push((*trait_defid, TraitClass::SealedBySupertrait(*sup)));
+            }
+        }
+        tstore.trait_classes.extend(sealed);
+    }
+
     fn collect_rest_impls(&self, tstore: &mut TraitStore) {
         for impl_def in rustc_public::all_trait_impls() {
             //debug!("\n###################");
@@ -132,7 +410,8 @@ impl TraitCollectPass {
             // legitimately returns None for it below - but that impl still
             // provides real, concrete method bodies (monomorphized per
             // instantiation), and this trait-level bookkeeping doesn't need
-            // to know which struct to record that.
+            // to know which struct to record that. It's recorded in
+            // `trait_other_impls` instead, with every other non-ADT impl.
             let mut assoc_fn_defids = self.get_assoc_fn_defids(&impl_def);
             //debug!("assoc_fn_defids: {:?}", assoc_fn_defids);
             //debug!("trait_fn_defids: {:?}", tstore.trait_fns.get(&trait_defid));
@@ -198,20 +477,31 @@ impl TraitCollectPass {
             }
 
             // Get Struct DefId - only needed for the struct-specific
-            // bookkeeping below; a blanket impl legitimately has none, in
-            // which case we skip just that part, not the trait-level
-            // registration above (already done, and independent of this).
-            let result = std::panic::catch_unwind(|| self.get_struct_defid(&trait_impl));
-            if result.is_err() {
-                continue;
-            }
-            let struct_defid;
-            if let Some(struct_defid_inner) = result.unwrap() {
-                struct_defid = struct_defid_inner;
-            } else {
-                //debug!("got a None struct_defid option (FIXME)");
-                continue;
-            }
+            // bookkeeping below; any other Self type (a primitive, a
+            // reference, a blanket impl's param, ...) legitimately has none,
+            // and goes in `trait_other_impls` instead, keyed by its type.
+            let struct_defid = match std::panic::catch_unwind(|| self.get_struct_defid(&trait_impl))
+            {
+                Ok(Some(struct_defid)) => struct_defid,
+                found => {
+                    let self_ty = trait_impl.value.self_ty();
+                    let self_ty = match found {
+                        Err(_) => SelfTyPat::Unknown(self_ty),
+                        Ok(_) if mentions_param(&self_ty) => SelfTyPat::Blanket(self_ty),
+                        Ok(_) => SelfTyPat::Concrete(self_ty),
+                    };
+                    tstore
+                        .trait_other_impls
+                        .entry(trait_defid)
+                        .or_default()
+                        .push(OtherImpl {
+                            self_ty,
+                            args: trait_impl.value.args().clone(),
+                            assoc_fns: assoc_fn_defids,
+                        });
+                    continue;
+                }
+            };
             //debug!("STRUCT: {:?}", struct_defid);
 
             // Add trait to list of traits that this struct impls
@@ -0,0 +1,165 @@
+//! `TypeId`s and `dyn Any`'s type tests.
+//!
+//! A `TypeId` value is an Adt constraint of `TypeId`'s own AdtDef that
+//! carries the type it identifies as its one generic arg (`TypeId` has no
+//! generics of its own, so nothing else reads them), or no generic args
+//! when it could be any type's. `TypeId::of::<T>()` (or the `type_id`
+//! intrinsic it's inlined to) makes a tagged one; `Any::type_id` through
+//! the vtable makes one per concrete type the receiver might be.
+//!
+//! `==`/`!=` on two of them is then a known `bool` when the tags decide it
+//! - both sides one and the same type, or no type in common - and unknown
+//! otherwise, so `interp_switchint` prunes the branch that can't be taken.
+//! `<dyn Any>::is::<T>()` gets the same answer straight from the receiver's
+//! concrete types (see `split_by_type`); `downcast_ref`/`downcast_mut`/
+//! `downcast` split the candidate set per branch and live in `dyn_error`
+//! with `dyn Error`'s.
+
+use rustc_public::mir::{LocalDecl, Operand};
+use rustc_public::ty::{FnDef, GenericArgKind, GenericArgs, RigidTy, Span, Ty, TyKind};
+
+use crate::Context;
+use crate::InterpPass;
+use crate::constraints::{ADTFields, Constraint, Constraints, RunningConstraint, TraitObjTy, VOID};
+use crate::dyn_error::concrete_adt;
+use crate::error::Error;
+use crate::interior::instantiated_sig;
+
+use log::debug;
+
+impl<'a> InterpPass<'a> {
+    /// Called from `stdlib_stub`: Some(result) for `TypeId::of`,
+    /// `Any::type_id` on a `dyn Any`, a `TypeId` comparison, or
+    /// `<dyn Any>::is`.
+    pub fn stub_type_id(
+        &self,
+        ctxt: &mut Context,
+        caller_scope: &VOID,
+        term_span: &Span,
+        local_decls: &[LocalDecl],
+        fndef: &FnDef,
+        genargs: &GenericArgs,
+        args: &Vec<Operand>,
+    ) -> Option<Result<Option<Constraints>, Error>> {
+        let path = Self::callee_path(fndef);
+        let method = Self::method_name(fndef);
+        let resolve = |ctxt: &Context, op: &Operand| {
+            self.resolve_arg(ctxt, term_span, caller_scope, &None, local_decls, op, false)
+        };
+
+        let result = if path.ends_with("TypeId::of") || path.ends_with("intrinsics::type_id") {
+            let out_ty = instantiated_sig(fndef, genargs)?.value.output();
+            Constraints::from(type_id_value(out_ty, Some(first_type_arg(genargs)?))?)
+        } else if path.ends_with("Any::type_id") {
+            let recv = args.first()?;
+            if !is_bare_any(recv.ty(local_decls).ok()?) {
+                return None;
+            }
+            let out_ty = instantiated_sig(fndef, genargs)?.value.output();
+            let mut ids = Constraints::new();
+            for leaf in resolve(ctxt, recv).inner.iter() {
+                let concrete = concrete_adt(leaf)
+                    .map(|(def, args)| Ty::from_rigid_kind(RigidTy::Adt(def, args)));
+                ids.push(type_id_value(out_ty, concrete)?);
+            }
+            if ids.inner.is_empty() {
+                ids.push(type_id_value(out_ty, None)?);
+            }
+            ids
+        } else if matches!(method.as_str(), "eq" | "ne")
+            && args.len() == 2
+            && is_type_id(args[0].ty(local_decls).ok()?)
+        {
+            let a = type_id_tags(&resolve(ctxt, &args[0]));
+            let b = type_id_tags(&resolve(ctxt, &args[1]));
+            let equal = match (a, b) {
+                (Some(a), Some(b)) if a.len() == 1 && a == b => Some(true),
+                (Some(a), Some(b)) if a.iter().all(|t| !b.contains(t)) => Some(false),
+                _ => None,
+            };
+            bool_value(equal.map(|eq| eq == (method == "eq")))
+        } else if method == "is" {
+            let recv = args.first()?;
+            if !self.is_dyn_object(recv.ty(local_decls).ok()?, TraitObjTy::is_any_trait) {
+                return None;
+            }
+            let target = first_type_arg(genargs)?;
+            let (hits, misses) = self.split_by_type(&resolve(ctxt, recv), target)?;
+            bool_value(match (hits.inner.is_empty(), misses.inner.is_empty()) {
+                (true, false) => Some(false),
+                (false, true) => Some(true),
+                _ => None,
+            })
+        } else {
+            return None;
+        };
+
+        debug!("TYPE ID {} -> {} disjuncts", path, result.inner.len());
+        Some(Ok(Some(result)))
+    }
+}
+
+/// A `TypeId` (`ty`) value identifying `of`, or any type if None.
+fn type_id_value(ty: Ty, of: Option<Ty>) -> Option<Constraint> {
+    let TyKind::RigidTy(RigidTy::Adt(def, _)) = ty.kind() else {
+        return None;
+    };
+    let tag = GenericArgs(of.into_iter().map(GenericArgKind::Type).collect());
+    Some(Constraint::new(
+        None,
+        Some(RunningConstraint::Adt(def, tag, None, ADTFields::new())),
+    ))
+}
+
+/// The types a `TypeId` value might identify, or None if one of them
+/// could be any type.
+fn type_id_tags(value: &Constraints) -> Option<Vec<Ty>> {
+    let mut tags = Vec::new();
+    for c in value.inner.iter() {
+        let Some(RunningConstraint::Adt(_, args, _, _)) = &c.cfc else {
+            return None;
+        };
+        let tag = *args.0.first()?.ty()?;
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    (!tags.is_empty()).then_some(tags)
+}
+
+fn bool_value(known: Option<bool>) -> Constraints {
+    Constraints::from(Constraint::new(
+        None,
+        Some(RunningConstraint::Scalar(known.map(i128::from))),
+    ))
+}
+
+fn first_type_arg(genargs: &GenericArgs) -> Option<Ty> {
+    genargs.0.iter().find_map(|a| a.ty().copied())
+}
+
+fn is_type_id(ty: Ty) -> bool {
+    let mut ty = ty;
+    while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() {
+        ty = inner;
+    }
+    match ty.kind() {
+        TyKind::RigidTy(RigidTy::Adt(def, _)) => def.0.name().ends_with("any::TypeId"),
+        _ => false,
+    }
+}
+
+/// `&dyn Any ..` itself - not a `Box<dyn Any>`, whose `type_id` is the
+/// `Box`'s.
+fn is_bare_any(ty: Ty) -> bool {
+    let mut ty = ty;
+    while let TyKind::RigidTy(RigidTy::Ref(_, inner, _)) = ty.kind() {
+        ty = inner;
+    }
+    let TyKind::RigidTy(RigidTy::Dynamic(preds, _)) = ty.kind() else {
+        return false;
+    };
+    preds
+        .iter()
+        .any(|p| TraitObjTy::new_from_bound_existential(p).is_some_and(|t| t.is_any_trait()))
+}
@@ -1,10 +1,12 @@
 //! Analysis options.
 
 use clap::error::ErrorKind;
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use itertools::Itertools;
 
 use crate::common::VerifOptType;
+use crate::config::{Config, Limits, PathFilter, STATS_FILE};
+use crate::cost::CostModel;
 
 const VERIFOPT_USAGE: &str = r#"verifopt [OPTIONS] INPUT -- [RUSTC OPTIONS]"#;
 
@@ -30,7 +32,10 @@ fn make_options_parser() -> Command {
             Arg::new("entry-func-name")
                 .long("entry-func")
                 .value_name("func-name")
-                .help("The name of entry function from which the flow analysis begins."),
+                .action(ArgAction::Append)
+                .help(
+                    "The name of entry function from which the flow analysis begins (repeatable).",
+                ),
         )
         .arg(
             Arg::new("entry-func-id")
@@ -43,10 +48,76 @@ fn make_options_parser() -> Command {
             Arg::new("verifopt-type")
                 .long("verifopt-type")
                 .value_name("analysis-type")
-                .value_parser(["flow-sensitive", "fsa"])
-                .default_value("flow-sensitive")
-                .help("The type of analysis.")
-                .long_help("Flow-sensitive analyses is supported now."),
+                .value_parser(["flow-sensitive", "fsa", "cha", "rta"])
+                .help("The type of analysis (default: flow-sensitive).")
+                .long_help(
+                    "The type of analysis: `flow-sensitive` (or `fsa`), or one of the \
+                     baselines, `cha` (class hierarchy analysis) or `rta` (rapid type \
+                     analysis). All three report and rewrite the same way; see \
+                     monomorph/src/baseline.rs.",
+                ),
+        )
+        .arg(
+            Arg::new("dispatch-profile")
+                .long("dispatch-profile")
+                .value_name("file")
+                .help("Observed (call site, impl) counts used to order devirtualized arms.")
+                .long_help(
+                    "Observed (call site, impl) counts used to order devirtualized arms \
+                     hottest first and mark rarely taken ones cold. Accepts votrace's \
+                     `calls` file as-is; see monomorph/src/profile.rs for the format.",
+                ),
+        )
+        .arg(
+            Arg::new("cost-model")
+                .long("cost-model")
+                .value_name("key=value,...|on|off")
+                .help("Turn on (and tune) the per-call-site devirtualization cost model.")
+                .long_help(
+                    "The devirtualization cost model is off by default: every call site \
+                     that has a target is rewritten. `on` turns it on with its defaults, \
+                     and comma-separated overrides of those (vcall, compare, switch, \
+                     inline_gain, inline_size, min_gain, max_arms) turn it on with them \
+                     changed. See monomorph/src/cost.rs.",
+                ),
+        )
+        .arg(
+            Arg::new("trace-dispatch")
+                .long("trace-dispatch")
+                .action(ArgAction::SetTrue)
+                .help(
+                    "Instrument dyn calls to log their runtime targets instead of devirtualizing.",
+                )
+                .long_help(
+                    "Instead of devirtualizing, instrument every dyn call to log \
+                     (call site, concrete impl) to stderr at runtime, and write the FSA \
+                     sets to `dispatch_sets` for `verifopt-check`. See \
+                     monomorph/src/trace.rs.",
+                ),
+        )
+        .arg(
+            Arg::new("no-rewrite")
+                .long("no-rewrite")
+                .action(ArgAction::SetTrue)
+                .help("Compile without analyzing or rewriting anything.")
+                .long_help(
+                    "Skip the analysis and compile with the original MIR, under the same \
+                     flags as a rewriting build. This is the baseline build for \
+                     `verifopt-diff`; see monomorph/src/diff.rs.",
+                ),
+        )
+        .arg(
+            Arg::new("stubs")
+                .long("stubs")
+                .value_name("file")
+                .action(ArgAction::Append)
+                .help("Load declarative function stubs from a TOML file (repeatable).")
+                .long_help(
+                    "Load `[[stub]]` summaries from a TOML file, for functions the \
+                     analysis shouldn't interpret. Files are tried in the order given, \
+                     then the shipped defaults (monomorph/stubs/std.toml). See \
+                     monomorph/src/stub_spec.rs for the format.",
+                ),
         )
         .arg(
             Arg::new("INPUT")
@@ -88,22 +159,67 @@ fn make_options_parser() -> Command {
 
 #[derive(Clone, Debug)]
 pub struct AnalysisOptions {
-    pub entry_func: String,
+    pub entry_funcs: Vec<String>,
     pub entry_def_id: Option<u32>,
     pub verifopt_type: VerifOptType,
+    pub profile: Option<String>,
+    pub cost_model: CostModel,
+    pub trace_dispatch: bool,
+    pub no_rewrite: bool,
+    pub stub_files: Vec<String>,
+    /// Callees the interpreter walks into
+    pub analysis_filter: PathFilter,
+    /// Fns whose dyn calls may be rewritten
+    pub rewrite_filter: PathFilter,
+    pub limits: Limits,
+    pub stats_file: String,
 }
 
 impl Default for AnalysisOptions {
     fn default() -> Self {
         Self {
-            entry_func: String::new(),
+            entry_funcs: Vec::new(),
             entry_def_id: None,
             verifopt_type: VerifOptType::FlowSensitive,
+            profile: None,
+            cost_model: CostModel::default(),
+            trace_dispatch: false,
+            no_rewrite: false,
+            stub_files: Vec::new(),
+            analysis_filter: PathFilter::default(),
+            rewrite_filter: PathFilter::default(),
+            limits: Limits::default(),
+            stats_file: STATS_FILE.to_string(),
         }
     }
 }
 
 impl AnalysisOptions {
+    /// Takes on a `verifopt.toml`'s settings. Called before `parse_from_args`,
+    /// so flags override it.
+    pub fn apply_config(&mut self, config: &Config) {
+        self.entry_funcs = config.entry.clone();
+        self.stub_files = config.stubs.clone();
+        self.analysis_filter = config.analysis.clone();
+        self.rewrite_filter = config.rewrite.clone();
+        self.limits = config.limits.clone();
+        if let Some(s) = &config.strategy {
+            self.cost_model = match CostModel::parse(s) {
+                Ok(model) => model,
+                Err(e) => {
+                    eprintln!("bad [rewrite] strategy: {}", e);
+                    std::process::exit(1);
+                }
+            };
+        }
+        if config.profile.is_some() {
+            self.profile = config.profile.clone();
+        }
+        if let Some(s) = &config.stats {
+            self.stats_file = s.clone();
+        }
+    }
+
     /// Parses options from a list of strings. Any content beyond the leftmost `--` token
     /// will be returned (excluding this token).
     pub fn parse_from_args(&mut self, args: &[String], from_env: bool) -> Vec<String> {
@@ -156,19 +272,45 @@ impl AnalysisOptions {
             }
         };
 
-        if let Some(s) = matches.get_one::<String>("entry-func-name") {
-            self.entry_func = s.clone();
+        if let Some(names) = matches.get_many::<String>("entry-func-name") {
+            self.entry_funcs = names.cloned().collect();
         }
         self.entry_def_id = matches.get_one::<u32>("entry-func-id").cloned();
 
-        if matches.contains_id("verifopt-type") {
-            self.verifopt_type = match matches.get_one::<String>("verifopt-type").unwrap().as_str()
-            {
+        if let Some(s) = matches.get_one::<String>("verifopt-type") {
+            self.verifopt_type = match s.as_str() {
                 "flow-sensitive" | "fsa" => VerifOptType::FlowSensitive,
+                "cha" => VerifOptType::ClassHierarchy,
+                "rta" => VerifOptType::RapidType,
                 _ => unreachable!(),
             }
         }
 
+        if let Some(s) = matches.get_one::<String>("dispatch-profile") {
+            self.profile = Some(s.clone());
+        }
+
+        if let Some(s) = matches.get_one::<String>("cost-model") {
+            self.cost_model = match CostModel::parse(s) {
+                Ok(model) => model,
+                Err(e) => {
+                    eprintln!("bad --cost-model: {}", e);
+                    std::process::exit(1);
+                }
+            };
+        }
+
+        if matches.get_flag("trace-dispatch") {
+            self.trace_dispatch = true;
+        }
+        if matches.get_flag("no-rewrite") {
+            self.no_rewrite = true;
+        }
+
+        if let Some(files) = matches.get_many::<String>("stubs") {
+            self.stub_files = files.cloned().collect();
+        }
+
         //if let Some(depth) = matches.get_one::<u32>("context-depth") {
         //    self.context_depth = *depth;
         //}
@@ -0,0 +1,74 @@
+//! The baseline analyses, `--verifopt-type cha` and `rta` (see
+//! `monomorph::baseline`), on `../testing_examples/baselines`, whose
+//! Animals are each constructed a different way: by an aggregate, as a
+//! promoted constant, in a `static`, in a `const`. FSA's answers for the
+//! same fixture are its golden file in `dispatch_examples.rs`.
+//!
+//! ```sh
+//! cargo test --test baselines -- --nocapture
+//! ```
+
+#[allow(dead_code)]
+mod support;
+
+use std::fs;
+use std::process::Command;
+use std::sync::Mutex;
+
+use support::DispatchSite;
+
+/// The tests share the fixture's `target` dir and `stats` file.
+static FIXTURE: Mutex<()> = Mutex::new(());
+
+/// The dispatch sites `cargo verifopt -- --verifopt-type <style>` finds.
+fn analyze(style: &str) -> Vec<DispatchSite> {
+    let _guard = FIXTURE.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = support::example_dir("baselines");
+    for f in ["stats", "found_ex", "notfound_ex"] {
+        let _ = fs::remove_file(dir.join(f));
+    }
+    let _ = Command::new("cargo")
+        .arg("clean")
+        .current_dir(&dir)
+        .output();
+
+    let output = support::cargo_verifopt(&dir, &["--release", "--", "--verifopt-type", style]);
+    assert!(
+        output.status.success(),
+        "`--verifopt-type {style}` failed.\nstdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let stats = fs::read_to_string(dir.join("stats")).unwrap_or_default();
+    support::parse_stats(&stats).sites
+}
+
+fn speak(tys: &[&str]) -> Vec<String> {
+    tys.iter()
+        .map(|ty| format!("<baselines::{ty} as baselines::Animal>::speak"))
+        .collect()
+}
+
+#[test]
+fn cha() {
+    let sites = analyze("cha");
+    assert_eq!(sites.len(), 3, "{sites:#?}");
+    for site in &sites {
+        assert_eq!(site.cha, speak(&["Bird", "Cat", "Cow", "Dog", "Fish"]));
+        assert_eq!(site.fsa, site.cha, "CHA's targets are its CHA column");
+        assert!(!site.is_maybe_example);
+    }
+}
+
+#[test]
+fn rta() {
+    let sites = analyze("rta");
+    assert_eq!(sites.len(), 3, "{sites:#?}");
+    for site in &sites {
+        assert_eq!(site.cha, speak(&["Bird", "Cat", "Cow", "Dog", "Fish"]));
+        // Every way of constructing one counts, and Cow isn't constructed
+        assert_eq!(site.fsa, speak(&["Bird", "Cat", "Dog", "Fish"]));
+        assert!(site.is_maybe_example);
+    }
+}
@@ -0,0 +1,107 @@
+//! `cargo verifopt`'s target selection, on `../testing_examples/cargo_targets`:
+//! each test picks one of the targets a plain `cargo verifopt` leaves out (an
+//! example, a harness bench, the build script) and checks that its dyn call
+//! was analyzed, from the right entry, by finding the site in `stats`.
+//!
+//! ```sh
+//! cargo test --test cargo_targets -- --nocapture
+//! ```
+
+#[allow(dead_code)]
+mod support;
+
+use std::fs;
+use std::process::Command;
+use std::sync::Mutex;
+
+use support::DispatchSite;
+
+/// The tests share the fixture's `target` dir and `stats` file.
+static FIXTURE: Mutex<()> = Mutex::new(());
+
+/// The `stats` `cargo verifopt <args>` writes.
+fn analyze(args: &[&str]) -> String {
+    let _guard = FIXTURE.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = support::example_dir("cargo_targets");
+    for f in ["stats", "found_ex", "notfound_ex"] {
+        let _ = fs::remove_file(dir.join(f));
+    }
+    let _ = Command::new("cargo")
+        .arg("clean")
+        .current_dir(&dir)
+        .output();
+
+    let output = support::cargo_verifopt(&dir, args);
+    assert!(
+        output.status.success(),
+        "`cargo verifopt {}` failed.\nstdout:\n{}\nstderr:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    fs::read_to_string(dir.join("stats")).unwrap_or_default()
+}
+
+/// The dispatch sites in `file`.
+fn sites_in(stats: &str, file: &str) -> Vec<DispatchSite> {
+    support::parse_stats(stats)
+        .sites
+        .into_iter()
+        .filter(|site| site.span.starts_with(&format!("{file}:")))
+        .collect()
+}
+
+fn names(krate: &str, tys: &[&str], tr: &str, method: &str) -> Vec<String> {
+    tys.iter()
+        .map(|ty| format!("<{krate}::{ty} as {krate}::{tr}>::{method}"))
+        .collect()
+}
+
+#[test]
+fn example() {
+    let sites = sites_in(
+        &analyze(&["--release", "--example", "tally"]),
+        "examples/tally.rs",
+    );
+    assert_eq!(sites.len(), 1, "{sites:#?}");
+    assert_eq!(
+        sites[0].cha,
+        names("tally", &["Lines", "Words"], "Counter", "count")
+    );
+    assert_eq!(sites[0].fsa, names("tally", &["Words"], "Counter", "count"));
+}
+
+#[test]
+fn bench_harness_entry() {
+    let sites = sites_in(&analyze(&["--bench", "speed"]), "benches/speed.rs");
+    assert_eq!(sites.len(), 1, "{sites:#?}");
+    assert_eq!(
+        sites[0].cha,
+        names("speed", &["Circle", "Square"], "Shape", "area")
+    );
+    assert_eq!(sites[0].fsa, names("speed", &["Square"], "Shape", "area"));
+}
+
+#[test]
+fn build_script() {
+    let sites = sites_in(&analyze(&["--release", "--all-targets"]), "build.rs");
+    assert_eq!(sites.len(), 1, "{sites:#?}");
+    let krate = "build_script_build";
+    assert_eq!(
+        sites[0].cha,
+        names(krate, &["Emit", "Rerun", "Skip"], "Step", "run")
+    );
+    assert_eq!(
+        sites[0].fsa,
+        names(krate, &["Emit", "Rerun"], "Step", "run")
+    );
+}
+
+#[test]
+fn default_targets() {
+    // The bin, but not the example, the bench or the build script
+    let stats = analyze(&["--release"]);
+    for file in ["examples/tally.rs", "benches/speed.rs", "build.rs"] {
+        assert!(sites_in(&stats, file).is_empty(), "{stats}");
+    }
+}
@@ -0,0 +1,139 @@
+//! Differential execution of the fixture crates in `../testing_examples`:
+//! builds each one with and without rewriting via the `verifopt-diff` binary
+//! and checks both builds behave identically (see `monomorph::diff`).
+//!
+//! Where `dispatch_examples.rs` checks the analysis' answers, this checks the
+//! rewriter's output: a divergence here is a miscompile, whatever the golden
+//! files say. Like there, fixtures the tool can't build yet are expected to
+//! fail until it can.
+//!
+//! ```sh
+//! cargo test --test differential -- shims --exact --nocapture
+//! ```
+
+#[allow(dead_code)]
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn run_diff(name: &str) {
+    let dir = support::example_dir(name);
+    assert!(
+        dir.exists(),
+        "fixture directory {:?} does not exist; is testing_examples/{name} still there?",
+        dir
+    );
+
+    let bin = PathBuf::from(env!("CARGO_BIN_EXE_verifopt-diff"));
+    let output = Command::new(&bin)
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"))
+        .env("LD_LIBRARY_PATH", support::ld_library_path_with_sysroot())
+        .output()
+        .unwrap_or_else(|e| panic!("failed to spawn {:?} for {:?}: {e}", bin, dir));
+
+    assert!(
+        output.status.success(),
+        "'{name}' behaves differently after rewriting (or didn't build).\nstdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+macro_rules! diff_test {
+    ($fn_name:ident, $dir:literal) => {
+        #[test]
+        fn $fn_name() {
+            run_diff($dir);
+        }
+    };
+}
+
+diff_test!(btreemap, "btreemap");
+diff_test!(casting_traitobj, "casting_traitobj");
+diff_test!(closures, "closures");
+diff_test!(constraint_hierarchy, "constraint_hierarchy");
+diff_test!(default, "default");
+diff_test!(fnptrs, "fnptrs");
+diff_test!(generic, "generic");
+diff_test!(list_fields, "list_fields");
+diff_test!(nested_struct, "nested_struct");
+diff_test!(recursive, "recursive");
+diff_test!(shims, "shims");
+diff_test!(switchint, "switchint");
+diff_test!(one_variant, "one_variant");
+diff_test!(two_variants, "two_variants");
+diff_test!(two_variants_static, "two_variants_static");
+diff_test!(two_variants_static_nonzst, "two_variants_static_nonzst");
+diff_test!(recursive_dyn, "recursive_dyn");
+diff_test!(recursive_dyn2, "recursive_dyn2");
+diff_test!(recursive_dyn3, "recursive_dyn3");
+diff_test!(mut_refs, "mut_refs");
+diff_test!(mut_refs2, "mut_refs2");
+diff_test!(simple, "simple");
+diff_test!(simple_single, "simple_single");
+diff_test!(r#static, "static");
+
+// Fixtures added with the stdlib models, heap/scalar domains, trait
+// handling, annotations, config and fn pointer work.
+diff_test!(collections, "collections");
+diff_test!(stub_spec, "stub_spec");
+diff_test!(interior, "interior");
+diff_test!(combinators, "combinators");
+diff_test!(iter_adapters, "iter_adapters");
+diff_test!(dyn_error, "dyn_error");
+diff_test!(any_downcast, "any_downcast");
+diff_test!(fmt_args, "fmt_args");
+diff_test!(heap_aliasing, "heap_aliasing");
+diff_test!(scalar_domain, "scalar_domain");
+diff_test!(signed_switch, "signed_switch");
+diff_test!(blanket_impls, "blanket_impls");
+diff_test!(assoc_bindings, "assoc_bindings");
+diff_test!(trait_upcasting, "trait_upcasting");
+diff_test!(sealed_traits, "sealed_traits");
+diff_test!(annotations, "annotations");
+diff_test!(config_file, "config_file");
+diff_test!(fn_ptr_targets, "fn_ptr_targets");
+diff_test!(fn_ptr_refs, "fn_ptr_refs");
+diff_test!(address_taken, "address_taken");
+
+/// The `rewritten` file `cargo verifopt <args>` leaves in `no_rewrite`, from
+/// a clean build.
+fn rewritten_by(args: &[&str]) -> String {
+    let dir = support::example_dir("no_rewrite");
+    for f in ["stats", "found_ex", "notfound_ex", "rewritten"] {
+        let _ = fs::remove_file(dir.join(f));
+    }
+    let _ = Command::new("cargo")
+        .arg("clean")
+        .current_dir(&dir)
+        .output();
+
+    let output = support::cargo_verifopt(&dir, args);
+    assert!(
+        output.status.success(),
+        "`cargo verifopt {}` failed.\nstdout:\n{}\nstderr:\n{}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    fs::read_to_string(dir.join("rewritten")).unwrap_or_default()
+}
+
+/// `--no-rewrite` wins over the fixture's `verifopt.toml`, which used to
+/// reset it when the options were parsed again with the config applied.
+#[test]
+fn no_rewrite_with_config() {
+    assert!(
+        support::example_dir("no_rewrite")
+            .join("verifopt.toml")
+            .exists()
+    );
+    assert!(
+        !rewritten_by(&["--release"]).is_empty(),
+        "the rewriting build left `main`'s dyn call alone"
+    );
+    assert_eq!(rewritten_by(&["--release", "--", "--no-rewrite"]), "");
+}
@@ -97,6 +97,28 @@ example_test!(mut_refs2, "mut_refs2", Passing);
 example_test!(simple, "simple", Passing);
 example_test!(simple_single, "simple_single", Passing);
 example_test!(r#static, "static", Passing);
+example_test!(collections, "collections", Passing);
+example_test!(stub_spec, "stub_spec", Passing);
+example_test!(interior, "interior", Passing);
+example_test!(combinators, "combinators", Passing);
+example_test!(iter_adapters, "iter_adapters", Passing);
+example_test!(dyn_error, "dyn_error", Passing);
+example_test!(any_downcast, "any_downcast", Passing);
+example_test!(fmt_args, "fmt_args", Passing);
+example_test!(heap_aliasing, "heap_aliasing", Passing);
+example_test!(scalar_domain, "scalar_domain", Passing);
+example_test!(signed_switch, "signed_switch", Passing);
+example_test!(blanket_impls, "blanket_impls", Passing);
+example_test!(assoc_bindings, "assoc_bindings", Passing);
+example_test!(trait_upcasting, "trait_upcasting", Passing);
+example_test!(sealed_traits, "sealed_traits", Passing);
+example_test!(sealed_lib, "sealed_lib", Passing);
+example_test!(annotations, "annotations", Passing);
+example_test!(config_file, "config_file", Passing);
+example_test!(fn_ptr_targets, "fn_ptr_targets", Passing);
+example_test!(fn_ptr_refs, "fn_ptr_refs", Passing);
+example_test!(address_taken, "address_taken", Passing);
+example_test!(baselines, "baselines", Passing);
 
 // Not yet characterized at all — run and report, don't assert.
 example_test!(no_vtable_check, "no_vtable_check", Unclassified);
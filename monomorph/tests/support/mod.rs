@@ -1,4 +1,5 @@
-//! Shared helpers for `tests/dispatch_examples.rs`.
+//! Shared helpers for `tests/dispatch_examples.rs` (and the other
+//! integration tests that run the tool on a fixture).
 //!
 //! Lives at `tests/support/mod.rs` (not `tests/support.rs`) so Cargo treats it
 //! as a plain module rather than its own integration-test binary.
@@ -6,7 +7,7 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Output};
 
 #[derive(Copy, Clone, Debug)]
 pub enum Expectation {
@@ -38,8 +39,13 @@ pub struct DispatchSite {
 pub struct ExampleRead {
     pub maybe_count: usize,
     pub not_count: usize,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub skip_calls: bool,
+    /// Spans whose `cha` column isn't compared (and is `[]` in the file):
+    /// a std trait's, like `Iterator`'s, which lists every impl of it in
+    /// std and so changes with the toolchain.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unchecked_cha: Vec<String>,
     pub sites: Vec<DispatchSite>,
 }
 
@@ -74,8 +80,8 @@ pub fn parse_stats(text: &str) -> ExampleResult {
                 extract_quoted_field(rest, "repr: \"").unwrap_or_else(|| rest.trim().to_string());
             let cha_line = lines.next().unwrap_or("");
             let fsa_line = lines.next().unwrap_or("");
-            let mut cha = extract_all_quoted_fields(cha_line, "name: \"");
-            let mut fsa = extract_all_quoted_fields(fsa_line, "name: \"");
+            let mut cha = extract_candidate_names(cha_line);
+            let mut fsa = extract_candidate_names(fsa_line);
             cha.sort();
             fsa.sort();
             sites.push(DispatchSite {
@@ -102,17 +108,20 @@ fn extract_quoted_field(s: &str, marker: &str) -> Option<String> {
     Some(rest[..end].to_string())
 }
 
-fn extract_all_quoted_fields(s: &str, marker: &str) -> Vec<String> {
+/// The fn names in a `[(DefId { .., name: ".." }, Option<GenericArgs>), ..]`
+/// line - each entry's own `DefId`, not the ones inside its `GenericArgs`
+/// (`AdtDef(DefId { .. })`, ...).
+fn extract_candidate_names(s: &str) -> Vec<String> {
     let mut names = Vec::new();
     let mut rest = s;
-    while let Some(idx) = rest.find(marker) {
-        rest = &rest[idx + marker.len()..];
-        match rest.find('"') {
-            Some(end) => {
-                names.push(rest[..end].to_string());
-                rest = &rest[end + 1..];
-            }
-            None => break,
+    while let Some(idx) = rest.find("(DefId {") {
+        let is_entry = idx > 0 && matches!(rest.as_bytes()[idx - 1], b'[' | b' ');
+        rest = &rest[idx + 1..];
+        if !is_entry {
+            continue;
+        }
+        if let Some(name) = extract_quoted_field(rest, "name: \"") {
+            names.push(name);
         }
     }
     names
@@ -122,7 +131,7 @@ fn manifest_dir() -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
 }
 
-fn example_dir(name: &str) -> PathBuf {
+pub fn example_dir(name: &str) -> PathBuf {
     manifest_dir().join("../testing_examples").join(name)
 }
 
@@ -135,7 +144,7 @@ fn golden_path(name: &str) -> PathBuf {
 /// Best-effort sysroot lookup so the child process can find `librustc_driver.so`
 /// (see monomorph/README.md's Troubleshooting section) without every
 /// developer having to export LD_LIBRARY_PATH by hand before running tests.
-fn ld_library_path_with_sysroot() -> String {
+pub fn ld_library_path_with_sysroot() -> String {
     let sysroot = Command::new("rustc")
         .args(["--print", "sysroot"])
         .output()
@@ -174,20 +183,7 @@ fn run_verifopt(dir: &Path) -> RunOutcome {
 
     let _ = Command::new("cargo").arg("clean").current_dir(dir).output();
 
-    let bin = PathBuf::from(env!("CARGO_BIN_EXE_cargo-verifopt"));
-    let output = Command::new(&bin)
-        .arg("verifopt")
-        .arg("--release")
-        .current_dir(dir)
-        .env("LD_LIBRARY_PATH", ld_library_path_with_sysroot())
-        .output()
-        .unwrap_or_else(|e| {
-            panic!(
-                "failed to spawn {:?} in {:?}: {e}\n\
-                 (is the pinned nightly toolchain from rust-toolchain.toml active?)",
-                bin, dir
-            )
-        });
+    let output = cargo_verifopt(dir, &["--release"]);
 
     let stats = fs::read_to_string(dir.join("stats")).ok();
 
@@ -206,6 +202,24 @@ fn run_verifopt(dir: &Path) -> RunOutcome {
     }
 }
 
+/// Runs `cargo verifopt <args>` in `dir`.
+pub fn cargo_verifopt(dir: &Path, args: &[&str]) -> Output {
+    let bin = PathBuf::from(env!("CARGO_BIN_EXE_cargo-verifopt"));
+    Command::new(&bin)
+        .arg("verifopt")
+        .args(args)
+        .current_dir(dir)
+        .env("LD_LIBRARY_PATH", ld_library_path_with_sysroot())
+        .output()
+        .unwrap_or_else(|e| {
+            panic!(
+                "failed to spawn {:?} in {:?}: {e}\n\
+                 (is the pinned nightly toolchain from rust-toolchain.toml active?)",
+                bin, dir
+            )
+        })
+}
+
 pub fn run_example(name: &str, expectation: Expectation) {
     let dir = example_dir(name);
     assert!(
@@ -247,37 +261,56 @@ pub fn run_example(name: &str, expectation: Expectation) {
             let stats = outcome.stats.unwrap_or_else(|| {
                 panic!("'{name}' ran successfully but produced no `stats` file in {dir:?}")
             });
-            let actual = parse_stats(&stats);
+            let mut actual = parse_stats(&stats);
 
             let golden_file = golden_path(name);
+            let golden: Option<ExampleRead> = fs::read_to_string(&golden_file).ok().map(|text| {
+                serde_json::from_str(&text).unwrap_or_else(|e| {
+                    panic!("failed to parse golden file {:?}: {e}", golden_file)
+                })
+            });
+            let (skip_calls, unchecked_cha) = golden.as_ref().map_or((false, Vec::new()), |g| {
+                (g.skip_calls, g.unchecked_cha.clone())
+            });
+            for site in actual.sites.iter_mut() {
+                if unchecked_cha.contains(&site.span) {
+                    site.cha.clear();
+                }
+            }
+
             if std::env::var("BLESS_GOLDEN").is_ok() {
                 if let Some(parent) = golden_file.parent() {
                     fs::create_dir_all(parent).expect("failed to create tests/golden");
                 }
+                let blessed = ExampleRead {
+                    maybe_count: actual.maybe_count,
+                    not_count: actual.not_count,
+                    skip_calls,
+                    unchecked_cha,
+                    sites: actual.sites,
+                };
                 fs::write(
                     &golden_file,
-                    serde_json::to_string_pretty(&actual).unwrap() + "\n",
+                    serde_json::to_string_pretty(&blessed).unwrap() + "\n",
                 )
                 .unwrap_or_else(|e| panic!("failed to write {:?}: {e}", golden_file));
                 eprintln!("[{name}] wrote golden file {:?}", golden_file);
                 return;
             }
 
-            let expected_text = fs::read_to_string(&golden_file).unwrap_or_else(|e| {
+            let ExampleRead {
+                maybe_count,
+                not_count,
+                sites,
+                ..
+            } = golden.unwrap_or_else(|| {
                 panic!(
-                    "'{name}' has no golden file at {:?} ({e}).\n\
+                    "'{name}' has no golden file at {:?}.\n\
                      Generate one with:\n\n    BLESS_GOLDEN=1 cargo test --test dispatch_examples -- {name}\n\n\
                      then review it before committing.",
                     golden_file
                 )
             });
-            let ExampleRead {
-                maybe_count,
-                not_count,
-                skip_calls,
-                sites,
-            } = serde_json::from_str(&expected_text)
-                .unwrap_or_else(|e| panic!("failed to parse golden file {:?}: {e}", golden_file));
 
             let expected = ExampleResult {
                 maybe_count,
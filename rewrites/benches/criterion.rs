@@ -5,7 +5,7 @@ use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_ma
 use std::time::Duration;
 
 use rand::Rng;
-use rewrites::{og0sf, og2sf, og5sf, og0sf_mir_rw, og2sf_mir_rw, vec0sf, vec2sf, visitor0sf, visitor0sf_import, visitor2sf, prime2sf};
+use rewrites::{og0sf, og2sf, og5sf, og0sf_mir_rw, og2sf_mir_rw, vec0sf, vec2sf, visitor0sf, visitor0sf_import, visitor2sf, prime2sf, skew4sf};
 
 fn bench_og0sf(c: &mut Criterion) {
     let cat: &og0sf::Cat = &og0sf::Cat {};
@@ -639,6 +639,46 @@ fn bench_prime2sf(c: &mut Criterion) {
     group.finish();
 }
 
+fn new_skew4sf_vec(
+    n_elems: usize,
+) -> Vec<(
+    Box<dyn skew4sf::Animal>,
+    DynMetadata<dyn skew4sf::Animal>,
+)> {
+    let mut vec = vec![];
+    for _ in 0..n_elems {
+        let animal = skew4sf::get_skewed_animal(rand::rng().random_range(..100usize));
+        let animal_vtable = core::ptr::metadata(&*animal);
+        vec.insert(0, (animal, animal_vtable));
+    }
+    vec
+}
+
+// Profile-guided arm ordering (`verifopt --profile`): same devirtualized
+// chain, hot impl compared last vs first vs first with cold-path hints.
+fn bench_skew4sf(c: &mut Criterion) {
+    let mut group = c.benchmark_group("skew4sf");
+    let vt = skew4sf::get_vtables();
+
+    for n_elems in [1000, 4000, 8000, 12000].iter() {
+        let vec = new_skew4sf_vec(*n_elems);
+
+        group.bench_function(BenchmarkId::new("skew4sf_not_rw", n_elems), |b| {
+            b.iter(|| std::hint::black_box(skew4sf::run_not_rw(&vec)))
+        });
+        group.bench_function(BenchmarkId::new("skew4sf_hot_last", n_elems), |b| {
+            b.iter(|| std::hint::black_box(skew4sf::run_hot_last(&vec, &vt)))
+        });
+        group.bench_function(BenchmarkId::new("skew4sf_hot_first", n_elems), |b| {
+            b.iter(|| std::hint::black_box(skew4sf::run_hot_first(&vec, &vt)))
+        });
+        group.bench_function(BenchmarkId::new("skew4sf_hot_first_cold", n_elems), |b| {
+            b.iter(|| std::hint::black_box(skew4sf::run_hot_first_cold(&vec, &vt)))
+        });
+    }
+    group.finish();
+}
+
 // TODO
 // more than 2 trait implementations
 // different paths -> trait method call
@@ -731,6 +771,15 @@ criterion_group! {
     targets = bench_prime2sf
 }
 
+criterion_group! {
+    name = skew4sf_benches;
+    config = Criterion::default()
+        .sample_size(SAMPLE_SIZE)
+        .warm_up_time(Duration::new(WARMUP_TIME, 0))
+        .measurement_time(Duration::new(MEASUREMENT_TIME, 0));
+    targets = bench_skew4sf
+}
+
 criterion_group! {
     name = all_benches;
     config = Criterion::default()
@@ -746,6 +795,7 @@ criterion_group! {
         bench_visitor0sf,
         bench_visitor2sf,
         bench_prime2sf,
+        bench_skew4sf,
 }
 
 criterion_group! {
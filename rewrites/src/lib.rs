@@ -1,4 +1,5 @@
 #![feature(ptr_metadata)]
+#![feature(cold_path)]
 
 pub mod og0sf;
 pub mod og0sf_mir_rw;
@@ -14,3 +15,5 @@ pub mod visitor0sf_import;
 pub mod visitor2sf;
 
 pub mod prime2sf;
+
+pub mod skew4sf;
@@ -0,0 +1,175 @@
+// Same shape as vec2sf, but with four implementors and a skewed mix of them
+// (see `get_skewed_animal`), to compare orderings of the devirtualized
+// comparison chain: the order the analysis happens to produce (hot impl
+// last), profile-guided hot-first, and hot-first with the rare arms marked
+// cold (what `verifopt --dispatch-profile` emits).
+
+use core::ptr::DynMetadata;
+
+pub trait Animal: Sync + Send {
+    fn speak(&self) -> usize;
+}
+
+pub struct Cat {
+    pub age: usize,
+    pub num_siblings: usize,
+}
+
+pub struct Dog {
+    pub age: usize,
+    pub num_siblings: usize,
+}
+
+pub struct Bird {
+    pub age: usize,
+    pub num_siblings: usize,
+}
+
+pub struct Fish {
+    pub age: usize,
+    pub num_siblings: usize,
+}
+
+impl Animal for Cat {
+    fn speak(&self) -> usize {
+        11111
+    }
+}
+
+impl Animal for Dog {
+    fn speak(&self) -> usize {
+        22222
+    }
+}
+
+impl Animal for Bird {
+    fn speak(&self) -> usize {
+        33333
+    }
+}
+
+impl Animal for Fish {
+    fn speak(&self) -> usize {
+        44444
+    }
+}
+
+pub fn get_animal(num: usize) -> Box<dyn Animal> {
+    match num {
+        0 => Box::new(Cat {
+            age: 9,
+            num_siblings: 11,
+        }),
+        1 => Box::new(Dog {
+            age: 7,
+            num_siblings: 3,
+        }),
+        2 => Box::new(Bird {
+            age: 2,
+            num_siblings: 5,
+        }),
+        _ => Box::new(Fish {
+            age: 1,
+            num_siblings: 300,
+        }),
+    }
+}
+
+/// `roll` in `0..100`: Fish 97% of the time, the other three 1% each.
+pub fn get_skewed_animal(roll: usize) -> Box<dyn Animal> {
+    match roll {
+        0 => get_animal(0),
+        1 => get_animal(1),
+        2 => get_animal(2),
+        _ => get_animal(3),
+    }
+}
+
+pub struct Vtables {
+    pub cat: DynMetadata<dyn Animal>,
+    pub dog: DynMetadata<dyn Animal>,
+    pub bird: DynMetadata<dyn Animal>,
+    pub fish: DynMetadata<dyn Animal>,
+}
+
+pub fn get_vtables() -> Vtables {
+    Vtables {
+        cat: core::ptr::metadata(&*get_animal(0)),
+        dog: core::ptr::metadata(&*get_animal(1)),
+        bird: core::ptr::metadata(&*get_animal(2)),
+        fish: core::ptr::metadata(&*get_animal(3)),
+    }
+}
+
+#[inline(always)]
+unsafe fn narrow<'a, T>(x: &'a Box<dyn Animal>) -> &'a T {
+    unsafe { &*(&**x as *const dyn Animal as *const T) }
+}
+
+pub fn run_not_rw(xs: &[(Box<dyn Animal>, DynMetadata<dyn Animal>)]) -> usize {
+    let mut ret = 0;
+    for (x, _) in xs {
+        ret = x.speak();
+    }
+    ret
+}
+
+pub fn run_hot_last(xs: &[(Box<dyn Animal>, DynMetadata<dyn Animal>)], vt: &Vtables) -> usize {
+    let mut ret = 0;
+    for (x, x_vtable) in xs.iter() {
+        ret = unsafe {
+            if *x_vtable == vt.cat {
+                <Cat as Animal>::speak(narrow(x))
+            } else if *x_vtable == vt.dog {
+                <Dog as Animal>::speak(narrow(x))
+            } else if *x_vtable == vt.bird {
+                <Bird as Animal>::speak(narrow(x))
+            } else {
+                <Fish as Animal>::speak(narrow(x))
+            }
+        };
+    }
+    ret
+}
+
+pub fn run_hot_first(xs: &[(Box<dyn Animal>, DynMetadata<dyn Animal>)], vt: &Vtables) -> usize {
+    let mut ret = 0;
+    for (x, x_vtable) in xs.iter() {
+        ret = unsafe {
+            if *x_vtable == vt.fish {
+                <Fish as Animal>::speak(narrow(x))
+            } else if *x_vtable == vt.bird {
+                <Bird as Animal>::speak(narrow(x))
+            } else if *x_vtable == vt.dog {
+                <Dog as Animal>::speak(narrow(x))
+            } else {
+                <Cat as Animal>::speak(narrow(x))
+            }
+        };
+    }
+    ret
+}
+
+pub fn run_hot_first_cold(
+    xs: &[(Box<dyn Animal>, DynMetadata<dyn Animal>)],
+    vt: &Vtables,
+) -> usize {
+    let mut ret = 0;
+    for (x, x_vtable) in xs.iter() {
+        ret = unsafe {
+            if *x_vtable == vt.fish {
+                <Fish as Animal>::speak(narrow(x))
+            } else if *x_vtable == vt.bird {
+                core::hint::cold_path();
+                <Bird as Animal>::speak(narrow(x))
+            } else if *x_vtable == vt.dog {
+                core::hint::cold_path();
+                <Dog as Animal>::speak(narrow(x))
+            } else {
+                core::hint::cold_path();
+                <Cat as Animal>::speak(narrow(x))
+            }
+        };
+    }
+    ret
+}
@@ -0,0 +1,124 @@
+// Calls through `fn` pointers against fns that share their signature but
+// never have their address taken:
+//   - `add_triangle` has the same signature as the `Plugin::install`
+//     pointers and is reachable, but only ever called directly, so FSA
+//     should leave `Triangle` out at `s.area()` in `total`; `extra` has
+//     the one Triangle, at a site of its own
+//   - `install_square` and `install_circle` become pointers where
+//     they're passed to `register`, a different fn from the call
+//   - `install_hexagon` is a pointer only in the `BUILTIN` constant, and
+//     `install_pentagon` only in the slice the `EXTRA` static points to:
+//     both are address-taken, so both reach `s.area()`
+//   - `describe` is reached only through a pointer stored in a `static`
+
+trait Shape {
+    fn area(&self) -> u32;
+}
+
+struct Square(u32);
+struct Circle(u32);
+struct Triangle(u32);
+struct Hexagon(u32);
+struct Pentagon(u32);
+
+#[votrace::trace]
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Circle {
+    fn area(&self) -> u32 {
+        3 * self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Triangle {
+    fn area(&self) -> u32 {
+        self.0 * self.0 / 2
+    }
+}
+
+#[votrace::trace]
+impl Shape for Hexagon {
+    fn area(&self) -> u32 {
+        5 * self.0 * self.0 / 2
+    }
+}
+
+#[votrace::trace]
+impl Shape for Pentagon {
+    fn area(&self) -> u32 {
+        7 * self.0 * self.0 / 4
+    }
+}
+
+fn install_square(shapes: &mut Vec<Box<dyn Shape>>) {
+    shapes.push(Box::new(Square(2)));
+}
+
+fn install_circle(shapes: &mut Vec<Box<dyn Shape>>) {
+    shapes.push(Box::new(Circle(2)));
+}
+
+fn add_triangle(shapes: &mut Vec<Box<dyn Shape>>) {
+    shapes.push(Box::new(Triangle(4)));
+}
+
+fn install_hexagon(shapes: &mut Vec<Box<dyn Shape>>) {
+    shapes.push(Box::new(Hexagon(2)));
+}
+
+fn install_pentagon(shapes: &mut Vec<Box<dyn Shape>>) {
+    shapes.push(Box::new(Pentagon(2)));
+}
+
+type Install = fn(&mut Vec<Box<dyn Shape>>);
+
+const BUILTIN: [Install; 1] = [install_hexagon];
+static EXTRA: &[Install] = &[install_pentagon];
+
+struct Plugin {
+    install: Install,
+}
+
+fn register(plugins: &mut Vec<Plugin>, f: Install) {
+    plugins.push(Plugin { install: f });
+}
+
+fn describe(n: usize) -> usize {
+    n * 10
+}
+
+static DESCRIBE: fn(usize) -> usize = describe;
+
+fn total(shapes: &[Box<dyn Shape>]) -> u32 {
+    shapes.iter().map(|s| s.area()).sum()
+}
+
+fn main() {
+    let mut plugins = Vec::new();
+    register(&mut plugins, install_square);
+    register(&mut plugins, install_circle);
+    for f in BUILTIN.iter().chain(EXTRA) {
+        register(&mut plugins, *f);
+    }
+
+    let mut shapes: Vec<Box<dyn Shape>> = Vec::new();
+    for p in &plugins {
+        (p.install)(&mut shapes);
+    }
+
+    let mut extra: Vec<Box<dyn Shape>> = Vec::new();
+    add_triangle(&mut extra);
+
+    println!(
+        "{} {} {}",
+        total(&shapes),
+        extra[0].area(),
+        DESCRIBE(shapes.len())
+    );
+}
@@ -0,0 +1,92 @@
+// The `verifopt` crate's annotations:
+//   - `#[verifopt::closed]` on the public `Animal`, so `--TRAIT CLASSES--`
+//     in the stats file reports it as closed (#[verifopt::closed]) rather
+//     than open
+//   - `#[verifopt::no_devirt]` on `Noise`, and on `loudest`, whose `Animal`
+//     call in the `map` closure should be left as a vtable call
+//   - `assert_targets!` around two dyn calls, one that FSA narrows to `Cat`
+//     and one where both `Cat` and `Dog` reach it; the build fails if FSA
+//     finds anything else
+//
+// The annotations only change what the rewrite does, so all four sites are
+// still logged. Only Cats and Dogs are made; `Cow`'s impls are what CHA
+// adds at each of them. The asserted calls sit in the closures
+// `assert_targets!` wraps them in, so FSA has to follow the captures.
+
+use verifopt::assert_targets;
+
+#[verifopt::closed]
+pub trait Animal {
+    fn speak(&self) -> String;
+}
+
+#[verifopt::no_devirt]
+pub trait Noise {
+    fn volume(&self) -> u32;
+}
+
+pub struct Cat;
+pub struct Dog;
+pub struct Cow;
+
+#[votrace::trace]
+impl Animal for Cat {
+    fn speak(&self) -> String {
+        "meow".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Animal for Dog {
+    fn speak(&self) -> String {
+        "woof".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Animal for Cow {
+    fn speak(&self) -> String {
+        "moo".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Noise for Cat {
+    fn volume(&self) -> u32 {
+        2
+    }
+}
+
+#[votrace::trace]
+impl Noise for Dog {
+    fn volume(&self) -> u32 {
+        7
+    }
+}
+
+#[votrace::trace]
+impl Noise for Cow {
+    fn volume(&self) -> u32 {
+        9
+    }
+}
+
+#[verifopt::no_devirt]
+#[inline(never)]
+fn loudest(animals: &[&dyn Animal]) -> String {
+    animals.iter().map(|a| a.speak()).max().unwrap_or_default()
+}
+
+fn main() {
+    let n = std::env::args().count();
+
+    let cat: &dyn Animal = &Cat;
+    let one = assert_targets!(cat.speak(), [Cat]);
+
+    let either: &dyn Animal = if n > 5 { &Dog } else { &Cat };
+    let two = assert_targets!(either.speak(), [Cat, Dog]);
+
+    let noisy: &dyn Noise = if n > 5 { &Cat } else { &Dog };
+
+    println!("{} {} {} {}", one, two, noisy.volume(), loudest(&[&Cat, &Dog]));
+}
@@ -0,0 +1,149 @@
+// `dyn Any` type tests picking the concrete type per branch:
+//   - a plugin registry keyed by `TypeId`, looked up with
+//     `TypeId::of::<T>()` and `downcast_ref`
+//   - `is::<T>()` guarding a branch, `downcast_mut`, `Box::downcast`
+//   - an explicit `type_id() == TypeId::of::<T>()` comparison
+//   - `downcast_ref::<Slot<u8>>()` on a value that's a `Slot<u8>` or a
+//     `Slot<u16>`, one generic struct's two instantiations
+//
+// Each `plugin` is only ever made from what one downcast let through: a
+// Logger in `describe` and after `registry.get::<Logger>()`, a Cache after
+// `downcast::<Cache>()`, and just the `Slot<u8>` in `small_slot`, where
+// comparing Adts alone would let the `Slot<u16>` through too. `Metrics` is
+// never constructed, and CHA has every Plugin impl at every site.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+
+trait Plugin {
+    fn run(&self) -> u32;
+}
+
+struct Logger {
+    level: u32,
+}
+#[votrace::trace]
+impl Plugin for Logger {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        self.level
+    }
+}
+
+struct Cache {
+    hits: u32,
+}
+#[votrace::trace]
+impl Plugin for Cache {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        self.hits * 2
+    }
+}
+
+#[allow(dead_code)]
+struct Metrics;
+#[votrace::trace]
+impl Plugin for Metrics {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        7
+    }
+}
+
+struct Slot<T> {
+    value: T,
+}
+#[votrace::trace]
+impl Plugin for Slot<u8> {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        self.value as u32
+    }
+}
+#[votrace::trace]
+impl Plugin for Slot<u16> {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        self.value as u32 + 1
+    }
+}
+
+struct Registry {
+    plugins: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Registry {
+    fn insert<T: Any>(&mut self, plugin: T) {
+        self.plugins.insert(TypeId::of::<T>(), Box::new(plugin));
+    }
+
+    #[inline(never)]
+    fn get<T: Any>(&self) -> Option<&T> {
+        self.plugins.get(&TypeId::of::<T>())?.downcast_ref::<T>()
+    }
+}
+
+#[inline(never)]
+fn describe(value: &dyn Any) -> u32 {
+    if value.is::<Logger>() {
+        let logger = value.downcast_ref::<Logger>().unwrap();
+        let plugin: &dyn Plugin = logger;
+        plugin.run()
+    } else if value.type_id() == TypeId::of::<Cache>() {
+        1
+    } else {
+        0
+    }
+}
+
+#[inline(never)]
+fn small_slot(slot: &dyn Any) -> u32 {
+    match slot.downcast_ref::<Slot<u8>>() {
+        Some(small) => {
+            let plugin: &dyn Plugin = small;
+            plugin.run()
+        }
+        None => 0,
+    }
+}
+
+fn main() {
+    let n = std::env::args().count() as u32;
+
+    let mut registry = Registry {
+        plugins: HashMap::new(),
+    };
+    registry.insert(Logger { level: n });
+    registry.insert(Cache { hits: n + 1 });
+
+    if let Some(logger) = registry.get::<Logger>() {
+        let plugin: &dyn Plugin = logger;
+        println!("{}", plugin.run());
+    }
+
+    let mut boxed: Box<dyn Any> = if n > 1 {
+        Box::new(Logger { level: 3 })
+    } else {
+        Box::new(Cache { hits: 4 })
+    };
+    println!("{}", describe(boxed.as_ref()));
+    if let Some(cache) = boxed.downcast_mut::<Cache>() {
+        cache.hits += 1;
+    }
+
+    let slot: Box<dyn Any> = if n > 2 {
+        Box::new(Slot { value: n as u8 })
+    } else {
+        Box::new(Slot { value: n as u16 })
+    };
+    println!("{}", small_slot(slot.as_ref()));
+
+    match boxed.downcast::<Cache>() {
+        Ok(cache) => {
+            let plugin: Box<dyn Plugin> = cache;
+            println!("{}", plugin.run());
+        }
+        Err(other) => println!("not a cache: {}", describe(other.as_ref())),
+    }
+}
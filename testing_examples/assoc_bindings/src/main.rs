@@ -0,0 +1,124 @@
+// Trait objects that bind an associated type:
+//   - `dyn Iterator<Item = u32>` and `dyn Iterator<Item = String>`, each
+//     with its own implementor, drained by `next` calls through the box
+//   - a user trait with an associated type, `dyn Source<Out = u8>`
+//   - `dyn Fn(u8) -> u8` and `dyn Fn(u8) -> String` closures
+//
+// `sum` only ever drains a Counter and `join` a Names; CHA for those two
+// `Iterator` sites spans std's impls too, so it is not compared (see
+// tests/README.md). `drain`'s CHA is {Bytes, Digits}: the binding rules out
+// `Chars` (Out = char), and FSA also drops `Bytes`, which is never
+// constructed. `chars.take()` has just `Chars` either way. The closure calls
+// aren't trait-object sites of their own.
+
+pub trait Source {
+    type Out;
+    fn take(&mut self) -> Option<Self::Out>;
+}
+
+pub struct Counter(u32);
+pub struct Names(Vec<String>);
+pub struct Evens(u32);
+pub struct Digits(u8);
+pub struct Bytes;
+pub struct Chars;
+
+#[votrace::trace]
+impl Iterator for Counter {
+    type Item = u32;
+    fn next(&mut self) -> Option<u32> {
+        if self.0 < 3 {
+            self.0 += 1;
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+#[votrace::trace]
+impl Iterator for Names {
+    type Item = String;
+    fn next(&mut self) -> Option<String> {
+        self.0.pop()
+    }
+}
+
+#[votrace::trace]
+impl Iterator for Evens {
+    type Item = u32;
+    fn next(&mut self) -> Option<u32> {
+        self.0 += 2;
+        Some(self.0)
+    }
+}
+
+#[votrace::trace]
+impl Source for Digits {
+    type Out = u8;
+    fn take(&mut self) -> Option<u8> {
+        self.0 = self.0.checked_sub(1)?;
+        Some(self.0)
+    }
+}
+
+#[votrace::trace]
+impl Source for Bytes {
+    type Out = u8;
+    fn take(&mut self) -> Option<u8> {
+        Some(0xff)
+    }
+}
+
+#[votrace::trace]
+impl Source for Chars {
+    type Out = char;
+    fn take(&mut self) -> Option<char> {
+        Some('c')
+    }
+}
+
+fn sum(it: &mut dyn Iterator<Item = u32>) -> u32 {
+    let mut total = 0;
+    while let Some(n) = it.next() {
+        total += n;
+    }
+    total
+}
+
+fn join(it: &mut dyn Iterator<Item = String>) -> String {
+    let mut out = String::new();
+    while let Some(s) = it.next() {
+        out.push_str(&s);
+    }
+    out
+}
+
+fn drain(src: &mut dyn Source<Out = u8>) -> usize {
+    let mut count = 0;
+    while src.take().is_some() {
+        count += 1;
+    }
+    count
+}
+
+fn main() {
+    let mut counter: Box<dyn Iterator<Item = u32>> = Box::new(Counter(0));
+    let mut names: Box<dyn Iterator<Item = String>> =
+        Box::new(Names(vec!["b".to_string(), "a".to_string()]));
+    let mut digits: Box<dyn Source<Out = u8>> = Box::new(Digits(4));
+    let mut chars: Box<dyn Source<Out = char>> = Box::new(Chars);
+
+    let double: Box<dyn Fn(u8) -> u8> = Box::new(|x| x * 2);
+    let show: Box<dyn Fn(u8) -> String> = Box::new(|x| format!("<{}>", x));
+
+    println!(
+        "{} {} {} {:?} {} {}",
+        sum(&mut *counter),
+        join(&mut *names),
+        drain(&mut *digits),
+        chars.take(),
+        double(4),
+        show(5)
+    );
+}
@@ -0,0 +1,74 @@
+// Run as FSA by `dispatch_examples.rs`, and with `--verifopt-type cha` and
+// `rta` by `tests/baselines.rs`. Each Animal reaches the program another
+// way, and RTA has to count each one as constructed:
+//   - `Cat` is built by an aggregate in `main`
+//   - `Bird` is fieldless, so `&Bird` is a promoted constant
+//   - `Dog` is only in the `PETS` static's initializer, behind a
+//     `&dyn Animal` (so `Animal: Sync`)
+//   - `Fish` is only in the `SCHOOL` const, a `[&dyn Animal; 1]`
+//
+// `Cow` is never made, so RTA's set is CHA's minus Cow at all three
+// sites. FSA's is what reaches each one: the Cat at `first.speak()`, the
+// Bird at `bird.speak()`, and the Dog and the Fish at `a.speak()`.
+
+trait Animal: Sync {
+    fn speak(&self) -> String;
+}
+
+struct Cat(u8);
+struct Bird;
+struct Dog;
+struct Fish;
+#[allow(dead_code)]
+struct Cow;
+
+#[votrace::trace]
+impl Animal for Cat {
+    fn speak(&self) -> String {
+        format!("meow{}", self.0)
+    }
+}
+
+#[votrace::trace]
+impl Animal for Bird {
+    fn speak(&self) -> String {
+        "tweet".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Animal for Dog {
+    fn speak(&self) -> String {
+        "woof".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Animal for Fish {
+    fn speak(&self) -> String {
+        "blub".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Animal for Cow {
+    fn speak(&self) -> String {
+        "moo".to_string()
+    }
+}
+
+static PETS: &[&dyn Animal] = &[&Dog];
+const SCHOOL: [&dyn Animal; 1] = [&Fish];
+
+#[inline(never)]
+fn speak_all(animals: &[&dyn Animal]) -> String {
+    animals.iter().map(|a| a.speak()).collect()
+}
+
+fn main() {
+    let cat = Cat(std::env::args().count() as u8);
+    let first: &dyn Animal = &cat;
+    let bird: &dyn Animal = &Bird;
+    println!("{} {}", first.speak(), bird.speak());
+    println!("{} {}", speak_all(PETS), speak_all(&SCHOOL));
+}
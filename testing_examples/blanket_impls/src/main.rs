@@ -0,0 +1,107 @@
+// Implementors CHA used to miss because their Self type isn't an ADT:
+//   - `impl Describe for i32`, `for &str`, `for (u8, u8)`, `for fn() -> u8`
+//   - `impl<T: Named> Describe for Tagged<T>` next to them (an ADT, but
+//     generic)
+//   - `impl<T: Named> Greet for T`, a blanket impl, dispatched through a
+//     `dyn Greet` holding a `Dog`, whose only impl of it is the blanket one
+//
+// `show` gets every Describe value but an `Unused`, which is never
+// constructed, so its FSA set should be the five other impls; CHA has all
+// six. `g.greet()` has the one blanket impl either way, instantiated for
+// `Dog` in FSA.
+
+pub trait Describe {
+    fn describe(&self) -> String;
+}
+
+pub trait Named {
+    fn name(&self) -> &'static str;
+}
+
+pub trait Greet {
+    fn greet(&self) -> String;
+}
+
+pub struct Dog;
+pub struct Tagged<T>(T);
+pub struct Unused;
+
+#[votrace::trace]
+impl Named for Dog {
+    fn name(&self) -> &'static str {
+        "dog"
+    }
+}
+
+#[votrace::trace]
+impl<T: Named> Greet for T {
+    fn greet(&self) -> String {
+        format!("hello {}", self.name())
+    }
+}
+
+#[votrace::trace]
+impl Describe for i32 {
+    fn describe(&self) -> String {
+        format!("int {}", self)
+    }
+}
+
+#[votrace::trace]
+impl Describe for &str {
+    fn describe(&self) -> String {
+        format!("str {}", self)
+    }
+}
+
+#[votrace::trace]
+impl Describe for (u8, u8) {
+    fn describe(&self) -> String {
+        format!("pair {} {}", self.0, self.1)
+    }
+}
+
+#[votrace::trace]
+impl Describe for fn() -> u8 {
+    fn describe(&self) -> String {
+        format!("fn {}", self())
+    }
+}
+
+#[votrace::trace]
+impl<T: Named> Describe for Tagged<T> {
+    fn describe(&self) -> String {
+        format!("tagged {}", self.0.name())
+    }
+}
+
+#[votrace::trace]
+impl Describe for Unused {
+    fn describe(&self) -> String {
+        String::from("unused")
+    }
+}
+
+fn seven() -> u8 {
+    7
+}
+
+fn show(d: &dyn Describe) -> String {
+    d.describe()
+}
+
+fn main() {
+    let n = 3i32;
+    let s = "hi";
+    let pair = (1u8, 2u8);
+    let f: fn() -> u8 = seven;
+    let tagged = Tagged(Dog);
+
+    let all: Vec<&dyn Describe> = vec![&n, &s, &pair, &f, &tagged];
+    for d in &all {
+        println!("{}", show(*d));
+    }
+
+    let g: Box<dyn Greet> = Box::new(Dog);
+    println!("{}", g.greet());
+}
@@ -0,0 +1,37 @@
+// A bench with the default harness: there's no `main` here, so the analysis
+// starts from the one the harness generates, which reaches `areas` through
+// the harness' list of benches. Only a Square is made, so FSA should leave
+// Circle out at `shape.area()`.
+
+#![feature(test)]
+
+extern crate test;
+
+use test::Bencher;
+
+trait Shape {
+    fn area(&self) -> u32;
+}
+
+struct Square(u32);
+#[allow(dead_code)]
+struct Circle(u32);
+
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+impl Shape for Circle {
+    fn area(&self) -> u32 {
+        3 * self.0 * self.0
+    }
+}
+
+#[bench]
+fn areas(b: &mut Bencher) {
+    let shape: &dyn Shape = &Square(test::black_box(3));
+    let area = shape.area();
+    b.iter(|| test::black_box(area));
+}
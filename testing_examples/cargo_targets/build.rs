@@ -0,0 +1,39 @@
+// A build script is analyzed from its `main`, like a bin. Only Emit and
+// Rerun are made, so FSA should leave Skip out at `step.run()`.
+
+trait Step {
+    fn run(&self) -> u32;
+}
+
+struct Emit;
+struct Rerun;
+#[allow(dead_code)]
+struct Skip;
+
+impl Step for Emit {
+    fn run(&self) -> u32 {
+        1
+    }
+}
+
+impl Step for Rerun {
+    fn run(&self) -> u32 {
+        println!("cargo:rerun-if-changed=build.rs");
+        2
+    }
+}
+
+impl Step for Skip {
+    fn run(&self) -> u32 {
+        0
+    }
+}
+
+fn main() {
+    let step: &dyn Step = if std::env::var_os("PROFILE").is_some() {
+        &Rerun
+    } else {
+        &Emit
+    };
+    println!("cargo:rustc-env=STEPS={}", step.run());
+}
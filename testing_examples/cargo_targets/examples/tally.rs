@@ -0,0 +1,28 @@
+// An example is analyzed from its `main`, like a bin. Only a Words is made,
+// so FSA should have just its impl at `counter.count(text)`.
+
+trait Counter {
+    fn count(&self, text: &str) -> usize;
+}
+
+struct Words;
+#[allow(dead_code)]
+struct Lines;
+
+impl Counter for Words {
+    fn count(&self, text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+}
+
+impl Counter for Lines {
+    fn count(&self, text: &str) -> usize {
+        text.lines().count()
+    }
+}
+
+fn main() {
+    let counter: &dyn Counter = &Words;
+    let text = std::env::args().collect::<Vec<_>>().join(" ");
+    println!("{}", counter.count(&text));
+}
@@ -0,0 +1,7 @@
+// The package's default target. `tests/cargo_targets.rs` runs `cargo
+// verifopt` on the others: `examples/tally.rs`, the harness bench
+// `benches/speed.rs` and `build.rs`, each with a dyn call of its own.
+
+fn main() {
+    println!("steps: {}", env!("STEPS"));
+}
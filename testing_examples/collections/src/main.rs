@@ -0,0 +1,153 @@
+// Registries kept in the std sequence/hash collections:
+//   - Vec<Box<dyn Handler>>            push, then iter() (via deref to a
+//                                      slice) and v[i] (Index)
+//   - HashMap<String, Box<dyn Command>> insert, get, values()
+//   - VecDeque<Box<dyn Handler>>       push_back, pop_front
+//   - HashMap<Route, Box<dyn Handler>> built by `HashMap::from` out of
+//                                      `(key, value)` pairs
+//
+// Only Log and Count go into the Vec and the VecDeque, so their `handle`
+// sites should be {Count, Log} against CHA's {Count, Log, Unused}; the
+// Command sites should be {Start, Status} without Stop. `routes` keys a
+// Count by a Route holding a Log: `route.handler.handle` should be just
+// {Log} and `handler.handle` just {Count}, not both from mixing up the
+// pair's halves.
+//
+// The registry map uses the default SipHash keys, so `values()` visits
+// its commands in the same order in every build.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+
+trait Handler {
+    fn handle(&self, x: u32) -> u32;
+}
+
+struct Log;
+#[votrace::trace]
+impl Handler for Log {
+    #[inline(never)]
+    fn handle(&self, x: u32) -> u32 {
+        println!("log {x}");
+        x
+    }
+}
+
+struct Count;
+#[votrace::trace]
+impl Handler for Count {
+    #[inline(never)]
+    fn handle(&self, x: u32) -> u32 {
+        x + 1
+    }
+}
+
+struct Unused;
+#[votrace::trace]
+impl Handler for Unused {
+    #[inline(never)]
+    fn handle(&self, x: u32) -> u32 {
+        x * 2
+    }
+}
+
+trait Command {
+    fn run(&self) -> String;
+}
+
+struct Start;
+#[votrace::trace]
+impl Command for Start {
+    #[inline(never)]
+    fn run(&self) -> String {
+        "start".to_string()
+    }
+}
+
+struct Status;
+#[votrace::trace]
+impl Command for Status {
+    #[inline(never)]
+    fn run(&self) -> String {
+        "status".to_string()
+    }
+}
+
+struct Stop;
+#[votrace::trace]
+impl Command for Stop {
+    #[inline(never)]
+    fn run(&self) -> String {
+        "stop".to_string()
+    }
+}
+
+struct Route {
+    id: u32,
+    handler: Box<dyn Handler>,
+}
+
+impl PartialEq for Route {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for Route {}
+
+impl Hash for Route {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+fn main() {
+    // keep Unused/Stop's vtables around so they're real CHA candidates
+    let _unused: &dyn Handler = &Unused;
+    let _stop: &dyn Command = &Stop;
+
+    let mut handlers: Vec<Box<dyn Handler>> = Vec::new();
+    handlers.push(Box::new(Log));
+    handlers.push(Box::new(Count));
+
+    let mut x = 0;
+    for h in handlers.iter() {
+        x = h.handle(x);
+    }
+    x = handlers[1].handle(x);
+
+    let mut commands: HashMap<String, Box<dyn Command>, BuildHasherDefault<DefaultHasher>> =
+        HashMap::default();
+    commands.insert("start".to_string(), Box::new(Start));
+    commands.insert("status".to_string(), Box::new(Status));
+
+    if let Some(cmd) = commands.get("status") {
+        println!("{}", cmd.run());
+    }
+    let mut outputs: Vec<String> = commands.values().map(|c| c.run()).collect();
+    outputs.sort();
+    println!("{outputs:?}");
+
+    let mut queue: VecDeque<Box<dyn Handler>> = VecDeque::new();
+    queue.push_back(Box::new(Count));
+    queue.push_back(Box::new(Log));
+    while let Some(h) = queue.pop_front() {
+        x = h.handle(x);
+    }
+
+    let routes: HashMap<Route, Box<dyn Handler>> = HashMap::from([(
+        Route {
+            id: 1,
+            handler: Box::new(Log),
+        },
+        Box::new(Count) as Box<dyn Handler>,
+    )]);
+    for (route, handler) in routes.iter() {
+        x = route.handler.handle(x + route.id);
+        x = handler.handle(x);
+    }
+
+    let seen: HashSet<u32> = [x, x + 1].into_iter().collect();
+    println!("{x} {}", seen.len());
+}
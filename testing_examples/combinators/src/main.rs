@@ -0,0 +1,117 @@
+// Trait objects through Option/Result combinators and `?`:
+//   - Option<Box<dyn Shape>>   `map(..).unwrap()`, `and_then`,
+//                              `unwrap_or_else`, `take`/`replace` on a field
+//   - Result<Box<dyn Shape>>   built with `ok_or`, threaded through `?`
+//                              (same error type) and `map_err`
+//
+// `lookup` only builds a Circle or a Square, and everything dispatched
+// on comes out of it or is a Square literal, so every site should be
+// {Circle, Square}: each combinator has to carry the box through (its
+// closure's argument, `?`'s `Ok`, the old value `replace` hands back)
+// instead of widening it to its return type, which gets CHA's set with
+// `Hexagon` in it.
+
+trait Shape {
+    fn area(&self) -> f64;
+    fn name(&self) -> &'static str;
+}
+
+struct Circle(f64);
+#[votrace::trace]
+impl Shape for Circle {
+    #[inline(never)]
+    fn area(&self) -> f64 {
+        3.0 * self.0 * self.0
+    }
+    #[inline(never)]
+    fn name(&self) -> &'static str {
+        "circle"
+    }
+}
+
+struct Square(f64);
+#[votrace::trace]
+impl Shape for Square {
+    #[inline(never)]
+    fn area(&self) -> f64 {
+        self.0 * self.0
+    }
+    #[inline(never)]
+    fn name(&self) -> &'static str {
+        "square"
+    }
+}
+
+#[allow(dead_code)]
+struct Hexagon;
+#[votrace::trace]
+impl Shape for Hexagon {
+    #[inline(never)]
+    fn area(&self) -> f64 {
+        6.0
+    }
+    #[inline(never)]
+    fn name(&self) -> &'static str {
+        "hexagon"
+    }
+}
+
+#[derive(Debug)]
+struct ParseError(&'static str);
+
+#[inline(never)]
+fn lookup(kind: u8) -> Option<Box<dyn Shape>> {
+    match kind {
+        0 => Some(Box::new(Circle(1.0))),
+        1 => Some(Box::new(Square(2.0))),
+        _ => None,
+    }
+}
+
+#[inline(never)]
+fn parse(kind: u8) -> Result<Box<dyn Shape>, ParseError> {
+    lookup(kind).ok_or(ParseError("unknown kind"))
+}
+
+#[inline(never)]
+fn parse_twice(a: u8, b: u8) -> Result<(Box<dyn Shape>, Box<dyn Shape>), ParseError> {
+    let first = parse(a)?;
+    let second = parse(b)?;
+    Ok((first, second))
+}
+
+struct Slot {
+    shape: Option<Box<dyn Shape>>,
+}
+
+fn main() {
+    let kind = std::env::args().count() as u8 - 1;
+
+    let label = lookup(kind).map(|s| s.name()).unwrap();
+    println!("{label}");
+
+    let shape = lookup(kind + 1).map(|s| s).unwrap();
+    println!("{} {}", shape.name(), shape.area());
+
+    let bigger = lookup(kind)
+        .and_then(|s| if s.area() > 1.0 { Some(s) } else { lookup(1) })
+        .unwrap_or_else(|| Box::new(Square(3.0)));
+    println!("{}", bigger.area());
+
+    match parse_twice(kind, kind + 1) {
+        Ok((a, b)) => println!("{} {}", a.name(), b.name()),
+        Err(e) => println!("{e:?}"),
+    }
+
+    let msg = parse(kind + 5)
+        .map_err(|e| e.0)
+        .map(|s| s.name())
+        .unwrap_or_else(|e| e);
+    println!("{msg}");
+
+    let mut slot = Slot { shape: lookup(kind) };
+    let old = slot.shape.replace(Box::new(Square(4.0))).unwrap();
+    println!("{}", old.name());
+    let cur = slot.shape.take().unwrap();
+    println!("{} {}", cur.name(), slot.shape.is_none());
+}
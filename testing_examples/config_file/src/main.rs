@@ -0,0 +1,72 @@
+// Settings from verifopt.toml:
+//   - `[analysis] exclude` keeps the interpreter out of `opaque::make`, so
+//     the `Box<dyn Shape>` it returns can be any `Shape`: FSA should report
+//     CHA's set at `b.area()`
+//   - `[rewrite] exclude` leaves the dyn call in `legacy::total` alone (its
+//     decision in `stats` says so), whatever the analysis finds there
+//   - `[limits] max_depth` raises the interpreter's call depth
+//
+// Only `a.area()` narrows, to the Square it's built from. `b` can be a
+// Triangle as far as the analysis knows, and it's in the slice
+// `legacy::total` gets, so that site keeps all three impls too.
+
+trait Shape {
+    fn area(&self) -> u32;
+}
+
+struct Square(u32);
+struct Circle(u32);
+#[allow(dead_code)]
+struct Triangle(u32);
+
+#[votrace::trace]
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Circle {
+    fn area(&self) -> u32 {
+        3 * self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Triangle {
+    fn area(&self) -> u32 {
+        self.0 * self.0 / 2
+    }
+}
+
+mod opaque {
+    use super::{Circle, Shape, Square};
+
+    pub fn make(n: u32) -> Box<dyn Shape> {
+        if n % 2 == 0 {
+            Box::new(Square(n))
+        } else {
+            Box::new(Circle(n))
+        }
+    }
+}
+
+mod legacy {
+    use super::Shape;
+
+    pub fn total(shapes: &[Box<dyn Shape>]) -> u32 {
+        shapes.iter().map(|s| s.area()).sum()
+    }
+}
+
+fn main() {
+    let n = std::env::args().count() as u32;
+
+    let a: Box<dyn Shape> = Box::new(Square(3));
+    let b = opaque::make(n);
+    println!("{} {}", a.area(), b.area());
+
+    let shapes = vec![a, b, Box::new(Circle(2)) as Box<dyn Shape>];
+    println!("{}", legacy::total(&shapes));
+}
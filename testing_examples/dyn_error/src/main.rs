@@ -0,0 +1,133 @@
+// Concrete error types through `Box<dyn Error>`:
+//   - `?` converting a ConfigError/IoLikeError into Box<dyn Error>
+//   - an explicit `.into()` into Box<dyn Error + Send + Sync>
+//   - `Display::fmt`/`source()` on the boxed error
+//   - `downcast_ref` and `Box::downcast` back to a concrete type
+//
+// `start` can fail with either a ConfigError or an IoLikeError, so `Shown`'s
+// `fmt` and the first `e.source()` should be {ConfigError, IoLikeError}.
+// `start_sync` only ever boxes a ConfigError, and the `Err` arm of
+// `downcast::<IoLikeError>` only gets what isn't one, so both of their
+// `source()` calls should be ConfigError's alone. `TimeoutError` is never
+// constructed. CHA for `Error` and `Display` spans std's impls too, so it is
+// not compared (see tests/README.md).
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+struct ConfigError {
+    key: &'static str,
+}
+#[votrace::trace]
+impl fmt::Display for ConfigError {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bad config key {}", self.key)
+    }
+}
+#[votrace::trace]
+impl Error for ConfigError {
+    #[inline(never)]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+#[derive(Debug)]
+struct IoLikeError {
+    cause: ConfigError,
+}
+#[votrace::trace]
+impl fmt::Display for IoLikeError {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "io failed")
+    }
+}
+#[votrace::trace]
+impl Error for IoLikeError {
+    #[inline(never)]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.cause)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+struct TimeoutError;
+#[votrace::trace]
+impl fmt::Display for TimeoutError {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "timed out")
+    }
+}
+#[votrace::trace]
+impl Error for TimeoutError {}
+
+#[inline(never)]
+fn read_config(n: usize) -> Result<u32, ConfigError> {
+    if n > 1 { Ok(n as u32) } else { Err(ConfigError { key: "port" }) }
+}
+
+#[inline(never)]
+fn open(n: usize) -> Result<u32, IoLikeError> {
+    if n > 2 {
+        Ok(0)
+    } else {
+        Err(IoLikeError {
+            cause: ConfigError { key: "path" },
+        })
+    }
+}
+
+#[inline(never)]
+fn start(n: usize) -> Result<u32, Box<dyn Error>> {
+    let port = read_config(n)?;
+    let fd = open(n)?;
+    Ok(port + fd)
+}
+
+#[inline(never)]
+fn start_sync(n: usize) -> Result<u32, Box<dyn Error + Send + Sync>> {
+    match read_config(n) {
+        Ok(p) => Ok(p),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Formats through the `dyn Error` vtable here rather than inside std's
+// `Display for Box<T>`, so the site has a span in this crate.
+struct Shown<'a>(&'a (dyn Error + 'static));
+impl fmt::Display for Shown<'_> {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.0, f)
+    }
+}
+
+fn main() {
+    let n = std::env::args().count();
+
+    match start(n) {
+        Ok(v) => println!("{v}"),
+        Err(e) => {
+            println!("{}", Shown(&*e));
+            if let Some(cause) = e.source() {
+                println!("caused by {}", Shown(cause));
+            }
+            if let Some(cfg) = e.downcast_ref::<ConfigError>() {
+                println!("config key {}", cfg.key);
+            }
+            match e.downcast::<IoLikeError>() {
+                Ok(io) => println!("io {}", io.cause),
+                Err(other) => println!("other {}", other.source().is_some()),
+            }
+        }
+    }
+
+    if let Err(e) = start_sync(n) {
+        println!("{} {}", Shown(&*e), e.source().is_none());
+    }
+}
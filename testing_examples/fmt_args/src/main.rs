@@ -0,0 +1,108 @@
+// `Display`/`Debug` impls and `fmt::Write` sinks behind `format_args!`:
+//   - `Box<dyn Display>` values wrapped in `Labeled` and printed with
+//     `println!` and `format!`, so the `dyn Display` call is made from
+//     inside a `Display::fmt` that only `fmt::write` ever calls
+//   - a `{:?}` placeholder on a concrete type
+//   - `write_str` on a custom `fmt::Write` sink, passed as `&mut dyn Write`
+//
+// Only a Meters and a Seconds are ever boxed, so `Labeled`'s call should be
+// {Meters, Seconds}, and `record`'s sink is only ever a Log. `Celsius` and
+// `Discard` are never constructed. CHA for `Display` and `fmt::Write` spans
+// std's impls too, so it is not compared (see tests/README.md).
+
+use std::fmt::{self, Display, Write};
+
+struct Meters(f64);
+#[votrace::trace]
+impl Display for Meters {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}m", self.0)
+    }
+}
+
+struct Seconds(u32);
+#[votrace::trace]
+impl Display for Seconds {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("t=")?;
+        write!(f, "{}s", self.0)
+    }
+}
+
+#[allow(dead_code)]
+struct Celsius(f64);
+#[votrace::trace]
+impl Display for Celsius {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}C", self.0)
+    }
+}
+
+// Formats its value through the `dyn Display` vtable here, so the site
+// has a span in this crate rather than in std's `Display for Box<T>`.
+struct Labeled<'a>(&'a dyn Display);
+impl Display for Labeled<'_> {
+    #[inline(never)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<")?;
+        Display::fmt(self.0, f)?;
+        f.write_str(">")
+    }
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+struct Log {
+    lines: Vec<String>,
+}
+#[votrace::trace]
+impl Write for Log {
+    #[inline(never)]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.lines.push(s.to_string());
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+struct Discard;
+#[votrace::trace]
+impl Write for Discard {
+    #[inline(never)]
+    fn write_str(&mut self, _s: &str) -> fmt::Result {
+        Ok(())
+    }
+}
+
+#[inline(never)]
+fn record(sink: &mut dyn Write, value: &dyn Display) -> fmt::Result {
+    sink.write_str(&format!("[{}]", Labeled(value)))
+}
+
+fn main() {
+    let n = std::env::args().count();
+
+    let values: Vec<Box<dyn Display>> = vec![Box::new(Meters(n as f64)), Box::new(Seconds(3))];
+    for v in values.iter() {
+        println!("{}", Labeled(v.as_ref()));
+    }
+
+    let joined = format!(
+        "{} and {}",
+        Labeled(values[0].as_ref()),
+        Labeled(values[1].as_ref())
+    );
+    println!("{joined} {:?}", Point { x: 1, y: n as i32 });
+
+    let mut log = Log { lines: Vec::new() };
+    record(&mut log, values[1].as_ref()).unwrap();
+    println!("{}", log.lines.concat());
+}
@@ -0,0 +1,66 @@
+// Calls through `fn` pointers read from a `static` table the analysis can't
+// see into (it's behind `black_box`), whose signature takes a pointer to a
+// fn that takes a reference:
+//   - the table's pointer type is `fn(Measure) -> Box<dyn Shape>`, while
+//     `make_circle` spells its argument `for<'a> fn(&'a str) -> u32`: the
+//     same type up to how its lifetime is bound and named, so FSA should
+//     find `Circle` as well as `Square` at `make(len).area()`
+//   - `make_triangle` has the same signature but its address is never
+//     taken, so `Triangle` should be left out
+
+type Measure = fn(&str) -> u32;
+
+trait Shape {
+    fn area(&self) -> u32;
+}
+
+struct Square(u32);
+struct Circle(u32);
+#[allow(dead_code)]
+struct Triangle(u32);
+
+#[votrace::trace]
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Circle {
+    fn area(&self) -> u32 {
+        3 * self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Triangle {
+    fn area(&self) -> u32 {
+        self.0 * self.0 / 2
+    }
+}
+
+fn len(s: &str) -> u32 {
+    s.len() as u32
+}
+
+fn make_square(measure: Measure) -> Box<dyn Shape> {
+    Box::new(Square(measure("square")))
+}
+
+fn make_circle(measure: for<'a> fn(&'a str) -> u32) -> Box<dyn Shape> {
+    Box::new(Circle(measure("circle")))
+}
+
+#[allow(dead_code)]
+fn make_triangle(measure: Measure) -> Box<dyn Shape> {
+    Box::new(Triangle(measure("triangle")))
+}
+
+static MAKERS: [fn(Measure) -> Box<dyn Shape>; 2] = [make_square, make_circle];
+
+fn main() {
+    let makers = std::hint::black_box(&MAKERS);
+    let total: u32 = makers.iter().map(|make| make(len).area()).sum();
+    println!("{}", total);
+}
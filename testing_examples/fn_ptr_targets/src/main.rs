@@ -0,0 +1,96 @@
+// Calls through `fn` pointers whose values the analysis has lost (they
+// sit in a `Vec` of structs):
+//   - `(f.make)(n)` can only reach the fns whose address is taken with
+//     that signature, `make_square` and `make_circle`; `make_triangle` has
+//     the same signature but is never cast to a pointer, so FSA should
+//     leave `Triangle` out at `s.area()`
+//   - `(f.extend)(&mut shapes)` pushes through its argument, which the
+//     pointer's return type says nothing about: FSA should still find
+//     `Hexagon` at `s.area()`
+//   - `extend`'s pointer type names its lifetime and `add_hexagon`'s
+//     doesn't, which mustn't keep them apart
+
+trait Shape {
+    fn area(&self) -> u32;
+}
+
+struct Square(u32);
+struct Circle(u32);
+struct Hexagon(u32);
+#[allow(dead_code)]
+struct Triangle(u32);
+
+#[votrace::trace]
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Circle {
+    fn area(&self) -> u32 {
+        3 * self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Hexagon {
+    fn area(&self) -> u32 {
+        5 * self.0 * self.0 / 2
+    }
+}
+
+#[votrace::trace]
+impl Shape for Triangle {
+    fn area(&self) -> u32 {
+        self.0 * self.0 / 2
+    }
+}
+
+fn make_square(n: u32) -> Box<dyn Shape> {
+    Box::new(Square(n))
+}
+
+fn make_circle(n: u32) -> Box<dyn Shape> {
+    Box::new(Circle(n))
+}
+
+#[allow(dead_code)]
+fn make_triangle(n: u32) -> Box<dyn Shape> {
+    Box::new(Triangle(n))
+}
+
+fn add_hexagon(shapes: &mut Vec<Box<dyn Shape>>) {
+    shapes.push(Box::new(Hexagon(2)));
+}
+
+struct Factory {
+    make: fn(u32) -> Box<dyn Shape>,
+    extend: for<'a> fn(&'a mut Vec<Box<dyn Shape>>),
+}
+
+fn build(factories: &[Factory], n: u32) -> Vec<Box<dyn Shape>> {
+    let mut shapes = Vec::new();
+    for f in factories {
+        shapes.push((f.make)(n));
+        (f.extend)(&mut shapes);
+    }
+    shapes
+}
+
+fn main() {
+    let factories = vec![
+        Factory {
+            make: make_square,
+            extend: add_hexagon,
+        },
+        Factory {
+            make: make_circle,
+            extend: add_hexagon,
+        },
+    ];
+    let shapes = build(&factories, 3);
+    let total: u32 = shapes.iter().map(|s| s.area()).sum();
+    println!("{} {}", shapes.len(), total);
+}
@@ -0,0 +1,149 @@
+// Writes through one alias of a heap object, dispatched through another:
+//   - Vec<Box<dyn Animal>>       an element replaced through `get_mut` in
+//                                one fn, and through `IndexMut` in another,
+//                                then dispatched through `iter()`
+//   - Rc<Pen>                    the pen's animal replaced through
+//                                `Rc::get_mut`, dispatched through a clone
+//   - Vec<Box<dyn Observer>>     observers added by `subscribe` and notified
+//                                by `publish`, both through `&mut Subject`
+//
+// The zoo's slot should end up {Cat, Cow, Dog}: the Cats pushed, plus what
+// each accessor wrote, where the `&mut` accessors used to widen it to every
+// impl. The pen keeps its Cat too, since `get_mut` may fail, so the clone
+// sees {Cat, Dog}; `publish` sees {Counter, Logger}. `Bird` and `Mute` are
+// never constructed.
+
+use std::rc::Rc;
+
+pub trait Animal {
+    fn speak(&self) -> usize;
+}
+
+pub struct Cat;
+#[votrace::trace]
+impl Animal for Cat {
+    #[inline(never)]
+    fn speak(&self) -> usize {
+        1
+    }
+}
+
+pub struct Dog;
+#[votrace::trace]
+impl Animal for Dog {
+    #[inline(never)]
+    fn speak(&self) -> usize {
+        2
+    }
+}
+
+pub struct Cow;
+#[votrace::trace]
+impl Animal for Cow {
+    #[inline(never)]
+    fn speak(&self) -> usize {
+        3
+    }
+}
+
+#[allow(dead_code)]
+pub struct Bird;
+#[votrace::trace]
+impl Animal for Bird {
+    #[inline(never)]
+    fn speak(&self) -> usize {
+        4
+    }
+}
+
+#[inline(never)]
+fn replace_first(zoo: &mut Vec<Box<dyn Animal>>) {
+    if let Some(first) = zoo.get_mut(0) {
+        *first = Box::new(Dog);
+    }
+}
+
+#[inline(never)]
+fn replace_last(zoo: &mut Vec<Box<dyn Animal>>) {
+    let last = zoo.len() - 1;
+    zoo[last] = Box::new(Cow);
+}
+
+pub struct Pen {
+    animal: Box<dyn Animal>,
+}
+
+pub trait Observer {
+    fn notify(&self, event: usize) -> usize;
+}
+
+pub struct Logger;
+#[votrace::trace]
+impl Observer for Logger {
+    #[inline(never)]
+    fn notify(&self, event: usize) -> usize {
+        event + 10
+    }
+}
+
+pub struct Counter;
+#[votrace::trace]
+impl Observer for Counter {
+    #[inline(never)]
+    fn notify(&self, event: usize) -> usize {
+        event + 20
+    }
+}
+
+#[allow(dead_code)]
+pub struct Mute;
+#[votrace::trace]
+impl Observer for Mute {
+    #[inline(never)]
+    fn notify(&self, _event: usize) -> usize {
+        0
+    }
+}
+
+pub struct Subject {
+    observers: Vec<Box<dyn Observer>>,
+}
+
+impl Subject {
+    #[inline(never)]
+    fn subscribe(&mut self, observer: Box<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    #[inline(never)]
+    fn publish(&self, event: usize) -> usize {
+        self.observers.iter().map(|o| o.notify(event)).sum()
+    }
+}
+
+fn main() {
+    let mut zoo: Vec<Box<dyn Animal>> = Vec::new();
+    zoo.push(Box::new(Cat));
+    zoo.push(Box::new(Cat));
+    replace_first(&mut zoo);
+    replace_last(&mut zoo);
+    let total: usize = zoo.iter().map(|a| a.speak()).sum();
+
+    let mut pen = Rc::new(Pen {
+        animal: Box::new(Cat),
+    });
+    if let Some(p) = Rc::get_mut(&mut pen) {
+        p.animal = Box::new(Dog);
+    }
+    let other = Rc::clone(&pen);
+    let penned = other.animal.speak();
+
+    let mut subject = Subject {
+        observers: Vec::new(),
+    };
+    subject.subscribe(Box::new(Logger));
+    subject.subscribe(Box::new(Counter));
+    let notified = subject.publish(std::env::args().count());
+
+    println!("{} {} {}", total, penned, notified);
+}
@@ -0,0 +1,165 @@
+// Trait objects behind interior mutability:
+//   - Rc<RefCell<Box<dyn State>>>  the state is replaced through an
+//                                  Rc::clone alias (`Machine::step`) and
+//                                  dispatched through the original
+//                                  (`Machine::run`)
+//   - Mutex<Vec<Box<dyn Job>>>     pushed through `lock()` in one fn,
+//                                  drained in another
+//   - Cell<&dyn Job>               `set`, then `get`
+//
+// The state cell starts as Start and `step` stores whatever `next`
+// returns, so both State sites should be {Done, Running, Start}: the
+// store through the alias has to reach `run`'s reads. `Stuck` is never
+// constructed, and CHA has it. Both Job sites should be {Print, Sum},
+// without `Idle`: the queue only gets what `submit` pushes, and the cell
+// `&PRINT` then `&sum`.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+trait State {
+    fn next(&self) -> Box<dyn State>;
+    fn name(&self) -> &'static str;
+}
+
+struct Start;
+#[votrace::trace]
+impl State for Start {
+    #[inline(never)]
+    fn next(&self) -> Box<dyn State> {
+        Box::new(Running(0))
+    }
+    #[inline(never)]
+    fn name(&self) -> &'static str {
+        "start"
+    }
+}
+
+struct Running(u32);
+#[votrace::trace]
+impl State for Running {
+    #[inline(never)]
+    fn next(&self) -> Box<dyn State> {
+        if self.0 < 2 {
+            Box::new(Running(self.0 + 1))
+        } else {
+            Box::new(Done)
+        }
+    }
+    #[inline(never)]
+    fn name(&self) -> &'static str {
+        "running"
+    }
+}
+
+struct Done;
+#[votrace::trace]
+impl State for Done {
+    #[inline(never)]
+    fn next(&self) -> Box<dyn State> {
+        Box::new(Done)
+    }
+    #[inline(never)]
+    fn name(&self) -> &'static str {
+        "done"
+    }
+}
+
+#[allow(dead_code)]
+struct Stuck;
+#[votrace::trace]
+impl State for Stuck {
+    #[inline(never)]
+    fn next(&self) -> Box<dyn State> {
+        Box::new(Stuck)
+    }
+    #[inline(never)]
+    fn name(&self) -> &'static str {
+        "stuck"
+    }
+}
+
+struct Machine {
+    state: Rc<RefCell<Box<dyn State>>>,
+}
+
+impl Machine {
+    #[inline(never)]
+    fn step(handle: &Rc<RefCell<Box<dyn State>>>) {
+        let next = handle.borrow().next();
+        *handle.borrow_mut() = next;
+    }
+
+    #[inline(never)]
+    fn run(&self) {
+        let handle = Rc::clone(&self.state);
+        for _ in 0..5 {
+            println!("{}", self.state.borrow().name());
+            Machine::step(&handle);
+        }
+    }
+}
+
+trait Job {
+    fn run(&self) -> u32;
+}
+
+struct Print;
+#[votrace::trace]
+impl Job for Print {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        println!("print");
+        1
+    }
+}
+
+struct Sum(u32);
+#[votrace::trace]
+impl Job for Sum {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        self.0 + 1
+    }
+}
+
+#[allow(dead_code)]
+struct Idle;
+#[votrace::trace]
+impl Job for Idle {
+    #[inline(never)]
+    fn run(&self) -> u32 {
+        0
+    }
+}
+
+#[inline(never)]
+fn submit(queue: &Mutex<Vec<Box<dyn Job>>>, job: Box<dyn Job>) {
+    queue.lock().unwrap().push(job);
+}
+
+#[inline(never)]
+fn drain(queue: &Mutex<Vec<Box<dyn Job>>>) -> u32 {
+    let jobs = std::mem::take(&mut *queue.lock().unwrap());
+    jobs.iter().map(|j| j.run()).sum()
+}
+
+static PRINT: Print = Print;
+
+fn main() {
+    let machine = Machine {
+        state: Rc::new(RefCell::new(Box::new(Start))),
+    };
+    machine.run();
+
+    let queue: Mutex<Vec<Box<dyn Job>>> = Mutex::new(Vec::new());
+    submit(&queue, Box::new(Print));
+    submit(&queue, Box::new(Sum(41)));
+    println!("{}", drain(&queue));
+
+    let sum = Sum(1);
+    let current: Cell<&dyn Job> = Cell::new(&PRINT);
+    current.set(&sum);
+    println!("{}", current.get().run());
+}
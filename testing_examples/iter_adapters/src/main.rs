@@ -0,0 +1,142 @@
+// Trait objects flowing through iterator adapter chains:
+//   - Vec<Box<dyn Handler>>   `iter().filter(..).map(|h| h.as_ref())`,
+//                             `enumerate`, `rev`, `for_each`, `fold`
+//   - chain/zip over two Vecs, `collect` into a Vec and a HashMap
+//   - Box<dyn Iterator<Item = &dyn Handler>>, a local `Active`, driven by
+//     `next` through the vtable
+//
+// `handlers` holds an Echo and a Mute and `extra` a Double, so the sites
+// over `handlers` alone should be {Echo, Mute}, the `chain` one
+// {Double, Echo, Mute}, and the ones over `extra` alone (`b`, `picked`)
+// {Double}; `Audit` is never constructed, and CHA has it everywhere.
+// `it.next()` should be just `Active`'s, where CHA has every std
+// `Iterator` whose `Item` fits (not compared, see tests/README.md).
+
+use std::collections::HashMap;
+
+trait Handler {
+    fn handle(&self, x: u32) -> u32;
+    fn enabled(&self) -> bool;
+}
+
+struct Echo;
+#[votrace::trace]
+impl Handler for Echo {
+    #[inline(never)]
+    fn handle(&self, x: u32) -> u32 {
+        x
+    }
+    #[inline(never)]
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+struct Double;
+#[votrace::trace]
+impl Handler for Double {
+    #[inline(never)]
+    fn handle(&self, x: u32) -> u32 {
+        x * 2
+    }
+    #[inline(never)]
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+struct Mute;
+#[votrace::trace]
+impl Handler for Mute {
+    #[inline(never)]
+    fn handle(&self, _x: u32) -> u32 {
+        0
+    }
+    #[inline(never)]
+    fn enabled(&self) -> bool {
+        false
+    }
+}
+
+#[allow(dead_code)]
+struct Audit;
+#[votrace::trace]
+impl Handler for Audit {
+    #[inline(never)]
+    fn handle(&self, x: u32) -> u32 {
+        x + 1000
+    }
+    #[inline(never)]
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+static ECHO: Echo = Echo;
+static MUTE: Mute = Mute;
+
+/// Hands out the enabled handlers of a fixed queue, last first.
+struct Active {
+    queue: Vec<&'static dyn Handler>,
+}
+
+#[votrace::trace]
+impl Iterator for Active {
+    type Item = &'static dyn Handler;
+
+    #[inline(never)]
+    fn next(&mut self) -> Option<&'static dyn Handler> {
+        while let Some(h) = self.queue.pop() {
+            if h.enabled() {
+                return Some(h);
+            }
+        }
+        None
+    }
+}
+
+#[inline(never)]
+fn active() -> Box<dyn Iterator<Item = &'static dyn Handler>> {
+    Box::new(Active {
+        queue: vec![&ECHO, &MUTE],
+    })
+}
+
+fn main() {
+    let n = std::env::args().count() as u32;
+
+    let handlers: Vec<Box<dyn Handler>> = vec![Box::new(Echo), Box::new(Mute)];
+    let extra: Vec<Box<dyn Handler>> = vec![Box::new(Double)];
+
+    let total: u32 = handlers
+        .iter()
+        .filter(|h| h.enabled())
+        .map(|h| h.as_ref())
+        .fold(0, |acc, h| acc + h.handle(n));
+    println!("{total}");
+
+    for (i, h) in handlers.iter().enumerate().rev() {
+        println!("{i} {}", h.handle(n));
+    }
+
+    handlers
+        .iter()
+        .chain(extra.iter())
+        .for_each(|h| println!("{}", h.handle(n)));
+
+    for (a, b) in handlers.iter().zip(extra.iter()) {
+        println!("{}", a.handle(b.handle(n)));
+    }
+
+    let picked: Vec<&dyn Handler> = extra.iter().map(|h| h.as_ref()).collect();
+    println!("{}", picked[0].handle(n));
+
+    let named: HashMap<u32, &Box<dyn Handler>> =
+        handlers.iter().enumerate().map(|(i, h)| (i as u32, h)).collect();
+    println!("{}", named[&0].handle(n));
+
+    let mut it = active();
+    while let Some(h) = it.next() {
+        println!("{}", h.handle(n));
+    }
+}
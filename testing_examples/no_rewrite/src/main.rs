@@ -0,0 +1,28 @@
+// One dyn call with a single possible target, which a rewriting build
+// always turns into a direct call, so whether `rewritten` gets written
+// says which kind of build ran.
+
+pub trait Animal {
+    fn speak(&self, base: usize) -> usize;
+}
+
+pub struct Cat;
+#[votrace::trace]
+impl Animal for Cat {
+    fn speak(&self, base: usize) -> usize {
+        base + 11111
+    }
+}
+
+pub struct Fish;
+#[votrace::trace]
+impl Animal for Fish {
+    fn speak(&self, base: usize) -> usize {
+        base + 22222
+    }
+}
+
+fn main() {
+    let animal: Box<dyn Animal> = Box::new(Cat);
+    println!("{}", animal.speak(std::env::args().count()));
+}
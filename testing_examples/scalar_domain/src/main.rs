@@ -0,0 +1,82 @@
+// Factories that pick an impl by a computed integer:
+//   - `make(n % 3)`          a remainder by a constant is one of 0, 1, 2
+//   - `make((n & 1) + 1)`    a mask and a checked add give 1 or 2
+//   - `make((n > 4) as usize * 2)`
+//                            a comparison is 0 or 1, so this is 0 or 2
+//   - a loop counter         widened to every value across the `while`'s
+//                            iterations, then bounded again by `i % 3`
+//
+// So `by_rem` and the loop's shape should be {Circle, Square, Triangle},
+// `by_mask` {Square, Triangle} and `by_cmp` {Circle, Square}. `Hexagon` is
+// only built in `make`'s `_` arm, which no call can reach; before the
+// interval domain, `n % 3` was just an unknown integer and took every arm.
+
+pub trait Shape {
+    fn sides(&self) -> usize;
+}
+
+pub struct Circle;
+pub struct Triangle;
+pub struct Square;
+pub struct Hexagon;
+
+#[votrace::trace]
+impl Shape for Circle {
+    fn sides(&self) -> usize {
+        0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Triangle {
+    fn sides(&self) -> usize {
+        3
+    }
+}
+
+#[votrace::trace]
+impl Shape for Square {
+    fn sides(&self) -> usize {
+        4
+    }
+}
+
+#[votrace::trace]
+impl Shape for Hexagon {
+    fn sides(&self) -> usize {
+        6
+    }
+}
+
+fn make(i: usize) -> Box<dyn Shape> {
+    match i {
+        0 => Box::new(Circle),
+        1 => Box::new(Triangle),
+        2 => Box::new(Square),
+        _ => Box::new(Hexagon),
+    }
+}
+
+fn main() {
+    let n = std::env::args().count();
+
+    let by_rem = make(n % 3);
+    let by_mask = make((n & 1) + 1);
+    let by_cmp = make((n > 4) as usize * 2);
+
+    let counts = [0u8; 3];
+    let mut total = 0;
+    let mut i = 0;
+    while i < counts.len() {
+        total += make(i % 3).sides() + counts[i] as usize;
+        i += 1;
+    }
+
+    println!(
+        "{} {} {} {}",
+        by_rem.sides(),
+        by_mask.sides(),
+        by_cmp.sides(),
+        total
+    );
+}
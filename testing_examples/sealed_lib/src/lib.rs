@@ -0,0 +1,57 @@
+// A library, so there is no `main` to start from: the analysis starts from
+// every non-generic fn it exports, here `total`. Nothing here is ever run,
+// so there are no traces to compare.
+//   - `Shape`: `pub`, so open, and `total`'s `shape.area()` keeps its
+//     vtable call: a downstream crate could pass in its own impl
+//   - `Hidden`: `pub(crate)`, so closed, and `hidden.secret()` is rewritten
+//
+// Both sites should be just `Square`'s; `Circle` implements both traits but
+// is never constructed, and CHA has it at each.
+
+pub trait Shape {
+    fn area(&self) -> u32;
+}
+
+pub(crate) trait Hidden {
+    fn secret(&self) -> u32;
+}
+
+pub struct Square(pub u32);
+
+#[allow(dead_code)]
+struct Circle(u32);
+
+impl Shape for Square {
+    #[inline(never)]
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+impl Shape for Circle {
+    #[inline(never)]
+    fn area(&self) -> u32 {
+        3 * self.0 * self.0
+    }
+}
+
+impl Hidden for Square {
+    #[inline(never)]
+    fn secret(&self) -> u32 {
+        self.0 + 1
+    }
+}
+
+impl Hidden for Circle {
+    #[inline(never)]
+    fn secret(&self) -> u32 {
+        self.0 + 2
+    }
+}
+
+pub fn total(n: u32) -> u32 {
+    let sq = Square(n);
+    let shape: &dyn Shape = &sq;
+    let hidden: &dyn Hidden = &sq;
+    shape.area() + hidden.secret()
+}
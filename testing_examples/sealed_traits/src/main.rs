@@ -0,0 +1,138 @@
+// One trait of each class `TraitClass` tells apart, dispatched on through a
+// `dyn`, for the `--TRAIT CLASSES--` section of the stats file:
+//   - `Shape`: `pub` at the crate root, so open
+//   - `Hidden`: `pub(crate)`, so closed (private)
+//   - `Inner`: `pub`, but in a private module, so closed (private) too
+//   - `Codec`: `pub`, with the private supertrait `private::Sealed`, so
+//     closed (sealed by supertrait)
+//   - `Keyed`: `pub`, with a required method taking the private `Token`, so
+//     closed (sealed by signature)
+//
+// Only a Square is ever made, so each of the five sites should be just its
+// impl, with CHA adding Circle's. This is a binary, so every site is
+// rewritten whatever its trait's class; `sealed_lib` is the library case.
+
+mod private {
+    pub trait Sealed {}
+    pub struct Token;
+
+    pub trait Inner {
+        fn depth(&self) -> u32;
+    }
+}
+
+use private::{Inner, Token};
+
+pub trait Shape {
+    fn area(&self) -> u32;
+}
+
+pub(crate) trait Hidden {
+    fn secret(&self) -> u32;
+}
+
+pub trait Codec: private::Sealed {
+    fn encode(&self) -> u32;
+}
+
+pub trait Keyed {
+    fn key(&self, token: Token) -> u32;
+}
+
+pub struct Square(u32);
+pub struct Circle(u32);
+
+#[votrace::trace]
+impl Shape for Square {
+    fn area(&self) -> u32 {
+        self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Circle {
+    fn area(&self) -> u32 {
+        3 * self.0 * self.0
+    }
+}
+
+#[votrace::trace]
+impl Hidden for Square {
+    fn secret(&self) -> u32 {
+        self.0 + 1
+    }
+}
+
+#[votrace::trace]
+impl Hidden for Circle {
+    fn secret(&self) -> u32 {
+        self.0 + 2
+    }
+}
+
+#[votrace::trace]
+impl Inner for Square {
+    fn depth(&self) -> u32 {
+        1
+    }
+}
+
+#[votrace::trace]
+impl Inner for Circle {
+    fn depth(&self) -> u32 {
+        2
+    }
+}
+
+#[votrace::trace]
+impl private::Sealed for Square {}
+#[votrace::trace]
+impl private::Sealed for Circle {}
+
+#[votrace::trace]
+impl Codec for Square {
+    fn encode(&self) -> u32 {
+        self.0 << 1
+    }
+}
+
+#[votrace::trace]
+impl Codec for Circle {
+    fn encode(&self) -> u32 {
+        self.0 << 2
+    }
+}
+
+#[votrace::trace]
+impl Keyed for Square {
+    fn key(&self, _token: Token) -> u32 {
+        self.0 ^ 5
+    }
+}
+
+#[votrace::trace]
+impl Keyed for Circle {
+    fn key(&self, _token: Token) -> u32 {
+        self.0 ^ 7
+    }
+}
+
+fn main() {
+    let n = std::env::args().count() as u32;
+    let sq = Square(n + 2);
+
+    let shape: &dyn Shape = &sq;
+    let hidden: &dyn Hidden = &sq;
+    let inner: &dyn Inner = &sq;
+    let codec: &dyn Codec = &sq;
+    let keyed: &dyn Keyed = &sq;
+
+    println!(
+        "{} {} {} {} {}",
+        shape.area(),
+        hidden.secret(),
+        inner.depth(),
+        codec.encode(),
+        keyed.key(Token)
+    );
+}
@@ -0,0 +1,64 @@
+// Factories that match on negative arms of a signed selector:
+//   - `make(n % 2 - 2)`      -3, -2 or -1: the `_` arm and the two negative
+//                            ones, but not `0`
+//   - `make(-1 - (n & 1))`   -2 or -1: only the negative arms
+//
+// `SwitchInt` holds `-2i32` as `0xffff_fffe`; read without its sign it
+// never equals `-2`, and the arms the selector does reach get pruned. So
+// `by_rem` should be {Circle, Hexagon, Triangle} and `by_mask` {Circle,
+// Triangle}. `Square` is only built for `0`, which neither can be.
+
+pub trait Shape {
+    fn sides(&self) -> usize;
+}
+
+pub struct Circle;
+pub struct Triangle;
+pub struct Square;
+pub struct Hexagon;
+
+#[votrace::trace]
+impl Shape for Circle {
+    fn sides(&self) -> usize {
+        0
+    }
+}
+
+#[votrace::trace]
+impl Shape for Triangle {
+    fn sides(&self) -> usize {
+        3
+    }
+}
+
+#[votrace::trace]
+impl Shape for Square {
+    fn sides(&self) -> usize {
+        4
+    }
+}
+
+#[votrace::trace]
+impl Shape for Hexagon {
+    fn sides(&self) -> usize {
+        6
+    }
+}
+
+fn make(i: i32) -> Box<dyn Shape> {
+    match i {
+        -2 => Box::new(Circle),
+        -1 => Box::new(Triangle),
+        0 => Box::new(Square),
+        _ => Box::new(Hexagon),
+    }
+}
+
+fn main() {
+    let n = std::env::args().count() as i32;
+
+    let by_rem = make(n % 2 - 2);
+    let by_mask = make(-1 - (n & 1));
+
+    println!("{} {}", by_rem.sides(), by_mask.sides());
+}
@@ -0,0 +1,81 @@
+// `[[stub]]` specs from a project stub file (`stubs.toml`, named in
+// `verifopt.toml`) standing in for the `opaque` module, which
+// `[analysis] exclude` keeps the interpreter out of:
+//   - `opaque::wrap`      returns = "identity"
+//   - `opaque::register`  effects = ["insert arg1 into arg0"],
+//                         returns = "unit"
+//   - `opaque::pick`      no stub, so summarized by its signature
+//
+// `a.area()` should be just {Circle} and the `shelf` site just {Square},
+// where without the stubs both would be every Shape. `b.area()` keeps
+// CHA's {Circle, Square, Triangle}: Triangle is only built in `pick`.
+
+trait Shape {
+    fn area(&self) -> u32;
+}
+
+struct Circle;
+#[votrace::trace]
+impl Shape for Circle {
+    #[inline(never)]
+    fn area(&self) -> u32 {
+        3
+    }
+}
+
+struct Square;
+#[votrace::trace]
+impl Shape for Square {
+    #[inline(never)]
+    fn area(&self) -> u32 {
+        4
+    }
+}
+
+struct Triangle;
+#[votrace::trace]
+impl Shape for Triangle {
+    #[inline(never)]
+    fn area(&self) -> u32 {
+        5
+    }
+}
+
+mod opaque {
+    use super::{Shape, Square, Triangle};
+
+    #[inline(never)]
+    pub fn wrap(s: Box<dyn Shape>) -> Box<dyn Shape> {
+        s
+    }
+
+    #[inline(never)]
+    pub fn register(shelf: &mut Vec<Box<dyn Shape>>, s: Box<dyn Shape>) {
+        shelf.push(s);
+    }
+
+    #[inline(never)]
+    pub fn pick(n: u32) -> Box<dyn Shape> {
+        if n > 2 {
+            Box::new(Triangle)
+        } else {
+            Box::new(Square)
+        }
+    }
+}
+
+fn main() {
+    let n = std::hint::black_box(3);
+
+    let a = opaque::wrap(Box::new(Circle));
+    println!("{}", a.area());
+
+    let mut shelf: Vec<Box<dyn Shape>> = Vec::new();
+    opaque::register(&mut shelf, Box::new(Square));
+    for s in shelf.iter() {
+        println!("{}", s.area());
+    }
+
+    let b = opaque::pick(n);
+    println!("{}", b.area());
+}
@@ -0,0 +1,135 @@
+// Trait objects coerced to a supertrait's trait object:
+//   - `&dyn Pet` upcast to `&dyn Animal`, then `name`/`legs` called on it
+//   - `Box<dyn Pet>` upcast to `Box<dyn Animal>`
+//   - a supertrait method (`Animal::legs`) called straight on a `&dyn Pet`,
+//     which goes through `Pet`'s vtable
+//   - two supertraits, `dyn Robot: Animal + Machine`, upcast to the second
+//     one, whose methods sit after a `TraitVPtr` entry in `Robot`'s vtable
+//
+// `describe` gets the upcast pet (a Dog or a Parrot) and the RoboDog, and
+// the boxed animal is a Dog or a Parrot. `Snake` is never constructed, but
+// it's an `Animal`, so CHA has it at the `dyn Animal` sites only; the
+// `dyn Pet`, `dyn Machine` and `dyn Robot` ones are exact either way. The
+// default `legs` is one candidate per type that inherits it (Dog, RoboDog).
+
+pub trait Animal {
+    fn name(&self) -> String;
+    fn legs(&self) -> u32 {
+        4
+    }
+}
+
+pub trait Pet: Animal {
+    fn owner(&self) -> &str;
+}
+
+pub trait Machine {
+    fn volts(&self) -> u32;
+}
+
+pub trait Robot: Animal + Machine {
+    fn model(&self) -> u32;
+}
+
+pub struct Dog;
+pub struct Parrot(String);
+pub struct Snake;
+pub struct RoboDog(u32);
+
+#[votrace::trace]
+impl Animal for Dog {
+    fn name(&self) -> String {
+        "dog".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Pet for Dog {
+    fn owner(&self) -> &str {
+        "ann"
+    }
+}
+
+#[votrace::trace]
+impl Animal for Parrot {
+    fn name(&self) -> String {
+        "parrot".to_string()
+    }
+    fn legs(&self) -> u32 {
+        2
+    }
+}
+
+#[votrace::trace]
+impl Pet for Parrot {
+    fn owner(&self) -> &str {
+        &self.0
+    }
+}
+
+#[votrace::trace]
+impl Animal for Snake {
+    fn name(&self) -> String {
+        "snake".to_string()
+    }
+    fn legs(&self) -> u32 {
+        0
+    }
+}
+
+#[votrace::trace]
+impl Animal for RoboDog {
+    fn name(&self) -> String {
+        "robodog".to_string()
+    }
+}
+
+#[votrace::trace]
+impl Machine for RoboDog {
+    fn volts(&self) -> u32 {
+        self.0
+    }
+}
+
+#[votrace::trace]
+impl Robot for RoboDog {
+    fn model(&self) -> u32 {
+        self.0 / 2
+    }
+}
+
+#[inline(never)]
+fn describe(a: &dyn Animal) -> String {
+    format!("{}/{}", a.name(), a.legs())
+}
+
+#[inline(never)]
+fn pet_legs(p: &dyn Pet) -> u32 {
+    p.legs()
+}
+
+#[inline(never)]
+fn power(m: &dyn Machine) -> u32 {
+    m.volts()
+}
+
+fn main() {
+    let n = std::env::args().count();
+
+    let pet: &dyn Pet = if n > 5 { &Dog } else { &Parrot("bo".to_string()) };
+    let animal: &dyn Animal = pet;
+    println!("{} {}", describe(animal), pet.owner());
+    println!("{}", pet_legs(pet));
+
+    let boxed: Box<dyn Pet> = if n > 5 {
+        Box::new(Parrot("cy".to_string()))
+    } else {
+        Box::new(Dog)
+    };
+    let boxed: Box<dyn Animal> = boxed;
+    println!("{}", boxed.name());
+
+    let robot: &dyn Robot = &RoboDog(12);
+    let machine: &dyn Machine = robot;
+    println!("{} {} {}", power(machine), robot.model(), describe(robot));
+}
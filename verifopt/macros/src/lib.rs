@@ -0,0 +1,54 @@
+//! The attribute macros re-exported by `verifopt`. Each one leaves its item
+//! as is, with a `verifopt_tool::` attribute of the same name on it that
+//! only exists under `cargo verifopt` (`--cfg verifopt`).
+
+use proc_macro::TokenStream;
+
+/// Treat this trait as closed to implementors outside this crate.
+#[proc_macro_attribute]
+pub fn closed(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !is_item(&item, "trait") {
+        return error("#[verifopt::closed] only applies to traits", item);
+    }
+    mark("closed", attr, item)
+}
+
+/// Leave the dyn calls in this function, or to this trait's methods, alone.
+#[proc_macro_attribute]
+pub fn no_devirt(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !is_item(&item, "fn") && !is_item(&item, "trait") {
+        return error(
+            "#[verifopt::no_devirt] only applies to functions and traits",
+            item,
+        );
+    }
+    mark("no_devirt", attr, item)
+}
+
+fn mark(name: &str, attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return error(&format!("#[verifopt::{}] takes no arguments", name), item);
+    }
+    let mut out: TokenStream = format!("#[cfg_attr(verifopt, verifopt_tool::{})]", name)
+        .parse()
+        .unwrap();
+    out.extend(item);
+    out
+}
+
+/// Whether `item` is a `keyword` item: the keyword is one of its top-level
+/// tokens, i.e. not inside its body or any other brackets.
+fn is_item(item: &TokenStream, keyword: &str) -> bool {
+    item.clone().into_iter().any(|tt| match tt {
+        proc_macro::TokenTree::Ident(ident) => ident.to_string() == keyword,
+        _ => false,
+    })
+}
+
+/// `item` as is, after a `compile_error!` with `msg`, so the item's own uses
+/// don't error too.
+fn error(msg: &str, item: TokenStream) -> TokenStream {
+    let mut out: TokenStream = format!("compile_error!({:?});", msg).parse().unwrap();
+    out.extend(item);
+    out
+}
@@ -0,0 +1,46 @@
+//! Annotations `verifopt` reads while analyzing a crate, to pin down (or
+//! opt out of) what it does to dynamic dispatch there.
+//!
+//! - `#[verifopt::closed]` on a trait asserts that its implementors in this
+//!   crate are all the implementors there will ever be, so it's treated like
+//!   a private or sealed trait (see `monomorph::trait_collect::TraitClass`).
+//! - `#[verifopt::no_devirt]` on a function leaves every dyn call in its body
+//!   as is; on a trait, every dyn call to one of its methods.
+//! - `verifopt::assert_targets!(expr, [Cat, Dog])` fails the `cargo
+//!   verifopt` build unless the dyn calls in `expr` dispatch to exactly the
+//!   impls for those Self types, per the analysis. It evaluates to `expr`.
+//!
+//! Outside `cargo verifopt` all three do nothing. Under it, the driver
+//! compiles the analyzed crate with `--cfg verifopt` and registers the
+//! `verifopt_tool` tool, which the attributes expand to (see
+//! `monomorph::annotations`).
+//!
+//! `cfg(verifopt)` is then unknown to plain builds, so declare it in the
+//! crate using the attributes:
+//!
+//! ```toml
+//! [lints.rust]
+//! unexpected_cfgs = { level = "warn", check-cfg = ["cfg(verifopt)"] }
+//! ```
+
+pub use verifopt_macros::{closed, no_devirt};
+
+/// Evaluates `$e`, and under `cargo verifopt` checks that its dyn calls
+/// dispatch to exactly the listed Self types' impls. `$e` runs in a closure,
+/// so it can't `return`, `break` or `?` out of the surrounding function.
+#[macro_export]
+macro_rules! assert_targets {
+    ($e:expr, [$($target:ty),* $(,)?]) => {
+        $crate::__assert_targets::<($($target,)*), _, _>(|| $e)
+    };
+}
+
+/// The call `assert_targets!` expands to: `monomorph::annotations` finds it
+/// in the caller's MIR and reads the expected Self types off `Targets`, and
+/// the dyn call sites to check off `F`, the closure. Never inlined, so the
+/// call is still there in optimized MIR.
+#[doc(hidden)]
+#[inline(never)]
+pub fn __assert_targets<Targets, R, F: FnOnce() -> R>(f: F) -> R {
+    f()
+}